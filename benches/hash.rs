@@ -0,0 +1,53 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares the id hash algorithm with the candidates to replace it, see
+//! `gqdb::hash`. Ids hash a few hundred bytes, the id fields of an object,
+//! so the small input dominates, the large one shows the throughput.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use gqdb::hash::HashAlgorithm;
+use gqdb::{test_vectors, Event};
+use sha2::{Digest, Sha256, Sha512_256};
+
+fn inputs() -> Vec<(&'static str, Vec<u8>)> {
+    let qso = serde_json::to_vec(&Event::Qso(test_vectors::qso())).unwrap();
+    let archive: Vec<u8> = qso.iter().copied().cycle().take(1024 * 1024).collect();
+    vec![("qso", qso), ("1 MiB", archive)]
+}
+
+fn hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hash");
+
+    for (name, input) in inputs() {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("current", name), &input, |b, input| {
+            b.iter(|| HashAlgorithm::CURRENT.digest(black_box(input)))
+        });
+        group.bench_with_input(BenchmarkId::new("sha256", name), &input, |b, input| {
+            b.iter(|| Sha256::digest(black_box(input)))
+        });
+        group.bench_with_input(BenchmarkId::new("sha512/256", name), &input, |b, input| {
+            b.iter(|| Sha512_256::digest(black_box(input)))
+        });
+        group.bench_with_input(BenchmarkId::new("blake3", name), &input, |b, input| {
+            b.iter(|| blake3::hash(black_box(input)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, hash);
+criterion_main!(benches);
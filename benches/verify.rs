@@ -0,0 +1,69 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use codes_iso_3166::part_1::CountryCode;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use gqdb::{generate_keypair, Qso, QsoData, Station};
+use std::collections::BTreeMap;
+
+fn qso_json() -> (String, Station) {
+    let keys = generate_keypair();
+
+    let station = Station::new(
+        &keys,
+        "LU4EV".to_string(),
+        "Radio Club Caseros".to_string(),
+        CountryCode::AR,
+    )
+    .unwrap();
+
+    let qso = Qso::new(
+        QsoData {
+            station_id: station.id.clone(),
+            callsign: "LW3DZR".to_string(),
+            freq: 14250300,
+            datetime: 1704141426,
+            mode: "CW".to_string(),
+            rst: "599".to_string(),
+            comments: "tnx for the qso 73".to_string(),
+            extra: BTreeMap::new(),
+            tags: vec![],
+            prev_id: None,
+        },
+        &keys,
+    );
+
+    (serde_json::to_string(&qso).unwrap(), station)
+}
+
+fn deserialize(c: &mut Criterion) {
+    let (json, _) = qso_json();
+
+    c.bench_function("deserialize qso", |b| {
+        b.iter(|| serde_json::from_str::<Qso>(black_box(&json)).unwrap())
+    });
+}
+
+fn deserialize_verify(c: &mut Criterion) {
+    let (json, station) = qso_json();
+
+    c.bench_function("deserialize and verify qso", |b| {
+        b.iter(|| {
+            let qso: Qso = serde_json::from_str(black_box(&json)).unwrap();
+            qso.verify(&station.pub_key).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, deserialize, deserialize_verify);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use gqdb::Event;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(event) = serde_json::from_slice::<Event>(data) {
+        let json_str = serde_json::to_string(&event).unwrap();
+        let event_dese: Event = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(event_dese, event);
+    }
+});
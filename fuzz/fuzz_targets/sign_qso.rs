@@ -0,0 +1,24 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use gqdb::{test_vectors, Qso, QsoData};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|qso_data: QsoData| {
+    let keys = test_vectors::station_keypair();
+    if let Ok(qso) = Qso::new_with_signer(qso_data, &keys) {
+        qso.verify(&keys.x_only_public_key().0).unwrap();
+    }
+});
@@ -0,0 +1,96 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::adif::{qso_fields, ADIF_VERSION, PROGRAM_ID};
+use crate::Qso;
+use anyhow::Result;
+use std::io::Write;
+
+/// Writes the QSOs as an ADX file, the XML variant of ADIF.
+pub fn write_adx<W: Write>(mut writer: W, qsos: &[Qso]) -> Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, "<ADX>")?;
+    writeln!(writer, "  <HEADER>")?;
+    write_element(&mut writer, "ADIF_VER", ADIF_VERSION)?;
+    write_element(&mut writer, "PROGRAMID", PROGRAM_ID)?;
+    writeln!(writer, "  </HEADER>")?;
+    writeln!(writer, "  <RECORDS>")?;
+
+    for qso in qsos {
+        writeln!(writer, "    <RECORD>")?;
+        for (name, value) in qso_fields(qso)? {
+            write!(writer, "  ")?;
+            write_element(&mut writer, &name, &value)?;
+        }
+        writeln!(writer, "    </RECORD>")?;
+    }
+
+    writeln!(writer, "  </RECORDS>")?;
+    writeln!(writer, "</ADX>")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_element<W: Write>(writer: &mut W, name: &str, value: &str) -> Result<()> {
+    writeln!(writer, "    <{}>{}</{}>", name, escape(value), name)?;
+    Ok(())
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverts [`escape`].
+#[cfg(any(feature = "fldigi", feature = "n1mm"))]
+pub(crate) fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adif::write_adx;
+    use crate::test_vectors;
+
+    #[test]
+    fn test_write_adx() {
+        let mut qso = test_vectors::qso();
+        qso.comments = "QRM & <QSB>".to_string();
+
+        let mut output = vec![];
+        write_adx(&mut output, &[qso]).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ADX>\n"));
+        assert!(output.contains("    <ADIF_VER>3.1.4</ADIF_VER>\n"));
+        assert!(output.contains("      <CALL>LW3DZR</CALL>\n"));
+        assert!(output.contains("      <QSO_DATE>20240101</QSO_DATE>\n"));
+        assert!(output.contains("      <COMMENT>QRM &amp; &lt;QSB&gt;</COMMENT>\n"));
+        assert!(output.ends_with("    </RECORD>\n  </RECORDS>\n</ADX>\n"));
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Mapping between QSOs and ADIF fields, shared by the ADI and ADX formats.
+
+use crate::bandplan::Band;
+use crate::csv::{format_freq, parse_freq, FreqUnit};
+use crate::{Id, Qso, QsoData};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDateTime};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// ADIF fields mapped to the QSO, the rest are kept in the extension fields.
+/// BAND is derived from the frequency.
+const QSO_FIELDS: &[&str] = &[
+    "CALL", "QSO_DATE", "TIME_ON", "FREQ", "BAND", "MODE", "RST_SENT", "COMMENT",
+];
+
+/// Returns the ADIF fields of the QSO, names in uppercase.
+///
+/// Extension fields are written as the ADIF field of the same name, so
+/// fields without native support survive a round trip. Extension fields
+/// that are not valid ADIF fields, or that clash with a mapped field, are
+/// skipped.
+pub fn qso_fields(qso: &Qso) -> Result<Vec<(String, String)>> {
+    let datetime = i64::try_from(qso.datetime)
+        .ok()
+        .and_then(|datetime| DateTime::from_timestamp(datetime, 0))
+        .ok_or_else(|| anyhow!("invalid datetime {}", qso.datetime))?;
+
+    let mut fields = vec![
+        ("CALL".to_string(), qso.callsign.clone()),
+        (
+            "QSO_DATE".to_string(),
+            datetime.format("%Y%m%d").to_string(),
+        ),
+        ("TIME_ON".to_string(), datetime.format("%H%M%S").to_string()),
+        ("FREQ".to_string(), format_freq(qso.freq, FreqUnit::Mhz)),
+    ];
+    if let Some(band) = Band::from_freq(qso.freq) {
+        fields.push(("BAND".to_string(), band.name().to_string()));
+    }
+    fields.push(("MODE".to_string(), qso.mode.clone()));
+    fields.push(("RST_SENT".to_string(), qso.rst.clone()));
+    if !qso.comments.is_empty() {
+        fields.push(("COMMENT".to_string(), qso.comments.clone()));
+    }
+
+    for (name, value) in &qso.extra {
+        let name = name.to_ascii_uppercase();
+        if !is_field_name(&name) || QSO_FIELDS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = match value {
+            Value::String(value) => value.clone(),
+            Value::Number(value) => value.to_string(),
+            Value::Bool(value) => if *value { "Y" } else { "N" }.to_string(),
+            _ => continue,
+        };
+        fields.push((name, value));
+    }
+
+    Ok(fields)
+}
+
+/// Builds the QSO of the station from ADIF fields, keyed by uppercase name.
+/// Fields without a QSO counterpart are kept as extension fields, named in
+/// lowercase.
+pub fn qso_data(station_id: &Id, fields: &BTreeMap<String, String>) -> Result<QsoData> {
+    let field = |name: &str| {
+        fields
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("missing field {}", name))
+    };
+
+    let date = field("QSO_DATE")?;
+    let time = field("TIME_ON")?;
+    // TIME_ON may omit the seconds.
+    let time = if time.len() == 4 {
+        format!("{}00", time)
+    } else {
+        time.to_string()
+    };
+    let datetime = NaiveDateTime::parse_from_str(&format!("{}{}", date, time), "%Y%m%d%H%M%S")
+        .with_context(|| format!("invalid datetime {} {}", date, time))?;
+
+    let extra = fields
+        .iter()
+        .filter(|(name, _)| !QSO_FIELDS.contains(&name.as_str()))
+        .map(|(name, value)| (name.to_ascii_lowercase(), Value::from(value.as_str())))
+        .collect();
+
+    Ok(QsoData {
+        station_id: station_id.clone(),
+        callsign: field("CALL")?.to_string(),
+        datetime: u64::try_from(datetime.and_utc().timestamp()).context("datetime before 1970")?,
+        freq: parse_freq(field("FREQ")?, FreqUnit::Mhz)?,
+        mode: field("MODE")?.to_uppercase(),
+        rst: fields.get("RST_SENT").cloned().unwrap_or_default(),
+        comments: fields.get("COMMENT").cloned().unwrap_or_default(),
+        extra,
+        tags: vec![],
+        prev_id: None,
+    })
+}
+
+/// ADIF field names can't contain the delimiters of the ADI format.
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !matches!(c, ',' | ':' | '<' | '>' | '{' | '}'))
+}
@@ -0,0 +1,203 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ADIF import and export of QSOs.
+//!
+//! Logs are read from and written to the ADI text format, and written to
+//! the ADX XML format preferred by some logging programs and award systems.
+//! Both formats share the mapping of [`qso_fields`] and [`qso_data`].
+
+mod adx;
+mod fields;
+
+pub(crate) use adx::escape;
+#[cfg(any(feature = "fldigi", feature = "n1mm"))]
+pub(crate) use adx::unescape;
+pub use adx::write_adx;
+pub use fields::{qso_data, qso_fields};
+
+use crate::progress::Monitor;
+use crate::{Id, Qso, QsoData};
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// ADIF version written in the headers.
+pub const ADIF_VERSION: &str = "3.1.4";
+
+/// Program id written in the headers.
+const PROGRAM_ID: &str = "GQDB";
+
+/// Reads the QSOs of the station from an ADI file.
+///
+/// Errors report the number of the offending record. Records are not
+/// validated, the QSOs are validated when signed.
+pub fn read_adi<R: Read>(reader: R, station_id: &Id) -> Result<Vec<QsoData>> {
+    read_adi_with_monitor(reader, station_id, &mut Monitor::new())
+}
+
+/// Reads the QSOs like [`read_adi`], reporting every record read to the
+/// monitor.
+pub fn read_adi_with_monitor<R: Read>(
+    mut reader: R,
+    station_id: &Id,
+    monitor: &mut Monitor,
+) -> Result<Vec<QsoData>> {
+    monitor.start(None)?;
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+
+    // A header is present unless the file starts with a field.
+    let mut rest = text.as_str();
+    if !rest.starts_with('<') {
+        let eoh = find_tag(rest, "EOH").ok_or_else(|| anyhow!("missing <EOH>"))?;
+        rest = &rest[eoh..];
+    }
+
+    let mut qsos = vec![];
+    let mut fields = BTreeMap::new();
+    while let Some(start) = rest.find('<') {
+        let end = rest[start..]
+            .find('>')
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow!("unterminated field"))?;
+        let spec = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        let mut parts = spec.split(':');
+        let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+        if name == "EOR" {
+            let record = qsos.len() + 1;
+            let qso = qso_data(station_id, &fields)
+                .with_context(|| format!("invalid record {}", record))?;
+            qsos.push(qso);
+            fields.clear();
+            monitor.record(true)?;
+            continue;
+        }
+
+        let Some(len) = parts.next() else {
+            // Tags without a length, e.g. <EOH>, carry no value.
+            continue;
+        };
+        let len: usize = len
+            .parse()
+            .with_context(|| format!("invalid length of field {}", name))?;
+        let Some(value) = rest.get(..len) else {
+            bail!("truncated field {}", name);
+        };
+        fields.insert(name, value.to_string());
+        rest = &rest[len..];
+    }
+
+    Ok(qsos)
+}
+
+/// Writes the QSOs as an ADI file.
+pub fn write_adi<W: Write>(writer: W, qsos: &[Qso]) -> Result<()> {
+    write_adi_with_monitor(writer, qsos, &mut Monitor::new())
+}
+
+/// Writes the QSOs like [`write_adi`], reporting every record written to
+/// the monitor.
+pub fn write_adi_with_monitor<W: Write>(
+    mut writer: W,
+    qsos: &[Qso],
+    monitor: &mut Monitor,
+) -> Result<()> {
+    monitor.start(Some(qsos.len() as u64))?;
+    writeln!(writer, "GQDB ADIF export")?;
+    write_field(&mut writer, "ADIF_VER", ADIF_VERSION)?;
+    write_field(&mut writer, "PROGRAMID", PROGRAM_ID)?;
+    writeln!(writer, "<EOH>")?;
+
+    for qso in qsos {
+        for (name, value) in qso_fields(qso)? {
+            write_field(&mut writer, &name, &value)?;
+        }
+        writeln!(writer, "<EOR>")?;
+        monitor.record(true)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_field<W: Write>(writer: &mut W, name: &str, value: &str) -> Result<()> {
+    write!(writer, "<{}:{}>{} ", name, value.len(), value)?;
+    Ok(())
+}
+
+/// Returns the position after the tag, ignoring case.
+fn find_tag(text: &str, name: &str) -> Option<usize> {
+    let tag = format!("<{}>", name);
+    text.to_ascii_uppercase()
+        .find(&tag)
+        .map(|start| start + tag.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adif::{read_adi, write_adi};
+    use crate::qso::GRIDSQUARE;
+    use crate::{test_vectors, Id};
+    use serde_json::json;
+
+    #[test]
+    fn test_read_write_adi() {
+        let station_id = Id::new("station");
+        let input = "Exported by a logger <ADIF_VER:5>3.1.4 <EOH>\n\
+                     <call:6>LW3DZR<qso_date:8>20240101<time_on:4>2037\
+                     <freq:7>14.0743<mode:3>ft8<rst_sent:3>-10<gridsquare:4>GF05<eor>\n\
+                     <CALL:5>LU1AA <QSO_DATE:8>20240102 <TIME_ON:6>000500 \
+                     <FREQ:5>7.030 <BAND:3>40m <MODE:2>CW <MY_SOTA_REF:9>LU/BA-001 <EOR>\n";
+
+        let qsos = read_adi(input.as_bytes(), &station_id).unwrap();
+        assert_eq!(qsos.len(), 2);
+        assert_eq!(qsos[0].callsign, "LW3DZR");
+        assert_eq!(qsos[0].datetime, 1704141420);
+        assert_eq!(qsos[0].freq, 14074300);
+        assert_eq!(qsos[0].mode, "FT8");
+        assert_eq!(qsos[0].extra[GRIDSQUARE], "GF05");
+        assert_eq!(qsos[1].extra["my_sota_ref"], "LU/BA-001");
+        assert!(!qsos[1].extra.contains_key("band"));
+        assert_eq!(qsos[1].datetime, 1704153900);
+        assert_eq!(qsos[1].rst, "");
+
+        let mut qso = test_vectors::qso();
+        qso.extra
+            .insert("my_sota_ref".to_string(), json!("LU/BA-001"));
+        qso.extra.insert("tx_pwr".to_string(), json!(100));
+        qso.extra.insert("bad field".to_string(), json!("skipped"));
+        let mut output = vec![];
+        write_adi(&mut output, std::slice::from_ref(&qso)).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("<CALL:6>LW3DZR <QSO_DATE:8>20240101 <TIME_ON:6>203706 "));
+        assert!(output.contains("<BAND:3>20m "));
+        assert!(output.contains("<MY_SOTA_REF:9>LU/BA-001 <TX_PWR:3>100 "));
+        assert!(!output.contains("skipped"));
+
+        let read = read_adi(output.as_bytes(), &qso.station_id).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].callsign, qso.callsign);
+        assert_eq!(read[0].datetime, qso.datetime);
+        assert_eq!(read[0].freq, qso.freq);
+        assert_eq!(read[0].comments, qso.comments);
+        assert_eq!(read[0].extra["my_sota_ref"], "LU/BA-001");
+        assert_eq!(read[0].extra["tx_pwr"], "100");
+
+        assert!(read_adi("<CALL:6>LW3DZR<EOR>".as_bytes(), &station_id).is_err());
+        assert!(read_adi("<CALL:60>LW3DZR<EOR>".as_bytes(), &station_id).is_err());
+    }
+}
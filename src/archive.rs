@@ -0,0 +1,557 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `.gqdb` archive format.
+//!
+//! An archive is a zstd compressed JSON Lines file. The first line is the
+//! [`ArchiveManifest`] signed by the archive creator, every following line is
+//! an [`Event`]. The manifest commits to the number of events of each kind
+//! and to the merkle root of the event ids in archive order.
+//!
+//! Since v1 the manifest also commits to the BLAKE3 hashes of the event
+//! lines, in chunks of [`CHUNK_EVENTS`] events. Readers check every chunk as
+//! it streams, and [`verify_integrity`] checks a whole archive without
+//! parsing the events, fast enough for multi-GB backups on low-end hardware.
+//!
+//! Archives are used for backups, bulk transfers between relays and award
+//! submissions.
+
+use crate::event::Event;
+use crate::hash::HashAlgorithm;
+use crate::id::IdBuilder;
+use crate::merkle;
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{limits, version, Id, Kind};
+use anyhow::{bail, Context, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// File extension of the archives.
+pub const EXTENSION: &str = "gqdb";
+
+/// Number of events covered by every chunk hash of a manifest.
+pub const CHUNK_EVENTS: usize = 1024;
+
+const COMPRESSION_LEVEL: i32 = 9;
+
+/// Maximum size of the manifest line. It bounds the chunk hashes to about
+/// 15 thousand, so an archive holds at most about 16 million events.
+const MAX_MANIFEST_SIZE: usize = limits::MAX_EVENT_SIZE;
+
+/// Header of an archive, signed by the station which created it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub id: Id,
+    pub creator_id: Id,
+    pub event_count: u64,
+    pub counts: BTreeMap<Kind, u64>,
+    pub merkle_root: Id,
+    /// BLAKE3 hashes of the event lines, newlines included, in chunks of
+    /// [`CHUNK_EVENTS`] events. Empty in v0 manifests.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunk_hashes: Vec<Id>,
+    pub created_at: u64,
+    pub version: u8,
+    pub sig: Signature,
+}
+
+impl ArchiveManifest {
+    /// Current version of the archive manifest object.
+    pub const VERSION: u8 = version::V1;
+
+    /// Versions accepted by the archive manifest verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0, version::V1];
+
+    /// Creates the manifest of the events and signs it using the creator
+    /// signer.
+    pub fn new(creator_id: Id, signer: &dyn Signer, events: &[Event]) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let (counts, merkle_root) = Self::summarize(events);
+        let event_count = events.len() as u64;
+
+        let mut chunks = ChunkHasher::default();
+        for event in events {
+            chunks.update(&serde_json::to_vec(event)?);
+        }
+        let chunk_hashes = chunks.finish();
+
+        let id = Self::compute_id(
+            &creator_id,
+            event_count,
+            &counts,
+            &merkle_root,
+            &chunk_hashes,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let manifest = Self {
+            id,
+            creator_id,
+            event_count,
+            counts,
+            merkle_root,
+            chunk_hashes,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        manifest.validate()?;
+
+        Ok(manifest)
+    }
+
+    /// Verify the object signature against the creator public key.
+    pub fn verify(&self, creator_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(creator_pub_key)
+    }
+
+    /// Checks that the events are the ones committed by the manifest.
+    pub fn verify_events(&self, events: &[Event]) -> Result<()> {
+        if events.len() as u64 != self.event_count {
+            bail!("event count mismatch");
+        }
+
+        let (counts, merkle_root) = Self::summarize(events);
+        if counts != self.counts {
+            bail!("event kind count mismatch");
+        }
+        if merkle_root != self.merkle_root {
+            bail!("merkle root mismatch");
+        }
+
+        Ok(())
+    }
+
+    fn summarize(events: &[Event]) -> (BTreeMap<Kind, u64>, Id) {
+        let mut counts = BTreeMap::new();
+        for event in events {
+            *counts.entry(event.kind()).or_default() += 1;
+        }
+        let ids: Vec<Id> = events.iter().map(|event| event.id().clone()).collect();
+        (counts, merkle::root(&ids))
+    }
+
+    /// Checks the hash of the chunk at `index`, the one of the events read so
+    /// far when it's incomplete.
+    fn verify_chunk(&self, index: usize, hash: &Id) -> Result<()> {
+        if self.chunk_hashes.get(index) != Some(hash) {
+            bail!("chunk {} hash mismatch", index);
+        }
+        Ok(())
+    }
+
+    fn compute_id(
+        creator_id: &Id,
+        event_count: u64,
+        counts: &BTreeMap<Kind, u64>,
+        merkle_root: &Id,
+        chunk_hashes: &[Id],
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        let mut id = IdBuilder::new(
+            HashAlgorithm::for_version(version),
+            &(
+                creator_id,
+                event_count,
+                json!(counts),
+                merkle_root,
+                created_at,
+                version,
+            ),
+        );
+        if !chunk_hashes.is_empty() {
+            id.optional("chunk_hashes", chunk_hashes);
+        }
+
+        id.finish()
+    }
+}
+
+impl Validate for ArchiveManifest {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.counts.values().sum::<u64>() != self.event_count {
+            bail!("event kind counts don't add up");
+        }
+
+        version::check_optional_fields(self.version, !self.chunk_hashes.is_empty())?;
+        let chunks = self.event_count.div_ceil(CHUNK_EVENTS as u64);
+        if self.version >= version::V1 && self.chunk_hashes.len() as u64 != chunks {
+            bail!("expected {} chunk hashes", chunks);
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for ArchiveManifest {
+    const KIND: Kind = Kind::ArchiveManifest;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.creator_id,
+            self.event_count,
+            &self.counts,
+            &self.merkle_root,
+            &self.chunk_hashes,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+/// Hashes event lines in chunks of [`CHUNK_EVENTS`] events.
+#[derive(Default)]
+struct ChunkHasher {
+    hasher: blake3::Hasher,
+    events: usize,
+    hashes: Vec<Id>,
+}
+
+impl ChunkHasher {
+    /// Hashes an event line, without its newline. Returns the index of the
+    /// chunk completed by the line, if any.
+    fn update(&mut self, line: &[u8]) -> Option<usize> {
+        self.hasher.update(line);
+        self.hasher.update(b"\n");
+        self.events += 1;
+        if self.events % CHUNK_EVENTS != 0 {
+            return None;
+        }
+
+        self.push();
+        Some(self.hashes.len() - 1)
+    }
+
+    /// Returns the hashes of every chunk, the last one maybe incomplete.
+    fn finish(mut self) -> Vec<Id> {
+        if self.events % CHUNK_EVENTS != 0 {
+            self.push();
+        }
+        self.hashes
+    }
+
+    fn push(&mut self) {
+        let hash = Id::from_bytes(*self.hasher.finalize().as_bytes());
+        self.hashes.push(hash);
+        self.hasher.reset();
+    }
+}
+
+/// Writes an archive with the events, signed by the creator. Returns the
+/// archive manifest.
+///
+/// Fails if there are too many events for the manifest to be read back,
+/// they must be split in several archives.
+pub fn write<W: Write>(
+    writer: W,
+    creator_id: Id,
+    signer: &dyn Signer,
+    events: &[Event],
+) -> Result<ArchiveManifest> {
+    let manifest = ArchiveManifest::new(creator_id, signer, events)?;
+    let manifest_line = manifest_line(&manifest)?;
+
+    let mut encoder = zstd::Encoder::new(writer, COMPRESSION_LEVEL)?;
+    encoder.write_all(&manifest_line)?;
+    for event in events {
+        serde_json::to_writer(&mut encoder, event)?;
+        encoder.write_all(b"\n")?;
+    }
+    encoder.finish()?.flush()?;
+
+    Ok(manifest)
+}
+
+/// Serializes the manifest line, failing if it's too long to be read.
+fn manifest_line(manifest: &ArchiveManifest) -> Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(manifest)?;
+    if line.len() > MAX_MANIFEST_SIZE {
+        bail!(
+            "archive manifest longer than {} bytes, too many events",
+            MAX_MANIFEST_SIZE
+        );
+    }
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Reads an archive, verifying the manifest signature against the creator
+/// public key and the events against the manifest.
+///
+/// The signatures of the events themselves are not verified.
+pub fn read<R: Read>(
+    reader: R,
+    creator_pub_key: &XOnlyPublicKey,
+) -> Result<(ArchiveManifest, Vec<Event>)> {
+    let mut events = vec![];
+    let manifest = read_lines(reader, creator_pub_key, |index, line| {
+        let event: Event = serde_json::from_slice(line)
+            .with_context(|| format!("invalid event at line {}", index + 2))?;
+        events.push(event);
+        Ok(())
+    })?;
+
+    manifest.verify_events(&events)?;
+
+    Ok((manifest, events))
+}
+
+/// Checks the integrity of an archive without parsing its events: the
+/// manifest signature against the creator public key and the event lines
+/// against the chunk hashes of the manifest. Returns the manifest.
+///
+/// Unlike [`read`], the event ids aren't checked against the merkle root of
+/// the manifest, and v0 archives, without chunk hashes, are rejected.
+pub fn verify_integrity<R: Read>(
+    reader: R,
+    creator_pub_key: &XOnlyPublicKey,
+) -> Result<ArchiveManifest> {
+    read_lines(reader, creator_pub_key, |_, _| Ok(())).and_then(|manifest| {
+        if manifest.version < version::V1 {
+            bail!("v{} archive without chunk hashes", manifest.version);
+        }
+        Ok(manifest)
+    })
+}
+
+/// Reads the manifest and streams the event lines, without their newline,
+/// to `on_line`, checking the line count and the chunk hashes if the
+/// manifest has them.
+fn read_lines<R: Read>(
+    reader: R,
+    creator_pub_key: &XOnlyPublicKey,
+    mut on_line: impl FnMut(usize, &[u8]) -> Result<()>,
+) -> Result<ArchiveManifest> {
+    let mut reader = BufReader::new(zstd::Decoder::new(reader)?);
+    let mut line = vec![];
+
+    if read_line(&mut reader, &mut line, MAX_MANIFEST_SIZE)? == 0 {
+        bail!("empty archive");
+    }
+    let manifest: ArchiveManifest =
+        serde_json::from_slice(trim_newline(&line)).context("invalid archive manifest")?;
+    manifest.verify(creator_pub_key)?;
+
+    let mut chunks = ChunkHasher::default();
+    let mut count = 0;
+    loop {
+        line.clear();
+        if read_line(&mut reader, &mut line, limits::MAX_EVENT_SIZE)? == 0 {
+            break;
+        }
+        if count >= manifest.event_count {
+            bail!("unexpected event after the last one");
+        }
+
+        let line = trim_newline(&line);
+        if !manifest.chunk_hashes.is_empty() {
+            if let Some(index) = chunks.update(line) {
+                manifest.verify_chunk(index, &chunks.hashes[index])?;
+            }
+        }
+        on_line(count as usize, line)?;
+        count += 1;
+    }
+
+    if count != manifest.event_count {
+        bail!("event count mismatch");
+    }
+    if !manifest.chunk_hashes.is_empty() {
+        let hashes = chunks.finish();
+        if let Some(last) = hashes.last() {
+            manifest.verify_chunk(hashes.len() - 1, last)?;
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Reads a line, failing if it's longer than `max_len` bytes without the
+/// newline, so a crafted archive can't decompress into an unbounded line.
+pub(crate) fn read_line<R: BufRead>(
+    reader: &mut R,
+    line: &mut Vec<u8>,
+    max_len: usize,
+) -> Result<usize> {
+    let len = reader
+        .by_ref()
+        .take(max_len as u64 + 1)
+        .read_until(b'\n', line)?;
+    if len > max_len && !line.ends_with(b"\n") {
+        bail!("line longer than {} bytes", max_len);
+    }
+    Ok(len)
+}
+
+pub(crate) fn trim_newline(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\n").unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::{self, manifest_line, read_line, CHUNK_EVENTS, MAX_MANIFEST_SIZE};
+    use crate::event::Event;
+    use crate::keys::generate_keypair;
+    use crate::signable::Validate;
+    use crate::{limits, test_vectors, version, Kind, Qso, QsoData, Station};
+    use codes_iso_3166::part_1::CountryCode;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_archive() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let mut events = vec![Event::Station(station.clone())];
+        events.extend((0..CHUNK_EVENTS as u64 + 10).map(|i| {
+            Event::Qso(Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    datetime: 1704141426 + i,
+                    mode: "CW".to_string(),
+                    rst: "599".to_string(),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            ))
+        }));
+
+        let mut archive = vec![];
+        let manifest = archive::write(&mut archive, station.id.clone(), &keys, &events).unwrap();
+        assert_eq!(manifest.counts[&Kind::Qso], CHUNK_EVENTS as u64 + 10);
+        assert_eq!(manifest.chunk_hashes.len(), 2);
+        assert_eq!(
+            archive::verify_integrity(archive.as_slice(), &station.pub_key).unwrap(),
+            manifest
+        );
+
+        let (manifest_dese, events_dese) =
+            archive::read(archive.as_slice(), &station.pub_key).unwrap();
+        assert_eq!(manifest_dese, manifest);
+        assert_eq!(events_dese, events);
+
+        let other = generate_keypair().x_only_public_key().0;
+        assert!(archive::read(archive.as_slice(), &other).is_err());
+
+        // An archive missing an event.
+        let mut encoder = zstd::Encoder::new(vec![], 0).unwrap();
+        serde_json::to_writer(&mut encoder, &manifest).unwrap();
+        for event in &events[1..] {
+            encoder.write_all(b"\n").unwrap();
+            serde_json::to_writer(&mut encoder, event).unwrap();
+        }
+        let truncated = encoder.finish().unwrap();
+        assert!(archive::read(truncated.as_slice(), &station.pub_key).is_err());
+        assert!(archive::verify_integrity(truncated.as_slice(), &station.pub_key).is_err());
+
+        // An archive with an event line reformatted, same event but other
+        // bytes, caught by the chunk hashes.
+        let mut encoder = zstd::Encoder::new(vec![], 0).unwrap();
+        serde_json::to_writer(&mut encoder, &manifest).unwrap();
+        encoder.write_all(b"\n").unwrap();
+        for (index, event) in events.iter().enumerate() {
+            match index {
+                5 => serde_json::to_writer_pretty(&mut encoder, event).unwrap(),
+                _ => serde_json::to_writer(&mut encoder, event).unwrap(),
+            }
+            encoder.write_all(b"\n").unwrap();
+        }
+        let altered = encoder.finish().unwrap();
+        let err = archive::verify_integrity(altered.as_slice(), &station.pub_key).unwrap_err();
+        assert_eq!(err.to_string(), "chunk 0 hash mismatch");
+        assert!(archive::read(altered.as_slice(), &station.pub_key).is_err());
+
+        // A line longer than any event is rejected before it's buffered.
+        let mut encoder = zstd::Encoder::new(vec![], 0).unwrap();
+        serde_json::to_writer(&mut encoder, &manifest).unwrap();
+        encoder.write_all(b"\n").unwrap();
+        std::io::copy(
+            &mut std::io::repeat(b' ').take(limits::MAX_EVENT_SIZE as u64 + 1),
+            &mut encoder,
+        )
+        .unwrap();
+        let bomb = encoder.finish().unwrap();
+        let err = archive::verify_integrity(bomb.as_slice(), &station.pub_key).unwrap_err();
+        assert!(err.to_string().starts_with("line longer than"));
+
+        // v0 manifests have no chunk hashes.
+        let mut v0 = manifest.clone();
+        v0.chunk_hashes.clear();
+        v0.version = version::V0;
+        assert!(v0.validate().is_ok());
+        v0.version = version::V1;
+        assert!(v0.validate().is_err());
+
+        // The manifest line of the most chunk hashes that fit is read back,
+        // one more hash and it isn't written.
+        let mut large = manifest.clone();
+        let hash_len = serde_json::to_vec(&large.chunk_hashes[0]).unwrap().len() + 1;
+        let room = MAX_MANIFEST_SIZE - (manifest_line(&large).unwrap().len() - 1);
+        large
+            .chunk_hashes
+            .extend(vec![large.chunk_hashes[0].clone(); room / hash_len]);
+        let line = manifest_line(&large).unwrap();
+        assert!(line.len() - 1 > MAX_MANIFEST_SIZE - hash_len);
+        let mut read = vec![];
+        read_line(&mut line.as_slice(), &mut read, MAX_MANIFEST_SIZE).unwrap();
+        assert_eq!(read, line);
+        large.chunk_hashes.push(large.chunk_hashes[0].clone());
+        assert!(manifest_line(&large).is_err());
+
+        let line = vec![b' '; MAX_MANIFEST_SIZE];
+        read_line(&mut line.as_slice(), &mut vec![], MAX_MANIFEST_SIZE).unwrap();
+        let line = vec![b' '; MAX_MANIFEST_SIZE + 1];
+        assert!(read_line(&mut line.as_slice(), &mut vec![], MAX_MANIFEST_SIZE).is_err());
+
+        // An empty archive has no chunks.
+        let mut empty = vec![];
+        let manifest = archive::write(&mut empty, station.id.clone(), &keys, &[]).unwrap();
+        assert!(manifest.chunk_hashes.is_empty());
+        assert_eq!(
+            archive::verify_integrity(empty.as_slice(), &station.pub_key).unwrap(),
+            manifest
+        );
+    }
+}
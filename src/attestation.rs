@@ -0,0 +1,281 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::checkpoint::covered_qsos;
+use crate::merkle::{self, MerkleProof};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::trust::{Role, TrustStore};
+use crate::{version, Checkpoint, Id, Kind, Qso};
+use anyhow::{anyhow, bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// A statement signed by an auditor station, e.g. a contest committee,
+/// declaring it reviewed the log of another station.
+///
+/// The attestation covers the first `qso_count` QSOs of the station log by
+/// their merkle root, taken from a [`Checkpoint`] of the station or computed
+/// by the auditor from the published log.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Attestation {
+    pub id: Id,
+    pub auditor_id: Id,
+    pub station_id: Id,
+    pub qso_count: u64,
+    pub merkle_root: Id,
+    /// The reviewed checkpoint, if the attestation was issued over one.
+    pub checkpoint_id: Option<Id>,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl Attestation {
+    /// Current version of the attestation object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the attestation verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new Attestation over a station checkpoint and signs the
+    /// object using the auditor signer.
+    pub fn for_checkpoint(
+        auditor_id: Id,
+        signer: &dyn Signer,
+        checkpoint: &Checkpoint,
+    ) -> Result<Self> {
+        Self::new(
+            auditor_id,
+            signer,
+            checkpoint.station_id.clone(),
+            checkpoint.qso_count,
+            checkpoint.merkle_root.clone(),
+            Some(checkpoint.id.clone()),
+        )
+    }
+
+    /// Creates a new Attestation over the station log, in log order, and
+    /// signs the object using the auditor signer.
+    pub fn for_log(
+        auditor_id: Id,
+        signer: &dyn Signer,
+        station_id: Id,
+        qsos: &[Qso],
+    ) -> Result<Self> {
+        if qsos.iter().any(|qso| qso.station_id != station_id) {
+            bail!("qso station mismatch");
+        }
+
+        let ids: Vec<Id> = qsos.iter().map(|qso| qso.id.clone()).collect();
+        Self::new(
+            auditor_id,
+            signer,
+            station_id,
+            ids.len() as u64,
+            merkle::root(&ids),
+            None,
+        )
+    }
+
+    fn new(
+        auditor_id: Id,
+        signer: &dyn Signer,
+        station_id: Id,
+        qso_count: u64,
+        merkle_root: Id,
+        checkpoint_id: Option<Id>,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(
+            &auditor_id,
+            &station_id,
+            qso_count,
+            &merkle_root,
+            checkpoint_id.as_ref(),
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let attestation = Self {
+            id,
+            auditor_id,
+            station_id,
+            qso_count,
+            merkle_root,
+            checkpoint_id,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        attestation.validate()?;
+
+        Ok(attestation)
+    }
+
+    /// Verify the object signature against the auditor public key.
+    pub fn verify(&self, auditor_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(auditor_pub_key)
+    }
+
+    /// Verify the object signature against the key of the auditor, which must
+    /// be an anchor of the trust store with the [`Role::Auditor`] role.
+    pub fn verify_trusted(&self, trust_store: &TrustStore) -> Result<()> {
+        let anchor = trust_store
+            .anchor(&self.auditor_id)
+            .filter(|anchor| anchor.roles.contains(&Role::Auditor))
+            .ok_or_else(|| anyhow!("untrusted auditor {}", self.auditor_id))?;
+        self.verify(&anchor.pub_key)
+    }
+
+    /// Verify that the log, in log order, starts with the QSOs the auditor
+    /// reviewed.
+    pub fn verify_log(&self, qsos: &[Qso]) -> Result<()> {
+        covered_qsos(&self.station_id, self.qso_count, &self.merkle_root, qsos).map(|_| ())
+    }
+
+    /// Verify that the QSO is part of the reviewed log, e.g. a QSO submitted
+    /// for an award.
+    pub fn verify_qso(&self, qso: &Qso, proof: &MerkleProof) -> Result<()> {
+        if qso.station_id != self.station_id {
+            bail!("qso not in the reviewed log");
+        }
+
+        if proof.leaf_count as u64 != self.qso_count {
+            bail!("invalid proof leaf count");
+        }
+
+        merkle::verify_proof(&self.merkle_root, &qso.id, proof)
+    }
+
+    fn compute_id(
+        auditor_id: &Id,
+        station_id: &Id,
+        qso_count: u64,
+        merkle_root: &Id,
+        checkpoint_id: Option<&Id>,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                auditor_id,
+                station_id,
+                qso_count,
+                merkle_root,
+                checkpoint_id,
+                created_at,
+                version,
+            ),
+        )
+    }
+}
+
+impl Validate for Attestation {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.auditor_id == self.station_id {
+            bail!("station attesting its own log");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for Attestation {
+    const KIND: Kind = Kind::Attestation;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.auditor_id,
+            &self.station_id,
+            self.qso_count,
+            &self.merkle_root,
+            self.checkpoint_id.as_ref(),
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attestation::Attestation;
+    use crate::merkle;
+    use crate::trust::{Anchor, Role, TrustStore};
+    use crate::{test_vectors, Checkpoint, Qso};
+
+    #[test]
+    fn test_attestation() {
+        let station = test_vectors::station();
+        let auditor = test_vectors::counterparty();
+        let auditor_keys = test_vectors::counterparty_keypair();
+        let log: Vec<Qso> = vec![test_vectors::qso()];
+
+        let checkpoint =
+            Checkpoint::new(station.id.clone(), &test_vectors::station_keypair(), &log).unwrap();
+        let attestation =
+            Attestation::for_checkpoint(auditor.id.clone(), &auditor_keys, &checkpoint).unwrap();
+        assert_eq!(attestation.checkpoint_id.as_ref(), Some(&checkpoint.id));
+
+        let json_str = serde_json::to_string(&attestation).unwrap();
+        let attestation: Attestation = serde_json::from_str(&json_str).unwrap();
+        attestation.verify(&auditor.pub_key).unwrap();
+        assert!(attestation.verify(&station.pub_key).is_err());
+        attestation.verify_log(&log).unwrap();
+        assert!(attestation.verify_log(&[]).is_err());
+
+        let proof = merkle::prove(&[log[0].id.clone()], 0).unwrap();
+        attestation.verify_qso(&log[0], &proof).unwrap();
+
+        let mut trust_store = TrustStore::new();
+        assert!(attestation.verify_trusted(&trust_store).is_err());
+        trust_store
+            .add_anchor(Anchor {
+                station_id: auditor.id.clone(),
+                pub_key: auditor.pub_key,
+                roles: vec![Role::Auditor],
+            })
+            .unwrap();
+        attestation.verify_trusted(&trust_store).unwrap();
+
+        let from_log =
+            Attestation::for_log(auditor.id.clone(), &auditor_keys, station.id.clone(), &log)
+                .unwrap();
+        assert_eq!(from_log.merkle_root, checkpoint.merkle_root);
+        assert!(from_log.checkpoint_id.is_none());
+
+        assert!(Attestation::for_log(station.id.clone(), &auditor_keys, station.id, &log).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::merkle::{self, MerkleProof};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{text, version, Id, Kind};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+const AWARD_MAX_LEN: usize = 64;
+const LEVEL_MAX_LEN: usize = 32;
+
+/// An award endorsement issued by an award sponsor station.
+///
+/// The certificate lists the qualifying QSO ids and their merkle root. The ids
+/// may be left out of published copies, in that case single QSOs can still be
+/// proven part of the award with a merkle proof against the root.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AwardCertificate {
+    pub id: Id,
+    pub sponsor_id: Id,
+    pub recipient_id: Id,
+    pub award: String,
+    pub level: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub qso_ids: Vec<Id>,
+    pub merkle_root: Id,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl AwardCertificate {
+    /// Current version of the award certificate object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the award certificate verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new AwardCertificate and signs the object using the sponsor
+    /// signer.
+    pub fn new(
+        sponsor_id: Id,
+        signer: &dyn Signer,
+        recipient_id: Id,
+        award: String,
+        level: String,
+        qso_ids: Vec<Id>,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let merkle_root = merkle::root(&qso_ids);
+
+        let id = Self::compute_id(
+            &sponsor_id,
+            &recipient_id,
+            &award,
+            &level,
+            &merkle_root,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let certificate = Self {
+            id,
+            sponsor_id,
+            recipient_id,
+            award,
+            level,
+            qso_ids,
+            merkle_root,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        certificate.validate()?;
+
+        Ok(certificate)
+    }
+
+    /// Verify the object signature against the sponsor public key.
+    pub fn verify(&self, sponsor_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(sponsor_pub_key)
+    }
+
+    /// Verify that the QSO is one of the qualifying QSOs of the award.
+    pub fn verify_qso(&self, qso_id: &Id, proof: &MerkleProof) -> Result<()> {
+        merkle::verify_proof(&self.merkle_root, qso_id, proof)
+    }
+
+    /// Returns a copy of the certificate without the QSO ids list. The copy
+    /// keeps the same id and signature.
+    pub fn without_qso_ids(&self) -> Self {
+        Self {
+            qso_ids: vec![],
+            ..self.clone()
+        }
+    }
+
+    /// The id covers the merkle root instead of the QSO ids, so the ids list
+    /// can be stripped without invalidating the signature.
+    fn compute_id(
+        sponsor_id: &Id,
+        recipient_id: &Id,
+        award: &str,
+        level: &str,
+        merkle_root: &Id,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                sponsor_id,
+                recipient_id,
+                award,
+                level,
+                merkle_root,
+                created_at,
+                version,
+            ),
+        )
+    }
+}
+
+impl Validate for AwardCertificate {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if !text::is_valid_required(&self.award, AWARD_MAX_LEN) {
+            bail!("invalid award");
+        }
+
+        if !text::is_valid(&self.level, LEVEL_MAX_LEN) {
+            bail!("invalid level");
+        }
+
+        if !self.qso_ids.is_empty() && merkle::root(&self.qso_ids) != self.merkle_root {
+            bail!("merkle root mismatch");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for AwardCertificate {
+    const KIND: Kind = Kind::AwardCertificate;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.sponsor_id,
+            &self.recipient_id,
+            &self.award,
+            &self.level,
+            &self.merkle_root,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::awards::AwardCertificate;
+    use crate::keys::generate_keypair;
+    use crate::merkle;
+    use crate::{Id, Station};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_award_certificate() {
+        let sponsor_keys = generate_keypair();
+        let recipient_keys = generate_keypair();
+
+        let sponsor = Station::new(
+            &sponsor_keys,
+            "LU4AA".to_string(),
+            "Radio Club Argentino".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let recipient = Station::new(
+            &recipient_keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso_ids: Vec<Id> = (0..5).map(|i| Id::new(&i.to_string())).collect();
+
+        let award = AwardCertificate::new(
+            sponsor.id.clone(),
+            &sponsor_keys,
+            recipient.id.clone(),
+            "TDA".to_string(),
+            "Gold".to_string(),
+            qso_ids.clone(),
+        )
+        .unwrap();
+
+        award.verify(&sponsor.pub_key).unwrap();
+        assert!(award.verify(&recipient.pub_key).is_err());
+
+        let json_msg = serde_json::to_string(&award.without_qso_ids()).unwrap();
+        let stripped: AwardCertificate = serde_json::from_str(&json_msg).unwrap();
+        stripped.verify(&sponsor.pub_key).unwrap();
+
+        let proof = merkle::prove(&qso_ids, 3).unwrap();
+        stripped.verify_qso(&qso_ids[3], &proof).unwrap();
+        assert!(stripped.verify_qso(&qso_ids[2], &proof).is_err());
+
+        let mut tampered = award.clone();
+        tampered.qso_ids.pop();
+        assert!(tampered.verify(&sponsor.pub_key).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::Band;
+use crate::contest::ModeCategory;
+use crate::event::Event;
+use crate::store::Store;
+use crate::{Entity, Id, Logbook, Qso, Station};
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Columns of the application data file, named after the ADIF fields the
+/// ARRL checkers read.
+const HEADER: &[&str] = &[
+    "ENTITY",
+    "CALL",
+    "QSO_DATE",
+    "TIME_ON",
+    "BAND",
+    "MODE",
+    "QSL_RCVD_VIA",
+    "CONFIRMATION_ID",
+];
+
+/// A confirmed QSO submitted for the DXCC award.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DxccRecord {
+    /// The entity of the worked station, as declared in its station object.
+    pub entity: Entity,
+    pub qso: Qso,
+    /// The id of the QSO of the worked station confirming the contact.
+    pub confirmation: Id,
+}
+
+/// The QSOs of an ARRL DXCC award application: the first confirmed QSO
+/// with every entity on every band and [`ModeCategory`], which covers the
+/// mixed award and its band and mode endorsements.
+///
+/// Confirmations are the signed QSOs of the worked stations, submitted as
+/// electronic QSLs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DxccApplication {
+    records: BTreeMap<(String, Band, ModeCategory), DxccRecord>,
+}
+
+impl DxccApplication {
+    /// Creates an empty DxccApplication.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects the confirmed QSOs of the logbook, see
+    /// [`Logbook::confirmations_for`].
+    pub fn from_logbook<S: Store>(logbook: &Logbook<S>) -> Result<Self> {
+        let mut application = Self::new();
+        for qso in logbook.qsos()? {
+            for confirmation in logbook.confirmations_for(&qso.id)? {
+                if let Some(Event::Station(worked)) =
+                    logbook.store().get(&confirmation.station_id)?
+                {
+                    application.add(&qso, &worked, &confirmation);
+                }
+            }
+        }
+        Ok(application)
+    }
+
+    /// Adds the QSO confirmed by the worked station, returns true if it's
+    /// the first one with the entity on the band and mode category. The
+    /// earliest QSO of every entity, band and mode category is kept. The
+    /// confirmation must have been verified, e.g. with
+    /// [`Logbook::confirmations_for`].
+    pub fn add(&mut self, qso: &Qso, worked: &Station, confirmation: &Qso) -> bool {
+        let Some(band) = Band::from_freq(qso.freq) else {
+            return false;
+        };
+        if worked.country == Entity::InternationalWaters {
+            return false;
+        }
+
+        let slot = (
+            worked.country.to_string(),
+            band,
+            ModeCategory::of(&qso.mode),
+        );
+        let record = DxccRecord {
+            entity: worked.country,
+            qso: qso.clone(),
+            confirmation: confirmation.id.clone(),
+        };
+
+        match self.records.get(&slot) {
+            Some(stored) if stored.qso.datetime <= qso.datetime => false,
+            stored => {
+                let new = stored.is_none();
+                self.records.insert(slot, record);
+                new
+            }
+        }
+    }
+
+    /// Returns the records, by entity, band and mode category.
+    pub fn records(&self) -> impl Iterator<Item = &DxccRecord> {
+        self.records.values()
+    }
+
+    /// Returns the number of entities confirmed, on any band and mode.
+    pub fn entities(&self) -> usize {
+        let mut entities: Vec<&String> = self.records.keys().map(|(entity, _, _)| entity).collect();
+        entities.dedup();
+        entities.len()
+    }
+
+    /// Writes the application as CSV with a header row, one record per line.
+    /// Times are UTC.
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<()> {
+        let mut writer = ::csv::Writer::from_writer(writer);
+        writer.write_record(HEADER)?;
+
+        for ((_, band, _), record) in &self.records {
+            let datetime = i64::try_from(record.qso.datetime)
+                .ok()
+                .and_then(|datetime| DateTime::from_timestamp(datetime, 0))
+                .ok_or_else(|| anyhow!("invalid datetime {}", record.qso.datetime))?;
+
+            writer.write_record([
+                record.entity.to_string(),
+                record.qso.callsign.clone(),
+                datetime.format("%Y%m%d").to_string(),
+                datetime.format("%H%M").to_string(),
+                band.name().to_uppercase(),
+                record.qso.mode.clone(),
+                "E".to_string(),
+                record.confirmation.to_string(),
+            ])?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::awards::DxccApplication;
+    use crate::event::Event;
+    use crate::store::{MemoryStore, Store};
+    use crate::{test_vectors, Id, Logbook, QsoData};
+
+    #[test]
+    fn test_dxcc_application() {
+        let mut logbook = Logbook::new(
+            test_vectors::counterparty_keypair(),
+            test_vectors::counterparty(),
+            MemoryStore::new(),
+        )
+        .unwrap();
+
+        let data = |datetime, freq| QsoData {
+            station_id: Id::new("ignored"),
+            callsign: test_vectors::station().callsign,
+            datetime,
+            freq,
+            comments: String::new(),
+            ..test_vectors::qso_data()
+        };
+        let first = logbook
+            .log_qso(data(test_vectors::CREATED_AT + 60, 14_200_000))
+            .unwrap();
+        logbook
+            .log_qso(data(test_vectors::CREATED_AT + 120, 14_210_000))
+            .unwrap();
+        logbook
+            .log_qso(data(test_vectors::CREATED_AT, 7_100_000))
+            .unwrap();
+
+        let store = logbook.store_mut();
+        store
+            .insert(Event::Station(test_vectors::station()))
+            .unwrap();
+        store.insert(Event::Qso(test_vectors::qso())).unwrap();
+
+        let application = DxccApplication::from_logbook(&logbook).unwrap();
+        assert_eq!(application.entities(), 1);
+        let records: Vec<_> = application.records().collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].qso, first);
+        assert_eq!(records[0].confirmation, test_vectors::qso().id);
+
+        let mut csv = vec![];
+        application.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            format!(
+                "ENTITY,CALL,QSO_DATE,TIME_ON,BAND,MODE,QSL_RCVD_VIA,CONFIRMATION_ID\n\
+                 AR,LU4EV,20240101,2038,20M,SSB,E,{}\n",
+                test_vectors::qso().id
+            )
+        );
+    }
+}
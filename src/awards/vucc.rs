@@ -0,0 +1,192 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::Band;
+use crate::{geo, CoSignedQso, Id};
+use anyhow::{bail, Result};
+use secp256k1::XOnlyPublicKey;
+use std::collections::BTreeMap;
+
+/// Lowest frequency counted for VUCC, in Hz.
+const VUCC_MIN_FREQ: u64 = 50_000_000;
+
+/// Tracks the unique grid squares confirmed per band above 50 MHz for the
+/// VHF/UHF Century Club award.
+///
+/// Only QSOs confirmed by the counterparty ( [`CoSignedQso`] ) carrying the
+/// contacted station grid square are counted. Grid squares are counted by
+/// their 4 characters field and square.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VuccTracker {
+    grids: BTreeMap<Band, BTreeMap<String, Id>>,
+}
+
+impl VuccTracker {
+    /// Creates an empty VuccTracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verifies the confirmed QSO and counts its grid square, returns true if
+    /// the grid square is new for the band.
+    pub fn add(
+        &mut self,
+        confirmed: &CoSignedQso,
+        station_pub_key: &XOnlyPublicKey,
+        counterparty_pub_key: &XOnlyPublicKey,
+    ) -> Result<bool> {
+        confirmed.verify(station_pub_key, counterparty_pub_key)?;
+
+        let qso = &confirmed.qso;
+
+        if qso.freq < VUCC_MIN_FREQ {
+            bail!("frequency below 50 MHz");
+        }
+
+        let Some(band) = Band::from_freq(qso.freq) else {
+            bail!("frequency out of band");
+        };
+
+        let Some(gridsquare) = qso.gridsquare() else {
+            bail!("missing grid square");
+        };
+
+        if !geo::is_valid_grid(gridsquare) || gridsquare.len() < 4 {
+            bail!("invalid grid square");
+        }
+
+        let grid = gridsquare[..4].to_ascii_uppercase();
+        let grids = self.grids.entry(band).or_default();
+
+        if grids.contains_key(&grid) {
+            return Ok(false);
+        }
+
+        grids.insert(grid, qso.id.clone());
+
+        Ok(true)
+    }
+
+    /// Returns the number of grid squares confirmed on the band.
+    pub fn count(&self, band: Band) -> usize {
+        self.grids.get(&band).map(BTreeMap::len).unwrap_or(0)
+    }
+
+    /// Returns the grid squares confirmed on the band.
+    pub fn grids(&self, band: Band) -> impl Iterator<Item = &str> {
+        self.grids
+            .get(&band)
+            .into_iter()
+            .flat_map(|grids| grids.keys().map(String::as_str))
+    }
+
+    /// Returns the ids of the QSOs confirming each grid square on the band,
+    /// to be referenced by an [`crate::awards::AwardCertificate`].
+    pub fn qso_ids(&self, band: Band) -> Vec<Id> {
+        self.grids
+            .get(&band)
+            .map(|grids| grids.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::awards::VuccTracker;
+    use crate::bandplan::Band;
+    use crate::cosign::{nonce, CoSignSession};
+    use crate::keys::generate_keypair;
+    use crate::qso::GRIDSQUARE;
+    use crate::{test_vectors, CoSignedQso, Qso, QsoData, Station};
+    use codes_iso_3166::part_1::CountryCode;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_vucc() {
+        let keys = generate_keypair();
+        let counterparty_keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let counterparty = Station::new(
+            &counterparty_keys,
+            "CX2ABC".to_string(),
+            "Radio Club Uruguayo".to_string(),
+            CountryCode::UY,
+        )
+        .unwrap();
+
+        let confirmed = |freq, grid: &str| {
+            let mut extra = BTreeMap::new();
+            extra.insert(GRIDSQUARE.to_string(), json!(grid));
+
+            let qso = Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    callsign: counterparty.callsign.clone(),
+                    freq,
+                    datetime: 1704141426,
+                    extra,
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            );
+
+            let (station_nonce, station_pub_nonce) = nonce().unwrap();
+            let (counterparty_nonce, counterparty_pub_nonce) = nonce().unwrap();
+            let session = CoSignSession::new(
+                qso,
+                counterparty.id.clone(),
+                station.pub_key,
+                counterparty.pub_key,
+                station_pub_nonce,
+                counterparty_pub_nonce,
+            )
+            .unwrap();
+            let station_sig = session.sign(&keys, station_nonce).unwrap();
+            let counterparty_sig = session
+                .sign(&counterparty_keys, counterparty_nonce)
+                .unwrap();
+            session.finish(&station_sig, &counterparty_sig).unwrap()
+        };
+
+        let mut tracker = VuccTracker::new();
+        let mut add = |qso: &CoSignedQso| tracker.add(qso, &station.pub_key, &counterparty.pub_key);
+
+        assert!(add(&confirmed(144_100_000, "GF15ak")).unwrap());
+        assert!(!add(&confirmed(144_200_000, "gf15BB")).unwrap());
+        assert!(add(&confirmed(144_200_000, "GF14")).unwrap());
+        assert!(add(&confirmed(432_100_000, "GF15")).unwrap());
+        assert!(add(&confirmed(14_250_000, "GF15")).is_err());
+
+        let mut unconfirmed = confirmed(144_100_000, "GF16");
+        unconfirmed.counterparty_station_id = station.id.clone();
+        assert!(add(&unconfirmed).is_err());
+
+        assert_eq!(tracker.count(Band::M2), 2);
+        assert_eq!(tracker.count(Band::Cm70), 1);
+        assert_eq!(tracker.count(Band::M6), 0);
+        assert_eq!(
+            tracker.grids(Band::M2).collect::<Vec<_>>(),
+            vec!["GF14", "GF15"]
+        );
+        assert_eq!(tracker.qso_ids(Band::M2).len(), 2);
+    }
+}
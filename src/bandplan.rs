@@ -0,0 +1,352 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ITU amateur band allocations.
+//!
+//! Frequencies are expressed in Hz. The allocations are the ITU Radio
+//! Regulations amateur allocations per region, national band plans may be
+//! narrower.
+
+use crate::contest::ModeCategory;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// ITU region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Region {
+    /// Europe, Africa, Middle East and northern Asia.
+    R1,
+    /// The Americas.
+    R2,
+    /// Asia-Pacific.
+    R3,
+}
+
+/// Amateur band.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Band {
+    #[serde(rename = "2200m")]
+    M2200,
+    #[serde(rename = "630m")]
+    M630,
+    #[serde(rename = "160m")]
+    M160,
+    #[serde(rename = "80m")]
+    M80,
+    #[serde(rename = "60m")]
+    M60,
+    #[serde(rename = "40m")]
+    M40,
+    #[serde(rename = "30m")]
+    M30,
+    #[serde(rename = "20m")]
+    M20,
+    #[serde(rename = "17m")]
+    M17,
+    #[serde(rename = "15m")]
+    M15,
+    #[serde(rename = "12m")]
+    M12,
+    #[serde(rename = "10m")]
+    M10,
+    #[serde(rename = "6m")]
+    M6,
+    #[serde(rename = "2m")]
+    M2,
+    #[serde(rename = "1.25m")]
+    M125,
+    #[serde(rename = "70cm")]
+    Cm70,
+    #[serde(rename = "23cm")]
+    Cm23,
+}
+
+/// Bands a licensee is allowed to operate on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum LicenseClass {
+    /// All the amateur allocations of the region.
+    #[default]
+    Full,
+    /// Only the listed bands.
+    Bands(Vec<Band>),
+}
+
+impl LicenseClass {
+    fn allows(&self, band: Band) -> bool {
+        match self {
+            LicenseClass::Full => true,
+            LicenseClass::Bands(bands) => bands.contains(&band),
+        }
+    }
+}
+
+/// ( band, lower edge, upper edge ) for each region, edges in Hz.
+type Allocation = (Band, u64, u64);
+
+const KHZ: u64 = 1_000;
+const MHZ: u64 = 1_000_000;
+
+const COMMON: &[Allocation] = &[
+    (Band::M2200, 135_700, 137_800),
+    (Band::M630, 472 * KHZ, 479 * KHZ),
+    (Band::M60, 5_351_500, 5_366_500),
+    (Band::M30, 10_100 * KHZ, 10_150 * KHZ),
+    (Band::M20, 14_000 * KHZ, 14_350 * KHZ),
+    (Band::M17, 18_068 * KHZ, 18_168 * KHZ),
+    (Band::M15, 21_000 * KHZ, 21_450 * KHZ),
+    (Band::M12, 24_890 * KHZ, 24_990 * KHZ),
+    (Band::M10, 28 * MHZ, 29_700 * KHZ),
+    (Band::Cm23, 1_240 * MHZ, 1_300 * MHZ),
+];
+
+const REGION_1: &[Allocation] = &[
+    (Band::M160, 1_810 * KHZ, 2_000 * KHZ),
+    (Band::M80, 3_500 * KHZ, 3_800 * KHZ),
+    (Band::M40, 7_000 * KHZ, 7_200 * KHZ),
+    (Band::M6, 50 * MHZ, 52 * MHZ),
+    (Band::M2, 144 * MHZ, 146 * MHZ),
+    (Band::Cm70, 430 * MHZ, 440 * MHZ),
+];
+
+const REGION_2: &[Allocation] = &[
+    (Band::M160, 1_800 * KHZ, 2_000 * KHZ),
+    (Band::M80, 3_500 * KHZ, 4_000 * KHZ),
+    (Band::M40, 7_000 * KHZ, 7_300 * KHZ),
+    (Band::M6, 50 * MHZ, 54 * MHZ),
+    (Band::M2, 144 * MHZ, 148 * MHZ),
+    (Band::M125, 222 * MHZ, 225 * MHZ),
+    (Band::Cm70, 420 * MHZ, 450 * MHZ),
+];
+
+const REGION_3: &[Allocation] = &[
+    (Band::M160, 1_800 * KHZ, 2_000 * KHZ),
+    (Band::M80, 3_500 * KHZ, 3_900 * KHZ),
+    (Band::M40, 7_000 * KHZ, 7_200 * KHZ),
+    (Band::M6, 50 * MHZ, 54 * MHZ),
+    (Band::M2, 144 * MHZ, 148 * MHZ),
+    (Band::Cm70, 430 * MHZ, 440 * MHZ),
+];
+
+impl Region {
+    fn allocations(&self) -> impl Iterator<Item = &'static Allocation> {
+        let regional = match self {
+            Region::R1 => REGION_1,
+            Region::R2 => REGION_2,
+            Region::R3 => REGION_3,
+        };
+        COMMON.iter().chain(regional)
+    }
+}
+
+impl Band {
+    /// Every band, from the lowest to the highest frequency.
+    pub const ALL: &'static [Band] = &[
+        Band::M2200,
+        Band::M630,
+        Band::M160,
+        Band::M80,
+        Band::M60,
+        Band::M40,
+        Band::M30,
+        Band::M20,
+        Band::M17,
+        Band::M15,
+        Band::M12,
+        Band::M10,
+        Band::M6,
+        Band::M2,
+        Band::M125,
+        Band::Cm70,
+        Band::Cm23,
+    ];
+
+    /// Returns the band containing the frequency in any region.
+    pub fn from_freq(freq: u64) -> Option<Band> {
+        [Region::R1, Region::R2, Region::R3]
+            .iter()
+            .find_map(|region| band_for(freq, *region))
+    }
+
+    /// Returns the band name ( e.g. `20m` ).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Band::M2200 => "2200m",
+            Band::M630 => "630m",
+            Band::M160 => "160m",
+            Band::M80 => "80m",
+            Band::M60 => "60m",
+            Band::M40 => "40m",
+            Band::M30 => "30m",
+            Band::M20 => "20m",
+            Band::M17 => "17m",
+            Band::M15 => "15m",
+            Band::M12 => "12m",
+            Band::M10 => "10m",
+            Band::M6 => "6m",
+            Band::M2 => "2m",
+            Band::M125 => "1.25m",
+            Band::Cm70 => "70cm",
+            Band::Cm23 => "23cm",
+        }
+    }
+}
+
+impl FromStr for Band {
+    type Err = anyhow::Error;
+
+    /// Parses a band name ( e.g. `20m` ).
+    fn from_str(s: &str) -> Result<Self> {
+        match Band::ALL.iter().find(|band| band.name() == s) {
+            Some(band) => Ok(*band),
+            None => bail!("unknown band {}", s),
+        }
+    }
+}
+
+impl Display for Band {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Returns the band containing the frequency in the region.
+pub fn band_for(freq: u64, region: Region) -> Option<Band> {
+    region
+        .allocations()
+        .find(|(_, lower, upper)| freq >= *lower && freq <= *upper)
+        .map(|(band, _, _)| *band)
+}
+
+/// Checks that the frequency is inside an amateur allocation of the region
+/// which the license class allows, returning the band.
+pub fn validate_frequency(freq: u64, region: Region, license_class: &LicenseClass) -> Result<Band> {
+    let Some(band) = band_for(freq, region) else {
+        bail!("frequency {} Hz out of band in region {:?}", freq, region);
+    };
+
+    if !license_class.allows(band) {
+        bail!("band {} not allowed by license class", band);
+    }
+
+    Ok(band)
+}
+
+/// ( band, upper edge of the CW and digital segment, lower edge of the phone
+/// segment ) in Hz. The edges are the widest across regions, so only
+/// combinations no band plan allows are flagged.
+type Segments = (Band, u64, u64);
+
+const SEGMENTS: &[Segments] = &[
+    (Band::M160, 1_838 * KHZ, 1_850 * KHZ),
+    (Band::M80, 3_570 * KHZ, 3_600 * KHZ),
+    (Band::M40, 7_040 * KHZ, 7_100 * KHZ),
+    (Band::M30, 10_150 * KHZ, u64::MAX),
+    (Band::M20, 14_070 * KHZ, 14_150 * KHZ),
+    (Band::M17, 18_095 * KHZ, 18_110 * KHZ),
+    (Band::M15, 21_070 * KHZ, 21_200 * KHZ),
+    (Band::M12, 24_915 * KHZ, 24_930 * KHZ),
+    (Band::M10, 28_200 * KHZ, 28_300 * KHZ),
+    (Band::M6, 50_100 * KHZ, u64::MAX),
+];
+
+/// Dial frequencies of FT8 and FT4, in Hz.
+const FT8_FT4_FREQS: &[u64] = &[
+    1_840 * KHZ,
+    3_573 * KHZ,
+    3_575 * KHZ,
+    7_074 * KHZ,
+    7_047_500,
+    10_136 * KHZ,
+    10_140 * KHZ,
+    14_074 * KHZ,
+    14_080 * KHZ,
+    18_100 * KHZ,
+    18_104 * KHZ,
+    21_074 * KHZ,
+    21_140 * KHZ,
+    24_915 * KHZ,
+    24_919 * KHZ,
+    28_074 * KHZ,
+    28_180 * KHZ,
+    50_313 * KHZ,
+    50_318 * KHZ,
+    144_174 * KHZ,
+];
+
+/// Audio passband above the dial frequency used by FT8 and FT4.
+const FT8_FT4_PASSBAND: u64 = 3 * KHZ;
+
+/// Returns false if the mode on the frequency is almost certainly a typo,
+/// e.g. FT8 on the 20m phone segment or SSB on the FT8 frequency.
+///
+/// Only phone modes, FT8 and FT4 are checked, other modes and frequencies
+/// outside the amateur bands are considered plausible.
+pub fn is_plausible_mode(freq: u64, mode: &str) -> bool {
+    let on_ft8_ft4 = FT8_FT4_FREQS
+        .iter()
+        .any(|dial| freq >= *dial && freq <= dial + FT8_FT4_PASSBAND);
+    let segments =
+        Band::from_freq(freq).and_then(|band| SEGMENTS.iter().find(|(other, _, _)| *other == band));
+
+    match mode.to_ascii_uppercase().as_str() {
+        "FT8" | "FT4" => segments.is_none_or(|(_, _, phone)| freq < *phone),
+        mode if ModeCategory::of(mode) == ModeCategory::Phone => {
+            !on_ft8_ft4 && segments.is_none_or(|(_, narrow, _)| freq > *narrow)
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::{
+        band_for, is_plausible_mode, validate_frequency, Band, LicenseClass, Region,
+    };
+
+    #[test]
+    fn test_band_for() {
+        assert_eq!(band_for(14_074_000, Region::R2), Some(Band::M20));
+        assert_eq!(band_for(7_250_000, Region::R2), Some(Band::M40));
+        assert_eq!(band_for(7_250_000, Region::R1), None);
+        assert_eq!(band_for(1_704_141_426, Region::R2), None);
+        assert_eq!(Band::from_freq(223_500_000), Some(Band::M125));
+    }
+
+    #[test]
+    fn test_validate_frequency() {
+        assert!(validate_frequency(14_250_300, Region::R2, &LicenseClass::Full).is_ok());
+        assert!(validate_frequency(14_400_000, Region::R2, &LicenseClass::Full).is_err());
+
+        let novice = LicenseClass::Bands(vec![Band::M80, Band::M40, Band::M10]);
+        assert!(validate_frequency(7_100_000, Region::R2, &novice).is_ok());
+        assert!(validate_frequency(14_100_000, Region::R2, &novice).is_err());
+    }
+
+    #[test]
+    fn test_is_plausible_mode() {
+        assert!(is_plausible_mode(14_074_000, "FT8"));
+        assert!(is_plausible_mode(14_075_500, "ft8"));
+        assert!(!is_plausible_mode(14_250_000, "FT8"));
+        assert!(is_plausible_mode(14_250_000, "SSB"));
+        assert!(!is_plausible_mode(14_074_000, "SSB"));
+        assert!(!is_plausible_mode(14_030_000, "USB"));
+        assert!(!is_plausible_mode(7_075_000, "LSB"));
+        assert!(is_plausible_mode(7_150_000, "LSB"));
+        assert!(!is_plausible_mode(10_120_000, "SSB"));
+        assert!(is_plausible_mode(14_250_000, "CW"));
+        assert!(is_plausible_mode(14_074_000, "RTTY"));
+        assert!(is_plausible_mode(145_500_000, "FM"));
+    }
+}
@@ -0,0 +1,375 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::Band;
+use crate::callsign::{Callsign, CallsignPolicy, ItuCallsign};
+use crate::event::{Event, Filter};
+use crate::limits::{self, Limits};
+use crate::qso::{COMMENTS_MAX_LEN, MODE_MAX_LEN};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::store::Store;
+use crate::time::unix_timstamp;
+use crate::{geo, text, version, Id, Kind, SwlReport, Tag};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// Maximum difference in Hz between the frequency of a beacon and the one
+/// of a reception report, receivers are rarely calibrated better.
+pub const BEACON_FREQ_TOLERANCE: u64 = 1_000;
+
+/// When a beacon transmits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BeaconSchedule {
+    /// Transmits around the clock.
+    Continuous,
+    /// Transmits `duration` seconds every `period` seconds, starting `offset`
+    /// seconds into every period counted from the unix epoch, e.g. the 10
+    /// seconds every 3 minutes of the NCDXF/IARU beacons.
+    Cyclic {
+        period: u32,
+        offset: u32,
+        duration: u32,
+    },
+}
+
+impl BeaconSchedule {
+    /// Returns true if the beacon transmits at the time.
+    pub fn is_on_air(&self, datetime: u64) -> bool {
+        match *self {
+            BeaconSchedule::Continuous => true,
+            BeaconSchedule::Cyclic {
+                period,
+                offset,
+                duration,
+            } => {
+                let elapsed = datetime % u64::from(period.max(1));
+                elapsed >= u64::from(offset) && elapsed < u64::from(offset) + u64::from(duration)
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let BeaconSchedule::Cyclic {
+            period,
+            offset,
+            duration,
+        } = *self
+        {
+            if duration == 0 || u64::from(offset) + u64::from(duration) > u64::from(period) {
+                bail!("invalid schedule");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The details of a [`Beacon`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaconData {
+    /// The callsign transmitted, e.g. `LU4AA/B`.
+    pub callsign: String,
+    pub freq: u64,
+    pub mode: String,
+    /// The Maidenhead grid square of the transmitter.
+    pub grid: String,
+    pub schedule: BeaconSchedule,
+    pub comments: String,
+}
+
+/// A propagation beacon or another unattended transmitter, signed by the
+/// station owning it.
+///
+/// Beacons are tagged with their callsign and band, so relays can serve the
+/// beacons of a band. Listeners report a reception with an [`SwlReport`]
+/// whose station is the beacon id, see [`Beacon::heard_by`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Beacon {
+    pub id: Id,
+    /// The owner station.
+    pub station_id: Id,
+    pub callsign: String,
+    pub freq: u64,
+    pub mode: String,
+    pub grid: String,
+    pub schedule: BeaconSchedule,
+    pub comments: String,
+    pub tags: Vec<Tag>,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl Beacon {
+    /// Current version of the beacon object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the beacon verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new Beacon and signs the object using the owner signer.
+    pub fn new(station_id: Id, signer: &dyn Signer, data: BeaconData) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let callsign = Callsign::normalize(&data.callsign);
+
+        let mut tags = vec![Tag::Callsign(callsign.clone())];
+        tags.extend(Band::from_freq(data.freq).map(Tag::Band));
+
+        let callsign = callsign.to_string();
+        let id = Self::compute_id(
+            &station_id,
+            &callsign,
+            data.freq,
+            &data.mode,
+            &data.grid,
+            &data.schedule,
+            &data.comments,
+            &tags,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let beacon = Self {
+            id,
+            station_id,
+            callsign,
+            freq: data.freq,
+            mode: data.mode,
+            grid: data.grid,
+            schedule: data.schedule,
+            comments: data.comments,
+            tags,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        beacon.validate()?;
+
+        Ok(beacon)
+    }
+
+    /// Verify the object signature against the owner public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Returns true if the report is a reception of the beacon: addressed
+    /// to the beacon, with its base callsign since reports carry no
+    /// modifiers, within [`BEACON_FREQ_TOLERANCE`] of
+    /// its frequency and while it was on the air. The report signature is
+    /// not verified.
+    pub fn heard_by(&self, report: &SwlReport) -> bool {
+        report.station_id == self.id
+            && Callsign::normalize(&report.callsign).base()
+                == Callsign::normalize(&self.callsign).base()
+            && report.freq.abs_diff(self.freq) <= BEACON_FREQ_TOLERANCE
+            && self.schedule.is_on_air(report.datetime)
+    }
+
+    /// Returns the stored reception reports of the beacon, see
+    /// [`Beacon::heard_by`].
+    pub fn reports(&self, store: &dyn Store) -> Result<Vec<SwlReport>> {
+        Ok(store
+            .query(&Filter::new().kinds([Kind::SwlReport]))?
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::SwlReport(report) if self.heard_by(&report) => Some(report),
+                _ => None,
+            })
+            .collect())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compute_id(
+        station_id: &Id,
+        callsign: &str,
+        freq: u64,
+        mode: &str,
+        grid: &str,
+        schedule: &BeaconSchedule,
+        comments: &str,
+        tags: &[Tag],
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                station_id, callsign, freq, mode, grid, schedule, comments, tags, created_at,
+                version,
+            ),
+        )
+    }
+}
+
+impl Validate for Beacon {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        ItuCallsign.validate(&self.callsign)?;
+
+        if self.freq == 0 {
+            bail!("invalid freq");
+        }
+
+        if !text::is_valid_required(&self.mode, MODE_MAX_LEN) {
+            bail!("invalid mode");
+        }
+
+        if !geo::is_valid_grid(&self.grid) {
+            bail!("invalid grid square {}", self.grid);
+        }
+
+        self.schedule.validate()?;
+
+        if !text::is_valid_multiline(&self.comments, COMMENTS_MAX_LEN) {
+            bail!("invalid comments");
+        }
+
+        if self.tags.len() > crate::MAX_TAGS {
+            bail!("more than {} tags", crate::MAX_TAGS);
+        }
+        limits::check_tags(&self.tags, Limits::DEFAULT.tag)?;
+
+        Ok(())
+    }
+}
+
+impl Signable for Beacon {
+    const KIND: Kind = Kind::Beacon;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.callsign,
+            self.freq,
+            &self.mode,
+            &self.grid,
+            &self.schedule,
+            &self.comments,
+            &self.tags,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::Band;
+    use crate::beacon::{Beacon, BeaconData, BeaconSchedule};
+    use crate::event::{Event, Filter};
+    use crate::keys::generate_keypair;
+    use crate::propagation::Reception;
+    use crate::signable::Validate;
+    use crate::store::{MemoryStore, Store};
+    use crate::swl::{SwlReport, SwlReportData};
+    use crate::{test_vectors, Kind};
+
+    #[test]
+    fn test_beacon() {
+        let owner = test_vectors::station();
+        let schedule = BeaconSchedule::Cyclic {
+            period: 180,
+            offset: 30,
+            duration: 10,
+        };
+        let beacon = Beacon::new(
+            owner.id.clone(),
+            &test_vectors::station_keypair(),
+            BeaconData {
+                callsign: "lu4aa/b".to_string(),
+                freq: 14_100_000,
+                mode: "CW".to_string(),
+                grid: "GF05tk".to_string(),
+                schedule,
+                comments: "100 W to a vertical".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(beacon.callsign, "LU4AA/B");
+
+        let json_str = serde_json::to_string(&Event::Beacon(beacon.clone())).unwrap();
+        let event: Event = serde_json::from_str(&json_str).unwrap();
+        event.verify(&owner.pub_key).unwrap();
+
+        let listener = test_vectors::counterparty();
+        let report = |datetime, freq| {
+            SwlReport::new(
+                listener.id.clone(),
+                &test_vectors::counterparty_keypair(),
+                SwlReportData {
+                    station_id: beacon.id.clone(),
+                    callsign: "LU4AA".to_string(),
+                    datetime,
+                    freq,
+                    mode: "CW".to_string(),
+                    rst: "339".to_string(),
+                    comments: String::new(),
+                },
+            )
+            .unwrap()
+        };
+        let heard = report(1_704_067_235, 14_100_300);
+        assert!(beacon.heard_by(&heard));
+        assert!(!beacon.heard_by(&report(1_704_067_245, 14_100_300)));
+        assert!(!beacon.heard_by(&report(1_704_067_235, 14_102_000)));
+
+        let mut store = MemoryStore::new();
+        store.insert(event.clone()).unwrap();
+        store.insert(Event::SwlReport(heard.clone())).unwrap();
+        store
+            .insert(Event::SwlReport(report(1_704_067_000, 14_100_000)))
+            .unwrap();
+        assert_eq!(beacon.reports(&store).unwrap(), vec![heard.clone()]);
+        let reception = Reception::from_beacon_report(&listener, &beacon, &heard);
+        assert_eq!(reception.sender_grid.as_deref(), Some("GF05tk"));
+        let beacons = Filter::new().kinds([Kind::Beacon]).tags([Band::M20.into()]);
+        assert_eq!(store.query(&beacons).unwrap(), vec![event]);
+
+        let mut invalid = beacon.clone();
+        invalid.schedule = BeaconSchedule::Cyclic {
+            period: 180,
+            offset: 175,
+            duration: 10,
+        };
+        assert!(invalid.validate().is_err());
+        let mut invalid = beacon;
+        invalid.grid = "ZZ99".to_string();
+        assert!(invalid.validate().is_err());
+        assert!(invalid
+            .verify(&generate_keypair().x_only_public_key().0)
+            .is_err());
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::event::{Event, Filter};
+use crate::logbook::Confirmations;
+use crate::store::{MemoryStore, Store};
+use crate::trust::{Role, TrustStore};
+use crate::{Certificate, Id, Kind, Qso, Station};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+
+/// Everything needed to verify the QSOs of a station without network access:
+/// the station, its certificate chain from a root anchor, the QSOs, the
+/// confirmations of the counterparties and the stations which signed the
+/// certificates and confirmations.
+///
+/// The bundle itself isn't signed, every object inside it is. It's written
+/// as a single JSON document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub station: Station,
+    /// Certificate chain, from the one issued by a root anchor to the one
+    /// issued to the station. Empty if the station is an anchor itself.
+    pub certificates: Vec<Certificate>,
+    pub qsos: Vec<Qso>,
+    /// QSOs of the counterparties confirming the QSOs of the station.
+    pub confirmations: Vec<Qso>,
+    /// Certificate issuers and confirming stations.
+    pub stations: Vec<Station>,
+}
+
+impl Bundle {
+    /// Builds the bundle of the QSOs of the station.
+    ///
+    /// The certificates must contain the chain issued to the station, in any
+    /// order. The issuer stations, the confirmations and the confirming
+    /// stations are taken from the store.
+    pub fn build(
+        store: &dyn Store,
+        station: &Station,
+        certificates: &[Certificate],
+        qsos: &[Qso],
+    ) -> Result<Self> {
+        if qsos.iter().any(|qso| qso.station_id != station.id) {
+            bail!("qso station mismatch");
+        }
+
+        let mut chain = vec![];
+        let mut subject_id = &station.id;
+        while let Some(certificate) = certificates
+            .iter()
+            .find(|certificate| certificate.subject_id() == subject_id)
+        {
+            if chain.contains(certificate) {
+                bail!("certificate loop");
+            }
+            chain.push(certificate.clone());
+            subject_id = certificate.issuer_id();
+        }
+        chain.reverse();
+
+        let mut station_ids: BTreeSet<Id> = chain
+            .iter()
+            .map(|certificate| certificate.issuer_id().clone())
+            .collect();
+
+        let candidates = store.query(&Filter::new().kinds([Kind::Qso]))?;
+        let confirmations = Confirmations::new(store, station, &candidates)?;
+        let mut confirmation_ids = BTreeSet::new();
+        let mut bundle_confirmations = vec![];
+        for qso in qsos {
+            for confirmation in confirmations.for_qso(qso) {
+                station_ids.insert(confirmation.station_id.clone());
+                if confirmation_ids.insert(confirmation.id.clone()) {
+                    bundle_confirmations.push(confirmation);
+                }
+            }
+        }
+
+        let mut stations = vec![];
+        for id in &station_ids {
+            if let Some(Event::Station(issuer)) = store.get(id)? {
+                stations.push(issuer);
+            }
+        }
+
+        Ok(Self {
+            station: station.clone(),
+            certificates: chain,
+            qsos: qsos.to_vec(),
+            confirmations: bundle_confirmations,
+            stations,
+        })
+    }
+
+    /// Verifies every object of the bundle: the certificate chain must start
+    /// at a root anchor of the trust store and end at the station, the QSOs
+    /// must be signed by the station and every confirmation must confirm one
+    /// of them.
+    pub fn verify(&self, trust_store: &TrustStore) -> Result<()> {
+        self.station.verify()?;
+
+        let stations: BTreeMap<&Id, &Station> = self
+            .stations
+            .iter()
+            .map(|station| (&station.id, station))
+            .collect();
+
+        match self.certificates.first() {
+            Some(first) => {
+                let anchor = trust_store
+                    .anchor(first.issuer_id())
+                    .filter(|anchor| anchor.roles.contains(&Role::Root))
+                    .ok_or_else(|| anyhow!("chain not issued by a root anchor"))?;
+                first.verify(&anchor.pub_key)?;
+
+                for pair in self.certificates.windows(2) {
+                    if pair[1].issuer_id() != pair[0].subject_id() {
+                        bail!("broken certificate chain");
+                    }
+                    let issuer = stations
+                        .get(pair[1].issuer_id())
+                        .ok_or_else(|| anyhow!("missing issuer {}", pair[1].issuer_id()))?;
+                    issuer.verify()?;
+                    pair[1].verify(&issuer.pub_key)?;
+                }
+
+                let last = self.certificates.last().unwrap_or(first);
+                if last.subject_id() != &self.station.id {
+                    bail!("certificate chain doesn't end at the station");
+                }
+            }
+            None => {
+                if trust_store
+                    .anchor(&self.station.id)
+                    .is_none_or(|anchor| anchor.pub_key != self.station.pub_key)
+                {
+                    bail!("missing certificate chain");
+                }
+            }
+        }
+
+        for qso in &self.qsos {
+            if qso.station_id != self.station.id {
+                bail!("qso {} station mismatch", qso.id);
+            }
+            qso.verify(&self.station.pub_key)
+                .with_context(|| format!("invalid qso {}", qso.id))?;
+        }
+
+        let mut store = MemoryStore::new();
+        for station in stations.values() {
+            store.insert(Event::Station((*station).clone()))?;
+        }
+        let candidates: Vec<Event> = self.confirmations.iter().cloned().map(Event::Qso).collect();
+        let confirmations = Confirmations::new(&store, &self.station, &candidates)?;
+        let mut confirmed = BTreeSet::new();
+        for qso in &self.qsos {
+            for confirmation in confirmations.for_qso(qso) {
+                confirmed.insert(confirmation.id);
+            }
+        }
+        if let Some(unrelated) = self
+            .confirmations
+            .iter()
+            .find(|confirmation| !confirmed.contains(&confirmation.id))
+        {
+            bail!("invalid confirmation {}", unrelated.id);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the bundle as JSON.
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer(writer, self).context("failed to write bundle")
+    }
+
+    /// Reads a JSON bundle. The bundle must be verified with
+    /// [`Bundle::verify`] before use.
+    pub fn read<R: Read>(reader: R) -> Result<Self> {
+        serde_json::from_reader(reader).context("invalid bundle")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::keys::generate_keypair;
+    use crate::store::{MemoryStore, Store};
+    use crate::trust::{Anchor, Role, TrustStore};
+    use crate::{test_vectors, Bundle, Certificate, Qso, QsoData, Station};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_bundle() {
+        let root_keys = generate_keypair();
+        let root = Station::new(
+            &root_keys,
+            "LU1AA".to_string(),
+            "Radio Club Argentino".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        let club = test_vectors::counterparty();
+        let station = test_vectors::station();
+        let qso = test_vectors::qso();
+
+        let mut trust_store = TrustStore::new();
+        trust_store
+            .add_anchor(Anchor {
+                station_id: root.id.clone(),
+                pub_key: root.pub_key,
+                roles: vec![Role::Root],
+            })
+            .unwrap();
+
+        let certificates = [
+            Certificate::new(
+                club.id.clone(),
+                &test_vectors::counterparty_keypair(),
+                station.id.clone(),
+            ),
+            Certificate::new(root.id.clone(), &root_keys, club.id.clone()),
+        ];
+
+        let confirmation = Qso::new(
+            QsoData {
+                station_id: club.id.clone(),
+                callsign: station.callsign.clone(),
+                datetime: qso.datetime + 60,
+                freq: qso.freq,
+                mode: qso.mode.clone(),
+                comments: String::new(),
+                ..test_vectors::qso_data()
+            },
+            &test_vectors::counterparty_keypair(),
+        );
+
+        let mut store = MemoryStore::new();
+        for event in [
+            Event::Station(root.clone()),
+            Event::Station(club.clone()),
+            Event::Qso(confirmation.clone()),
+        ] {
+            store.insert(event).unwrap();
+        }
+
+        let bundle = Bundle::build(&store, &station, &certificates, &[qso]).unwrap();
+        assert_eq!(bundle.certificates[0], certificates[1]);
+        assert_eq!(bundle.confirmations, vec![confirmation]);
+
+        let mut file = vec![];
+        bundle.write(&mut file).unwrap();
+        let bundle = Bundle::read(file.as_slice()).unwrap();
+        bundle.verify(&trust_store).unwrap();
+
+        assert!(bundle.verify(&TrustStore::new()).is_err());
+
+        let mut forged = bundle.clone();
+        forged.certificates.remove(0);
+        assert!(forged.verify(&trust_store).is_err());
+
+        let mut forged = bundle.clone();
+        forged.confirmations[0].comments = "73".to_string();
+        assert!(forged.verify(&trust_store).is_err());
+
+        let mut forged = bundle;
+        forged.stations.clear();
+        assert!(forged.verify(&trust_store).is_err());
+    }
+}
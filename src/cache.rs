@@ -0,0 +1,175 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::signable::Signable;
+use crate::Id;
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use std::collections::{BTreeMap, HashMap};
+
+type CacheKey = (Id, XOnlyPublicKey, Signature);
+
+/// Hit and miss counters of a [`VerifyCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// LRU cache of verified signatures.
+///
+/// Objects synced from several relays are verified once: later verifications
+/// of the same id, public key and signature skip the signature check. The id
+/// is still recomputed from the object fields and the fields validated, so a
+/// tampered copy of a cached object is rejected.
+#[derive(Debug, Clone)]
+pub struct VerifyCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, u64>,
+    lru: BTreeMap<u64, CacheKey>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl VerifyCache {
+    /// Creates a cache holding up to `capacity` verified signatures.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Verify the object against the public key, skipping the signature check
+    /// if it was already verified.
+    pub fn verify<T: Signable>(&mut self, object: &T, pub_key: &XOnlyPublicKey) -> Result<()> {
+        let id = object.generate_id();
+        if id != *object.id() {
+            bail!("invalid id");
+        }
+
+        let key = (id, *pub_key, *object.sig());
+
+        if self.touch(&key) {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+            key.0.verify(pub_key, object.sig())?;
+            self.insert(key);
+        }
+
+        object.validate()
+    }
+
+    /// Returns the hit and miss counters.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Returns the number of cached signatures.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every cached signature, keeping the counters.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+    }
+
+    /// Marks the key as recently used, returns false if it isn't cached.
+    fn touch(&mut self, key: &CacheKey) -> bool {
+        let Some(tick) = self.entries.get_mut(key) else {
+            return false;
+        };
+        self.tick += 1;
+        let old_tick = std::mem::replace(tick, self.tick);
+        if let Some(key) = self.lru.remove(&old_tick) {
+            self.lru.insert(self.tick, key);
+        }
+        true
+    }
+
+    fn insert(&mut self, key: CacheKey) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.lru.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        self.tick += 1;
+        self.entries.insert(key.clone(), self.tick);
+        self.lru.insert(self.tick, key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::{CacheStats, VerifyCache};
+    use crate::keys::generate_keypair;
+    use crate::Station;
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_verify_cache() {
+        let station = |callsign: &str| {
+            Station::new(
+                &generate_keypair(),
+                callsign.to_string(),
+                "Radio Club Caseros".to_string(),
+                CountryCode::AR,
+            )
+            .unwrap()
+        };
+
+        let lu4ev = station("LU4EV");
+        let lw3dzr = station("LW3DZR");
+        let lu1aa = station("LU1AA");
+
+        let mut cache = VerifyCache::new(2);
+        cache.verify(&lu4ev, &lu4ev.pub_key).unwrap();
+        cache.verify(&lu4ev, &lu4ev.pub_key).unwrap();
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1 });
+
+        assert!(cache.verify(&lu4ev, &lw3dzr.pub_key).is_err());
+        assert_eq!(cache.len(), 1);
+
+        let mut tampered = lu4ev.clone();
+        tampered.operator = "Other".to_string();
+        assert!(cache.verify(&tampered, &lu4ev.pub_key).is_err());
+
+        cache.verify(&lw3dzr, &lw3dzr.pub_key).unwrap();
+        cache.verify(&lu4ev, &lu4ev.pub_key).unwrap();
+        cache.verify(&lu1aa, &lu1aa.pub_key).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        // lw3dzr was the least recently used entry.
+        cache.verify(&lu4ev, &lu4ev.pub_key).unwrap();
+        cache.verify(&lw3dzr, &lw3dzr.pub_key).unwrap();
+        assert_eq!(cache.stats(), CacheStats { hits: 3, misses: 5 });
+    }
+}
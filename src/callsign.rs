@@ -0,0 +1,434 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{bail, Result};
+use regex::Regex;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+
+/// Maximum length in bytes of a callsign, modifiers included.
+pub(crate) const CALLSIGN_MAX_LEN: usize = 16;
+
+thread_local! {
+    static IS_CALLSIGN: Regex = Regex::new(&format!("^[A-Z0-9]{{2,{}}}$", CALLSIGN_MAX_LEN)).unwrap();
+    static IS_ITU_BASE: Regex = Regex::new("^[A-Z0-9]{0,2}[A-Z][0-9][A-Z0-9]{0,3}[A-Z]$").unwrap();
+    static IS_MODIFIER: Regex = Regex::new("^[A-Z0-9]{1,4}$").unwrap();
+}
+
+/// Suffix modifiers which are never a country prefix.
+const SUFFIXES: &[&str] = &["P", "M", "MM", "AM", "QRP", "A", "R", "B"];
+
+/// A normalized callsign.
+///
+/// Different logging software writes the same callsign in different ways
+/// ( `lu4ev`, ` LU4EV `, `LU4EV/P/CE0Y` ), normalized callsigns compare equal
+/// regardless of those differences.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Callsign(String);
+
+impl Callsign {
+    /// Normalizes a raw callsign: uppercase, without whitespace and with the
+    /// modifiers in canonical order ( `PREFIX/BASE/SUFFIX...`, suffixes sorted ).
+    pub fn normalize(raw: &str) -> Self {
+        let cleaned: String = raw
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_uppercase)
+            .collect();
+
+        let parts: Vec<&str> = cleaned.split('/').filter(|p| !p.is_empty()).collect();
+        if parts.len() < 2 {
+            return Self(parts.concat());
+        }
+
+        let base_index = base_index(&parts);
+        let mut prefix = None;
+        let mut suffixes = vec![];
+
+        for (i, part) in parts.iter().enumerate() {
+            if i == base_index {
+                continue;
+            }
+            if prefix.is_none() && is_prefix(part) {
+                prefix = Some(*part);
+            } else {
+                suffixes.push(*part);
+            }
+        }
+
+        suffixes.sort_unstable();
+
+        let mut normalized = vec![];
+        normalized.extend(prefix);
+        normalized.push(parts[base_index]);
+        normalized.extend(suffixes);
+
+        Self(normalized.join("/"))
+    }
+
+    /// Returns the normalized callsign.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the base callsign, without prefix and suffix modifiers.
+    pub fn base(&self) -> &str {
+        let parts: Vec<&str> = self.0.split('/').collect();
+        parts[base_index(&parts)]
+    }
+
+    /// Returns the prefix modifier ( `CE0Y` in `CE0Y/LU4EV` ), if any.
+    pub fn prefix(&self) -> Option<&str> {
+        let parts: Vec<&str> = self.0.split('/').collect();
+        if base_index(&parts) > 0 {
+            Some(parts[0])
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if both raw callsigns normalize to the same callsign.
+    pub fn matches(a: &str, b: &str) -> bool {
+        Self::normalize(a) == Self::normalize(b)
+    }
+}
+
+impl Display for Callsign {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for Callsign {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A callsign pattern of a query: a callsign, matched after normalization,
+/// or a glob where `*` matches any run of characters and `?` a single one,
+/// e.g. `LU4*` for every callsign starting with `LU4`.
+///
+/// Stores look patterns up in their callsign index from the literal
+/// [`CallsignPattern::prefix`], patterns starting with a wildcard scan every
+/// callsign.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct CallsignPattern(String);
+
+impl CallsignPattern {
+    /// Parses a raw pattern: uppercase and without whitespace, normalized as
+    /// a callsign when it has no wildcards. Fails on empty patterns, patterns
+    /// longer than the callsign limit and characters other than printable
+    /// ASCII or with brackets.
+    pub fn new(raw: &str) -> Result<Self> {
+        let pattern: String = raw
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_uppercase)
+            .collect();
+
+        if pattern.is_empty()
+            || pattern.len() > CALLSIGN_MAX_LEN
+            || !pattern
+                .chars()
+                .all(|c| c.is_ascii_graphic() && c != '[' && c != ']')
+        {
+            bail!("invalid callsign pattern {}", raw);
+        }
+
+        if pattern.contains(['*', '?']) {
+            Ok(Self(pattern))
+        } else {
+            Ok(Self(Callsign::normalize(&pattern).0))
+        }
+    }
+
+    /// Returns the pattern.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true if the pattern has no wildcards.
+    pub fn is_exact(&self) -> bool {
+        !self.0.contains(['*', '?'])
+    }
+
+    /// Returns the literal start of the pattern, before the first wildcard.
+    /// Every matching callsign starts with it.
+    pub fn prefix(&self) -> &str {
+        let end = self.0.find(['*', '?']).unwrap_or(self.0.len());
+        &self.0[..end]
+    }
+
+    /// Returns true if the normalized callsign matches the pattern.
+    pub fn matches(&self, callsign: &Callsign) -> bool {
+        glob(self.0.as_bytes(), callsign.as_str().as_bytes())
+    }
+}
+
+impl From<Callsign> for CallsignPattern {
+    fn from(callsign: Callsign) -> Self {
+        Self(callsign.0)
+    }
+}
+
+impl FromStr for CallsignPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl Display for CallsignPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for CallsignPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        Self::new(&raw).map_err(D::Error::custom)
+    }
+}
+
+/// Decides which callsigns are accepted in stations and QSOs.
+///
+/// Verification always applies [`StrictCallsign`], so objects accepted by one
+/// peer are accepted by all of them. Other policies are opted in through
+/// [`crate::ValidationOptions::with_callsign_policy`] and
+/// [`crate::Station::from_data_with_policy`] by applications dealing with
+/// callsigns the strict policy rejects.
+pub trait CallsignPolicy: Debug + Send + Sync {
+    /// Returns an error if the callsign isn't accepted.
+    fn validate(&self, callsign: &str) -> Result<()>;
+}
+
+/// The default policy: a base callsign of 2 to 16 uppercase letters and
+/// digits with optional prefix and suffix modifiers, in the normalized form
+/// produced by [`Callsign::normalize`], e.g. `CE0Y/LU4EV/P`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StrictCallsign;
+
+impl CallsignPolicy for StrictCallsign {
+    fn validate(&self, callsign: &str) -> Result<()> {
+        let parts: Vec<&str> = callsign.split('/').collect();
+        let base_index = base_index(&parts);
+
+        let valid = callsign.len() <= CALLSIGN_MAX_LEN
+            && parts.iter().enumerate().all(|(i, part)| {
+                if i == base_index {
+                    IS_CALLSIGN.with(|is_callsign| is_callsign.is_match(part))
+                } else {
+                    IS_MODIFIER.with(|is_modifier| is_modifier.is_match(part))
+                }
+            })
+            && Callsign::normalize(callsign).as_str() == callsign;
+
+        if !valid {
+            bail!("invalid callsign");
+        }
+        Ok(())
+    }
+}
+
+/// Accepts callsigns following the ITU format ( a prefix, a digit and a
+/// suffix ending in a letter ) with an optional prefix modifier and suffix
+/// modifiers, e.g. `CE0Y/LU4EV/P`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ItuCallsign;
+
+impl CallsignPolicy for ItuCallsign {
+    fn validate(&self, callsign: &str) -> Result<()> {
+        let parts: Vec<&str> = callsign.split('/').collect();
+        let base_index = base_index(&parts);
+
+        let valid = parts.len() <= 3
+            && IS_ITU_BASE.with(|is_base| is_base.is_match(parts[base_index]))
+            && parts.iter().enumerate().all(|(i, part)| {
+                i == base_index || IS_MODIFIER.with(|is_modifier| is_modifier.is_match(part))
+            });
+
+        if !valid {
+            bail!("invalid callsign");
+        }
+        Ok(())
+    }
+}
+
+/// Accepts any printable ASCII identifier within the callsign length limit,
+/// e.g. the listener numbers used in SWL reports ( `ONL-12345` ).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PermissiveCallsign;
+
+impl CallsignPolicy for PermissiveCallsign {
+    fn validate(&self, callsign: &str) -> Result<()> {
+        if callsign.is_empty()
+            || callsign.len() > CALLSIGN_MAX_LEN
+            || !callsign.chars().all(|c| c.is_ascii_graphic())
+        {
+            bail!("invalid callsign");
+        }
+        Ok(())
+    }
+}
+
+/// The base callsign is the longest part containing both letters and digits.
+fn base_index(parts: &[&str]) -> usize {
+    let looks_like_call = |part: &str| {
+        part.chars().any(|c| c.is_ascii_digit()) && part.chars().any(|c| c.is_ascii_alphabetic())
+    };
+
+    parts
+        .iter()
+        .enumerate()
+        .filter(|(_, part)| looks_like_call(part))
+        .max_by_key(|(i, part)| (part.len(), std::cmp::Reverse(*i)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Matches the text against a glob with `*` and `?` wildcards.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.split_first(), text.split_first()) {
+        (None, _) => text.is_empty(),
+        (Some((b'*', rest)), _) => {
+            glob(rest, text) || (!text.is_empty() && glob(pattern, &text[1..]))
+        }
+        (Some((b'?', rest)), Some((_, text_rest))) => glob(rest, text_rest),
+        (Some((c, rest)), Some((t, text_rest))) if c == t => glob(rest, text_rest),
+        _ => false,
+    }
+}
+
+fn is_prefix(part: &str) -> bool {
+    !SUFFIXES.contains(&part) && part.chars().any(|c| c.is_ascii_alphabetic()) && part.len() <= 4
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::callsign::{
+        Callsign, CallsignPattern, CallsignPolicy, ItuCallsign, PermissiveCallsign, StrictCallsign,
+    };
+    use crate::validation::ValidationOptions;
+    use crate::{test_vectors, Station, StationData};
+    use codes_iso_3166::part_1::CountryCode;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(Callsign::normalize(" lu4ev ").as_str(), "LU4EV");
+        assert_eq!(Callsign::normalize("LU4EV/P").as_str(), "LU4EV/P");
+        assert_eq!(Callsign::normalize("lu4ev/p/ce0y").as_str(), "CE0Y/LU4EV/P");
+        assert_eq!(Callsign::normalize("CE0Y/LU4EV/P").as_str(), "CE0Y/LU4EV/P");
+        assert_eq!(Callsign::normalize("LU4EV/QRP/P").as_str(), "LU4EV/P/QRP");
+        assert_eq!(Callsign::normalize("W1AW/7").as_str(), "W1AW/7");
+    }
+
+    #[test]
+    fn test_base() {
+        assert_eq!(Callsign::normalize("CE0Y/LU4EV/P").base(), "LU4EV");
+        assert_eq!(Callsign::normalize("LU4EV").base(), "LU4EV");
+        assert_eq!(Callsign::normalize("LU4EV/P/CE0Y").prefix(), Some("CE0Y"));
+        assert_eq!(Callsign::normalize("LU4EV/P").prefix(), None);
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(Callsign::matches("lu4ev", "LU4EV "));
+        assert!(Callsign::matches("LU4EV/P/CE0Y", "ce0y/lu4ev/p"));
+        assert!(!Callsign::matches("LU4EV", "LU4EV/P"));
+    }
+
+    #[test]
+    fn test_pattern() {
+        let matches = |pattern: &str, callsign: &str| {
+            CallsignPattern::new(pattern)
+                .unwrap()
+                .matches(&Callsign::normalize(callsign))
+        };
+        assert!(matches("lu4*", "LU4EV"));
+        assert!(matches("LU4*", "lu4ev/p"));
+        assert!(matches("LU?EV", "LU4EV"));
+        assert!(matches("*/P", "LU4EV/P"));
+        assert!(matches("lu4ev/p/ce0y", "CE0Y/LU4EV/P"));
+        assert!(!matches("LU4*", "CE0Y/LU4EV"));
+        assert!(!matches("LU?EV", "LU44EV"));
+        assert!(!matches("LU4EV", "LU4EV/P"));
+
+        let pattern = CallsignPattern::new(" lu4* ").unwrap();
+        assert_eq!(pattern.as_str(), "LU4*");
+        assert_eq!(pattern.prefix(), "LU4");
+        assert!(!pattern.is_exact());
+        assert_eq!(CallsignPattern::new("*4EV").unwrap().prefix(), "");
+        assert!(CallsignPattern::new("LU4EV").unwrap().is_exact());
+
+        for invalid in ["", "LU[4]*", "LU4EV*ABCDEFGHIJKLMN", "LU4É"] {
+            assert!(CallsignPattern::new(invalid).is_err(), "{}", invalid);
+        }
+        assert!(serde_json::from_str::<CallsignPattern>("\"LU[4]*\"").is_err());
+    }
+
+    #[test]
+    fn test_policies() {
+        for callsign in ["LU4EV", "W1AW", "2E0ABC", "3DA0RU"] {
+            StrictCallsign.validate(callsign).unwrap();
+            ItuCallsign.validate(callsign).unwrap();
+            PermissiveCallsign.validate(callsign).unwrap();
+        }
+
+        // Strict accepts what normalization produces, nothing else.
+        StrictCallsign.validate("CE0Y/LU4EV/P").unwrap();
+        StrictCallsign
+            .validate(Callsign::normalize("lu4ev/qrp/p").as_str())
+            .unwrap();
+        for callsign in [
+            "LU4EV/P/CE0Y",
+            "LU4EV//P",
+            "lu4ev",
+            "LU4EV/PORTABLE",
+            "ONL-12345",
+        ] {
+            assert!(StrictCallsign.validate(callsign).is_err(), "{}", callsign);
+        }
+        ItuCallsign.validate("CE0Y/LU4EV/P").unwrap();
+        ItuCallsign.validate("W1AW/7").unwrap();
+        assert!(ItuCallsign.validate("LU4EV/P/QRP/MM").is_err());
+        assert!(ItuCallsign.validate("ABCDEF").is_err());
+        assert!(ItuCallsign.validate("ONL-12345").is_err());
+
+        PermissiveCallsign.validate("ONL-12345").unwrap();
+        assert!(PermissiveCallsign.validate("").is_err());
+        assert!(PermissiveCallsign.validate("ONL 12345").is_err());
+
+        let station_data = || StationData {
+            callsign: "ONL-12345".to_string(),
+            operator: "Radio Club Caseros".to_string(),
+            country: CountryCode::AR.into(),
+            extra: BTreeMap::new(),
+            profile: None,
+            qsl_route: None,
+        };
+        let keys = test_vectors::station_keypair();
+        assert!(Station::from_data(station_data(), &keys).is_err());
+        let station =
+            Station::from_data_with_policy(station_data(), &keys, &PermissiveCallsign).unwrap();
+
+        assert!(station.verify().is_err());
+        let options = ValidationOptions::default().with_callsign_policy(PermissiveCallsign);
+        station.verify_with_options(&options).unwrap();
+    }
+}
@@ -11,29 +11,43 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
 use crate::time::unix_timstamp;
-use crate::Id;
-use anyhow::{bail, Error};
+use crate::{version, Id, Kind};
+use anyhow::Error;
 use secp256k1::schnorr::Signature;
 use secp256k1::{Keypair, XOnlyPublicKey};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
 
 /// Represents a certificate issued by a station..
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Certificate {
     id: Id,
     issuer_id: Id,
     subject_id: Id,
     created_at: u64,
     version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
     sig: Signature,
 }
 
 impl Certificate {
+    /// Current version of the certificate object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the certificate verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Returns true if the certificate version is supported.
+    pub fn is_supported_version(version: u8) -> bool {
+        Self::SUPPORTED_VERSIONS.contains(&version)
+    }
+
     pub fn new(issuer_id: Id, issuer_key_pair: &Keypair, subject_id: Id) -> Self {
         let created_at = unix_timstamp();
-        let id = Self::generate_id(&issuer_id, &subject_id, created_at, 0);
+        let id = Self::compute_id(&issuer_id, &subject_id, created_at, Self::VERSION);
         let sig = id.sign(issuer_key_pair);
 
         Self {
@@ -41,31 +55,95 @@ impl Certificate {
             issuer_id,
             subject_id,
             created_at,
-            version: 0,
+            version: Self::VERSION,
             sig,
         }
     }
 
+    /// Creates a certificate with the given creation time, signed by the issuer
+    /// signer.
+    pub(crate) fn create(
+        issuer_id: Id,
+        signer: &dyn Signer,
+        subject_id: Id,
+        created_at: u64,
+    ) -> Result<Self, Error> {
+        let pub_key = signer.public_key()?;
+        let id = Self::compute_id(&issuer_id, &subject_id, created_at, Self::VERSION);
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        Ok(Self {
+            id,
+            issuer_id,
+            subject_id,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        })
+    }
+
+    /// Returns the id of the issuer station.
+    pub fn issuer_id(&self) -> &Id {
+        &self.issuer_id
+    }
+
+    /// Returns the id of the subject station.
+    pub fn subject_id(&self) -> &Id {
+        &self.subject_id
+    }
+
     pub fn verify(&self, issuer_pub_key: &XOnlyPublicKey) -> Result<(), Error> {
-        let id = Self::generate_id(
+        self.verify_signature(issuer_pub_key)
+    }
+
+    fn compute_id(issuer_id: &Id, subject_id: &Id, created_at: u64, version: u8) -> Id {
+        Id::hash(version, &(issuer_id, subject_id, created_at, version))
+    }
+}
+
+impl Validate for Certificate {
+    fn validate(&self) -> Result<(), Error> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)
+    }
+}
+
+impl Signable for Certificate {
+    const KIND: Kind = Kind::Certificate;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
             &self.issuer_id,
             &self.subject_id,
             self.created_at,
             self.version,
-        );
-
-        if id != self.id {
-            bail!("invalid id");
-        }
-
-        id.verify(issuer_pub_key, &self.sig)?;
-        Ok(())
+        )
     }
+}
 
-    fn generate_id(issuer_id: &Id, subject_id: &Id, created_at: u64, version: u8) -> Id {
-        let json: Value = json!([issuer_id, subject_id, created_at, version]);
-        let json_str = json.to_string();
-        Id::new(&json_str)
+#[cfg(feature = "testing")]
+impl<'a> arbitrary::Arbitrary<'a> for Certificate {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let sig: [u8; 64] = u.arbitrary()?;
+        Ok(Self {
+            id: u.arbitrary()?,
+            issuer_id: u.arbitrary()?,
+            subject_id: u.arbitrary()?,
+            created_at: u.arbitrary()?,
+            version: u.arbitrary()?,
+            sig: Signature::from_slice(&sig).expect("64 bytes signature"),
+        })
     }
 }
 
@@ -75,7 +153,6 @@ mod tests {
     use crate::keys::generate_keypair;
     use crate::Station;
     use codes_iso_3166::part_1::CountryCode;
-    use serde_json;
 
     #[test]
     fn test_certificate() {
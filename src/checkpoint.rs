@@ -0,0 +1,252 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::merkle;
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{version, Id, Kind, Qso};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// A signed snapshot of a station log, published periodically: the number
+/// of QSOs, the id of the last one and the merkle root of their ids, in log
+/// order.
+///
+/// An auditor keeping the checkpoints of a station can later check that the
+/// published log still starts with the QSOs each checkpoint covered, so QSOs
+/// deleted, modified or inserted before a checkpoint are detected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Checkpoint {
+    pub id: Id,
+    pub station_id: Id,
+    pub qso_count: u64,
+    /// Id of the last QSO, the head of the chain, see [`Qso::prev_id`].
+    pub head: Option<Id>,
+    pub merkle_root: Id,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl Checkpoint {
+    /// Current version of the checkpoint object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the checkpoint verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new Checkpoint of the station log and signs the object using
+    /// the station signer. The QSOs must be in log order.
+    pub fn new(station_id: Id, signer: &dyn Signer, qsos: &[Qso]) -> Result<Self> {
+        if qsos.iter().any(|qso| qso.station_id != station_id) {
+            bail!("qso station mismatch");
+        }
+
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let ids: Vec<Id> = qsos.iter().map(|qso| qso.id.clone()).collect();
+        let qso_count = ids.len() as u64;
+        let head = ids.last().cloned();
+        let merkle_root = merkle::root(&ids);
+        let id = Self::compute_id(
+            &station_id,
+            qso_count,
+            head.as_ref(),
+            &merkle_root,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let checkpoint = Self {
+            id,
+            station_id,
+            qso_count,
+            head,
+            merkle_root,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        checkpoint.validate()?;
+
+        Ok(checkpoint)
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Verify that the log, in log order, starts with the QSOs covered by the
+    /// checkpoint. QSOs logged after the checkpoint are ignored.
+    pub fn verify_log(&self, qsos: &[Qso]) -> Result<()> {
+        let covered = covered_qsos(&self.station_id, self.qso_count, &self.merkle_root, qsos)?;
+        if covered.last().map(|qso| &qso.id) != self.head.as_ref() {
+            bail!("log head mismatch");
+        }
+        Ok(())
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        qso_count: u64,
+        head: Option<&Id>,
+        merkle_root: &Id,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                station_id,
+                qso_count,
+                head,
+                merkle_root,
+                created_at,
+                version,
+            ),
+        )
+    }
+}
+
+impl Validate for Checkpoint {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.head.is_some() != (self.qso_count > 0) {
+            bail!("invalid checkpoint head");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for Checkpoint {
+    const KIND: Kind = Kind::Checkpoint;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            self.qso_count,
+            self.head.as_ref(),
+            &self.merkle_root,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+/// Returns the first `qso_count` QSOs of the station log, checking that their
+/// ids have the given merkle root.
+pub(crate) fn covered_qsos<'a>(
+    station_id: &Id,
+    qso_count: u64,
+    merkle_root: &Id,
+    qsos: &'a [Qso],
+) -> Result<&'a [Qso]> {
+    let Some(covered) = usize::try_from(qso_count)
+        .ok()
+        .and_then(|count| qsos.get(..count))
+    else {
+        bail!("log shorter than the covered log");
+    };
+
+    if covered.iter().any(|qso| &qso.station_id != station_id) {
+        bail!("qso station mismatch");
+    }
+
+    let ids: Vec<Id> = covered.iter().map(|qso| qso.id.clone()).collect();
+    if &merkle::root(&ids) != merkle_root {
+        bail!("log rewritten since it was covered");
+    }
+
+    Ok(covered)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::Checkpoint;
+    use crate::keys::generate_keypair;
+    use crate::{test_vectors, Qso, QsoData, Station};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_checkpoint() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let log: Vec<Qso> = (0..6)
+            .map(|i| {
+                Qso::new(
+                    QsoData {
+                        station_id: station.id.clone(),
+                        callsign: format!("LW{}DZR", i),
+                        datetime: 1704141426 + i,
+                        comments: "".to_string(),
+                        ..test_vectors::qso_data()
+                    },
+                    &keys,
+                )
+            })
+            .collect();
+
+        let checkpoint = Checkpoint::new(station.id.clone(), &keys, &log[..4]).unwrap();
+        assert_eq!(checkpoint.head.as_ref(), Some(&log[3].id));
+
+        let json_str = serde_json::to_string(&checkpoint).unwrap();
+        let checkpoint: Checkpoint = serde_json::from_str(&json_str).unwrap();
+        checkpoint.verify(&station.pub_key).unwrap();
+
+        checkpoint.verify_log(&log[..4]).unwrap();
+        checkpoint.verify_log(&log).unwrap();
+        assert!(checkpoint.verify_log(&log[..3]).is_err());
+
+        let mut rewritten = log.clone();
+        rewritten.remove(1);
+        assert!(checkpoint.verify_log(&rewritten).is_err());
+
+        let mut rewritten = log.clone();
+        rewritten.swap(0, 1);
+        assert!(checkpoint.verify_log(&rewritten).is_err());
+
+        let empty = Checkpoint::new(station.id.clone(), &keys, &[]).unwrap();
+        empty.verify(&station.pub_key).unwrap();
+        empty.verify_log(&log).unwrap();
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::callsign::Callsign;
+use crate::merkle::{self, MerkleProof};
+use crate::qso::{self, QsoIdSrc};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{limits, version, Id, Kind, Qso, QsoData, Tag};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A QSO published inside a [`LogChunk`].
+///
+/// The QSO is not signed on its own, its id is computed like a [`Qso`] id
+/// using the station id and creation time of the chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ChunkQso {
+    pub id: Id,
+    #[serde(deserialize_with = "limits::callsign")]
+    pub callsign: String,
+    pub datetime: u64,
+    pub freq: u64,
+    #[serde(deserialize_with = "limits::mode")]
+    pub mode: String,
+    #[serde(deserialize_with = "limits::rst")]
+    pub rst: String,
+    #[serde(deserialize_with = "limits::comments")]
+    pub comments: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_id: Option<Id>,
+}
+
+/// A list of QSOs signed with a single signature over the merkle root of
+/// their ids.
+///
+/// Large logs ( e.g. a DXpedition ) are published with one signature per chunk
+/// instead of one per QSO. A single QSO can still be proven part of the log
+/// with the chunk header, see [`LogChunk::without_qsos`], and a merkle proof.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct LogChunk {
+    pub id: Id,
+    pub station_id: Id,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub qsos: Vec<ChunkQso>,
+    pub qso_count: u64,
+    pub merkle_root: Id,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl LogChunk {
+    /// Current version of the log chunk object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the log chunk verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Version used to compute the ids of the chunk QSOs. It's part of the
+    /// chunk layout, so it's pinned instead of following [`Qso::VERSION`].
+    const QSO_VERSION: u8 = version::V1;
+
+    /// Creates a new LogChunk with the QSOs of the station and signs the
+    /// merkle root of their ids.
+    pub fn new(station_id: Id, signer: &dyn Signer, qsos: Vec<QsoData>) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+
+        let qsos = qsos
+            .into_iter()
+            .map(|mut qso_data| {
+                if qso_data.station_id != station_id {
+                    bail!("qso station mismatch");
+                }
+
+                qso_data.callsign = Callsign::normalize(&qso_data.callsign).to_string();
+                let id = Qso::compute_id(&QsoIdSrc {
+                    station_id: &station_id,
+                    callsign: &qso_data.callsign,
+                    datetime: qso_data.datetime,
+                    freq: qso_data.freq,
+                    mode: &qso_data.mode,
+                    rst: &qso_data.rst,
+                    comments: &qso_data.comments,
+                    created_at,
+                    version: Self::QSO_VERSION,
+                    nonce: None,
+                    extra: &qso_data.extra,
+                    redactable: None,
+                    tags: &qso_data.tags,
+                    sortable: false,
+                    prev_id: qso_data.prev_id.as_ref(),
+                });
+
+                Ok(ChunkQso {
+                    id,
+                    callsign: qso_data.callsign,
+                    datetime: qso_data.datetime,
+                    freq: qso_data.freq,
+                    mode: qso_data.mode,
+                    rst: qso_data.rst,
+                    comments: qso_data.comments,
+                    extra: qso_data.extra,
+                    tags: qso_data.tags,
+                    prev_id: qso_data.prev_id,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let qso_ids: Vec<Id> = qsos.iter().map(|qso| qso.id.clone()).collect();
+        let qso_count = qso_ids.len() as u64;
+        let merkle_root = merkle::root(&qso_ids);
+
+        let id = Self::compute_id(
+            &station_id,
+            qso_count,
+            &merkle_root,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let chunk = Self {
+            id,
+            station_id,
+            qsos,
+            qso_count,
+            merkle_root,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        chunk.validate()?;
+
+        Ok(chunk)
+    }
+
+    /// Verify the object signature against the station public key and, if
+    /// present, the QSOs against the merkle root.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Builds the inclusion proof of the QSO at `index`.
+    pub fn prove(&self, index: usize) -> Result<MerkleProof> {
+        let qso_ids: Vec<Id> = self.qsos.iter().map(|qso| qso.id.clone()).collect();
+        merkle::prove(&qso_ids, index)
+    }
+
+    /// Verify that the QSO is part of the chunk. The chunk itself must be
+    /// verified first.
+    pub fn verify_qso(&self, qso: &ChunkQso, proof: &MerkleProof) -> Result<()> {
+        let id_src = Self::qso_id_src(&self.station_id, self.created_at, qso);
+        qso::validate(&id_src)?;
+
+        if Qso::compute_id(&id_src) != qso.id {
+            bail!("invalid qso id");
+        }
+
+        if proof.leaf_count as u64 != self.qso_count {
+            bail!("invalid proof leaf count");
+        }
+
+        merkle::verify_proof(&self.merkle_root, &qso.id, proof)
+    }
+
+    /// Returns a copy of the chunk without the QSOs. The copy keeps the same
+    /// id and signature.
+    pub fn without_qsos(&self) -> Self {
+        Self {
+            qsos: vec![],
+            ..self.clone()
+        }
+    }
+
+    fn qso_id_src<'a>(station_id: &'a Id, created_at: u64, qso: &'a ChunkQso) -> QsoIdSrc<'a> {
+        QsoIdSrc {
+            station_id,
+            callsign: &qso.callsign,
+            datetime: qso.datetime,
+            freq: qso.freq,
+            mode: &qso.mode,
+            rst: &qso.rst,
+            comments: &qso.comments,
+            created_at,
+            version: Self::QSO_VERSION,
+            nonce: None,
+            extra: &qso.extra,
+            redactable: None,
+            tags: &qso.tags,
+            sortable: false,
+            prev_id: qso.prev_id.as_ref(),
+        }
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        qso_count: u64,
+        merkle_root: &Id,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(station_id, qso_count, merkle_root, created_at, version),
+        )
+    }
+}
+
+impl Validate for LogChunk {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.qsos.is_empty() {
+            return Ok(());
+        }
+
+        if self.qsos.len() as u64 != self.qso_count {
+            bail!("qso count mismatch");
+        }
+
+        for qso in &self.qsos {
+            let id_src = Self::qso_id_src(&self.station_id, self.created_at, qso);
+            qso::validate(&id_src)?;
+            if Qso::compute_id(&id_src) != qso.id {
+                bail!("invalid qso id");
+            }
+        }
+
+        let qso_ids: Vec<Id> = self.qsos.iter().map(|qso| qso.id.clone()).collect();
+        if merkle::root(&qso_ids) != self.merkle_root {
+            bail!("merkle root mismatch");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for LogChunk {
+    const KIND: Kind = Kind::LogChunk;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            self.qso_count,
+            &self.merkle_root,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::LogChunk;
+    use crate::keys::generate_keypair;
+    use crate::{test_vectors, QsoData, Station};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_log_chunk() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qsos = (0..25)
+            .map(|i| QsoData {
+                station_id: station.id.clone(),
+                callsign: format!("lw{}dzr", i),
+                datetime: 1704141426 + i,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                comments: "".to_string(),
+                ..test_vectors::qso_data()
+            })
+            .collect();
+
+        let chunk = LogChunk::new(station.id.clone(), &keys, qsos).unwrap();
+        assert_eq!(chunk.qsos[0].callsign, "LW0DZR");
+
+        let json_str = serde_json::to_string(&chunk).unwrap();
+        let mut chunk_dese: LogChunk = serde_json::from_str(&json_str).unwrap();
+        chunk_dese.verify(&station.pub_key).unwrap();
+
+        let header = chunk.without_qsos();
+        header.verify(&station.pub_key).unwrap();
+
+        let proof = chunk.prove(7).unwrap();
+        header.verify_qso(&chunk.qsos[7], &proof).unwrap();
+        assert!(header.verify_qso(&chunk.qsos[8], &proof).is_err());
+
+        let mut tampered = chunk.qsos[7].clone();
+        tampered.rst = "339".to_string();
+        assert!(header.verify_qso(&tampered, &proof).is_err());
+
+        chunk_dese.qsos[3].freq = 7000000;
+        assert!(chunk_dese.verify(&station.pub_key).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::qso::COMMENTS_MAX_LEN;
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{text, version, Id, Kind};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// Maximum length in bytes of the contest and bonus identifiers.
+const IDENTIFIER_MAX_LEN: usize = 32;
+
+/// Bonus points claimed by a station for an activity other than QSOs, e.g.
+/// the emergency power bonus of Field Day.
+///
+/// Claims are separate objects so the station can publish them as the
+/// activities happen and the contest committee can dispute each one on its
+/// own. The contest ruleset decides which bonuses exist and caps their
+/// points.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BonusClaim {
+    pub id: Id,
+    pub station_id: Id,
+    /// The contest, as in the ADIF `CONTEST_ID` field.
+    pub contest_id: String,
+    /// The bonus, as named by the contest ruleset.
+    pub bonus: String,
+    pub points: u64,
+    /// Evidence of the activity, e.g. a link to the press coverage.
+    pub comments: String,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl BonusClaim {
+    /// Current version of the bonus claim object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the bonus claim verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new BonusClaim and signs the object using the station
+    /// signer.
+    pub fn new(
+        station_id: Id,
+        signer: &dyn Signer,
+        contest_id: String,
+        bonus: String,
+        points: u64,
+        comments: String,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(
+            &station_id,
+            &contest_id,
+            &bonus,
+            points,
+            &comments,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let claim = Self {
+            id,
+            station_id,
+            contest_id,
+            bonus,
+            points,
+            comments,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        claim.validate()?;
+
+        Ok(claim)
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        contest_id: &str,
+        bonus: &str,
+        points: u64,
+        comments: &str,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                station_id, contest_id, bonus, points, comments, created_at, version,
+            ),
+        )
+    }
+}
+
+impl Validate for BonusClaim {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if !text::is_valid_required(&self.contest_id, IDENTIFIER_MAX_LEN) {
+            bail!("invalid contest id");
+        }
+
+        if !text::is_valid_required(&self.bonus, IDENTIFIER_MAX_LEN) {
+            bail!("invalid bonus");
+        }
+
+        if !text::is_valid_multiline(&self.comments, COMMENTS_MAX_LEN) {
+            bail!("invalid comments");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for BonusClaim {
+    const KIND: Kind = Kind::BonusClaim;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.contest_id,
+            &self.bonus,
+            self.points,
+            &self.comments,
+            self.created_at,
+            self.version,
+        )
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::contest::{BonusClaim, ContestLog, ModeCategory, Ruleset, Scope};
+use crate::{Id, Qso};
+use std::collections::BTreeMap;
+
+/// The ADIF contest id of ARRL Field Day, used in the bonus claims.
+pub const FIELD_DAY: &str = "ARRL-FD";
+
+/// Bonus points of each GOTA QSO.
+const GOTA_POINTS_PER_QSO: u64 = 5;
+
+/// Maximum GOTA bonus.
+const GOTA_MAX_BONUS: u64 = 500;
+
+/// The bonuses of ARRL Field Day, with the most points each one can earn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FieldDayBonus {
+    /// 100 points per transmitter on emergency power, up to 20.
+    EmergencyPower,
+    MediaPublicity,
+    PublicLocation,
+    InformationTable,
+    SectionManagerMessage,
+    /// 10 points per formal message relayed, up to 10.
+    MessageRelay,
+    W1awBulletin,
+    EducationalActivity,
+    ElectedOfficial,
+    ServedAgency,
+    Satellite,
+    AlternatePower,
+    WebSubmission,
+    /// 20 points per youth operator, up to 5.
+    Youth,
+    SocialMedia,
+    SafetyOfficer,
+    SiteResponsibilities,
+    GotaCoach,
+}
+
+impl FieldDayBonus {
+    /// Every Field Day bonus.
+    pub const ALL: &'static [FieldDayBonus] = &[
+        FieldDayBonus::EmergencyPower,
+        FieldDayBonus::MediaPublicity,
+        FieldDayBonus::PublicLocation,
+        FieldDayBonus::InformationTable,
+        FieldDayBonus::SectionManagerMessage,
+        FieldDayBonus::MessageRelay,
+        FieldDayBonus::W1awBulletin,
+        FieldDayBonus::EducationalActivity,
+        FieldDayBonus::ElectedOfficial,
+        FieldDayBonus::ServedAgency,
+        FieldDayBonus::Satellite,
+        FieldDayBonus::AlternatePower,
+        FieldDayBonus::WebSubmission,
+        FieldDayBonus::Youth,
+        FieldDayBonus::SocialMedia,
+        FieldDayBonus::SafetyOfficer,
+        FieldDayBonus::SiteResponsibilities,
+        FieldDayBonus::GotaCoach,
+    ];
+
+    /// Returns the bonus name used in the [`BonusClaim`]s.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FieldDayBonus::EmergencyPower => "emergency_power",
+            FieldDayBonus::MediaPublicity => "media_publicity",
+            FieldDayBonus::PublicLocation => "public_location",
+            FieldDayBonus::InformationTable => "information_table",
+            FieldDayBonus::SectionManagerMessage => "section_manager_message",
+            FieldDayBonus::MessageRelay => "message_relay",
+            FieldDayBonus::W1awBulletin => "w1aw_bulletin",
+            FieldDayBonus::EducationalActivity => "educational_activity",
+            FieldDayBonus::ElectedOfficial => "elected_official",
+            FieldDayBonus::ServedAgency => "served_agency",
+            FieldDayBonus::Satellite => "satellite",
+            FieldDayBonus::AlternatePower => "alternate_power",
+            FieldDayBonus::WebSubmission => "web_submission",
+            FieldDayBonus::Youth => "youth",
+            FieldDayBonus::SocialMedia => "social_media",
+            FieldDayBonus::SafetyOfficer => "safety_officer",
+            FieldDayBonus::SiteResponsibilities => "site_responsibilities",
+            FieldDayBonus::GotaCoach => "gota_coach",
+        }
+    }
+
+    /// Returns the bonus with the name.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|bonus| bonus.code() == code)
+    }
+
+    /// Returns the most points the bonus earns.
+    pub fn max_points(&self) -> u64 {
+        match self {
+            FieldDayBonus::EmergencyPower => 2000,
+            FieldDayBonus::WebSubmission | FieldDayBonus::SiteResponsibilities => 50,
+            _ => 100,
+        }
+    }
+}
+
+/// The ARRL Field Day ruleset.
+///
+/// Phone QSOs earn 1 point, CW and digital QSOs 2 points. A station counts
+/// once per band and mode. There are no multipliers, the QSO points are
+/// multiplied by the power multiplier instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldDay {
+    /// Highest transmitter output power used, in watts.
+    pub max_power: u32,
+    /// Commercial mains or a motor driven generator powered a transmitter.
+    pub commercial_power: bool,
+}
+
+impl FieldDay {
+    /// Returns the power multiplier: 5 up to 5 W off commercial power, 2 up
+    /// to 100 W and 1 above.
+    pub fn power_multiplier(&self) -> u64 {
+        match self.max_power {
+            0..=5 if !self.commercial_power => 5,
+            0..=100 => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns the bonus points of the Field Day claims of the station. The
+    /// claims must be verified first.
+    ///
+    /// Each bonus counts once, with the points of its highest claim capped
+    /// to [`FieldDayBonus::max_points`]. Unknown bonuses are ignored.
+    pub fn bonus_points(&self, station_id: &Id, claims: &[BonusClaim]) -> u64 {
+        let mut bonuses = BTreeMap::new();
+        for claim in claims {
+            if &claim.station_id != station_id || claim.contest_id != FIELD_DAY {
+                continue;
+            }
+            let Some(bonus) = FieldDayBonus::from_code(&claim.bonus) else {
+                continue;
+            };
+            let points = bonuses.entry(bonus).or_insert(0);
+            *points = claim.points.min(bonus.max_points()).max(*points);
+        }
+        bonuses.values().sum()
+    }
+}
+
+impl Ruleset for FieldDay {
+    fn dupe_scope(&self) -> Scope {
+        Scope::BandMode
+    }
+
+    fn qso_points(&self, qso: &Qso) -> u64 {
+        match ModeCategory::of(&qso.mode) {
+            ModeCategory::Phone => 1,
+            ModeCategory::Cw | ModeCategory::Digital => 2,
+        }
+    }
+
+    fn score(&self, qso_points: u64, _multipliers: usize) -> u64 {
+        qso_points * self.power_multiplier()
+    }
+}
+
+/// The claimed Field Day score of a club.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FieldDayScore {
+    /// QSO points of the main and GOTA stations.
+    pub qso_points: u64,
+    pub power_multiplier: u64,
+    pub bonus_points: u64,
+    /// 5 points per GOTA QSO, up to 500.
+    pub gota_bonus: u64,
+    pub total: u64,
+}
+
+impl FieldDayScore {
+    /// Scores the log of the station and the log of its Get On The Air
+    /// station, if any.
+    ///
+    /// The GOTA station keeps its own log and dupe sheet. Its QSO points add
+    /// to the ones of the main station and earn the GOTA bonus, the power
+    /// multiplier is the one of the main station.
+    pub fn new(
+        station_id: &Id,
+        main: &ContestLog<FieldDay>,
+        gota: Option<&ContestLog<FieldDay>>,
+        claims: &[BonusClaim],
+    ) -> Self {
+        let field_day = main.ruleset();
+        let qso_points = main.qso_points() + gota.map_or(0, ContestLog::qso_points);
+        let power_multiplier = field_day.power_multiplier();
+        let bonus_points = field_day.bonus_points(station_id, claims);
+        let gota_bonus = gota.map_or(0, |gota| {
+            (gota.qsos() * GOTA_POINTS_PER_QSO).min(GOTA_MAX_BONUS)
+        });
+
+        Self {
+            qso_points,
+            power_multiplier,
+            bonus_points,
+            gota_bonus,
+            total: field_day.score(qso_points, 0) + bonus_points + gota_bonus,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::contest::{
+        BonusClaim, ContestLog, FieldDay, FieldDayBonus, FieldDayScore, Outcome, FIELD_DAY,
+    };
+    use crate::{test_vectors, Qso, QsoData};
+
+    #[test]
+    fn test_field_day() {
+        let station = test_vectors::station();
+        let keys = test_vectors::station_keypair();
+        let qso = |callsign: &str, freq, mode: &str| {
+            Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    callsign: callsign.to_string(),
+                    freq,
+                    mode: mode.to_string(),
+                    comments: "2A ENY".to_string(),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            )
+        };
+
+        let field_day = FieldDay {
+            max_power: 100,
+            commercial_power: false,
+        };
+        assert_eq!(field_day.power_multiplier(), 2);
+
+        let mut main = ContestLog::new(field_day);
+        assert_eq!(
+            main.add(&qso("W1AW", 14_030_000, "CW")),
+            Outcome::Counted {
+                points: 2,
+                new_multipliers: vec![]
+            }
+        );
+        main.add(&qso("W1AW", 14_250_000, "SSB"));
+        main.add(&qso("W1AW", 14_074_000, "FT8"));
+        let dupe = qso("W1AW", 14_260_000, "USB");
+        assert!(matches!(main.add(&dupe), Outcome::Dupe(_)));
+        assert_eq!(main.qso_points(), 5);
+        assert_eq!(main.score(), 10);
+
+        let mut gota = ContestLog::new(field_day);
+        gota.add(&qso("W1AW", 14_250_000, "SSB"));
+        gota.add(&qso("K1ABC", 7_200_000, "LSB"));
+
+        let claim = |bonus: FieldDayBonus, points| {
+            BonusClaim::new(
+                station.id.clone(),
+                &keys,
+                FIELD_DAY.to_string(),
+                bonus.code().to_string(),
+                points,
+                String::new(),
+            )
+            .unwrap()
+        };
+        let claims = [
+            claim(FieldDayBonus::EmergencyPower, 300),
+            claim(FieldDayBonus::MediaPublicity, 100),
+            claim(FieldDayBonus::MediaPublicity, 100),
+            claim(FieldDayBonus::WebSubmission, 100),
+        ];
+        claims[0].verify(&station.pub_key).unwrap();
+
+        let score = FieldDayScore::new(&station.id, &main, Some(&gota), &claims);
+        assert_eq!(
+            score,
+            FieldDayScore {
+                qso_points: 7,
+                power_multiplier: 2,
+                bonus_points: 450,
+                gota_bonus: 10,
+                total: 474,
+            }
+        );
+
+        let qrp = FieldDay {
+            max_power: 5,
+            commercial_power: false,
+        };
+        assert_eq!(qrp.power_multiplier(), 5);
+        assert_eq!(
+            FieldDay {
+                commercial_power: true,
+                ..qrp
+            }
+            .power_multiplier(),
+            2
+        );
+    }
+}
@@ -0,0 +1,390 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Live contest helpers.
+//!
+//! [`Dedupe`] and [`Multipliers`] are updated one QSO at a time, so a logger
+//! can tell the operator whether a callsign is a dupe or a new multiplier
+//! before the QSO is logged, and keep the running totals while the contest
+//! goes on. A [`ContestLog`] combines both with the scoring of a
+//! [`Ruleset`], e.g. [`FieldDay`].
+
+mod claim;
+mod field_day;
+
+pub use claim::BonusClaim;
+pub use field_day::{FieldDay, FieldDayBonus, FieldDayScore, FIELD_DAY};
+
+use crate::bandplan::Band;
+use crate::{dxcc, Callsign, Id, Qso};
+use codes_iso_3166::part_1::CountryCode;
+use std::collections::{HashMap, HashSet};
+
+/// The modes contests count apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ModeCategory {
+    Cw,
+    Phone,
+    Digital,
+}
+
+impl ModeCategory {
+    /// Returns the category of an ADIF mode or submode.
+    pub fn of(mode: &str) -> Self {
+        match mode.to_ascii_uppercase().as_str() {
+            "CW" => ModeCategory::Cw,
+            "SSB" | "USB" | "LSB" | "AM" | "FM" | "DV" | "PH" | "PHONE" => ModeCategory::Phone,
+            _ => ModeCategory::Digital,
+        }
+    }
+}
+
+/// Where a contest counts a station or a multiplier once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Once in the whole contest.
+    Contest,
+    /// Once per band.
+    #[default]
+    Band,
+    /// Once per band and [`ModeCategory`].
+    BandMode,
+}
+
+/// The band and mode a QSO counts for, as required by its [`Scope`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Slot {
+    band: Option<Band>,
+    mode: Option<ModeCategory>,
+}
+
+impl Scope {
+    fn slot(&self, freq: u64, mode: &str) -> Slot {
+        match self {
+            Scope::Contest => Slot {
+                band: None,
+                mode: None,
+            },
+            Scope::Band => Slot {
+                band: Band::from_freq(freq),
+                mode: None,
+            },
+            Scope::BandMode => Slot {
+                band: Band::from_freq(freq),
+                mode: Some(ModeCategory::of(mode)),
+            },
+        }
+    }
+}
+
+/// Dupe checking of a contest log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dedupe {
+    scope: Scope,
+    worked: HashMap<(Callsign, Slot), Id>,
+}
+
+impl Dedupe {
+    /// Creates an empty Dedupe counting each station once per scope.
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            worked: HashMap::new(),
+        }
+    }
+
+    /// Returns the QSO the contact would duplicate, if any.
+    pub fn dupe_of(&self, callsign: &str, freq: u64, mode: &str) -> Option<&Id> {
+        let key = (Callsign::normalize(callsign), self.scope.slot(freq, mode));
+        self.worked.get(&key)
+    }
+
+    /// Returns true if the contact would be a dupe.
+    pub fn is_dupe(&self, callsign: &str, freq: u64, mode: &str) -> bool {
+        self.dupe_of(callsign, freq, mode).is_some()
+    }
+
+    /// Adds the QSO to the log, returns false if it's a dupe.
+    pub fn add(&mut self, qso: &Qso) -> bool {
+        let key = (
+            Callsign::normalize(&qso.callsign),
+            self.scope.slot(qso.freq, &qso.mode),
+        );
+        if self.worked.contains_key(&key) {
+            return false;
+        }
+        self.worked.insert(key, qso.id.clone());
+        true
+    }
+
+    /// Returns the number of QSOs which aren't dupes.
+    pub fn len(&self) -> usize {
+        self.worked.len()
+    }
+
+    /// Returns true if no QSO was added.
+    pub fn is_empty(&self) -> bool {
+        self.worked.is_empty()
+    }
+}
+
+/// A contest multiplier.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Multiplier {
+    /// A CQ or ITU zone, depending on the contest.
+    Zone(u8),
+    Country(CountryCode),
+    /// An ARRL/RAC section or any other exchange based multiplier.
+    Section(String),
+}
+
+impl Multiplier {
+    /// Returns the country multiplier of the callsign, if its prefix is
+    /// known.
+    pub fn country(callsign: &str) -> Option<Self> {
+        dxcc::resolve(callsign).map(Multiplier::Country)
+    }
+
+    /// Returns the section multiplier, uppercase.
+    pub fn section(section: &str) -> Self {
+        Multiplier::Section(section.trim().to_ascii_uppercase())
+    }
+}
+
+/// Multipliers worked during a contest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Multipliers {
+    scope: Scope,
+    worked: HashMap<Slot, HashSet<Multiplier>>,
+}
+
+impl Multipliers {
+    /// Creates an empty tracker counting each multiplier once per scope.
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            worked: HashMap::new(),
+        }
+    }
+
+    /// Returns true if the multiplier wasn't worked yet on the frequency and
+    /// mode.
+    pub fn is_new(&self, freq: u64, mode: &str, multiplier: &Multiplier) -> bool {
+        self.worked
+            .get(&self.scope.slot(freq, mode))
+            .is_none_or(|worked| !worked.contains(multiplier))
+    }
+
+    /// Adds the multipliers of a QSO, returns the new ones.
+    pub fn add(
+        &mut self,
+        qso: &Qso,
+        multipliers: impl IntoIterator<Item = Multiplier>,
+    ) -> Vec<Multiplier> {
+        let worked = self
+            .worked
+            .entry(self.scope.slot(qso.freq, &qso.mode))
+            .or_default();
+        multipliers
+            .into_iter()
+            .filter(|multiplier| worked.insert(multiplier.clone()))
+            .collect()
+    }
+
+    /// Returns the total number of multipliers, the sum of every band and
+    /// mode for the narrower scopes.
+    pub fn count(&self) -> usize {
+        self.worked.values().map(HashSet::len).sum()
+    }
+
+    /// Returns the number of multipliers worked on the band.
+    pub fn count_band(&self, band: Band) -> usize {
+        self.worked
+            .iter()
+            .filter(|(slot, _)| slot.band == Some(band))
+            .map(|(_, worked)| worked.len())
+            .sum()
+    }
+}
+
+/// The scoring rules of a contest.
+pub trait Ruleset {
+    /// Where a station counts once.
+    fn dupe_scope(&self) -> Scope;
+
+    /// Where a multiplier counts once.
+    fn multiplier_scope(&self) -> Scope {
+        Scope::Band
+    }
+
+    /// Returns the points of a QSO which isn't a dupe.
+    fn qso_points(&self, qso: &Qso) -> u64;
+
+    /// Returns the multipliers of the QSO, e.g. from its exchange.
+    fn multipliers(&self, _qso: &Qso) -> Vec<Multiplier> {
+        vec![]
+    }
+
+    /// Returns the claimed score of the log.
+    fn score(&self, qso_points: u64, multipliers: usize) -> u64 {
+        qso_points * multipliers as u64
+    }
+}
+
+/// How a QSO added to a [`ContestLog`] counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The QSO duplicates the QSO with the id, no points.
+    Dupe(Id),
+    Counted {
+        points: u64,
+        new_multipliers: Vec<Multiplier>,
+    },
+}
+
+/// The running score of a contest log.
+#[derive(Debug, Clone)]
+pub struct ContestLog<R> {
+    ruleset: R,
+    dedupe: Dedupe,
+    multipliers: Multipliers,
+    qsos: u64,
+    qso_points: u64,
+}
+
+impl<R: Ruleset> ContestLog<R> {
+    /// Creates an empty log scored by the ruleset.
+    pub fn new(ruleset: R) -> Self {
+        Self {
+            dedupe: Dedupe::new(ruleset.dupe_scope()),
+            multipliers: Multipliers::new(ruleset.multiplier_scope()),
+            ruleset,
+            qsos: 0,
+            qso_points: 0,
+        }
+    }
+
+    /// Returns the ruleset of the log.
+    pub fn ruleset(&self) -> &R {
+        &self.ruleset
+    }
+
+    /// Returns the dupe sheet, to check a callsign before logging it.
+    pub fn dedupe(&self) -> &Dedupe {
+        &self.dedupe
+    }
+
+    /// Returns the multipliers worked.
+    pub fn multipliers(&self) -> &Multipliers {
+        &self.multipliers
+    }
+
+    /// Adds the QSO to the log.
+    pub fn add(&mut self, qso: &Qso) -> Outcome {
+        if let Some(id) = self.dedupe.dupe_of(&qso.callsign, qso.freq, &qso.mode) {
+            return Outcome::Dupe(id.clone());
+        }
+        self.dedupe.add(qso);
+
+        let points = self.ruleset.qso_points(qso);
+        self.qsos += 1;
+        self.qso_points += points;
+        let new_multipliers = self.multipliers.add(qso, self.ruleset.multipliers(qso));
+
+        Outcome::Counted {
+            points,
+            new_multipliers,
+        }
+    }
+
+    /// Returns the number of QSOs which aren't dupes.
+    pub fn qsos(&self) -> u64 {
+        self.qsos
+    }
+
+    /// Returns the sum of the QSO points.
+    pub fn qso_points(&self) -> u64 {
+        self.qso_points
+    }
+
+    /// Returns the claimed score.
+    pub fn score(&self) -> u64 {
+        self.ruleset
+            .score(self.qso_points, self.multipliers.count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::Band;
+    use crate::contest::{Dedupe, Multiplier, Multipliers, Scope};
+    use crate::{test_vectors, Qso, QsoData};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_contest() {
+        let qso = |callsign: &str, freq, mode: &str| {
+            Qso::new(
+                QsoData {
+                    callsign: callsign.to_string(),
+                    freq,
+                    mode: mode.to_string(),
+                    rst: "599".to_string(),
+                    comments: String::new(),
+                    ..test_vectors::qso_data()
+                },
+                &test_vectors::station_keypair(),
+            )
+        };
+
+        let mut dedupe = Dedupe::new(Scope::Band);
+        let first = qso("CX2ABC", 14_025_000, "CW");
+        assert!(!dedupe.is_dupe("CX2ABC", 14_025_000, "CW"));
+        assert!(dedupe.add(&first));
+        assert_eq!(dedupe.dupe_of("cx2abc", 14_040_000, "SSB"), Some(&first.id));
+        assert!(!dedupe.add(&qso("CX2ABC", 14_030_000, "CW")));
+        assert!(dedupe.add(&qso("CX2ABC", 7_025_000, "CW")));
+        assert_eq!(dedupe.len(), 2);
+
+        let mut dedupe = Dedupe::new(Scope::BandMode);
+        assert!(dedupe.add(&first));
+        assert!(!dedupe.is_dupe("CX2ABC", 14_250_000, "SSB"));
+
+        let mut multipliers = Multipliers::new(Scope::Band);
+        let uruguay = Multiplier::country("CX2ABC").unwrap();
+        assert_eq!(uruguay, Multiplier::Country(CountryCode::UY));
+        assert!(multipliers.is_new(14_025_000, "CW", &uruguay));
+        assert_eq!(
+            multipliers.add(&first, [uruguay.clone(), Multiplier::Zone(13)]),
+            vec![uruguay.clone(), Multiplier::Zone(13)]
+        );
+        assert!(!multipliers.is_new(14_200_000, "SSB", &uruguay));
+        assert_eq!(
+            multipliers.add(
+                &qso("CX3XYZ", 14_030_000, "CW"),
+                [uruguay.clone(), Multiplier::Zone(13)]
+            ),
+            vec![]
+        );
+        assert_eq!(
+            multipliers.add(&qso("CX2ABC", 7_025_000, "CW"), [uruguay]),
+            vec![Multiplier::country("CX2ABC").unwrap()]
+        );
+        assert_eq!(multipliers.count(), 3);
+        assert_eq!(multipliers.count_band(Band::M20), 2);
+        assert_eq!(
+            Multiplier::section(" enj "),
+            Multiplier::Section("ENJ".to_string())
+        );
+    }
+}
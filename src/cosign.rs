@@ -0,0 +1,409 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! QSO records endorsed by both stations of the contact with a single MuSig2
+//! signature.
+//!
+//! The logging station signs the QSO as usual. At contact time, e.g. over the
+//! digital mode itself, the stations exchange the QSO and a [`PubNonce`] each
+//! from [`nonce`], build the same [`CoSignSession`] and send each other their
+//! [`PartialSignature`]. [`CoSignSession::finish`] combines them in one
+//! BIP340 signature for the aggregate key of both stations.
+
+use crate::{schnorr, Id, Qso};
+use anyhow::{bail, Context, Result};
+use k256::elliptic_curve::Field;
+use k256::{ProjectivePoint, Scalar};
+use secp256k1::schnorr::Signature;
+use secp256k1::{Keypair, Parity, PublicKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Formatter};
+use zeroize::Zeroize;
+
+const KEY_LIST_TAG: &str = "gqdb/musig2/key_list";
+const KEY_COEFFICIENT_TAG: &str = "gqdb/musig2/key_coefficient";
+const NONCE_COEFFICIENT_TAG: &str = "gqdb/musig2/nonce_coefficient";
+
+/// A QSO record endorsed by both stations of the contact.
+///
+/// The signature covers the co-sign id, which binds the QSO id to the
+/// counterparty station id, for the aggregate key of the station and the
+/// counterparty public keys, in that order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CoSignedQso {
+    pub qso: Qso,
+    pub counterparty_station_id: Id,
+    pub sig: Signature,
+}
+
+impl CoSignedQso {
+    /// Verify both the QSO signature and the aggregate signature.
+    pub fn verify(
+        &self,
+        station_pub_key: &XOnlyPublicKey,
+        counterparty_pub_key: &XOnlyPublicKey,
+    ) -> Result<()> {
+        self.qso.verify(station_pub_key)?;
+        let key = KeyAgg::new(station_pub_key, counterparty_pub_key)?;
+        let id = Self::generate_id(&self.qso, &self.counterparty_station_id);
+        id.verify(&key.pub_key, &self.sig)?;
+        Ok(())
+    }
+
+    fn generate_id(qso: &Qso, counterparty_station_id: &Id) -> Id {
+        Id::hash(qso.version, &(&qso.id, counterparty_station_id))
+    }
+}
+
+/// Secret nonces of a station for one co-signature, consumed by
+/// [`CoSignSession::sign`].
+pub struct SecNonce {
+    first: Scalar,
+    second: Scalar,
+    pub_nonce: PubNonce,
+}
+
+impl Debug for SecNonce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecNonce")
+            .field("pub_nonce", &self.pub_nonce)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SecNonce {
+    fn drop(&mut self) {
+        self.first.zeroize();
+        self.second.zeroize();
+    }
+}
+
+/// Public nonces of a station, sent to the other station.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PubNonce {
+    pub first: PublicKey,
+    pub second: PublicKey,
+}
+
+/// Partial signature of a station, sent to the other station.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    #[serde(with = "hex")]
+    pub s: [u8; 32],
+}
+
+/// Returns fresh nonces for the next co-signature of a station and the public
+/// nonces to send to the other station. Nonces must never be reused.
+pub fn nonce() -> Result<(SecNonce, PubNonce)> {
+    let mut rng = rand::thread_rng();
+    let first = Scalar::random(&mut rng);
+    let second = Scalar::random(&mut rng);
+    let pub_nonce = PubNonce {
+        first: schnorr::public_key(&(ProjectivePoint::GENERATOR * first))?,
+        second: schnorr::public_key(&(ProjectivePoint::GENERATOR * second))?,
+    };
+
+    Ok((
+        SecNonce {
+            first,
+            second,
+            pub_nonce: pub_nonce.clone(),
+        },
+        pub_nonce,
+    ))
+}
+
+/// Aggregate key of the station and the counterparty.
+struct KeyAgg {
+    pub_key: XOnlyPublicKey,
+    points: [ProjectivePoint; 2],
+    coefficients: [Scalar; 2],
+    /// Whether the secret keys are negated, the aggregate point had an odd y.
+    negate: bool,
+}
+
+impl KeyAgg {
+    fn new(
+        station_pub_key: &XOnlyPublicKey,
+        counterparty_pub_key: &XOnlyPublicKey,
+    ) -> Result<Self> {
+        if station_pub_key == counterparty_pub_key {
+            bail!("the station and the counterparty keys are the same");
+        }
+
+        let pub_keys = [
+            station_pub_key.serialize(),
+            counterparty_pub_key.serialize(),
+        ];
+        let list = schnorr::hash_to_scalar(KEY_LIST_TAG, &[&pub_keys[0], &pub_keys[1]]).to_bytes();
+        // The second key gets a coefficient of one, as in BIP327.
+        let coefficients = [
+            schnorr::hash_to_scalar(KEY_COEFFICIENT_TAG, &[&list, &pub_keys[0]]),
+            Scalar::ONE,
+        ];
+        let points = [
+            schnorr::lift_x(station_pub_key),
+            schnorr::lift_x(counterparty_pub_key),
+        ];
+
+        let point = points[0] * coefficients[0] + points[1] * coefficients[1];
+        let pub_key = schnorr::public_key(&point)?.x_only_public_key().0;
+
+        Ok(Self {
+            pub_key,
+            points,
+            coefficients,
+            negate: !schnorr::has_even_y(&point),
+        })
+    }
+}
+
+/// A co-signature in progress, built the same way by both stations.
+#[derive(Debug, Clone)]
+pub struct CoSignSession {
+    qso: Qso,
+    counterparty_station_id: Id,
+    pub_keys: [XOnlyPublicKey; 2],
+    nonces: [PubNonce; 2],
+}
+
+impl CoSignSession {
+    /// Starts the co-signature of the QSO signed by the station, with the
+    /// public nonces of both stations.
+    pub fn new(
+        qso: Qso,
+        counterparty_station_id: Id,
+        station_pub_key: XOnlyPublicKey,
+        counterparty_pub_key: XOnlyPublicKey,
+        station_nonce: PubNonce,
+        counterparty_nonce: PubNonce,
+    ) -> Result<Self> {
+        qso.verify(&station_pub_key)
+            .context("the QSO isn't signed by the station")?;
+
+        Ok(Self {
+            qso,
+            counterparty_station_id,
+            pub_keys: [station_pub_key, counterparty_pub_key],
+            nonces: [station_nonce, counterparty_nonce],
+        })
+    }
+
+    /// Returns the partial signature of the station or the counterparty,
+    /// whichever the keys belong to. Consumes the nonces.
+    pub fn sign(&self, keys: &Keypair, nonce: SecNonce) -> Result<PartialSignature> {
+        let (pub_key, parity) = keys.x_only_public_key();
+        let Some(index) = self.pub_keys.iter().position(|key| *key == pub_key) else {
+            bail!("the keys don't belong to the session stations");
+        };
+        if nonce.pub_nonce != self.nonces[index] {
+            bail!("the session doesn't include the nonces");
+        }
+
+        let context = self.context()?;
+        let mut k = nonce.first + nonce.second * context.nonce_coefficient;
+        if context.negate_nonce {
+            k = -k;
+        }
+        let mut secret = schnorr::scalar(&keys.secret_bytes())?;
+        if (parity == Parity::Odd) != context.key.negate {
+            secret = -secret;
+        }
+
+        let s = k + context.challenge * context.key.coefficients[index] * secret;
+        k.zeroize();
+        secret.zeroize();
+
+        Ok(PartialSignature {
+            s: s.to_bytes().into(),
+        })
+    }
+
+    /// Checks the partial signatures and combines them in the co-signed QSO.
+    pub fn finish(
+        self,
+        station_sig: &PartialSignature,
+        counterparty_sig: &PartialSignature,
+    ) -> Result<CoSignedQso> {
+        let context = self.context()?;
+
+        let mut s = Scalar::ZERO;
+        for (index, partial) in [station_sig, counterparty_sig].into_iter().enumerate() {
+            let partial = schnorr::scalar(&partial.s)?;
+            let mut nonce = schnorr::point(&self.nonces[index].first)
+                + schnorr::point(&self.nonces[index].second) * context.nonce_coefficient;
+            if context.negate_nonce {
+                nonce = -nonce;
+            }
+            let mut point = context.key.points[index];
+            if context.key.negate {
+                point = -point;
+            }
+            if ProjectivePoint::GENERATOR * partial
+                != nonce + point * (context.challenge * context.key.coefficients[index])
+            {
+                let signer = ["station", "counterparty"][index];
+                bail!("invalid partial signature of the {}", signer);
+            }
+            s += partial;
+        }
+
+        let sig = schnorr::signature(&context.nonce, &s)?;
+        context
+            .id
+            .verify(&context.key.pub_key, &sig)
+            .context("invalid aggregate signature")?;
+
+        Ok(CoSignedQso {
+            qso: self.qso,
+            counterparty_station_id: self.counterparty_station_id,
+            sig,
+        })
+    }
+
+    fn context(&self) -> Result<SessionContext> {
+        let key = KeyAgg::new(&self.pub_keys[0], &self.pub_keys[1])?;
+        let id = CoSignedQso::generate_id(&self.qso, &self.counterparty_station_id);
+
+        let first = schnorr::point(&self.nonces[0].first) + schnorr::point(&self.nonces[1].first);
+        let second =
+            schnorr::point(&self.nonces[0].second) + schnorr::point(&self.nonces[1].second);
+        let nonce_coefficient = schnorr::hash_to_scalar(
+            NONCE_COEFFICIENT_TAG,
+            &[
+                &encode_nonce(&first),
+                &encode_nonce(&second),
+                &key.pub_key.serialize(),
+                id.as_bytes(),
+            ],
+        );
+
+        let mut nonce = first + second * nonce_coefficient;
+        // As in BIP327, a nonce at infinity only happens with a dishonest
+        // signer and is replaced by the generator.
+        if nonce == ProjectivePoint::IDENTITY {
+            nonce = ProjectivePoint::GENERATOR;
+        }
+        let negate_nonce = !schnorr::has_even_y(&nonce);
+        if negate_nonce {
+            nonce = -nonce;
+        }
+        let challenge = schnorr::challenge(&nonce, &key.pub_key, &id);
+
+        Ok(SessionContext {
+            key,
+            id,
+            nonce,
+            negate_nonce,
+            nonce_coefficient,
+            challenge,
+        })
+    }
+}
+
+/// Encodes an aggregate nonce point, the point at infinity as zeros as in
+/// BIP327.
+fn encode_nonce(point: &ProjectivePoint) -> [u8; 33] {
+    schnorr::public_key(point)
+        .map(|key| key.serialize())
+        .unwrap_or([0; 33])
+}
+
+struct SessionContext {
+    key: KeyAgg,
+    id: Id,
+    nonce: ProjectivePoint,
+    negate_nonce: bool,
+    nonce_coefficient: Scalar,
+    challenge: Scalar,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cosign::{nonce, CoSignSession, CoSignedQso};
+    use crate::test_vectors;
+
+    #[test]
+    fn test_cosign() {
+        let station = test_vectors::station();
+        let counterparty = test_vectors::counterparty();
+        let counterparty_keys = test_vectors::counterparty_keypair();
+        let qso = test_vectors::qso();
+
+        // Both stations build the same session from the exchanged nonces.
+        let (station_nonce, station_pub_nonce) = nonce().unwrap();
+        let (counterparty_nonce, counterparty_pub_nonce) = nonce().unwrap();
+        let session = CoSignSession::new(
+            qso.clone(),
+            counterparty.id.clone(),
+            station.pub_key,
+            counterparty.pub_key,
+            station_pub_nonce,
+            counterparty_pub_nonce,
+        )
+        .unwrap();
+        let station_sig = session
+            .sign(&test_vectors::station_keypair(), station_nonce)
+            .unwrap();
+        let counterparty_sig = session
+            .sign(&counterparty_keys, counterparty_nonce)
+            .unwrap();
+
+        let mut forged = counterparty_sig.clone();
+        forged.s[31] ^= 1;
+        let err = session.clone().finish(&station_sig, &forged).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid partial signature of the counterparty"
+        );
+
+        let cosigned = session.finish(&station_sig, &counterparty_sig).unwrap();
+        cosigned
+            .verify(&station.pub_key, &counterparty.pub_key)
+            .unwrap();
+
+        let json_msg = serde_json::to_string(&cosigned).unwrap();
+        let cosigned_dese: CoSignedQso = serde_json::from_str(&json_msg).unwrap();
+        cosigned_dese
+            .verify(&station.pub_key, &counterparty.pub_key)
+            .unwrap();
+
+        assert!(cosigned
+            .verify(&counterparty.pub_key, &station.pub_key)
+            .is_err());
+
+        // Nonces are bound to the session and QSOs must be signed by the
+        // station.
+        let (other_nonce, _) = nonce().unwrap();
+        let (_, pub_nonce) = nonce().unwrap();
+        let session = CoSignSession::new(
+            qso.clone(),
+            counterparty.id.clone(),
+            station.pub_key,
+            counterparty.pub_key,
+            pub_nonce.clone(),
+            pub_nonce.clone(),
+        )
+        .unwrap();
+        assert!(session.sign(&counterparty_keys, other_nonce).is_err());
+        assert!(CoSignSession::new(
+            qso,
+            counterparty.id.clone(),
+            counterparty.pub_key,
+            station.pub_key,
+            pub_nonce.clone(),
+            pub_nonce,
+        )
+        .is_err());
+    }
+}
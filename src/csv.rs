@@ -0,0 +1,367 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CSV import and export of QSOs.
+//!
+//! Spreadsheet logs use all kind of column names and units, a
+//! [`ColumnMapping`] describes them.
+
+use crate::progress::Monitor;
+use crate::{Id, Qso, QsoData};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// Unit of the frequency column.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FreqUnit {
+    Hz,
+    Khz,
+    #[default]
+    Mhz,
+}
+
+impl FreqUnit {
+    fn decimals(&self) -> usize {
+        match self {
+            FreqUnit::Hz => 0,
+            FreqUnit::Khz => 3,
+            FreqUnit::Mhz => 6,
+        }
+    }
+}
+
+/// Format of the date and time column. Times are always UTC.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum DatetimeFormat {
+    /// Seconds since the unix epoch.
+    Unix,
+    /// A [`chrono`] format string, e.g. `%Y-%m-%d %H:%M`.
+    Pattern(String),
+    /// `YYYY-MM-DD HH:MM:SS`, the seconds are optional when reading.
+    #[default]
+    Iso,
+}
+
+/// Names and units of the CSV columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub callsign: String,
+    pub datetime: String,
+    pub datetime_format: DatetimeFormat,
+    pub freq: String,
+    pub freq_unit: FreqUnit,
+    pub mode: String,
+    pub rst: String,
+    /// Optional column, comments are empty if the column is missing.
+    pub comments: Option<String>,
+    /// Columns stored as extension fields, keyed by column name.
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            callsign: "callsign".to_string(),
+            datetime: "datetime".to_string(),
+            datetime_format: DatetimeFormat::default(),
+            freq: "freq".to_string(),
+            freq_unit: FreqUnit::default(),
+            mode: "mode".to_string(),
+            rst: "rst".to_string(),
+            comments: Some("comments".to_string()),
+            extra: BTreeMap::new(),
+        }
+    }
+}
+
+/// Reads the QSOs of the station from a CSV file with a header row.
+///
+/// Errors report the line of the offending row. Rows are not validated, the
+/// QSOs are validated when signed.
+pub fn read_qsos<R: Read>(
+    reader: R,
+    station_id: &Id,
+    mapping: &ColumnMapping,
+) -> Result<Vec<QsoData>> {
+    read_qsos_with_monitor(reader, station_id, mapping, &mut Monitor::new())
+}
+
+/// Reads the QSOs like [`read_qsos`], reporting every row read to the
+/// monitor.
+pub fn read_qsos_with_monitor<R: Read>(
+    reader: R,
+    station_id: &Id,
+    mapping: &ColumnMapping,
+    monitor: &mut Monitor,
+) -> Result<Vec<QsoData>> {
+    monitor.start(None)?;
+    let mut reader = ::csv::ReaderBuilder::new()
+        .trim(::csv::Trim::All)
+        .from_reader(reader);
+    let headers = reader.headers()?.clone();
+
+    let find = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name))
+    };
+    let column = |name: &str| find(name).ok_or_else(|| anyhow!("missing column {}", name));
+
+    let callsign = column(&mapping.callsign)?;
+    let datetime = column(&mapping.datetime)?;
+    let freq = column(&mapping.freq)?;
+    let mode = column(&mapping.mode)?;
+    let rst = column(&mapping.rst)?;
+    let comments = mapping.comments.as_deref().and_then(find);
+    let extra = mapping
+        .extra
+        .iter()
+        .map(|(name, key)| Ok((column(name)?, key)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut qsos = vec![];
+
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let field = |index: usize| record.get(index).unwrap_or_default();
+
+        let qso = (|| -> Result<QsoData> {
+            Ok(QsoData {
+                station_id: station_id.clone(),
+                callsign: field(callsign).to_string(),
+                datetime: parse_datetime(field(datetime), &mapping.datetime_format)?,
+                freq: parse_freq(field(freq), mapping.freq_unit)?,
+                mode: field(mode).to_uppercase(),
+                rst: field(rst).to_string(),
+                comments: comments.map(field).unwrap_or_default().to_string(),
+                extra: extra
+                    .iter()
+                    .filter(|(index, _)| !field(*index).is_empty())
+                    .map(|(index, key)| (key.to_string(), Value::from(field(*index))))
+                    .collect(),
+                tags: vec![],
+                prev_id: None,
+            })
+        })()
+        .with_context(|| format!("invalid row at line {}", line))?;
+
+        qsos.push(qso);
+        monitor.record(true)?;
+    }
+
+    Ok(qsos)
+}
+
+/// Writes the QSOs as CSV with a header row.
+///
+/// Extension fields not listed in the mapping, and non string ones, are not
+/// exported.
+pub fn write_qsos<W: Write>(writer: W, qsos: &[Qso], mapping: &ColumnMapping) -> Result<()> {
+    write_qsos_with_monitor(writer, qsos, mapping, &mut Monitor::new())
+}
+
+/// Writes the QSOs like [`write_qsos`], reporting every row written to the
+/// monitor.
+pub fn write_qsos_with_monitor<W: Write>(
+    writer: W,
+    qsos: &[Qso],
+    mapping: &ColumnMapping,
+    monitor: &mut Monitor,
+) -> Result<()> {
+    monitor.start(Some(qsos.len() as u64))?;
+    let mut writer = ::csv::Writer::from_writer(writer);
+
+    let mut header = vec![
+        mapping.callsign.as_str(),
+        &mapping.datetime,
+        &mapping.freq,
+        &mapping.mode,
+        &mapping.rst,
+    ];
+    header.extend(mapping.comments.as_deref());
+    header.extend(mapping.extra.keys().map(String::as_str));
+    writer.write_record(&header)?;
+
+    for qso in qsos {
+        let mut record = vec![
+            qso.callsign.clone(),
+            format_datetime(qso.datetime, &mapping.datetime_format)?,
+            format_freq(qso.freq, mapping.freq_unit),
+            qso.mode.clone(),
+            qso.rst.clone(),
+        ];
+        if mapping.comments.is_some() {
+            record.push(qso.comments.clone());
+        }
+        for key in mapping.extra.values() {
+            let value = qso
+                .extra
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            record.push(value.to_string());
+        }
+        writer.write_record(&record)?;
+        monitor.record(true)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn parse_datetime(value: &str, format: &DatetimeFormat) -> Result<u64> {
+    let datetime = match format {
+        DatetimeFormat::Unix => return value.parse().context("invalid unix time"),
+        DatetimeFormat::Pattern(pattern) => NaiveDateTime::parse_from_str(value, pattern),
+        DatetimeFormat::Iso => NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")),
+    }
+    .with_context(|| format!("invalid datetime {}", value))?;
+
+    u64::try_from(datetime.and_utc().timestamp()).context("datetime before 1970")
+}
+
+fn format_datetime(datetime: u64, format: &DatetimeFormat) -> Result<String> {
+    let pattern = match format {
+        DatetimeFormat::Unix => return Ok(datetime.to_string()),
+        DatetimeFormat::Pattern(pattern) => pattern.as_str(),
+        DatetimeFormat::Iso => "%Y-%m-%d %H:%M:%S",
+    };
+
+    let datetime = i64::try_from(datetime)
+        .ok()
+        .and_then(|datetime| DateTime::from_timestamp(datetime, 0))
+        .ok_or_else(|| anyhow!("invalid datetime {}", datetime))?;
+
+    Ok(datetime.format(pattern).to_string())
+}
+
+/// Parses a decimal frequency without going through floats, so 14.0743 MHz is
+/// exactly 14074300 Hz.
+pub(crate) fn parse_freq(value: &str, unit: FreqUnit) -> Result<u64> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    let decimals = unit.decimals();
+
+    if int.is_empty()
+        || frac.len() > decimals
+        || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+    {
+        bail!("invalid frequency {}", value);
+    }
+
+    let digits = format!("{}{:0<width$}", int, frac, width = decimals);
+    digits
+        .parse()
+        .with_context(|| format!("invalid frequency {}", value))
+}
+
+pub(crate) fn format_freq(freq: u64, unit: FreqUnit) -> String {
+    let decimals = unit.decimals();
+    if decimals == 0 {
+        return freq.to_string();
+    }
+    let scale = 10u64.pow(decimals as u32);
+    format!(
+        "{}.{:0width$}",
+        freq / scale,
+        freq % scale,
+        width = decimals
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csv::{self, ColumnMapping, DatetimeFormat, FreqUnit};
+    use crate::keys::generate_keypair;
+    use crate::qso::GRIDSQUARE;
+    use crate::{Id, Qso};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_read_write() {
+        let station_id = Id::new("station");
+        let mapping = ColumnMapping {
+            callsign: "Call".to_string(),
+            datetime: "Date".to_string(),
+            datetime_format: DatetimeFormat::Pattern("%d/%m/%Y %H:%M".to_string()),
+            freq: "kHz".to_string(),
+            freq_unit: FreqUnit::Khz,
+            comments: None,
+            extra: BTreeMap::from([("Grid".to_string(), GRIDSQUARE.to_string())]),
+            ..ColumnMapping::default()
+        };
+
+        let input = "Call,Date,kHz,Mode,RST,Grid\n\
+                     LW3DZR,01/01/2024 20:37,14074.3,ft8,-10,GF05\n\
+                     LU1AA,02/01/2024 00:05,7030,CW,599,\n";
+
+        let qsos = csv::read_qsos(input.as_bytes(), &station_id, &mapping).unwrap();
+        assert_eq!(qsos.len(), 2);
+        assert_eq!(qsos[0].callsign, "LW3DZR");
+        assert_eq!(qsos[0].datetime, 1704141420);
+        assert_eq!(qsos[0].freq, 14074300);
+        assert_eq!(qsos[0].mode, "FT8");
+        assert_eq!(qsos[0].extra[GRIDSQUARE], "GF05");
+        assert_eq!(qsos[1].freq, 7030000);
+        assert!(qsos[1].extra.is_empty());
+
+        let keys = generate_keypair();
+        let qsos: Vec<Qso> = qsos.into_iter().map(|qso| Qso::new(qso, &keys)).collect();
+
+        let mut output = vec![];
+        csv::write_qsos(&mut output, &qsos, &mapping).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Call,Date,kHz,mode,rst,Grid\n\
+             LW3DZR,01/01/2024 20:37,14074.300,FT8,-10,GF05\n\
+             LU1AA,02/01/2024 00:05,7030.000,CW,599,\n"
+        );
+
+        let mut output = vec![];
+        csv::write_qsos(&mut output, &qsos, &ColumnMapping::default()).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("LW3DZR,2024-01-01 20:37:00,14.074300,FT8,-10,\n"));
+
+        let qsos_dese =
+            csv::read_qsos(output.as_bytes(), &station_id, &ColumnMapping::default()).unwrap();
+        assert_eq!(qsos_dese[1].freq, 7030000);
+        assert_eq!(qsos_dese[1].datetime, 1704153900);
+    }
+
+    #[test]
+    fn test_invalid_rows() {
+        let station_id = Id::new("station");
+        let mapping = ColumnMapping::default();
+
+        let err = csv::read_qsos(
+            "callsign,datetime,freq,mode,rst\nLW3DZR,2024-01-01 20:37,14.0743.1,CW,599\n"
+                .as_bytes(),
+            &station_id,
+            &mapping,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "invalid row at line 2");
+
+        assert!(csv::read_qsos(
+            "callsign,datetime,freq,mode\n".as_bytes(),
+            &station_id,
+            &mapping
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::keys::generate_keypair;
+use crate::kind::Kind;
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{version, Id};
+use anyhow::{anyhow, bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::{Keypair, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+
+/// Authorizes a secondary key ( a logging laptop, a phone ) to sign objects on
+/// behalf of a station, restricted to a time window and a set of object kinds.
+///
+/// The delegation is signed by the station key, so the station key can be kept
+/// offline while the delegate key is used for day to day logging.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Delegation {
+    pub id: Id,
+    pub station_id: Id,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::PublicKey"))]
+    pub delegate_pub_key: XOnlyPublicKey,
+    pub kinds: Vec<Kind>,
+    pub valid_from: u64,
+    pub valid_until: u64,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl Delegation {
+    /// Current version of the delegation object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the delegation verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Returns true if the delegation version is supported.
+    pub fn is_supported_version(version: u8) -> bool {
+        Self::SUPPORTED_VERSIONS.contains(&version)
+    }
+
+    /// Creates a new Delegation and signs the object using the given station
+    /// signer.
+    pub fn new(
+        station_id: Id,
+        signer: &dyn Signer,
+        delegate_pub_key: XOnlyPublicKey,
+        kinds: Vec<Kind>,
+        valid_from: u64,
+        valid_until: u64,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let version = Self::VERSION;
+        let id = Self::compute_id(
+            &station_id,
+            &delegate_pub_key,
+            &kinds,
+            valid_from,
+            valid_until,
+            created_at,
+            version,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let delegation = Self {
+            id,
+            station_id,
+            delegate_pub_key,
+            kinds,
+            valid_from,
+            valid_until,
+            created_at,
+            version,
+            sig,
+        };
+
+        delegation.validate()?;
+
+        Ok(delegation)
+    }
+
+    /// Creates a fresh operating key and a delegation to it valid from now
+    /// for `lifetime` seconds, e.g. a Field Day laptop signing QSOs for 48
+    /// hours.
+    ///
+    /// The window bounds the creation time of the signed objects, which the
+    /// key holder picks, so the delegate key can still sign objects backdated
+    /// into the window once it's over. Verifiers that know when an object was
+    /// received should check that time against the window too. The returned
+    /// key should never be persisted beyond the event.
+    pub fn ephemeral(
+        station_id: Id,
+        signer: &dyn Signer,
+        kinds: Vec<Kind>,
+        lifetime: u64,
+    ) -> Result<(Keypair, Self)> {
+        let delegate_keys = generate_keypair();
+        let valid_from = unix_timstamp();
+        let valid_until = valid_from
+            .checked_add(lifetime)
+            .ok_or_else(|| anyhow!("invalid lifetime"))?;
+        let delegation = Self::new(
+            station_id,
+            signer,
+            delegate_keys.x_only_public_key().0,
+            kinds,
+            valid_from,
+            valid_until,
+        )?;
+
+        Ok((delegate_keys, delegation))
+    }
+
+    /// Returns true if the validity window is over at the given time.
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.valid_until
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Returns true if the delegate key is allowed to sign an object of the
+    /// given kind created at the given time.
+    pub fn authorizes(&self, kind: Kind, created_at: u64) -> bool {
+        self.kinds.contains(&kind)
+            && created_at >= self.valid_from
+            && created_at <= self.valid_until
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        delegate_pub_key: &XOnlyPublicKey,
+        kinds: &[Kind],
+        valid_from: u64,
+        valid_until: u64,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                station_id,
+                delegate_pub_key,
+                kinds,
+                valid_from,
+                valid_until,
+                created_at,
+                version,
+            ),
+        )
+    }
+}
+
+impl Validate for Delegation {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.kinds.is_empty() {
+            bail!("invalid kinds");
+        }
+
+        if self.valid_from > self.valid_until {
+            bail!("invalid validity window");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for Delegation {
+    const KIND: Kind = Kind::Delegation;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.delegate_pub_key,
+            &self.kinds,
+            self.valid_from,
+            self.valid_until,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::delegation::Delegation;
+    use crate::keys::generate_keypair;
+    use crate::kind::Kind;
+    use crate::signer::ContextSigner;
+    use crate::time::unix_timstamp;
+    use crate::{signing_context, test_vectors, Station};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_delegation() {
+        let station_keys = generate_keypair();
+        let device_keys = generate_keypair();
+
+        let station = Station::new(
+            &station_keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let now = unix_timstamp();
+        let delegation = Delegation::new(
+            station.id.clone(),
+            &station_keys,
+            device_keys.x_only_public_key().0,
+            vec![Kind::Qso],
+            now - 60,
+            now + 3600,
+        )
+        .unwrap();
+
+        delegation.verify(&station.pub_key).unwrap();
+
+        let json_msg = serde_json::to_string(&delegation).unwrap();
+        let delegation_dese: Delegation = serde_json::from_str(&json_msg).unwrap();
+        delegation_dese.verify(&station.pub_key).unwrap();
+
+        assert!(delegation.authorizes(Kind::Qso, now));
+        assert!(!delegation.authorizes(Kind::Certificate, now));
+        assert!(!delegation.authorizes(Kind::Qso, now + 7200));
+
+        assert!(delegation
+            .verify(&device_keys.x_only_public_key().0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_ephemeral() {
+        let station_keys = test_vectors::station_keypair();
+        let station_id = test_vectors::station().id;
+
+        let (laptop_keys, delegation) = Delegation::ephemeral(
+            station_id.clone(),
+            &station_keys,
+            vec![Kind::Qso],
+            48 * 3600,
+        )
+        .unwrap();
+
+        delegation.verify(&test_vectors::station().pub_key).unwrap();
+        assert_eq!(
+            delegation.delegate_pub_key,
+            laptop_keys.x_only_public_key().0
+        );
+        assert_eq!(delegation.valid_until - delegation.valid_from, 48 * 3600);
+        assert!(delegation.authorizes(Kind::Qso, delegation.valid_from + 3600));
+        assert!(!delegation.is_expired(delegation.valid_until));
+        assert!(delegation.is_expired(delegation.valid_until + 1));
+        assert!(!delegation.authorizes(Kind::Qso, delegation.valid_until + 1));
+
+        assert!(
+            Delegation::ephemeral(station_id, &station_keys, vec![Kind::Qso], u64::MAX).is_err()
+        );
+    }
+
+    #[test]
+    fn test_signer() {
+        let signer = ContextSigner::new(signing_context(), test_vectors::station_keypair());
+        let device_keys = generate_keypair();
+
+        let now = unix_timstamp();
+        let delegation = Delegation::new(
+            test_vectors::station().id,
+            &signer,
+            device_keys.x_only_public_key().0,
+            vec![Kind::Qso],
+            now,
+            now + 3600,
+        )
+        .unwrap();
+        delegation.verify(&test_vectors::station().pub_key).unwrap();
+
+        let (_, delegation) =
+            Delegation::ephemeral(test_vectors::station().id, &signer, vec![Kind::Qso], 3600)
+                .unwrap();
+        delegation.verify(&test_vectors::station().pub_key).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_window() {
+        let station_keys = generate_keypair();
+        let device_keys = generate_keypair();
+
+        let station = Station::new(
+            &station_keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let delegation = Delegation::new(
+            station.id.clone(),
+            &station_keys,
+            device_keys.x_only_public_key().0,
+            vec![Kind::Qso],
+            100,
+            10,
+        );
+
+        assert!(delegation.is_err());
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::signer::{SignRequest, SignResponse, Signer};
+use anyhow::{anyhow, bail, Context, Result};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Request sent to an external signing device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum DeviceRequest {
+    GetPublicKey,
+    Sign(SignRequest),
+}
+
+/// Response returned by an external signing device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DeviceResponse {
+    PublicKey { pub_key: XOnlyPublicKey },
+    Sign(SignResponse),
+    Error { error: String },
+}
+
+/// Signer backed by an external signing device ( a hardware token, a
+/// microcontroller attached to a serial port ) so the station secret key never
+/// leaves the device.
+///
+/// The device speaks a line based protocol: every request is a single line of
+/// json encoded [`DeviceRequest`] and the device answers with a single line of
+/// json encoded [`DeviceResponse`].
+pub struct DeviceSigner<T> {
+    port: Mutex<BufReader<T>>,
+}
+
+impl DeviceSigner<File> {
+    /// Opens the device at the given path ( e.g. /dev/ttyACM0 ). The serial
+    /// line settings must be configured beforehand.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let port = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path.as_ref())
+            .with_context(|| format!("failed to open device {}", path.as_ref().display()))?;
+        Ok(Self::new(port))
+    }
+}
+
+impl<T: Read + Write> DeviceSigner<T> {
+    /// Creates a new DeviceSigner over an already opened device port.
+    pub fn new(port: T) -> Self {
+        Self {
+            port: Mutex::new(BufReader::new(port)),
+        }
+    }
+
+    fn call(&self, request: &DeviceRequest) -> Result<DeviceResponse> {
+        let mut port = self
+            .port
+            .lock()
+            .map_err(|_| anyhow!("device lock poisoned"))?;
+
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        port.get_mut().write_all(line.as_bytes())?;
+        port.get_mut().flush()?;
+
+        let mut response = String::new();
+        if port.read_line(&mut response)? == 0 {
+            bail!("device closed the connection");
+        }
+
+        let response: DeviceResponse =
+            serde_json::from_str(&response).context("invalid device response")?;
+
+        if let DeviceResponse::Error { error } = response {
+            bail!("device error: {}", error);
+        }
+
+        Ok(response)
+    }
+}
+
+impl<T: Read + Write> Signer for DeviceSigner<T> {
+    fn public_key(&self) -> Result<XOnlyPublicKey> {
+        match self.call(&DeviceRequest::GetPublicKey)? {
+            DeviceResponse::PublicKey { pub_key } => Ok(pub_key),
+            _ => bail!("unexpected device response"),
+        }
+    }
+
+    fn sign(&self, request: &SignRequest) -> Result<SignResponse> {
+        match self.call(&DeviceRequest::Sign(request.clone()))? {
+            DeviceResponse::Sign(response) => Ok(response),
+            _ => bail!("unexpected device response"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::{DeviceRequest, DeviceResponse, DeviceSigner};
+    use crate::keys::generate_keypair;
+    use crate::Station;
+    use codes_iso_3166::part_1::CountryCode;
+    use secp256k1::Keypair;
+    use std::io::{Read, Write};
+
+    /// Emulates a signing device answering every request written to it.
+    struct MockDevice {
+        keys: Keypair,
+        output: Vec<u8>,
+        pos: usize,
+        fail: bool,
+    }
+
+    impl MockDevice {
+        fn new(fail: bool) -> Self {
+            Self {
+                keys: generate_keypair(),
+                output: vec![],
+                pos: 0,
+                fail,
+            }
+        }
+    }
+
+    impl Read for MockDevice {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = (&self.output[self.pos..]).read(buf)?;
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    impl Write for MockDevice {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let request: DeviceRequest = serde_json::from_slice(buf).unwrap();
+            let response = match request {
+                _ if self.fail => DeviceResponse::Error {
+                    error: "user rejected".to_string(),
+                },
+                DeviceRequest::GetPublicKey => DeviceResponse::PublicKey {
+                    pub_key: self.keys.x_only_public_key().0,
+                },
+                DeviceRequest::Sign(request) => DeviceResponse::Sign(crate::SignResponse {
+                    sig: request.id.sign(&self.keys),
+                }),
+            };
+            serde_json::to_writer(&mut self.output, &response).unwrap();
+            self.output.push(b'\n');
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_device_signer() {
+        let signer = DeviceSigner::new(MockDevice::new(false));
+
+        let station = Station::new_with_signer(
+            &signer,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        station.verify().unwrap();
+    }
+
+    #[test]
+    fn test_device_error() {
+        let signer = DeviceSigner::new(MockDevice::new(true));
+
+        let station = Station::new_with_signer(
+            &signer,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        );
+
+        assert!(station.is_err());
+    }
+}
@@ -0,0 +1,222 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Station discovery by callsign.
+//!
+//! Anybody can publish a station with any callsign, so discovery returns every
+//! candidate found on the relays ranked by how much the [`TrustStore`] backs
+//! it. Callers confirming a QSO usually pick the first candidate.
+
+use crate::callsign::Callsign;
+use crate::event::{Event, Filter};
+use crate::signable::Signable;
+use crate::trust::{Role, TrustStore};
+use crate::{Id, Kind, RelayPool, Station};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// How much a candidate station is trusted, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrustLevel {
+    /// No certificate from a trusted root.
+    Unknown,
+    /// Holds a certificate issued by a root anchor.
+    Certified,
+    /// The station is a root anchor itself.
+    Anchor,
+}
+
+/// A station found for a callsign.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub station: Station,
+    pub trust: TrustLevel,
+    /// Ids of the valid certificates issued to the station by root anchors.
+    pub certificates: Vec<Id>,
+}
+
+/// Queries the pool for the stations using the callsign and returns them
+/// ranked by trust, newest first within the same trust level.
+///
+/// Stations with invalid signatures are dropped.
+pub fn discover(
+    pool: &mut RelayPool,
+    trust: &TrustStore,
+    callsign: &str,
+) -> Result<Vec<Candidate>> {
+    let stations: Vec<Station> = pool
+        .query(&Filter::new().kinds([Kind::Station]))?
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Station(station) => Some(station),
+            _ => None,
+        })
+        .filter(|station| Callsign::matches(&station.callsign, callsign))
+        .filter(|station| station.verify().is_ok())
+        .collect();
+
+    if stations.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut certificates: BTreeMap<Id, Vec<Id>> = BTreeMap::new();
+    let roots: Vec<Id> = trust
+        .anchors()
+        .filter(|anchor| anchor.roles.contains(&Role::Root))
+        .map(|anchor| anchor.station_id.clone())
+        .collect();
+
+    if !roots.is_empty() {
+        let filter = Filter::new().kinds([Kind::Certificate]).station_ids(roots);
+        for event in pool.query(&filter)? {
+            let Event::Certificate(certificate) = event else {
+                continue;
+            };
+            let Some(issuer) = trust.anchor(certificate.issuer_id()) else {
+                continue;
+            };
+            if issuer.roles.contains(&Role::Root) && certificate.verify(&issuer.pub_key).is_ok() {
+                certificates
+                    .entry(certificate.subject_id().clone())
+                    .or_default()
+                    .push(certificate.id().clone());
+            }
+        }
+    }
+
+    let mut candidates: Vec<Candidate> = stations
+        .into_iter()
+        .map(|station| {
+            let certificates = certificates.remove(&station.id).unwrap_or_default();
+            let trust = if trust
+                .anchor(&station.id)
+                .is_some_and(|anchor| anchor.pub_key == station.pub_key)
+            {
+                TrustLevel::Anchor
+            } else if !certificates.is_empty() {
+                TrustLevel::Certified
+            } else {
+                TrustLevel::Unknown
+            };
+
+            Candidate {
+                station,
+                trust,
+                certificates,
+            }
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| {
+        b.trust
+            .cmp(&a.trust)
+            .then(b.station.created_at.cmp(&a.station.created_at))
+    });
+
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::discovery::{self, TrustLevel};
+    use crate::event::{Event, Filter};
+    use crate::keys::generate_keypair;
+    use crate::trust::{Anchor, Role, TrustStore};
+    use crate::{Certificate, Relay, RelayPool, Signable, Station};
+    use anyhow::Result;
+    use codes_iso_3166::part_1::CountryCode;
+    use std::cell::RefCell;
+
+    struct MemoryRelay(RefCell<Vec<Event>>);
+
+    impl Relay for MemoryRelay {
+        fn url(&self) -> &str {
+            "wss://relay.example"
+        }
+
+        fn publish(&self, event: &Event) -> Result<()> {
+            self.0.borrow_mut().push(event.clone());
+            Ok(())
+        }
+
+        fn query(&self, filter: &Filter) -> Result<Vec<Event>> {
+            Ok(self
+                .0
+                .borrow()
+                .iter()
+                .filter(|event| filter.matches(event))
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_discover() {
+        let root_keys = generate_keypair();
+        let root = Station::new(
+            &root_keys,
+            "LU1AA".to_string(),
+            "Radio Club Argentino".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let station = |callsign: &str| {
+            let keys = generate_keypair();
+            Station::new(
+                &keys,
+                callsign.to_string(),
+                "Radio Club Caseros".to_string(),
+                CountryCode::AR,
+            )
+            .unwrap()
+        };
+
+        let genuine = station("LU4EV");
+        let impostor = station("lu4ev");
+        let other = station("LW3DZR");
+        let certificate = Certificate::new(root.id.clone(), &root_keys, genuine.id.clone());
+
+        let mut pool = RelayPool::new();
+        pool.add_relay(Box::new(MemoryRelay(RefCell::new(vec![]))));
+        for event in [
+            Event::Station(genuine.clone()),
+            Event::Station(impostor.clone()),
+            Event::Station(other),
+            Event::Certificate(certificate.clone()),
+        ] {
+            pool.publish(&event).unwrap();
+        }
+
+        let mut trust = TrustStore::new();
+        trust
+            .add_anchor(Anchor {
+                station_id: root.id.clone(),
+                pub_key: root.pub_key,
+                roles: vec![Role::Root],
+            })
+            .unwrap();
+
+        let candidates = discovery::discover(&mut pool, &trust, "LU4EV").unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].station, genuine);
+        assert_eq!(candidates[0].trust, TrustLevel::Certified);
+        assert_eq!(candidates[0].certificates, vec![certificate.id().clone()]);
+        assert_eq!(candidates[1].station, impostor);
+        assert_eq!(candidates[1].trust, TrustLevel::Unknown);
+
+        assert!(discovery::discover(&mut pool, &trust, "LU9ZZ")
+            .unwrap()
+            .is_empty());
+    }
+}
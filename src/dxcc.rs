@@ -0,0 +1,187 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resolution of callsign prefixes to countries.
+//!
+//! The table covers the ITU prefix blocks of the most active countries, it is
+//! not a complete DXCC list. Callsigns with an unknown prefix resolve to
+//! `None`.
+
+use crate::callsign::Callsign;
+use codes_iso_3166::part_1::CountryCode;
+
+/// ( first prefix, last prefix, country ), both prefixes of the same length.
+type PrefixBlock = (&'static str, &'static str, CountryCode);
+
+const PREFIXES: &[PrefixBlock] = &[
+    ("2E", "2E", CountryCode::GB),
+    ("3G", "3G", CountryCode::CL),
+    ("3Z", "3Z", CountryCode::PL),
+    ("4A", "4C", CountryCode::MX),
+    ("4D", "4I", CountryCode::PH),
+    ("4M", "4M", CountryCode::VE),
+    ("4X", "4X", CountryCode::IL),
+    ("4Z", "4Z", CountryCode::IL),
+    ("5J", "5K", CountryCode::CO),
+    ("5P", "5Q", CountryCode::DK),
+    ("6D", "6J", CountryCode::MX),
+    ("6K", "6N", CountryCode::KR),
+    ("7J", "7N", CountryCode::JP),
+    ("7S", "7S", CountryCode::SE),
+    ("8J", "8N", CountryCode::JP),
+    ("8S", "8S", CountryCode::SE),
+    ("8T", "8Y", CountryCode::IN),
+    ("9A", "9A", CountryCode::HR),
+    ("AA", "AK", CountryCode::US),
+    ("AM", "AO", CountryCode::ES),
+    ("AT", "AW", CountryCode::IN),
+    ("AX", "AX", CountryCode::AU),
+    ("AY", "AZ", CountryCode::AR),
+    ("B", "B", CountryCode::CN),
+    ("CA", "CE", CountryCode::CL),
+    ("CF", "CK", CountryCode::CA),
+    ("CL", "CM", CountryCode::CU),
+    ("CO", "CO", CountryCode::CU),
+    ("CP", "CP", CountryCode::BO),
+    ("CQ", "CU", CountryCode::PT),
+    ("CV", "CX", CountryCode::UY),
+    ("CY", "CY", CountryCode::CA),
+    ("DA", "DR", CountryCode::DE),
+    ("DS", "DS", CountryCode::KR),
+    ("DU", "DZ", CountryCode::PH),
+    ("E2", "E2", CountryCode::TH),
+    ("EA", "EH", CountryCode::ES),
+    ("EI", "EJ", CountryCode::IE),
+    ("EM", "EO", CountryCode::UA),
+    ("F", "F", CountryCode::FR),
+    ("G", "G", CountryCode::GB),
+    ("HA", "HA", CountryCode::HU),
+    ("HB", "HB", CountryCode::CH),
+    ("HB0", "HB0", CountryCode::LI),
+    ("HC", "HD", CountryCode::EC),
+    ("HF", "HF", CountryCode::PL),
+    ("HG", "HG", CountryCode::HU),
+    ("HJ", "HK", CountryCode::CO),
+    ("HL", "HL", CountryCode::KR),
+    ("HS", "HS", CountryCode::TH),
+    ("I", "I", CountryCode::IT),
+    ("J4", "J4", CountryCode::GR),
+    ("JA", "JS", CountryCode::JP),
+    ("K", "K", CountryCode::US),
+    ("KP3", "KP4", CountryCode::PR),
+    ("L2", "L9", CountryCode::AR),
+    ("LA", "LN", CountryCode::NO),
+    ("LO", "LW", CountryCode::AR),
+    ("M", "M", CountryCode::GB),
+    ("N", "N", CountryCode::US),
+    ("NP3", "NP4", CountryCode::PR),
+    ("OA", "OC", CountryCode::PE),
+    ("OE", "OE", CountryCode::AT),
+    ("OF", "OJ", CountryCode::FI),
+    ("OK", "OL", CountryCode::CZ),
+    ("OM", "OM", CountryCode::SK),
+    ("ON", "OT", CountryCode::BE),
+    ("OU", "OZ", CountryCode::DK),
+    ("PA", "PI", CountryCode::NL),
+    ("PP", "PY", CountryCode::BR),
+    ("R", "R", CountryCode::RU),
+    ("S5", "S5", CountryCode::SI),
+    ("SA", "SM", CountryCode::SE),
+    ("SP", "SR", CountryCode::PL),
+    ("SV", "SZ", CountryCode::GR),
+    ("TA", "TC", CountryCode::TR),
+    ("TK", "TK", CountryCode::FR),
+    ("TM", "TM", CountryCode::FR),
+    ("UA", "UI", CountryCode::RU),
+    ("UR", "UZ", CountryCode::UA),
+    ("VA", "VG", CountryCode::CA),
+    ("VK", "VK", CountryCode::AU),
+    ("VO", "VO", CountryCode::CA),
+    ("VU", "VU", CountryCode::IN),
+    ("VY", "VY", CountryCode::CA),
+    ("W", "W", CountryCode::US),
+    ("WP3", "WP4", CountryCode::PR),
+    ("XE", "XF", CountryCode::MX),
+    ("XJ", "XO", CountryCode::CA),
+    ("XQ", "XR", CountryCode::CL),
+    ("YB", "YH", CountryCode::ID),
+    ("YM", "YM", CountryCode::TR),
+    ("YO", "YR", CountryCode::RO),
+    ("YV", "YY", CountryCode::VE),
+    ("ZL", "ZM", CountryCode::NZ),
+    ("ZP", "ZP", CountryCode::PY),
+    ("ZR", "ZU", CountryCode::ZA),
+    ("ZV", "ZZ", CountryCode::BR),
+];
+
+/// Resolves the country of a callsign from its prefix.
+///
+/// A prefix modifier ( `CE0Y/LU4EV` ) takes precedence over the base callsign,
+/// maritime and aeronautical mobile callsigns have no country.
+pub fn resolve(callsign: &str) -> Option<CountryCode> {
+    let callsign = Callsign::normalize(callsign);
+
+    if callsign
+        .as_str()
+        .split('/')
+        .any(|part| part == "MM" || part == "AM")
+    {
+        return None;
+    }
+
+    let prefix = callsign.prefix().unwrap_or(callsign.base());
+
+    PREFIXES
+        .iter()
+        .filter(|(first, last, _)| {
+            prefix
+                .get(..first.len())
+                .map(|candidate| candidate >= *first && candidate <= *last)
+                .unwrap_or(false)
+        })
+        .max_by_key(|(first, _, _)| first.len())
+        .map(|(_, _, country)| *country)
+}
+
+/// Returns true if the callsign looks like a special event callsign
+/// ( e.g. `LU100RCA` ), which often don't follow the regular prefix blocks.
+pub fn is_special_event(callsign: &str) -> bool {
+    let callsign = Callsign::normalize(callsign);
+    let base = callsign.base();
+    base.len() > 6 || base.chars().filter(|c| c.is_ascii_digit()).count() > 2
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dxcc::{is_special_event, resolve};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("LU4EV"), Some(CountryCode::AR));
+        assert_eq!(resolve("lw3dzr"), Some(CountryCode::AR));
+        assert_eq!(resolve("W1AW"), Some(CountryCode::US));
+        assert_eq!(resolve("KP4AA"), Some(CountryCode::PR));
+        assert_eq!(resolve("HB0XX"), Some(CountryCode::LI));
+        assert_eq!(resolve("HB9XX"), Some(CountryCode::CH));
+        assert_eq!(resolve("CE0Y/LU4EV"), Some(CountryCode::CL));
+        assert_eq!(resolve("LU4EV/MM"), None);
+        assert_eq!(resolve("QQ1AA"), None);
+    }
+
+    #[test]
+    fn test_special_event() {
+        assert!(is_special_event("LU100RCA"));
+        assert!(!is_special_event("LU4EV"));
+    }
+}
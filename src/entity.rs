@@ -0,0 +1,162 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Error};
+use codes_iso_3166::part_1::CountryCode;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The location a station operates from.
+///
+/// Most DXCC entities are ISO 3166 countries, the rest are covered by
+/// dedicated variants. ISO countries serialize to their alpha-2 code, exactly
+/// like [`CountryCode`], the other entities to a lowercase name so they never
+/// collide with an ISO code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Entity {
+    /// An ISO 3166 country.
+    Country(CountryCode),
+    /// ITU Headquarters, Geneva ( 4U1I ).
+    ItuHq,
+    /// United Nations Headquarters, New York ( 4U1U ).
+    UnHq,
+    /// Sovereign Military Order of Malta ( 1A ).
+    Smom,
+    /// Mount Athos ( SV/A ).
+    MountAthos,
+    /// Scarborough Reef ( BS7 ).
+    ScarboroughReef,
+    /// Spratly Islands.
+    SpratlyIslands,
+    /// A deleted DXCC entity, by its DXCC entity number.
+    Deleted(u16),
+    /// Maritime or aeronautical mobile operation outside any entity.
+    InternationalWaters,
+}
+
+const NAMES: &[(Entity, &str)] = &[
+    (Entity::ItuHq, "itu_hq"),
+    (Entity::UnHq, "un_hq"),
+    (Entity::Smom, "smom"),
+    (Entity::MountAthos, "mount_athos"),
+    (Entity::ScarboroughReef, "scarborough_reef"),
+    (Entity::SpratlyIslands, "spratly_islands"),
+    (Entity::InternationalWaters, "international_waters"),
+];
+
+const DELETED_PREFIX: &str = "deleted:";
+
+impl Entity {
+    /// Returns the ISO country of the entity, if any.
+    pub fn country(&self) -> Option<CountryCode> {
+        match self {
+            Entity::Country(country) => Some(*country),
+            _ => None,
+        }
+    }
+}
+
+impl From<CountryCode> for Entity {
+    fn from(country: CountryCode) -> Self {
+        Entity::Country(country)
+    }
+}
+
+impl Display for Entity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Entity::Country(country) => write!(f, "{}", country),
+            Entity::Deleted(number) => write!(f, "{}{}", DELETED_PREFIX, number),
+            entity => {
+                let (_, name) = NAMES
+                    .iter()
+                    .find(|(known, _)| known == entity)
+                    .expect("every named entity has a name");
+                write!(f, "{}", name)
+            }
+        }
+    }
+}
+
+impl FromStr for Entity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(number) = s.strip_prefix(DELETED_PREFIX) {
+            let number = number
+                .parse()
+                .map_err(|_| anyhow!("invalid deleted entity {}", s))?;
+            return Ok(Entity::Deleted(number));
+        }
+
+        if let Some((entity, _)) = NAMES.iter().find(|(_, name)| *name == s) {
+            return Ok(*entity);
+        }
+
+        CountryCode::from_str(s)
+            .map(Entity::Country)
+            .map_err(|_| anyhow!("invalid entity {}", s))
+    }
+}
+
+impl Serialize for Entity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Entity::Country(country) => country.serialize(serializer),
+            entity => serializer.serialize_str(&entity.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Entity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Entity::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entity::Entity;
+    use codes_iso_3166::part_1::CountryCode;
+    use serde_json::json;
+
+    #[test]
+    fn test_serde() {
+        assert_eq!(
+            json!(Entity::Country(CountryCode::AR)),
+            json!(CountryCode::AR)
+        );
+
+        for entity in [
+            Entity::Country(CountryCode::AR),
+            Entity::ItuHq,
+            Entity::ScarboroughReef,
+            Entity::Deleted(247),
+            Entity::InternationalWaters,
+        ] {
+            let json = serde_json::to_string(&entity).unwrap();
+            assert_eq!(serde_json::from_str::<Entity>(&json).unwrap(), entity);
+        }
+
+        assert_eq!(json!(Entity::Deleted(247)), json!("deleted:247"));
+        assert!(serde_json::from_str::<Entity>("\"ZZ\"").is_err());
+    }
+}
@@ -0,0 +1,645 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::awards::AwardCertificate;
+use crate::bandplan::Band;
+use crate::contest::BonusClaim;
+use crate::geo::{BoundingBox, DistanceRange, LatLon};
+use crate::limits;
+use crate::signable::Signable;
+use crate::{
+    Attestation, BatchManifest, Beacon, Callsign, CallsignPattern, Certificate, Checkpoint,
+    Delegation, Id, Kind, LogChunk, NetAttendance, NetCheckIns, NetSession, Qso, RelayList,
+    Repeater, Report, Revocation, Spot, Station, StatsSnapshot, SwlAck, SwlReport, Tag, Tombstone,
+    TrustBundle,
+};
+use anyhow::{bail, Result};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// A signed object exchanged with relays, tagged with its kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", content = "object", rename_all = "snake_case")]
+pub enum Event {
+    Station(Station),
+    Qso(Qso),
+    Certificate(Certificate),
+    Delegation(Delegation),
+    AwardCertificate(AwardCertificate),
+    TrustBundle(TrustBundle),
+    BatchManifest(BatchManifest),
+    LogChunk(LogChunk),
+    RelayList(RelayList),
+    Revocation(Revocation),
+    Checkpoint(Checkpoint),
+    Attestation(Attestation),
+    Report(Report),
+    SwlReport(SwlReport),
+    SwlAck(SwlAck),
+    NetSession(NetSession),
+    NetCheckIns(NetCheckIns),
+    NetAttendance(NetAttendance),
+    Spot(Spot),
+    BonusClaim(BonusClaim),
+    Beacon(Beacon),
+    Repeater(Repeater),
+    StatsSnapshot(StatsSnapshot),
+    Tombstone(Tombstone),
+}
+
+impl Event {
+    /// Returns the kind of the object.
+    pub fn kind(&self) -> Kind {
+        match self {
+            Event::Station(_) => Station::KIND,
+            Event::Qso(_) => Qso::KIND,
+            Event::Certificate(_) => Certificate::KIND,
+            Event::Delegation(_) => Delegation::KIND,
+            Event::AwardCertificate(_) => AwardCertificate::KIND,
+            Event::TrustBundle(_) => TrustBundle::KIND,
+            Event::BatchManifest(_) => BatchManifest::KIND,
+            Event::LogChunk(_) => LogChunk::KIND,
+            Event::RelayList(_) => RelayList::KIND,
+            Event::Revocation(_) => Revocation::KIND,
+            Event::Checkpoint(_) => Checkpoint::KIND,
+            Event::Attestation(_) => Attestation::KIND,
+            Event::Report(_) => Report::KIND,
+            Event::SwlReport(_) => SwlReport::KIND,
+            Event::SwlAck(_) => SwlAck::KIND,
+            Event::NetSession(_) => NetSession::KIND,
+            Event::NetCheckIns(_) => NetCheckIns::KIND,
+            Event::NetAttendance(_) => NetAttendance::KIND,
+            Event::Spot(_) => Spot::KIND,
+            Event::BonusClaim(_) => BonusClaim::KIND,
+            Event::Beacon(_) => Beacon::KIND,
+            Event::Repeater(_) => Repeater::KIND,
+            Event::StatsSnapshot(_) => StatsSnapshot::KIND,
+            Event::Tombstone(_) => Tombstone::KIND,
+        }
+    }
+
+    /// Returns the object id.
+    pub fn id(&self) -> &Id {
+        match self {
+            Event::Station(station) => station.id(),
+            Event::Qso(qso) => qso.id(),
+            Event::Certificate(certificate) => certificate.id(),
+            Event::Delegation(delegation) => delegation.id(),
+            Event::AwardCertificate(certificate) => certificate.id(),
+            Event::TrustBundle(bundle) => bundle.id(),
+            Event::BatchManifest(manifest) => manifest.id(),
+            Event::LogChunk(chunk) => chunk.id(),
+            Event::RelayList(relay_list) => relay_list.id(),
+            Event::Revocation(revocation) => revocation.id(),
+            Event::Checkpoint(checkpoint) => checkpoint.id(),
+            Event::Attestation(attestation) => attestation.id(),
+            Event::Report(report) => report.id(),
+            Event::SwlReport(report) => report.id(),
+            Event::SwlAck(ack) => ack.id(),
+            Event::NetSession(session) => session.id(),
+            Event::NetCheckIns(list) => list.id(),
+            Event::NetAttendance(attendance) => attendance.id(),
+            Event::Spot(spot) => spot.id(),
+            Event::BonusClaim(claim) => claim.id(),
+            Event::Beacon(beacon) => beacon.id(),
+            Event::Repeater(repeater) => repeater.id(),
+            Event::StatsSnapshot(snapshot) => snapshot.id(),
+            Event::Tombstone(tombstone) => tombstone.id(),
+        }
+    }
+
+    /// Returns the id of the station which signed the object.
+    pub fn station_id(&self) -> &Id {
+        match self {
+            Event::Station(station) => &station.id,
+            Event::Qso(qso) => &qso.station_id,
+            Event::Certificate(certificate) => certificate.issuer_id(),
+            Event::Delegation(delegation) => &delegation.station_id,
+            Event::AwardCertificate(certificate) => &certificate.sponsor_id,
+            Event::TrustBundle(bundle) => &bundle.publisher_id,
+            Event::BatchManifest(manifest) => &manifest.station_id,
+            Event::LogChunk(chunk) => &chunk.station_id,
+            Event::RelayList(relay_list) => &relay_list.station_id,
+            Event::Revocation(revocation) => &revocation.station_id,
+            Event::Checkpoint(checkpoint) => &checkpoint.station_id,
+            Event::Attestation(attestation) => &attestation.auditor_id,
+            Event::Report(report) => &report.reporter_id,
+            Event::SwlReport(report) => &report.listener_id,
+            Event::SwlAck(ack) => &ack.station_id,
+            Event::NetSession(session) => &session.station_id,
+            Event::NetCheckIns(list) => &list.station_id,
+            Event::NetAttendance(attendance) => &attendance.station_id,
+            Event::Spot(spot) => &spot.station_id,
+            Event::BonusClaim(claim) => &claim.station_id,
+            Event::Beacon(beacon) => &beacon.station_id,
+            Event::Repeater(repeater) => &repeater.station_id,
+            Event::StatsSnapshot(snapshot) => &snapshot.station_id,
+            Event::Tombstone(tombstone) => &tombstone.station_id,
+        }
+    }
+
+    /// Returns the signed tags of the object, empty for kinds without tags.
+    pub fn tags(&self) -> &[Tag] {
+        match self {
+            Event::Qso(qso) => &qso.tags,
+            Event::Spot(spot) => &spot.tags,
+            Event::Beacon(beacon) => &beacon.tags,
+            Event::Repeater(repeater) => &repeater.tags,
+            _ => &[],
+        }
+    }
+
+    /// Returns the callsign of the object: the worked one of QSOs and the
+    /// heard one of spots and reports, the own one of stations, beacons and
+    /// repeaters. None for kinds without a callsign.
+    pub fn callsign(&self) -> Option<&str> {
+        match self {
+            Event::Station(station) => Some(&station.callsign),
+            Event::Qso(qso) => Some(&qso.callsign),
+            Event::SwlReport(report) => Some(&report.callsign),
+            Event::Spot(spot) => Some(&spot.callsign),
+            Event::Beacon(beacon) => Some(&beacon.callsign),
+            Event::Repeater(repeater) => Some(&repeater.callsign),
+            _ => None,
+        }
+    }
+
+    /// Returns the frequency in Hz of the object, the output one of
+    /// repeaters. None for kinds without a frequency.
+    pub fn freq(&self) -> Option<u64> {
+        match self {
+            Event::Qso(qso) => Some(qso.freq),
+            Event::SwlReport(report) => Some(report.freq),
+            Event::Spot(spot) => Some(spot.freq),
+            Event::Beacon(beacon) => Some(beacon.freq),
+            Event::Repeater(repeater) => Some(repeater.output_freq),
+            _ => None,
+        }
+    }
+
+    /// Returns the mode of the object. None for kinds without a mode.
+    pub fn mode(&self) -> Option<&str> {
+        match self {
+            Event::Qso(qso) => Some(&qso.mode),
+            Event::SwlReport(report) => Some(&report.mode),
+            Event::Spot(spot) => Some(&spot.mode),
+            Event::Beacon(beacon) => Some(&beacon.mode),
+            Event::Repeater(repeater) => Some(&repeater.mode),
+            _ => None,
+        }
+    }
+
+    /// Returns the grid square of the object: the worked one of QSOs, the own
+    /// one of stations, beacons and repeaters. None for kinds without a grid
+    /// square or when it's unknown.
+    pub fn grid(&self) -> Option<&str> {
+        match self {
+            Event::Station(station) => station.grid(),
+            Event::Qso(qso) => qso.gridsquare(),
+            Event::Beacon(beacon) => Some(&beacon.grid),
+            Event::Repeater(repeater) => Some(&repeater.grid),
+            _ => None,
+        }
+    }
+
+    /// Returns the proof of work of the object id, its leading zero bits.
+    /// Time sortable QSO ids can't carry a proof of work, their leading
+    /// zeros come from the creation time, so they count as zero.
+    pub fn pow_bits(&self) -> u32 {
+        match self {
+            Event::Qso(qso) if qso.sortable => 0,
+            _ => self.id().leading_zero_bits(),
+        }
+    }
+
+    /// Checks that the object carries a proof of work of at least
+    /// `min_difficulty` bits, as counted by [`Event::pow_bits`].
+    pub fn check_pow(&self, min_difficulty: u32) -> Result<()> {
+        let difficulty = self.pow_bits();
+        if difficulty < min_difficulty {
+            bail!(
+                "insufficient proof of work: {} < {}",
+                difficulty,
+                min_difficulty
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the earliest [`Tag::ExpiresAt`] of the object, if any.
+    pub fn expires_at(&self) -> Option<u64> {
+        self.tags()
+            .iter()
+            .filter_map(|tag| match tag {
+                Tag::ExpiresAt(expires_at) => Some(*expires_at),
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Returns true if the object expired at or before `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Verify the object signature against the public key of the station
+    /// which signed it, see [`Event::station_id`]. The event must fit in the
+    /// size budget of its kind, see [`crate::limits::max_event_size`].
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        limits::check_event_size(self)?;
+        match self {
+            Event::Station(station) => {
+                if station.pub_key != *station_pub_key {
+                    bail!("station public key mismatch");
+                }
+                station.verify()
+            }
+            Event::Qso(qso) => qso.verify(station_pub_key),
+            Event::Certificate(certificate) => Ok(certificate.verify(station_pub_key)?),
+            Event::Delegation(delegation) => delegation.verify(station_pub_key),
+            Event::AwardCertificate(certificate) => certificate.verify(station_pub_key),
+            Event::TrustBundle(bundle) => bundle.verify(station_pub_key),
+            Event::BatchManifest(manifest) => manifest.verify(station_pub_key),
+            Event::LogChunk(chunk) => chunk.verify(station_pub_key),
+            Event::RelayList(relay_list) => relay_list.verify(station_pub_key),
+            Event::Revocation(revocation) => revocation.verify(station_pub_key),
+            Event::Checkpoint(checkpoint) => checkpoint.verify(station_pub_key),
+            Event::Attestation(attestation) => attestation.verify(station_pub_key),
+            Event::Report(report) => report.verify(station_pub_key),
+            Event::SwlReport(report) => report.verify(station_pub_key),
+            Event::SwlAck(ack) => ack.verify(station_pub_key),
+            Event::NetSession(session) => session.verify(station_pub_key),
+            Event::NetCheckIns(list) => list.verify(station_pub_key),
+            Event::NetAttendance(attendance) => attendance.verify(station_pub_key),
+            Event::Spot(spot) => spot.verify(station_pub_key),
+            Event::BonusClaim(claim) => claim.verify(station_pub_key),
+            Event::Beacon(beacon) => beacon.verify(station_pub_key),
+            Event::Repeater(repeater) => repeater.verify(station_pub_key),
+            Event::StatsSnapshot(snapshot) => snapshot.verify(station_pub_key),
+            Event::Tombstone(tombstone) => tombstone.verify(station_pub_key),
+        }
+    }
+
+    /// Returns the object creation time.
+    pub fn created_at(&self) -> u64 {
+        match self {
+            Event::Station(station) => station.created_at,
+            Event::Qso(qso) => qso.created_at,
+            Event::Certificate(certificate) => Signable::created_at(certificate),
+            Event::Delegation(delegation) => delegation.created_at,
+            Event::AwardCertificate(certificate) => certificate.created_at,
+            Event::TrustBundle(bundle) => bundle.created_at,
+            Event::BatchManifest(manifest) => manifest.created_at,
+            Event::LogChunk(chunk) => chunk.created_at,
+            Event::RelayList(relay_list) => relay_list.created_at,
+            Event::Revocation(revocation) => revocation.created_at,
+            Event::Checkpoint(checkpoint) => checkpoint.created_at,
+            Event::Attestation(attestation) => attestation.created_at,
+            Event::Report(report) => report.created_at,
+            Event::SwlReport(report) => report.created_at,
+            Event::SwlAck(ack) => ack.created_at,
+            Event::NetSession(session) => session.created_at,
+            Event::NetCheckIns(list) => list.created_at,
+            Event::NetAttendance(attendance) => attendance.created_at,
+            Event::Spot(spot) => spot.created_at,
+            Event::BonusClaim(claim) => claim.created_at,
+            Event::Beacon(beacon) => beacon.created_at,
+            Event::Repeater(repeater) => repeater.created_at,
+            Event::StatsSnapshot(snapshot) => snapshot.created_at,
+            Event::Tombstone(tombstone) => tombstone.created_at,
+        }
+    }
+}
+
+/// Selects events by id, kind, station, tag and creation time, and by the
+/// callsign, band, mode and location of their content. Empty lists match
+/// everything.
+///
+/// Filters are also written as text queries, see [`crate::query`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Filter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<Id>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<Kind>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub station_ids: Vec<Id>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
+    pub callsigns: Vec<CallsignPattern>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
+    pub bands: Vec<Band>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub grids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<BoundingBox>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<DistanceRange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+impl Filter {
+    /// Creates a filter matching every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches the events with the given ids.
+    pub fn ids(mut self, ids: impl IntoIterator<Item = Id>) -> Self {
+        self.ids.extend(ids);
+        self
+    }
+
+    /// Matches the events of the given kinds.
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = Kind>) -> Self {
+        self.kinds.extend(kinds);
+        self
+    }
+
+    /// Matches the events signed by the given stations.
+    pub fn station_ids(mut self, station_ids: impl IntoIterator<Item = Id>) -> Self {
+        self.station_ids.extend(station_ids);
+        self
+    }
+
+    /// Matches the events with any of the given tags.
+    pub fn tags(mut self, tags: impl IntoIterator<Item = Tag>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+
+    /// Matches the events referencing any of the given ids with a
+    /// [`Tag::Ref`], e.g. the confirmations and amendments of a QSO. Like
+    /// every tag, references are matched together with the other tags of the
+    /// filter, not on top of them.
+    pub fn references(self, ids: impl IntoIterator<Item = Id>) -> Self {
+        self.tags(ids.into_iter().map(Tag::Ref))
+    }
+
+    /// Matches the events with a callsign matching any of the given
+    /// callsigns or patterns, see [`Event::callsign`].
+    pub fn callsigns<P: Into<CallsignPattern>>(
+        mut self,
+        callsigns: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.callsigns.extend(callsigns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Matches the events with a frequency within any of the given bands, in
+    /// any region.
+    pub fn bands(mut self, bands: impl IntoIterator<Item = Band>) -> Self {
+        self.bands.extend(bands);
+        self
+    }
+
+    /// Matches the events with any of the given modes, ignoring case.
+    pub fn modes(mut self, modes: impl IntoIterator<Item = String>) -> Self {
+        self.modes.extend(modes);
+        self
+    }
+
+    /// Matches the events with a grid square within any of the given ones,
+    /// ignoring case, e.g. `GF` for the grid field, see [`Event::grid`].
+    pub fn grids(mut self, grids: impl IntoIterator<Item = String>) -> Self {
+        self.grids.extend(grids);
+        self
+    }
+
+    /// Matches the events with the center of their grid square inside the
+    /// box.
+    pub fn bbox(mut self, bbox: BoundingBox) -> Self {
+        self.bbox = Some(bbox);
+        self
+    }
+
+    /// Matches the events with the center of their grid square within the
+    /// distance range.
+    pub fn distance(mut self, distance: DistanceRange) -> Self {
+        self.distance = Some(distance);
+        self
+    }
+
+    /// Returns true if the filter matches the band, mode, box or distance,
+    /// which stores don't index.
+    pub fn matches_content(&self) -> bool {
+        !self.bands.is_empty()
+            || !self.modes.is_empty()
+            || self.bbox.is_some()
+            || self.distance.is_some()
+    }
+
+    /// Matches the events created at or after the given time.
+    pub fn since(mut self, since: u64) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Matches the events created at or before the given time.
+    pub fn until(mut self, until: u64) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Limits the number of returned events.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns true if the event matches the filter.
+    pub fn matches(&self, event: &Event) -> bool {
+        let location = || event.grid().and_then(|grid| LatLon::from_grid(grid).ok());
+
+        (self.ids.is_empty() || self.ids.contains(event.id()))
+            && (self.kinds.is_empty() || self.kinds.contains(&event.kind()))
+            && (self.station_ids.is_empty() || self.station_ids.contains(event.station_id()))
+            && (self.tags.is_empty() || event.tags().iter().any(|tag| self.tags.contains(tag)))
+            && (self.callsigns.is_empty()
+                || event.callsign().is_some_and(|callsign| {
+                    let callsign = Callsign::normalize(callsign);
+                    self.callsigns
+                        .iter()
+                        .any(|pattern| pattern.matches(&callsign))
+                }))
+            && (self.bands.is_empty()
+                || event
+                    .freq()
+                    .and_then(Band::from_freq)
+                    .is_some_and(|band| self.bands.contains(&band)))
+            && (self.modes.is_empty()
+                || event
+                    .mode()
+                    .is_some_and(|mode| self.modes.iter().any(|m| m.eq_ignore_ascii_case(mode))))
+            && (self.grids.is_empty()
+                || event.grid().is_some_and(|grid| {
+                    let grid = grid.to_ascii_uppercase();
+                    self.grids
+                        .iter()
+                        .any(|prefix| grid.starts_with(&prefix.to_ascii_uppercase()))
+                }))
+            && self
+                .bbox
+                .is_none_or(|bbox| location().is_some_and(|point| bbox.contains(&point)))
+            && self
+                .distance
+                .as_ref()
+                .is_none_or(|distance| location().is_some_and(|point| distance.contains(&point)))
+            && self.since.is_none_or(|since| event.created_at() >= since)
+            && self.until.is_none_or(|until| event.created_at() <= until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{Event, Filter};
+    use crate::geo::{BoundingBox, DistanceRange};
+    use crate::keys::generate_keypair;
+    use crate::store::{MemoryStore, Store};
+    use crate::{test_vectors, Kind, Qso, QsoData, Station, Tag};
+    use codes_iso_3166::part_1::CountryCode;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_event_filter() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso = Qso::new(
+            QsoData {
+                station_id: station.id.clone(),
+                datetime: 1704141426,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                tags: vec![Tag::Ref(station.id.clone())],
+                ..test_vectors::qso_data()
+            },
+            &keys,
+        );
+
+        let event = Event::Qso(qso.clone());
+        let json_str = serde_json::to_string(&event).unwrap();
+        assert!(json_str.starts_with("{\"kind\":\"qso\""));
+        assert_eq!(serde_json::from_str::<Event>(&json_str).unwrap(), event);
+
+        assert!(Filter::new().matches(&event));
+        assert!(Filter::new().kinds([Kind::Qso]).matches(&event));
+        assert!(!Filter::new().kinds([Kind::Station]).matches(&event));
+        assert!(Filter::new()
+            .station_ids([station.id.clone()])
+            .matches(&event));
+        assert!(Filter::new().ids([qso.id.clone()]).matches(&event));
+        assert!(!Filter::new().since(qso.created_at + 1).matches(&event));
+        assert!(Filter::new().until(qso.created_at).matches(&event));
+
+        let references = Filter::new().references([station.id.clone()]);
+        assert!(references.matches(&event));
+        assert!(!references.matches(&Event::Station(station.clone())));
+        assert!(!Filter::new().references([qso.id.clone()]).matches(&event));
+        assert_eq!(
+            serde_json::to_value(&references).unwrap(),
+            json!({ "tags": [["ref", station.id.to_string()]] })
+        );
+    }
+
+    #[test]
+    fn test_geo_filter() {
+        let keys = generate_keypair();
+        let qso = |callsign: &str, grid: &str| {
+            Event::Qso(Qso::new(
+                QsoData {
+                    callsign: callsign.to_string(),
+                    datetime: 1704141426,
+                    comments: String::new(),
+                    extra: BTreeMap::from([("gridsquare".to_string(), json!(grid))]),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            ))
+        };
+        let caseros = qso("LU4EV", "GF05tk");
+        let new_york = qso("W2AAA", "fn20xr");
+        let berlin = qso("DL1AAA", "JO62qm");
+
+        let mut store = MemoryStore::new();
+        for event in [&caseros, &new_york, &berlin] {
+            store.insert(event.clone()).unwrap();
+        }
+        store
+            .insert(Event::Station(test_vectors::station()))
+            .unwrap();
+
+        let query = |filter: Filter| store.query(&filter).unwrap();
+        assert_eq!(
+            query(Filter::new().grids(["gf".to_string()])),
+            vec![caseros.clone()]
+        );
+        assert_eq!(
+            query(Filter::new().grids(["FN20".to_string(), "JO".to_string()])).len(),
+            2
+        );
+        let beyond = Filter::new().distance(DistanceRange::beyond("GF05tk", 5000));
+        assert_eq!(query(beyond.clone()).len(), 2);
+        assert!(!beyond.matches(&caseros));
+        assert_eq!(
+            query(Filter::new().distance(DistanceRange::within("GF05", 100))),
+            vec![caseros]
+        );
+        let europe = BoundingBox {
+            south: 35.0,
+            west: -10.0,
+            north: 70.0,
+            east: 40.0,
+        };
+        assert_eq!(query(Filter::new().bbox(europe)), vec![berlin]);
+        assert_eq!(
+            serde_json::to_value(Filter::new().distance(DistanceRange::beyond("GF05tk", 5000)))
+                .unwrap(),
+            json!({ "distance": { "from": "GF05tk", "min_km": 5000 } })
+        );
+    }
+
+    #[test]
+    fn test_check_pow() {
+        let qso =
+            Qso::new_sortable(test_vectors::qso_data(), &test_vectors::station_keypair()).unwrap();
+        assert!(qso.id.leading_zero_bits() >= 32);
+
+        // The creation time prefix doesn't count as proof of work.
+        let event = Event::Qso(qso);
+        event.check_pow(0).unwrap();
+        let err = event.check_pow(8).unwrap_err();
+        assert_eq!(err.to_string(), "insufficient proof of work: 0 < 8");
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! fldigi XML-RPC integration, enabled by the `fldigi` feature.
+//!
+//! fldigi doesn't announce logged QSOs on its own XML-RPC interface, and its
+//! log panel can't tell a save from the operator clearing it by hand. It
+//! does send every saved QSO to a remote logbook server, fllog, as a single
+//! `log.add_record` XML-RPC call carrying the ADIF record. [`FldigiBridge`]
+//! serves that interface, so only QSOs the operator saved are reported.
+//! Point fldigi's "logbook server" setting to the address of the bridge.
+
+use crate::adif::{read_adi, unescape};
+use crate::{Id, QsoData};
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Default port of the fllog XML-RPC server, the one fldigi connects to.
+pub const DEFAULT_PORT: u16 = 8421;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum size of an XML-RPC request, headers included.
+const MAX_REQUEST_LEN: u64 = 64 * 1024;
+
+thread_local! {
+    static METHOD: Regex = Regex::new(r"<methodName>\s*([^<]*?)\s*</methodName>").unwrap();
+    static VALUE: Regex =
+        Regex::new(r"<value>\s*(?:<(\w+)>([^<]*)</\w+>|([^<]*)</value>)").unwrap();
+}
+
+/// Receives the QSOs saved by fldigi.
+#[derive(Debug)]
+pub struct FldigiBridge {
+    listener: TcpListener,
+    station_id: Id,
+}
+
+impl FldigiBridge {
+    /// Listens on the address for the QSOs logged by the station.
+    pub fn bind(addr: impl ToSocketAddrs, station_id: Id) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            station_id,
+        })
+    }
+
+    /// Returns the underlying listener, e.g. to get the bound address.
+    pub fn listener(&self) -> &TcpListener {
+        &self.listener
+    }
+
+    /// Blocks until fldigi saves a QSO. Other logbook calls, like the
+    /// duplicate checks, are answered with empty results, and malformed
+    /// requests are skipped.
+    pub fn recv(&self) -> Result<QsoData> {
+        loop {
+            let (stream, peer) = self.listener.accept()?;
+            match self.handle(stream) {
+                Ok(Some(qso)) => return Ok(qso),
+                Ok(None) => {}
+                Err(err) => tracing::debug!(%peer, "skipping fldigi request: {:#}", err),
+            }
+        }
+    }
+
+    /// Answers a single XML-RPC call, returning the QSO it adds if any.
+    fn handle(&self, mut stream: TcpStream) -> Result<Option<QsoData>> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut reader = BufReader::new((&stream).take(MAX_REQUEST_LEN));
+        let mut content_length = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                bail!("truncated fldigi request");
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = Some(value.trim().parse::<u64>()?);
+                }
+            }
+        }
+        let content_length =
+            content_length.ok_or_else(|| anyhow!("missing fldigi content length"))?;
+        if content_length > MAX_REQUEST_LEN {
+            bail!("fldigi request too large");
+        }
+        let mut body = String::new();
+        reader.take(content_length).read_to_string(&mut body)?;
+
+        let (method, param) = parse_call(&body)?;
+        let qso = match method.as_str() {
+            "log.add_record" => Some(parse_record(&param, &self.station_id)?),
+            _ => None,
+        };
+
+        let response = "<?xml version=\"1.0\"?><methodResponse><params><param>\
+                        <value><string></string></value></param></params></methodResponse>";
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\n\
+             Content-Type: text/xml\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            response.len(),
+            response
+        )?;
+        Ok(qso)
+    }
+}
+
+/// Returns the method name and the first scalar parameter of an XML-RPC
+/// method call, the parameter is empty if there's none.
+fn parse_call(xml: &str) -> Result<(String, String)> {
+    let method = METHOD
+        .with(|method| method.captures(xml).map(|captures| captures[1].to_string()))
+        .ok_or_else(|| anyhow!("missing xml-rpc method name"))?;
+    let param = VALUE.with(|value| {
+        let Some(captures) = value.captures(xml) else {
+            return Ok(String::new());
+        };
+        let text = match (captures.get(1), captures.get(2), captures.get(3)) {
+            (Some(kind), Some(text), _) => match kind.as_str() {
+                "string" | "int" | "i4" | "double" => text.as_str(),
+                kind => bail!("unsupported xml-rpc type {}", kind),
+            },
+            (None, _, Some(text)) => text.as_str(),
+            _ => unreachable!("one of the alternatives matched"),
+        };
+        Ok(unescape(text.trim()))
+    })?;
+    Ok((method, param))
+}
+
+/// Converts the ADIF record of a `log.add_record` call.
+pub fn parse_record(record: &str, station_id: &Id) -> Result<QsoData> {
+    let mut record = record.trim().to_string();
+    if !record.to_ascii_uppercase().ends_with("<EOR>") {
+        record.push_str("<EOR>");
+    }
+    let mut qsos = read_adi(record.as_bytes(), station_id).context("invalid fldigi record")?;
+    match qsos.len() {
+        1 => Ok(qsos.remove(0)),
+        len => bail!("expected one fldigi record, got {}", len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fldigi::{parse_record, FldigiBridge};
+    use crate::qso::GRIDSQUARE;
+    use crate::Id;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::thread;
+
+    const RECORD: &str = "<CALL:6>LW3DZR<QSO_DATE:8>20240101<TIME_ON:4>2037\
+                          <FREQ:9>14.070150<MODE:6>BPSK31<RST_SENT:3>599\
+                          <COMMENT:8>tnx & 73<GRIDSQUARE:4>GF05<EOR>";
+
+    fn call(addr: SocketAddr, method: &str, param: &str) -> String {
+        let body = format!(
+            "<?xml version=\"1.0\"?><methodCall><methodName>{}</methodName>\
+             <params><param><value><string>{}</string></value></param></params></methodCall>",
+            method,
+            crate::adif::escape(param)
+        );
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /RPC2 HTTP/1.1\r\nContent-Type: text/xml\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_fldigi_bridge() {
+        let station_id = Id::new("station");
+        let bridge = FldigiBridge::bind("127.0.0.1:0", station_id.clone()).unwrap();
+        let addr = bridge.listener().local_addr().unwrap();
+        let client = thread::spawn(move || {
+            [
+                call(addr, "log.check_dup", "LW3DZR"),
+                call(addr, "log.add_record", RECORD),
+            ]
+        });
+
+        let qso = bridge.recv().unwrap();
+        assert_eq!(qso.callsign, "LW3DZR");
+        assert_eq!(qso.datetime, 1704141420);
+        assert_eq!(qso.freq, 14070150);
+        assert_eq!(qso.mode, "BPSK31");
+        assert_eq!(qso.rst, "599");
+        assert_eq!(qso.comments, "tnx & 73");
+        assert_eq!(qso.extra[GRIDSQUARE], "GF05");
+        for response in client.join().unwrap() {
+            assert!(response.starts_with("HTTP/1.1 200"));
+        }
+
+        let unterminated = RECORD.trim_end_matches("<EOR>");
+        assert_eq!(parse_record(unterminated, &station_id).unwrap(), qso);
+        assert!(parse_record("", &station_id).is_err());
+        assert!(parse_record(&RECORD.repeat(2), &station_id).is_err());
+    }
+}
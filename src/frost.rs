@@ -0,0 +1,796 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! FROST threshold signatures, so a club station or a contest team signs with
+//! any `threshold` of its members and no member ever holds the station key.
+//!
+//! The members generate the key together, without a dealer:
+//!
+//! 1. Each member calls [`dkg_part1`] and broadcasts its [`DkgRound1`].
+//! 2. With the round 1 packages of the others, each member calls
+//!    [`dkg_part2`] and sends each other member its [`DkgRound2`] share.
+//! 3. [`dkg_finish`] checks the received shares and returns the member
+//!    [`KeyPackage`]. All the members get the same [`GroupKey`], whose public
+//!    key is the station public key.
+//!
+//! Signing takes two rounds too. The signing members call [`commit`] and send
+//! their [`SigningCommitments`] to a coordinator, which builds the
+//! [`SigningPackage`] of the id. Each member answers with the
+//! [`SignatureShare`] returned by [`sign`], and [`aggregate`] combines them
+//! in a regular BIP340 signature for the group key, verified by
+//! [`Id::verify`] like any other. [`Coordinator`] runs both rounds behind the
+//! [`Signer`] trait.
+
+use crate::schnorr;
+use crate::signer::{SignRequest, SignResponse, Signer};
+use crate::Id;
+use anyhow::{bail, Context, Result};
+use k256::elliptic_curve::Field;
+use k256::{ProjectivePoint, Scalar};
+use secp256k1::schnorr::Signature;
+use secp256k1::{PublicKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::Mutex;
+use zeroize::Zeroize;
+
+const DKG_TAG: &str = "gqdb/frost/dkg";
+const BINDING_TAG: &str = "gqdb/frost/binding";
+
+/// Secret state of a member between the key generation rounds, see
+/// [`dkg_part1`].
+pub struct DkgSecret {
+    index: u16,
+    threshold: u16,
+    members: u16,
+    coefficients: Vec<Scalar>,
+}
+
+impl Debug for DkgSecret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DkgSecret")
+            .field("index", &self.index)
+            .field("threshold", &self.threshold)
+            .field("members", &self.members)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for DkgSecret {
+    fn drop(&mut self) {
+        self.coefficients.zeroize();
+    }
+}
+
+/// Key generation package a member broadcasts to the others.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DkgRound1 {
+    /// Index of the member, from 1 to the number of members.
+    pub index: u16,
+    /// Commitments to the coefficients of the member polynomial, the first
+    /// one to its secret.
+    pub commitments: Vec<PublicKey>,
+    /// Proof of knowledge of the secret, so a member can't cancel the
+    /// secrets of the others.
+    pub proof_nonce: PublicKey,
+    #[serde(with = "hex")]
+    pub proof: [u8; 32],
+}
+
+/// Key generation share a member sends privately to another member.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DkgRound2 {
+    pub from: u16,
+    pub to: u16,
+    #[serde(with = "hex")]
+    share: [u8; 32],
+}
+
+impl Debug for DkgRound2 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DkgRound2")
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for DkgRound2 {
+    fn drop(&mut self) {
+        self.share.zeroize();
+    }
+}
+
+/// The public side of a threshold key, known to every member and to the
+/// coordinator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupKey {
+    /// Number of members needed to sign.
+    pub threshold: u16,
+    /// The station public key.
+    pub pub_key: XOnlyPublicKey,
+    /// Public keys of the member shares, checking each signature share.
+    pub verifying_shares: BTreeMap<u16, PublicKey>,
+}
+
+/// The key of a member: its secret share and the group key.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyPackage {
+    pub index: u16,
+    pub group: GroupKey,
+    #[serde(with = "hex")]
+    secret_share: [u8; 32],
+}
+
+impl KeyPackage {
+    fn secret_share(&self) -> Result<Scalar> {
+        schnorr::scalar(&self.secret_share)
+    }
+}
+
+impl Debug for KeyPackage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPackage")
+            .field("index", &self.index)
+            .field("group", &self.group)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for KeyPackage {
+    fn drop(&mut self) {
+        self.secret_share.zeroize();
+    }
+}
+
+/// Starts the key generation of member `index` of `members`, any `threshold`
+/// of them will sign. Returns the secret state kept for the next rounds and
+/// the package broadcast to the other members.
+pub fn dkg_part1(index: u16, threshold: u16, members: u16) -> Result<(DkgSecret, DkgRound1)> {
+    if threshold < 2 || threshold > members {
+        bail!("invalid threshold {} of {}", threshold, members);
+    }
+    if index == 0 || index > members {
+        bail!("invalid member index {}", index);
+    }
+
+    let mut rng = rand::thread_rng();
+    let secret = DkgSecret {
+        index,
+        threshold,
+        members,
+        coefficients: (0..threshold).map(|_| Scalar::random(&mut rng)).collect(),
+    };
+    let commitments = secret
+        .coefficients
+        .iter()
+        .map(|coefficient| schnorr::public_key(&(ProjectivePoint::GENERATOR * coefficient)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut nonce = Scalar::random(&mut rng);
+    let proof_nonce = schnorr::public_key(&(ProjectivePoint::GENERATOR * nonce))?;
+    let challenge = dkg_challenge(index, &commitments[0], &proof_nonce);
+    let proof = (nonce + secret.coefficients[0] * challenge)
+        .to_bytes()
+        .into();
+    nonce.zeroize();
+
+    Ok((
+        secret,
+        DkgRound1 {
+            index,
+            commitments,
+            proof_nonce,
+            proof,
+        },
+    ))
+}
+
+/// Checks the round 1 packages of the other members and returns the shares
+/// to send them.
+pub fn dkg_part2(secret: &DkgSecret, round1: &[DkgRound1]) -> Result<Vec<DkgRound2>> {
+    check_round1(secret, round1)?;
+
+    Ok(round1
+        .iter()
+        .map(|package| DkgRound2 {
+            from: secret.index,
+            to: package.index,
+            share: evaluate(&secret.coefficients, package.index)
+                .to_bytes()
+                .into(),
+        })
+        .collect())
+}
+
+/// Checks the shares received from the other members against their round 1
+/// packages and returns the key of the member.
+pub fn dkg_finish(
+    secret: &DkgSecret,
+    round1: &[DkgRound1],
+    round2: &[DkgRound2],
+) -> Result<KeyPackage> {
+    check_round1(secret, round1)?;
+    if round2.len() != round1.len() {
+        bail!("{} shares expected, got {}", round1.len(), round2.len());
+    }
+
+    let own_commitments: Vec<ProjectivePoint> = secret
+        .coefficients
+        .iter()
+        .map(|coefficient| ProjectivePoint::GENERATOR * coefficient)
+        .collect();
+    let mut commitments = BTreeMap::from([(secret.index, own_commitments)]);
+    for package in round1 {
+        let points = package.commitments.iter().map(schnorr::point).collect();
+        commitments.insert(package.index, points);
+    }
+
+    let mut secret_share = evaluate(&secret.coefficients, secret.index);
+    for share in round2 {
+        if share.to != secret.index {
+            bail!(
+                "share for member {} received by member {}",
+                share.to,
+                secret.index
+            );
+        }
+        let Some(commitments) = commitments
+            .get(&share.from)
+            .filter(|_| share.from != secret.index)
+        else {
+            bail!("share from unknown member {}", share.from);
+        };
+        if round2
+            .iter()
+            .filter(|other| other.from == share.from)
+            .count()
+            > 1
+        {
+            bail!("duplicated share from member {}", share.from);
+        }
+
+        let value = schnorr::scalar(&share.share)?;
+        if ProjectivePoint::GENERATOR * value != evaluate_commitments(commitments, secret.index) {
+            bail!("invalid share from member {}", share.from);
+        }
+        secret_share += value;
+    }
+
+    let mut group_point: ProjectivePoint = commitments.values().map(|points| points[0]).sum();
+    let mut verifying_shares: BTreeMap<u16, ProjectivePoint> = (1..=secret.members)
+        .map(|index| {
+            let point = commitments
+                .values()
+                .map(|points| evaluate_commitments(points, index))
+                .sum();
+            (index, point)
+        })
+        .collect();
+
+    // BIP340 keys have an even y, negating all the shares negates the key.
+    if !schnorr::has_even_y(&group_point) {
+        group_point = -group_point;
+        secret_share = -secret_share;
+        for point in verifying_shares.values_mut() {
+            *point = -*point;
+        }
+    }
+
+    let pub_key = schnorr::public_key(&group_point)?.x_only_public_key().0;
+    let verifying_shares = verifying_shares
+        .iter()
+        .map(|(index, point)| Ok((*index, schnorr::public_key(point)?)))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    let key = KeyPackage {
+        index: secret.index,
+        group: GroupKey {
+            threshold: secret.threshold,
+            pub_key,
+            verifying_shares,
+        },
+        secret_share: secret_share.to_bytes().into(),
+    };
+    secret_share.zeroize();
+
+    Ok(key)
+}
+
+fn check_round1(secret: &DkgSecret, round1: &[DkgRound1]) -> Result<()> {
+    if round1.len() != secret.members as usize - 1 {
+        bail!(
+            "{} round 1 packages expected, got {}",
+            secret.members - 1,
+            round1.len()
+        );
+    }
+
+    let mut seen = BTreeSet::new();
+    for package in round1 {
+        if package.index == 0 || package.index > secret.members || package.index == secret.index {
+            bail!("invalid member index {}", package.index);
+        }
+        if !seen.insert(package.index) {
+            bail!("duplicated round 1 package from member {}", package.index);
+        }
+        if package.commitments.len() != secret.threshold as usize {
+            bail!("invalid commitments from member {}", package.index);
+        }
+
+        let challenge = dkg_challenge(package.index, &package.commitments[0], &package.proof_nonce);
+        let proof = schnorr::scalar(&package.proof)?;
+        if ProjectivePoint::GENERATOR * proof
+            != schnorr::point(&package.proof_nonce)
+                + schnorr::point(&package.commitments[0]) * challenge
+        {
+            bail!("invalid proof of knowledge from member {}", package.index);
+        }
+    }
+
+    Ok(())
+}
+
+fn dkg_challenge(index: u16, commitment: &PublicKey, nonce: &PublicKey) -> Scalar {
+    schnorr::hash_to_scalar(
+        DKG_TAG,
+        &[
+            &index.to_be_bytes(),
+            &commitment.serialize(),
+            &nonce.serialize(),
+        ],
+    )
+}
+
+/// Evaluates the polynomial at the member index.
+fn evaluate(coefficients: &[Scalar], index: u16) -> Scalar {
+    let x = Scalar::from(index as u64);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::ZERO, |y, coefficient| y * x + coefficient)
+}
+
+/// Evaluates the commitments to a polynomial at the member index, giving the
+/// commitment to the value.
+fn evaluate_commitments(commitments: &[ProjectivePoint], index: u16) -> ProjectivePoint {
+    let x = Scalar::from(index as u64);
+    commitments
+        .iter()
+        .rev()
+        .fold(ProjectivePoint::IDENTITY, |y, commitment| {
+            y * x + commitment
+        })
+}
+
+/// Secret nonces of a member for one signature, consumed by [`sign`].
+pub struct SigningNonces {
+    hiding: Scalar,
+    binding: Scalar,
+    commitments: SigningCommitments,
+}
+
+impl Debug for SigningNonces {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningNonces")
+            .field("commitments", &self.commitments)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SigningNonces {
+    fn drop(&mut self) {
+        self.hiding.zeroize();
+        self.binding.zeroize();
+    }
+}
+
+/// Commitments to the nonces of a member, sent to the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningCommitments {
+    pub index: u16,
+    pub hiding: PublicKey,
+    pub binding: PublicKey,
+}
+
+/// The id to sign and the commitments of the signing members, sent by the
+/// coordinator to each of them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningPackage {
+    pub id: Id,
+    pub commitments: Vec<SigningCommitments>,
+}
+
+impl SigningPackage {
+    pub fn new(id: Id, mut commitments: Vec<SigningCommitments>) -> Result<Self> {
+        commitments.sort_by_key(|commitments| commitments.index);
+        let package = Self { id, commitments };
+        package.check()?;
+        Ok(package)
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.commitments.is_empty() {
+            bail!("no signing commitments");
+        }
+        for pair in self.commitments.windows(2) {
+            if pair[0].index >= pair[1].index {
+                bail!(
+                    "unsorted or duplicated commitments of member {}",
+                    pair[1].index
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the group nonce and the binding factor of each member.
+    fn nonce(&self, group: &GroupKey) -> Result<(ProjectivePoint, BTreeMap<u16, Scalar>)> {
+        self.check()?;
+        if self.commitments.len() < group.threshold as usize {
+            bail!(
+                "{} signers needed, got {}",
+                group.threshold,
+                self.commitments.len()
+            );
+        }
+
+        let mut encoded = vec![];
+        for commitments in &self.commitments {
+            encoded.extend(commitments.index.to_be_bytes());
+            encoded.extend(commitments.hiding.serialize());
+            encoded.extend(commitments.binding.serialize());
+        }
+
+        let mut nonce = ProjectivePoint::IDENTITY;
+        let mut binding_factors = BTreeMap::new();
+        for commitments in &self.commitments {
+            let binding_factor = schnorr::hash_to_scalar(
+                BINDING_TAG,
+                &[
+                    &group.pub_key.serialize(),
+                    self.id.as_bytes(),
+                    &encoded,
+                    &commitments.index.to_be_bytes(),
+                ],
+            );
+            nonce += schnorr::point(&commitments.hiding)
+                + schnorr::point(&commitments.binding) * binding_factor;
+            binding_factors.insert(commitments.index, binding_factor);
+        }
+
+        if nonce == ProjectivePoint::IDENTITY {
+            bail!("group nonce at infinity");
+        }
+
+        Ok((nonce, binding_factors))
+    }
+
+    /// Returns the lagrange coefficient of the member in the signing set.
+    fn lagrange(&self, index: u16) -> Scalar {
+        let x = Scalar::from(index as u64);
+        let mut numerator = Scalar::ONE;
+        let mut denominator = Scalar::ONE;
+        for other in self.commitments.iter().filter(|other| other.index != index) {
+            let other = Scalar::from(other.index as u64);
+            numerator *= other;
+            denominator *= other - x;
+        }
+        numerator * denominator.invert().unwrap()
+    }
+}
+
+/// Signature share of a member, sent to the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureShare {
+    pub index: u16,
+    #[serde(with = "hex")]
+    pub share: [u8; 32],
+}
+
+/// First signing round, returns fresh nonces for the next signature of the
+/// member and the commitments to send to the coordinator. Nonces must never be
+/// reused.
+pub fn commit(key: &KeyPackage) -> Result<(SigningNonces, SigningCommitments)> {
+    let mut rng = rand::thread_rng();
+    let hiding = Scalar::random(&mut rng);
+    let binding = Scalar::random(&mut rng);
+    let commitments = SigningCommitments {
+        index: key.index,
+        hiding: schnorr::public_key(&(ProjectivePoint::GENERATOR * hiding))?,
+        binding: schnorr::public_key(&(ProjectivePoint::GENERATOR * binding))?,
+    };
+
+    Ok((
+        SigningNonces {
+            hiding,
+            binding,
+            commitments: commitments.clone(),
+        },
+        commitments,
+    ))
+}
+
+/// Second signing round, returns the signature share of the member for the
+/// package. Consumes the nonces.
+pub fn sign(
+    key: &KeyPackage,
+    nonces: SigningNonces,
+    package: &SigningPackage,
+) -> Result<SignatureShare> {
+    if !package.commitments.contains(&nonces.commitments) || nonces.commitments.index != key.index {
+        bail!(
+            "the package doesn't include the commitments of member {}",
+            key.index
+        );
+    }
+
+    let (nonce, binding_factors) = package.nonce(&key.group)?;
+    let mut k = nonces.hiding + nonces.binding * binding_factors[&key.index];
+    if !schnorr::has_even_y(&nonce) {
+        k = -k;
+    }
+    let challenge = schnorr::challenge(&nonce, &key.group.pub_key, &package.id);
+    let mut secret_share = key.secret_share()?;
+    let share = k + package.lagrange(key.index) * challenge * secret_share;
+    k.zeroize();
+    secret_share.zeroize();
+
+    Ok(SignatureShare {
+        index: key.index,
+        share: share.to_bytes().into(),
+    })
+}
+
+/// Checks the signature shares of the package and combines them in the
+/// signature of the id for the group key.
+pub fn aggregate(
+    group: &GroupKey,
+    package: &SigningPackage,
+    shares: &[SignatureShare],
+) -> Result<Signature> {
+    let (mut nonce, binding_factors) = package.nonce(group)?;
+    let negate = !schnorr::has_even_y(&nonce);
+    if negate {
+        nonce = -nonce;
+    }
+    let challenge = schnorr::challenge(&nonce, &group.pub_key, &package.id);
+
+    if shares.len() != package.commitments.len() {
+        bail!(
+            "{} signature shares expected, got {}",
+            package.commitments.len(),
+            shares.len()
+        );
+    }
+
+    let mut s = Scalar::ZERO;
+    for commitments in &package.commitments {
+        let index = commitments.index;
+        let share = shares
+            .iter()
+            .find(|share| share.index == index)
+            .with_context(|| format!("missing signature share of member {}", index))?;
+        let verifying_share = group
+            .verifying_shares
+            .get(&index)
+            .with_context(|| format!("unknown member {}", index))?;
+
+        let z = schnorr::scalar(&share.share)?;
+        let mut member_nonce = schnorr::point(&commitments.hiding)
+            + schnorr::point(&commitments.binding) * binding_factors[&index];
+        if negate {
+            member_nonce = -member_nonce;
+        }
+        if ProjectivePoint::GENERATOR * z
+            != member_nonce
+                + schnorr::point(verifying_share) * (package.lagrange(index) * challenge)
+        {
+            bail!("invalid signature share of member {}", index);
+        }
+        s += z;
+    }
+
+    let sig = schnorr::signature(&nonce, &s)?;
+    package
+        .id
+        .verify(&group.pub_key, &sig)
+        .context("invalid aggregated signature")?;
+
+    Ok(sig)
+}
+
+/// A member taking part in threshold signatures, e.g. the device of a club
+/// officer reached over the network.
+pub trait Participant {
+    /// First round, returns the commitments to fresh nonces.
+    fn commit(&self) -> Result<SigningCommitments>;
+
+    /// Second round, signs the package with the nonces of the last
+    /// commitments.
+    fn sign(&self, package: &SigningPackage) -> Result<SignatureShare>;
+}
+
+/// A member holding its key in process.
+pub struct LocalParticipant {
+    key: KeyPackage,
+    nonces: Mutex<Option<SigningNonces>>,
+}
+
+impl LocalParticipant {
+    pub fn new(key: KeyPackage) -> Self {
+        Self {
+            key,
+            nonces: Mutex::new(None),
+        }
+    }
+}
+
+impl Participant for LocalParticipant {
+    fn commit(&self) -> Result<SigningCommitments> {
+        let (nonces, commitments) = commit(&self.key)?;
+        *self.nonces.lock().unwrap() = Some(nonces);
+        Ok(commitments)
+    }
+
+    fn sign(&self, package: &SigningPackage) -> Result<SignatureShare> {
+        let nonces = self
+            .nonces
+            .lock()
+            .unwrap()
+            .take()
+            .context("no pending commitments")?;
+        sign(&self.key, nonces, package)
+    }
+}
+
+/// Signs as the group by running both signing rounds with the participants,
+/// at least `threshold` of them.
+pub struct Coordinator {
+    group: GroupKey,
+    participants: Vec<Box<dyn Participant>>,
+}
+
+impl Coordinator {
+    pub fn new(group: GroupKey, participants: Vec<Box<dyn Participant>>) -> Result<Self> {
+        if participants.len() < group.threshold as usize {
+            bail!(
+                "{} participants needed, got {}",
+                group.threshold,
+                participants.len()
+            );
+        }
+        Ok(Self {
+            group,
+            participants,
+        })
+    }
+}
+
+impl Signer for Coordinator {
+    fn public_key(&self) -> Result<XOnlyPublicKey> {
+        Ok(self.group.pub_key)
+    }
+
+    fn sign(&self, request: &SignRequest) -> Result<SignResponse> {
+        let commitments = self
+            .participants
+            .iter()
+            .map(|participant| participant.commit())
+            .collect::<Result<Vec<_>>>()?;
+        let package = SigningPackage::new(request.id.clone(), commitments)?;
+        let shares = self
+            .participants
+            .iter()
+            .map(|participant| participant.sign(&package))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(SignResponse {
+            sig: aggregate(&self.group, &package, &shares)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frost::{
+        aggregate, commit, dkg_finish, dkg_part1, dkg_part2, sign, Coordinator, DkgRound2,
+        KeyPackage, LocalParticipant, SigningPackage,
+    };
+    use crate::signable::Signable;
+    use crate::Station;
+    use codes_iso_3166::part_1::CountryCode;
+
+    fn keygen(threshold: u16, members: u16) -> Vec<KeyPackage> {
+        let (secrets, round1): (Vec<_>, Vec<_>) = (1..=members)
+            .map(|index| dkg_part1(index, threshold, members).unwrap())
+            .unzip();
+        let others = |index: u16| {
+            round1
+                .iter()
+                .filter(|package| package.index != index)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let round2: Vec<DkgRound2> = secrets
+            .iter()
+            .flat_map(|secret| dkg_part2(secret, &others(secret.index)).unwrap())
+            .collect();
+
+        secrets
+            .iter()
+            .map(|secret| {
+                let shares: Vec<DkgRound2> = round2
+                    .iter()
+                    .filter(|share| share.to == secret.index)
+                    .cloned()
+                    .collect();
+                dkg_finish(secret, &others(secret.index), &shares).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_frost() {
+        let keys = keygen(2, 3);
+        let group = keys[0].group.clone();
+        assert!(keys.iter().all(|key| key.group == group));
+
+        // Any two members sign as the club station.
+        let coordinator = Coordinator::new(
+            group.clone(),
+            vec![
+                Box::new(LocalParticipant::new(keys[0].clone())),
+                Box::new(LocalParticipant::new(keys[2].clone())),
+            ],
+        )
+        .unwrap();
+        let station = Station::new_with_signer(
+            &coordinator,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        assert_eq!(station.pub_key, group.pub_key);
+        station.verify().unwrap();
+        station.id.verify(&group.pub_key, station.sig()).unwrap();
+
+        let coordinator = Coordinator::new(
+            group.clone(),
+            vec![Box::new(LocalParticipant::new(keys[1].clone()))],
+        );
+        assert!(coordinator.is_err());
+
+        // A tampered share is caught and blamed on its member.
+        let (nonces_1, commitments_1) = commit(&keys[0]).unwrap();
+        let (nonces_2, commitments_2) = commit(&keys[1]).unwrap();
+        let package =
+            SigningPackage::new(station.id.clone(), vec![commitments_2, commitments_1]).unwrap();
+        let share_1 = sign(&keys[0], nonces_1, &package).unwrap();
+        let mut share_2 = sign(&keys[1], nonces_2, &package).unwrap();
+        aggregate(&group, &package, &[share_1.clone(), share_2.clone()]).unwrap();
+        share_2.share[31] ^= 1;
+        let err = aggregate(&group, &package, &[share_1, share_2]).unwrap_err();
+        assert_eq!(err.to_string(), "invalid signature share of member 2");
+
+        // Nonces are bound to their commitments.
+        let (nonces, _) = commit(&keys[0]).unwrap();
+        assert!(sign(&keys[0], nonces, &package).is_err());
+
+        assert!(dkg_part1(1, 1, 3).is_err());
+        assert!(dkg_part1(4, 2, 3).is_err());
+    }
+}
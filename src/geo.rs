@@ -0,0 +1,310 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Maidenhead grid squares and great-circle distance and bearing.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// A point on the earth surface, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatLon {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl LatLon {
+    /// Returns the center of a 2, 4, 6 or 8 characters Maidenhead locator.
+    pub fn from_grid(grid: &str) -> Result<Self> {
+        let chars: Vec<char> = grid.chars().map(|c| c.to_ascii_uppercase()).collect();
+
+        if chars.is_empty() || chars.len() > 8 || chars.len() & 1 == 1 {
+            bail!("invalid grid square {}", grid);
+        }
+
+        // ( first valid character, number of characters, degrees of longitude
+        // per step ), latitude steps are half the longitude steps.
+        let pairs = [
+            ('A', 18, 20.0),
+            ('0', 10, 2.0),
+            ('A', 24, 2.0 / 24.0),
+            ('0', 10, 2.0 / 240.0),
+        ];
+
+        let mut lon = -180.0;
+        let mut lat = -90.0;
+        let mut lon_step = 0.0;
+
+        for (pair, (first, count, step)) in chars.chunks(2).zip(pairs) {
+            let lon_index = pair[0] as i32 - first as i32;
+            let lat_index = pair[1] as i32 - first as i32;
+            if !(0..count).contains(&lon_index) || !(0..count).contains(&lat_index) {
+                bail!("invalid grid square {}", grid);
+            }
+            lon += lon_index as f64 * step;
+            lat += lat_index as f64 * step / 2.0;
+            lon_step = step;
+        }
+
+        Ok(Self {
+            lat: lat + lon_step / 4.0,
+            lon: lon + lon_step / 2.0,
+        })
+    }
+
+    /// Great-circle distance in km.
+    pub fn distance_km(&self, other: &LatLon) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.lon - self.lon).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+
+    /// Initial great-circle bearing ( beam heading ) in degrees from north.
+    pub fn bearing(&self, other: &LatLon) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lon = (other.lon - self.lon).to_radians();
+
+        let y = d_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+
+        (y.atan2(x).to_degrees() + 360.0) % 360.0
+    }
+
+    /// Returns the points splitting the great-circle path to the other point
+    /// in `segments` equal parts, both ends included.
+    pub fn great_circle(&self, other: &LatLon, segments: usize) -> Vec<LatLon> {
+        let segments = segments.max(1);
+        let angle = self.distance_km(other) / EARTH_RADIUS_KM;
+        if angle == 0.0 {
+            return vec![*self; segments + 1];
+        }
+
+        let (lat1, lon1) = (self.lat.to_radians(), self.lon.to_radians());
+        let (lat2, lon2) = (other.lat.to_radians(), other.lon.to_radians());
+
+        (0..=segments)
+            .map(|i| {
+                let f = i as f64 / segments as f64;
+                let a = ((1.0 - f) * angle).sin() / angle.sin();
+                let b = (f * angle).sin() / angle.sin();
+                let x = a * lat1.cos() * lon1.cos() + b * lat2.cos() * lon2.cos();
+                let y = a * lat1.cos() * lon1.sin() + b * lat2.cos() * lon2.sin();
+                let z = a * lat1.sin() + b * lat2.sin();
+                LatLon {
+                    lat: z.atan2(x.hypot(y)).to_degrees(),
+                    lon: y.atan2(x).to_degrees(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// An area between two parallels and two meridians, in degrees. Boxes with
+/// `west` greater than `east` cross the antimeridian.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BoundingBox {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl BoundingBox {
+    /// Returns true if the point is inside the box or on its edges.
+    pub fn contains(&self, point: &LatLon) -> bool {
+        let lon = if self.west <= self.east {
+            point.lon >= self.west && point.lon <= self.east
+        } else {
+            point.lon >= self.west || point.lon <= self.east
+        };
+        lon && point.lat >= self.south && point.lat <= self.north
+    }
+
+    /// The bit patterns the box is compared and hashed by. Negative zeros
+    /// are folded into zeros, and NaNs equal themselves.
+    fn bits(&self) -> [u64; 4] {
+        [self.south, self.west, self.north, self.east].map(|degrees| (degrees + 0.0).to_bits())
+    }
+}
+
+impl PartialEq for BoundingBox {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits() == other.bits()
+    }
+}
+
+impl Eq for BoundingBox {}
+
+impl Hash for BoundingBox {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+    }
+}
+
+/// A range of great-circle distances in km from a grid square, e.g. the
+/// contacts beyond 5000 km of the station.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DistanceRange {
+    pub from: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_km: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_km: Option<u32>,
+}
+
+impl DistanceRange {
+    /// Matches the points at `km` or farther from the grid square.
+    pub fn beyond(from: &str, km: u32) -> Self {
+        Self {
+            from: from.to_string(),
+            min_km: Some(km),
+            max_km: None,
+        }
+    }
+
+    /// Matches the points at `km` or closer to the grid square.
+    pub fn within(from: &str, km: u32) -> Self {
+        Self {
+            from: from.to_string(),
+            min_km: None,
+            max_km: Some(km),
+        }
+    }
+
+    /// Returns true if the distance from the center of the grid square to
+    /// the point is in the range. False if the grid square is invalid.
+    pub fn contains(&self, point: &LatLon) -> bool {
+        let Ok(from) = LatLon::from_grid(&self.from) else {
+            return false;
+        };
+        let distance = from.distance_km(point);
+        self.min_km.is_none_or(|min| distance >= f64::from(min))
+            && self.max_km.is_none_or(|max| distance <= f64::from(max))
+    }
+}
+
+/// Returns true if the grid is a valid Maidenhead locator.
+pub fn is_valid_grid(grid: &str) -> bool {
+    LatLon::from_grid(grid).is_ok()
+}
+
+/// Returns the distance in km and the beam heading in degrees from the first
+/// grid square to the second one.
+pub fn qrb(from_grid: &str, to_grid: &str) -> Result<(f64, f64)> {
+    let from = LatLon::from_grid(from_grid)?;
+    let to = LatLon::from_grid(to_grid)?;
+    Ok((from.distance_km(&to), from.bearing(&to)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geo::{is_valid_grid, qrb, BoundingBox, DistanceRange, LatLon};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_from_grid() {
+        let center = LatLon::from_grid("GF05").unwrap();
+        assert_eq!(
+            center,
+            LatLon {
+                lat: -34.5,
+                lon: -59.0
+            }
+        );
+
+        let center = LatLon::from_grid("gf05tl").unwrap();
+        assert!((center.lat - -34.521).abs() < 0.01);
+        assert!((center.lon - -58.375).abs() < 0.01);
+
+        assert!(is_valid_grid("FN31pr"));
+        assert!(!is_valid_grid("FN3"));
+        assert!(!is_valid_grid("SN31"));
+        assert!(!is_valid_grid("FN31zz"));
+    }
+
+    #[test]
+    fn test_qrb() {
+        // Buenos Aires to New York
+        let (distance, bearing) = qrb("GF05tk", "FN20xr").unwrap();
+        assert!((distance - 8530.0).abs() < 50.0);
+        assert!((bearing - 348.0).abs() < 2.0);
+
+        let (distance, _) = qrb("GF05", "GF05").unwrap();
+        assert_eq!(distance, 0.0);
+
+        let from = LatLon::from_grid("GF05tk").unwrap();
+        let to = LatLon::from_grid("FN20xr").unwrap();
+        let path = from.great_circle(&to, 4);
+        assert_eq!(path.len(), 5);
+        assert!((path[0].lat - from.lat).abs() < 1e-9 && (path[0].lon - from.lon).abs() < 1e-9);
+        assert!((path[4].lat - to.lat).abs() < 1e-9 && (path[4].lon - to.lon).abs() < 1e-9);
+        let half = from.distance_km(&path[2]);
+        assert!((half - from.distance_km(&to) / 2.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_areas() {
+        let new_york = LatLon::from_grid("FN20xr").unwrap();
+        assert!(DistanceRange::beyond("GF05tk", 5000).contains(&new_york));
+        assert!(!DistanceRange::within("GF05tk", 5000).contains(&new_york));
+        assert!(!DistanceRange::beyond("XX99", 0).contains(&new_york));
+
+        let americas = BoundingBox {
+            south: -60.0,
+            west: -170.0,
+            north: 75.0,
+            east: -30.0,
+        };
+        assert!(americas.contains(&new_york));
+        assert!(!americas.contains(&LatLon::from_grid("JO62").unwrap()));
+
+        let pacific = BoundingBox {
+            south: -50.0,
+            west: 150.0,
+            north: 50.0,
+            east: -120.0,
+        };
+        assert!(pacific.contains(&LatLon::from_grid("AL08").unwrap()));
+        assert!(pacific.contains(&LatLon::from_grid("RG39").unwrap()));
+        assert!(!pacific.contains(&new_york));
+
+        // Equal boxes hash equally, including the signed zeros.
+        let negative_zero = BoundingBox {
+            south: -0.0,
+            ..pacific
+        };
+        let boxes = HashSet::from([
+            negative_zero,
+            BoundingBox {
+                south: 0.0,
+                ..pacific
+            },
+        ]);
+        assert_eq!(boxes.len(), 1);
+        let invalid = BoundingBox {
+            south: f64::NAN,
+            ..pacific
+        };
+        assert_eq!(invalid, invalid);
+        assert_ne!(invalid, pacific);
+    }
+}
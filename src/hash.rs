@@ -0,0 +1,141 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hash algorithms of object ids.
+//!
+//! The algorithm is part of the id layout, so it's selected by the object
+//! version, see [`HashAlgorithm::for_version`]. Every version so far hashes
+//! with SHA-256. A faster algorithm, e.g. SHA-512/256 or BLAKE3 on embedded
+//! devices, would come with a new version, so objects signed with the older
+//! versions keep their ids. Candidates must produce 32 byte digests, the
+//! size of an [`Id`](crate::Id). The `hash` benchmark compares them.
+
+use crate::version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+/// The hash algorithm of object ids.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// The algorithm of the current object versions.
+    pub const CURRENT: HashAlgorithm = HashAlgorithm::Sha256;
+
+    /// Returns the algorithm of the ids of the object version. Unknown
+    /// versions get the current algorithm, they are rejected by the object
+    /// verification anyway.
+    pub fn for_version(version: u8) -> HashAlgorithm {
+        match version {
+            version::V0 | version::V1 | version::V2 => HashAlgorithm::Sha256,
+            _ => HashAlgorithm::CURRENT,
+        }
+    }
+
+    /// Returns the name of the algorithm, as serialized.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+        }
+    }
+
+    /// Returns the digest of the data.
+    pub fn digest(&self, data: &[u8]) -> [u8; 32] {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    /// Returns an incremental hasher.
+    pub(crate) fn hasher(&self) -> Hasher {
+        match self {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+}
+
+/// Incremental hasher of a [`HashAlgorithm`], also written to as an
+/// [`std::io::Write`].
+#[derive(Debug, Clone)]
+pub(crate) enum Hasher {
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    /// Hashes the data.
+    pub(crate) fn update(&mut self, data: impl AsRef<[u8]>) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the digest of everything hashed.
+    pub(crate) fn finalize(self) -> [u8; 32] {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finalize().into(),
+        }
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::HashAlgorithm;
+    use crate::{version, Id};
+    use std::io::Write;
+
+    #[test]
+    fn test_hash_algorithm() {
+        for version in [version::V0, version::V1, version::V2] {
+            assert_eq!(HashAlgorithm::for_version(version), HashAlgorithm::Sha256);
+        }
+        assert_eq!(HashAlgorithm::default(), HashAlgorithm::CURRENT);
+        assert_eq!(
+            serde_json::to_string(&HashAlgorithm::Sha256).unwrap(),
+            format!("\"{}\"", HashAlgorithm::Sha256.as_str())
+        );
+
+        // sha256("abc")
+        assert_eq!(
+            hex::encode(HashAlgorithm::Sha256.digest(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let mut hasher = HashAlgorithm::Sha256.hasher();
+        write!(hasher, "a").unwrap();
+        hasher.update("bc");
+        assert_eq!(hasher.finalize(), HashAlgorithm::Sha256.digest(b"abc"));
+
+        let fields = ("LU4EV", 1704141426u64, version::V2);
+        assert_eq!(
+            Id::hash_with(HashAlgorithm::for_version(version::V2), &fields),
+            Id::hash(version::V2, &fields)
+        );
+    }
+}
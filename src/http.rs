@@ -0,0 +1,384 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Blocking HTTP client for GQDB servers.
+//!
+//! Endpoints:
+//!
+//! * `POST /events` publishes the json encoded [`Event`] in the body.
+//! * `GET /events/<id>` returns the event with the given id, or 404.
+//! * `POST /query` returns a json array with the events matching the json
+//!   encoded [`Filter`] in the body.
+//! * `POST /count` returns the number of events matching the json encoded
+//!   [`Filter`] in the body, as a json number.
+//!
+//! The client speaks plain HTTP/1.1 over a TCP connection, TLS is expected to
+//! be terminated by a local proxy.
+
+use crate::event::{Event, Filter};
+use crate::relay::Relay;
+use crate::Id;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::DeserializeOwned;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Max length of the status, header and chunk size lines of a response.
+const MAX_LINE_LEN: usize = 8 * 1024;
+
+/// Max number of headers of a response.
+const MAX_HEADERS: usize = 128;
+
+/// Max size of a response body.
+const MAX_BODY_LEN: usize = 32 * 1024 * 1024;
+
+/// HTTP client for a GQDB server.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    url: String,
+    host: String,
+    base_path: String,
+}
+
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl HttpClient {
+    /// Creates a client for the server at `url` ( e.g. `http://host:8080/api` ).
+    pub fn new(url: &str) -> Result<Self> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            bail!("unsupported url {}", url);
+        };
+
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        if authority.is_empty() {
+            bail!("missing host in {}", url);
+        }
+
+        let host = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{}:80", authority)
+        };
+
+        Ok(Self {
+            url: url.to_string(),
+            host,
+            base_path: path.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Publishes an event.
+    pub fn publish(&self, event: &Event) -> Result<()> {
+        let body = serde_json::to_vec(event)?;
+        let response = self.request("POST", "/events", Some(&body))?;
+        check_status(&response)
+    }
+
+    /// Returns the event with the given id, if the server has it.
+    pub fn get(&self, id: &Id) -> Result<Option<Event>> {
+        let response = self.request("GET", &format!("/events/{}", id), None)?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        check_status(&response)?;
+        parse_body(&response).map(Some)
+    }
+
+    /// Returns the events matching the filter.
+    pub fn query(&self, filter: &Filter) -> Result<Vec<Event>> {
+        let body = serde_json::to_vec(filter)?;
+        let response = self.request("POST", "/query", Some(&body))?;
+        check_status(&response)?;
+        parse_body(&response)
+    }
+
+    /// Returns the number of events matching the filter.
+    pub fn count(&self, filter: &Filter) -> Result<u64> {
+        let body = serde_json::to_vec(filter)?;
+        let response = self.request("POST", "/count", Some(&body))?;
+        check_status(&response)?;
+        parse_body(&response)
+    }
+
+    fn request(&self, method: &str, path: &str, body: Option<&[u8]>) -> Result<Response> {
+        let mut stream = TcpStream::connect(&self.host)
+            .with_context(|| format!("failed to connect to {}", self.host))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let body = body.unwrap_or_default();
+        let head = format!(
+            "{} {}{} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            method,
+            self.base_path,
+            path,
+            self.host,
+            body.len()
+        );
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        read_response(BufReader::new(stream))
+    }
+}
+
+impl Relay for HttpClient {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn publish(&self, event: &Event) -> Result<()> {
+        HttpClient::publish(self, event)
+    }
+
+    fn query(&self, filter: &Filter) -> Result<Vec<Event>> {
+        HttpClient::query(self, filter)
+    }
+
+    fn count(&self, filter: &Filter) -> Result<u64> {
+        HttpClient::count(self, filter)
+    }
+}
+
+fn check_status(response: &Response) -> Result<()> {
+    if !(200..300).contains(&response.status) {
+        bail!(
+            "server returned {}: {}",
+            response.status,
+            String::from_utf8_lossy(&response.body)
+        );
+    }
+    Ok(())
+}
+
+fn parse_body<T: DeserializeOwned>(response: &Response) -> Result<T> {
+    serde_json::from_slice(&response.body).context("invalid response body")
+}
+
+fn read_response<R: BufRead>(mut reader: R) -> Result<Response> {
+    let mut status_line = String::new();
+    read_line(&mut reader, &mut status_line)?;
+
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| anyhow!("invalid status line"))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut headers = 0;
+
+    loop {
+        let mut line = String::new();
+        if read_line(&mut reader, &mut line)? == 0 {
+            bail!("unexpected end of headers");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            bail!("too many headers");
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(value.parse::<usize>()?);
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.eq_ignore_ascii_case("chunked");
+            }
+        }
+    }
+
+    let mut body = vec![];
+    if chunked {
+        loop {
+            let mut size_line = String::new();
+            read_line(&mut reader, &mut size_line)?;
+            let size_hex = size_line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size_hex, 16).context("invalid chunk size")?;
+            let body_len = body
+                .len()
+                .checked_add(size)
+                .filter(|body_len| *body_len <= MAX_BODY_LEN)
+                .context("response body too large")?;
+            body.resize(body_len, 0);
+            reader.read_exact(&mut body[body_len - size..])?;
+            let mut crlf = [0; 2];
+            reader.read_exact(&mut crlf)?;
+            if size == 0 {
+                break;
+            }
+        }
+    } else if let Some(content_length) = content_length {
+        if content_length > MAX_BODY_LEN {
+            bail!("response body too large");
+        }
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body)?;
+    } else {
+        reader
+            .take(MAX_BODY_LEN as u64 + 1)
+            .read_to_end(&mut body)?;
+        if body.len() > MAX_BODY_LEN {
+            bail!("response body too large");
+        }
+    }
+
+    Ok(Response { status, body })
+}
+
+/// Reads a line of at most [`MAX_LINE_LEN`] bytes.
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> Result<usize> {
+    let len = reader.by_ref().take(MAX_LINE_LEN as u64).read_line(line)?;
+    if len == MAX_LINE_LEN && !line.ends_with('\n') {
+        bail!("line too long");
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{Event, Filter};
+    use crate::http::{read_response, HttpClient, MAX_BODY_LEN, MAX_LINE_LEN};
+    use crate::keys::generate_keypair;
+    use crate::Station;
+    use codes_iso_3166::part_1::CountryCode;
+    use std::io::{BufRead, BufReader, Cursor, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves one canned response per connection and returns the requests.
+    fn serve(responses: Vec<String>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = vec![];
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.strip_prefix("Content-Length: ") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                request.push_str(&String::from_utf8(body).unwrap());
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    fn test_http_client() {
+        let station = Station::new(
+            &generate_keypair(),
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        let event = Event::Station(station.clone());
+        let event_json = serde_json::to_string(&event).unwrap();
+        let events_json = format!("[{}]", event_json);
+
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n".to_string(),
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                event_json.len(),
+                event_json
+            ),
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+            format!(
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                events_json.len(),
+                events_json
+            ),
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 4\r\n\r\noops".to_string(),
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n42".to_string(),
+        ]);
+
+        let client = HttpClient::new(&url).unwrap();
+        client.publish(&event).unwrap();
+        assert_eq!(client.get(&station.id).unwrap(), Some(event.clone()));
+        assert_eq!(client.get(&station.id).unwrap(), None);
+        assert_eq!(client.query(&Filter::new()).unwrap(), vec![event]);
+        assert!(client.query(&Filter::new()).is_err());
+        assert_eq!(client.count(&Filter::new()).unwrap(), 42);
+
+        let requests = handle.join().unwrap();
+        assert!(requests[0].starts_with("POST /api/events HTTP/1.1"));
+        assert!(requests[1].starts_with(&format!("GET /api/events/{} HTTP/1.1", station.id)));
+        assert!(requests[3].starts_with("POST /api/query HTTP/1.1"));
+        assert!(requests[5].starts_with("POST /api/count HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_response_limits() {
+        let read = |response: String| read_response(Cursor::new(response.into_bytes()));
+
+        let response = read("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n[]".to_string()).unwrap();
+        assert_eq!(response.body, b"[]");
+
+        assert!(read(format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_LEN + 1
+        ))
+        .is_err());
+        assert!(read(format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1\r\n[\r\n{:x}\r\n",
+            usize::MAX
+        ))
+        .is_err());
+        assert!(read(format!(
+            "HTTP/1.1 200 OK\r\nX-Pad: {}\r\n\r\n",
+            "a".repeat(MAX_LINE_LEN)
+        ))
+        .is_err());
+        assert!(read(format!(
+            "HTTP/1.1 200 OK\r\n\r\n{}",
+            " ".repeat(MAX_BODY_LEN + 1)
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_invalid_url() {
+        assert!(HttpClient::new("https://relay.example").is_err());
+        assert!(HttpClient::new("http://").is_err());
+    }
+}
@@ -11,16 +11,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::hash::{HashAlgorithm, Hasher};
 use anyhow::{Context, Error};
+use hex::FromHex;
 use secp256k1::schnorr::Signature;
-use secp256k1::{Keypair, Message, XOnlyPublicKey, SECP256K1};
+use secp256k1::{Keypair, Message, Secp256k1, Signing, Verification, XOnlyPublicKey, SECP256K1};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use sha2::{Digest, Sha256};
 use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::FromStr;
 
 /// Object Id
 ///
-/// 32-bytes lowercase hex-encoded sha256 of the the serialized object data.
+/// 32-bytes lowercase hex-encoded hash of the the serialized object data,
+/// sha256 for every object version so far, see [`HashAlgorithm`].
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Id {
     bytes: [u8; 32],
@@ -29,10 +33,86 @@ pub struct Id {
 impl Id {
     /// Creates a new Id from a str.
     pub fn new(value: &str) -> Self {
-        let mut hasher = Sha256::new();
-        hasher.update(value);
-        let hash = hasher.finalize();
-        Self { bytes: hash.into() }
+        Self {
+            bytes: HashAlgorithm::Sha256.digest(value.as_bytes()),
+        }
+    }
+
+    /// Creates a new Id from the raw hash bytes.
+    pub(crate) fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self { bytes }
+    }
+
+    /// Returns the raw hash bytes of the id.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.bytes
+    }
+
+    /// Creates a new Id hashing the json encoding of the id fields as it is
+    /// written, without building an intermediate string.
+    ///
+    /// The encoding must be the one of the equivalent [`serde_json::Value`],
+    /// which holds
+    /// for strings, numbers, ids, keys, sequences and maps with string keys.
+    /// Structs and other maps must be converted to a value first.
+    ///
+    /// Hashes with the algorithm of the object version, see
+    /// [`HashAlgorithm::for_version`].
+    pub(crate) fn hash<T: Serialize + ?Sized>(version: u8, fields: &T) -> Self {
+        Self::hash_with(HashAlgorithm::for_version(version), fields)
+    }
+
+    /// Creates a new Id like [`Id::hash`] with the given algorithm, for the
+    /// hashes that don't belong to a versioned object.
+    pub(crate) fn hash_with<T: Serialize + ?Sized>(algorithm: HashAlgorithm, fields: &T) -> Self {
+        let mut hasher = algorithm.hasher();
+        serde_json::to_writer(&mut hasher, fields).expect("id fields are serializable");
+        Self {
+            bytes: hasher.finalize(),
+        }
+    }
+
+    /// Returns the number of leading zero bits of the id, used as the proof of
+    /// work difficulty of the object.
+    pub fn leading_zero_bits(&self) -> u32 {
+        let mut bits = 0;
+        for byte in self.bytes {
+            bits += byte.leading_zeros();
+            if byte != 0 {
+                break;
+            }
+        }
+        bits
+    }
+
+    /// Length of the creation time prefix of the time sortable ids.
+    pub const SORTABLE_PREFIX_LEN: usize = 8;
+
+    /// Returns the prefix of the time sortable ids of the objects created at
+    /// `created_at`: the big endian creation time.
+    ///
+    /// Time sortable ids are an opt-in alternative to plain content hashes,
+    /// see [`crate::Qso::new_sortable`]. The hash is truncated to the last 24
+    /// bytes, still out of reach of collisions, and objects stored by id in
+    /// a key-value store sort by creation time, so time range scans become
+    /// prefix or range scans over the keys. The tradeoffs:
+    ///
+    /// * the id no longer looks random, it reveals the creation time and
+    ///   groups the objects of a second together, which is exactly what some
+    ///   storage layouts want to avoid.
+    /// * the id can't carry a proof of work, its leading bits are the time.
+    /// * the same data signed with both schemes gets two different ids.
+    pub fn sortable_prefix(created_at: u64) -> [u8; Self::SORTABLE_PREFIX_LEN] {
+        created_at.to_be_bytes()
+    }
+
+    /// Returns the time sortable variant of a content hash.
+    pub(crate) fn into_sortable(self, created_at: u64) -> Self {
+        let mut bytes = [0; 32];
+        let (prefix, hash) = bytes.split_at_mut(Self::SORTABLE_PREFIX_LEN);
+        prefix.copy_from_slice(&Self::sortable_prefix(created_at));
+        hash.copy_from_slice(&self.bytes[..32 - Self::SORTABLE_PREFIX_LEN]);
+        Self { bytes }
     }
 
     /// Sign the id.
@@ -40,11 +120,27 @@ impl Id {
         keys.sign_schnorr(Message::from_digest(self.bytes))
     }
 
+    /// Sign the id using the given context instead of the global one, e.g. a
+    /// context randomized with [`crate::signing_context`].
+    pub fn sign_with_context<C: Signing>(&self, secp: &Secp256k1<C>, keys: &Keypair) -> Signature {
+        secp.sign_schnorr(&Message::from_digest(self.bytes), keys)
+    }
+
     /// Verify the id signature.
     pub fn verify(&self, pub_key: &XOnlyPublicKey, sig: &Signature) -> Result<(), Error> {
+        self.verify_with_context(SECP256K1, pub_key, sig)
+    }
+
+    /// Verify the id signature using the given context instead of the global
+    /// one.
+    pub fn verify_with_context<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        pub_key: &XOnlyPublicKey,
+        sig: &Signature,
+    ) -> Result<(), Error> {
         let message = Message::from_digest(self.bytes);
-        SECP256K1
-            .verify_schnorr(sig, &message, pub_key)
+        secp.verify_schnorr(sig, &message, pub_key)
             .context("failed to verify signature")?;
         Ok(())
     }
@@ -56,6 +152,16 @@ impl Display for Id {
     }
 }
 
+impl FromStr for Id {
+    type Err = Error;
+
+    /// Parses the lowercase or uppercase hex encoding of an id.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let bytes = <[u8; 32]>::from_hex(s).context("invalid id")?;
+        Ok(Self { bytes })
+    }
+}
+
 impl Serialize for Id {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -83,3 +189,123 @@ impl<'de> Deserialize<'de> for Id {
         Ok(Self { bytes })
     }
 }
+
+/// Builds the id of an object with optional fields.
+///
+/// Optional fields are appended as a trailing json object only when at least
+/// one of them is present, so objects not using them keep the same id they
+/// always had. Like in a json object the optional fields must be added in
+/// key order.
+pub(crate) struct IdBuilder {
+    hasher: Hasher,
+    last_key: Option<&'static str>,
+}
+
+impl IdBuilder {
+    /// Starts the id with the fields every object has, hashed with the
+    /// algorithm of the object version, see [`Id::hash_with`].
+    pub(crate) fn new<T: Serialize + ?Sized>(algorithm: HashAlgorithm, fields: &T) -> Self {
+        let mut writer = WithoutLastByte {
+            hasher: algorithm.hasher(),
+            last: None,
+        };
+        serde_json::to_writer(&mut writer, fields).expect("id fields are serializable");
+        // The closing bracket is written by finish.
+        debug_assert_eq!(writer.last, Some(b']'));
+
+        Self {
+            hasher: writer.hasher,
+            last_key: None,
+        }
+    }
+
+    /// Adds an optional field.
+    pub(crate) fn optional<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) {
+        debug_assert!(self.last_key < Some(key), "optional fields out of order");
+
+        self.hasher.update(match self.last_key {
+            None => ",{",
+            Some(_) => ",",
+        });
+        serde_json::to_writer(&mut self.hasher, key).expect("keys are serializable");
+        self.hasher.update(":");
+        serde_json::to_writer(&mut self.hasher, value).expect("id fields are serializable");
+        self.last_key = Some(key);
+    }
+
+    /// Returns the id.
+    pub(crate) fn finish(mut self) -> Id {
+        if self.last_key.is_some() {
+            self.hasher.update("}");
+        }
+        self.hasher.update("]");
+        Id {
+            bytes: self.hasher.finalize(),
+        }
+    }
+}
+
+/// Hashes everything written but the last byte.
+struct WithoutLastByte {
+    hasher: Hasher,
+    last: Option<u8>,
+}
+
+impl Write for WithoutLastByte {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some((last, rest)) = buf.split_last() {
+            if let Some(previous) = self.last.replace(*last) {
+                self.hasher.update([previous]);
+            }
+            self.hasher.update(rest);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::HashAlgorithm;
+    use crate::id::IdBuilder;
+    use crate::{version, Id};
+    use serde_json::{json, Map, Value};
+
+    #[test]
+    fn test_leading_zero_bits() {
+        let mut id = Id::new("test");
+        id.bytes = [0xff; 32];
+        assert_eq!(id.leading_zero_bits(), 0);
+        id.bytes[0] = 0x00;
+        id.bytes[1] = 0x1f;
+        assert_eq!(id.leading_zero_bits(), 11);
+        id.bytes = [0; 32];
+        assert_eq!(id.leading_zero_bits(), 256);
+    }
+
+    #[test]
+    fn test_id_builder() {
+        let fields = ("LU4EV", "tnx \"fb\" qso\n", 1704141426u64, 1u8);
+        let value = json!(["LU4EV", "tnx \"fb\" qso\n", 1704141426u64, 1u8]);
+        assert_eq!(Id::hash(version::V0, &fields), Id::new(&value.to_string()));
+        assert_eq!(
+            IdBuilder::new(HashAlgorithm::Sha256, &fields).finish(),
+            Id::hash(version::V0, &fields)
+        );
+
+        let mut builder = IdBuilder::new(HashAlgorithm::Sha256, &fields);
+        builder.optional("extra", &json!({"b": 1, "a": [true, null]}));
+        builder.optional("nonce", &42u64);
+
+        let mut optional = Map::new();
+        optional.insert("nonce".to_string(), json!(42));
+        optional.insert("extra".to_string(), json!({"a": [true, null], "b": 1}));
+        let mut value = value.as_array().unwrap().clone();
+        value.push(Value::Object(optional));
+
+        assert_eq!(builder.finish(), Id::new(&Value::Array(value).to_string()));
+    }
+}
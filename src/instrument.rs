@@ -0,0 +1,140 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Metrics and tracing.
+//!
+//! Metrics are recorded through the [`metrics`] facade and spans are emitted
+//! with [`tracing`] around signing, verification and store operations. Both
+//! are no-ops until the application installs a metrics recorder or a tracing
+//! subscriber.
+
+use crate::Kind;
+use std::time::Duration;
+
+/// Counter of successfully verified objects, labeled by `kind`.
+pub const OBJECTS_VERIFIED: &str = "gqdb_objects_verified_total";
+
+/// Counter of objects with an invalid id or signature, labeled by `kind`.
+pub const SIGNATURE_FAILURES: &str = "gqdb_signature_failures_total";
+
+/// Counter of objects failing the field validation, labeled by `kind`.
+pub const VALIDATION_FAILURES: &str = "gqdb_validation_failures_total";
+
+/// Histogram of the verification time in seconds, labeled by `kind`.
+pub const VERIFY_SECONDS: &str = "gqdb_verify_seconds";
+
+/// Counter of signed ids.
+pub const IDS_SIGNED: &str = "gqdb_ids_signed_total";
+
+/// Counter of the events processed by store imports, labeled by `outcome`:
+/// `imported`, `duplicate` or `rejected`.
+pub const EVENTS_IMPORTED: &str = "gqdb_events_imported_total";
+
+/// Outcome of an object verification.
+pub(crate) enum Verification {
+    Verified,
+    InvalidSignature,
+    InvalidFields,
+}
+
+pub(crate) fn record_verify(kind: Kind, verification: Verification, elapsed: Duration) {
+    let name = match verification {
+        Verification::Verified => OBJECTS_VERIFIED,
+        Verification::InvalidSignature => SIGNATURE_FAILURES,
+        Verification::InvalidFields => VALIDATION_FAILURES,
+    };
+    metrics::counter!(name, "kind" => kind.as_str()).increment(1);
+    metrics::histogram!(VERIFY_SECONDS, "kind" => kind.as_str()).record(elapsed.as_secs_f64());
+}
+
+pub(crate) fn record_sign() {
+    metrics::counter!(IDS_SIGNED).increment(1);
+}
+
+pub(crate) fn record_import(outcome: &'static str) {
+    metrics::counter!(EVENTS_IMPORTED, "outcome" => outcome).increment(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instrument::{OBJECTS_VERIFIED, SIGNATURE_FAILURES, VERIFY_SECONDS};
+    use crate::{test_vectors, Kind};
+    use metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    use std::sync::Mutex;
+
+    /// Records the keys of the registered metrics.
+    #[derive(Default)]
+    struct KeyRecorder {
+        keys: Mutex<Vec<String>>,
+    }
+
+    impl KeyRecorder {
+        fn record(&self, key: &Key) {
+            let labels: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            let key = format!("{}{{{}}}", key.name(), labels.join(","));
+            self.keys.lock().unwrap().push(key);
+        }
+    }
+
+    impl Recorder for KeyRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            self.record(key);
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            self.record(key);
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            self.record(key);
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_verify_metrics() {
+        let station = test_vectors::station();
+        let mut qso = test_vectors::qso();
+
+        let recorder = KeyRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            qso.verify(&station.pub_key).unwrap();
+            qso.rst = "57".to_string();
+            assert!(qso.verify(&station.pub_key).is_err());
+        });
+
+        assert_eq!(
+            *recorder.keys.lock().unwrap(),
+            vec![
+                format!("{}{{kind=qso}}", OBJECTS_VERIFIED),
+                format!("{}{{kind=qso}}", VERIFY_SECONDS),
+                format!("{}{{kind=qso}}", SIGNATURE_FAILURES),
+                format!("{}{{kind=qso}}", VERIFY_SECONDS),
+            ]
+        );
+
+        let kind: Kind = serde_json::from_value(Kind::AwardCertificate.as_str().into()).unwrap();
+        assert_eq!(kind, Kind::AwardCertificate);
+    }
+}
@@ -11,9 +11,401 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use secp256k1::Keypair;
+use crate::signer::{SignRequest, SignResponse, Signer};
+use anyhow::{bail, Context, Result};
+use rand::RngCore;
+use secp256k1::{Keypair, Secp256k1, SignOnly, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::{Debug, Formatter};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 pub fn generate_keypair() -> Keypair {
     let (secret_key, _) = secp256k1::generate_keypair(&mut rand::thread_rng());
     Keypair::from_secret_key(secp256k1::SECP256K1, &secret_key)
 }
+
+/// Derives a key pair from a seed, the same seed always returns the same key
+/// pair.
+///
+/// Meant for tests and test vectors, station keys must come from
+/// [`generate_keypair`].
+pub fn generate_keypair_from_seed(seed: &[u8; 32]) -> Keypair {
+    let mut counter: u32 = 0;
+    loop {
+        let mut hasher = Sha256::new();
+        hasher.update(seed);
+        hasher.update(counter.to_be_bytes());
+        // An invalid secret key is astronomically unlikely, retry anyway.
+        if let Ok(secret_key) = secp256k1::SecretKey::from_slice(&hasher.finalize()) {
+            return Keypair::from_secret_key(secp256k1::SECP256K1, &secret_key);
+        }
+        counter += 1;
+    }
+}
+
+/// Returns a signing context randomized with fresh entropy, hardening the
+/// signing operations against side-channel attacks.
+pub fn signing_context() -> Secp256k1<SignOnly> {
+    let mut secp = Secp256k1::signing_only();
+    secp.randomize(&mut rand::thread_rng());
+    secp
+}
+
+/// A station secret key wiped from memory when dropped.
+///
+/// The key bytes never show up in `Debug` output and can only be read through
+/// [`SecretKey::dangerous_export_secret`]. The key signs through the
+/// [`Signer`] trait, the expanded key pair lives only for the duration of
+/// each signature.
+#[derive(Clone)]
+pub struct SecretKey {
+    bytes: [u8; 32],
+}
+
+impl SecretKey {
+    /// Generates a new random secret key.
+    pub fn generate() -> Self {
+        let (mut secret_key, _) = secp256k1::generate_keypair(&mut rand::thread_rng());
+        let key = Self {
+            bytes: secret_key.secret_bytes(),
+        };
+        secret_key.non_secure_erase();
+        key
+    }
+
+    /// Creates a secret key from its raw bytes, wiping the source.
+    pub fn from_bytes(bytes: &mut [u8; 32]) -> Result<Self> {
+        let result = secp256k1::SecretKey::from_slice(bytes)
+            .map(|mut secret_key| secret_key.non_secure_erase())
+            .context("invalid secret key");
+        let key = Self { bytes: *bytes };
+        bytes.zeroize();
+        result.map(|_| key)
+    }
+
+    /// Returns the raw secret key bytes.
+    ///
+    /// Anyone holding these bytes can sign as the station. Only meant for
+    /// backups and transfers to a hardware signer.
+    pub fn dangerous_export_secret(&self) -> &[u8; 32] {
+        &self.bytes
+    }
+
+    /// Returns the public key of the station.
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        let mut keys = self.keypair();
+        let pub_key = keys.x_only_public_key().0;
+        keys.non_secure_erase();
+        pub_key
+    }
+
+    fn keypair(&self) -> Keypair {
+        Keypair::from_seckey_slice(secp256k1::SECP256K1, &self.bytes)
+            .expect("secret key validated on creation")
+    }
+}
+
+impl Signer for SecretKey {
+    fn public_key(&self) -> Result<XOnlyPublicKey> {
+        Ok(SecretKey::public_key(self))
+    }
+
+    fn sign(&self, request: &SignRequest) -> Result<SignResponse> {
+        let mut keys = self.keypair();
+        let sig = request.id.sign(&keys);
+        keys.non_secure_erase();
+        Ok(SignResponse { sig })
+    }
+}
+
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+impl Zeroize for SecretKey {
+    fn zeroize(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecretKey {}
+
+/// A share of a station secret key, see [`split`].
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Share {
+    /// Evaluation point of the share, from 1 to the number of shares.
+    pub index: u8,
+    /// Number of shares needed to recover the key.
+    pub threshold: u8,
+    /// Public key of the shared key pair.
+    pub pub_key: XOnlyPublicKey,
+    #[serde(with = "hex")]
+    data: [u8; 32],
+    /// First bytes of the sha256 of the other fields, detects corrupted
+    /// shares.
+    #[serde(with = "hex")]
+    checksum: [u8; 4],
+}
+
+impl Share {
+    /// Returns an error if the share was corrupted.
+    pub fn verify_checksum(&self) -> Result<()> {
+        if Self::compute_checksum(self.index, self.threshold, &self.pub_key, &self.data)
+            != self.checksum
+        {
+            bail!("invalid checksum for share {}", self.index);
+        }
+        Ok(())
+    }
+
+    fn compute_checksum(
+        index: u8,
+        threshold: u8,
+        pub_key: &XOnlyPublicKey,
+        data: &[u8; 32],
+    ) -> [u8; 4] {
+        let mut hasher = Sha256::new();
+        hasher.update([index, threshold]);
+        hasher.update(pub_key.serialize());
+        hasher.update(data);
+        let hash = hasher.finalize();
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+}
+
+impl Debug for Share {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Share")
+            .field("index", &self.index)
+            .field("threshold", &self.threshold)
+            .field("pub_key", &self.pub_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
+/// Splits the secret key of the key pair in `n` shares, any `threshold` of
+/// them recover the key with [`recover`].
+///
+/// Uses Shamir secret sharing over GF(256), so a club can distribute the
+/// custody of the station key among its officers.
+pub fn split(keys: &Keypair, threshold: u8, n: u8) -> Result<Vec<Share>> {
+    if threshold < 2 || threshold > n {
+        bail!("invalid threshold {} of {}", threshold, n);
+    }
+
+    let pub_key = keys.x_only_public_key().0;
+    let mut secret = keys.secret_bytes();
+    let mut coefficients = vec![[0u8; 32]; threshold as usize - 1];
+    for coefficient in coefficients.iter_mut() {
+        rand::thread_rng().fill_bytes(coefficient);
+    }
+
+    let shares = (1..=n)
+        .map(|index| {
+            let mut data = [0u8; 32];
+            for (pos, byte) in data.iter_mut().enumerate() {
+                // Horner evaluation of the polynomial with the secret byte as
+                // the constant term.
+                let mut y = 0;
+                for coefficient in coefficients.iter().rev() {
+                    y = gf_mul(y, index) ^ coefficient[pos];
+                }
+                *byte = gf_mul(y, index) ^ secret[pos];
+            }
+
+            let checksum = Share::compute_checksum(index, threshold, &pub_key, &data);
+            Share {
+                index,
+                threshold,
+                pub_key,
+                data,
+                checksum,
+            }
+        })
+        .collect();
+
+    secret.zeroize();
+    coefficients.zeroize();
+
+    Ok(shares)
+}
+
+/// Recovers the key pair from at least `threshold` shares produced by
+/// [`split`]. Repeated shares are ignored, but shares with the same index and
+/// different data are rejected.
+pub fn recover(shares: &[Share]) -> Result<Keypair> {
+    let Some(first) = shares.first() else {
+        bail!("no shares");
+    };
+
+    for share in shares {
+        share.verify_checksum()?;
+        if share.threshold != first.threshold || share.pub_key != first.pub_key {
+            bail!("shares of different keys");
+        }
+    }
+
+    // Copies of the same share are fine, as long as they agree.
+    let mut unique: Vec<&Share> = Vec::with_capacity(shares.len());
+    for share in shares {
+        if share.index == 0 {
+            bail!("invalid share index {}", share.index);
+        }
+        match unique.iter().find(|s| s.index == share.index) {
+            Some(other) if other.data != share.data => {
+                bail!("conflicting shares with index {}", share.index);
+            }
+            Some(_) => {}
+            None => unique.push(share),
+        }
+    }
+
+    if unique.len() < first.threshold as usize {
+        bail!("{} shares needed, got {}", first.threshold, unique.len());
+    }
+    let shares = &unique[..first.threshold as usize];
+
+    let mut secret = [0u8; 32];
+    for share in shares {
+        // Lagrange basis polynomial of the share evaluated at zero.
+        let mut basis = 1;
+        for other in shares.iter().filter(|other| other.index != share.index) {
+            basis = gf_mul(basis, gf_div(other.index, other.index ^ share.index));
+        }
+        for (byte, data) in secret.iter_mut().zip(share.data) {
+            *byte ^= gf_mul(basis, data);
+        }
+    }
+
+    let keys = Keypair::from_seckey_slice(secp256k1::SECP256K1, &secret);
+    secret.zeroize();
+    let keys = keys.context("invalid recovered key")?;
+
+    if keys.x_only_public_key().0 != first.pub_key {
+        bail!("recovered key doesn't match the shares public key");
+    }
+
+    Ok(keys)
+}
+
+/// Multiplication in GF(256) with the AES reduction polynomial, without data
+/// dependent branches.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    // b^254 is the inverse of b.
+    let mut inverse = 1;
+    for _ in 0..254 {
+        inverse = gf_mul(inverse, b);
+    }
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::{self, generate_keypair, SecretKey, Share};
+    use crate::Station;
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_secret_key() {
+        let key = SecretKey::generate();
+        assert_eq!(format!("{:?}", key), "SecretKey(..)");
+
+        let station = Station::new_with_signer(
+            &key,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        station.verify().unwrap();
+        assert_eq!(station.pub_key, key.public_key());
+
+        let mut bytes = *key.dangerous_export_secret();
+        let imported = SecretKey::from_bytes(&mut bytes).unwrap();
+        assert_eq!(bytes, [0; 32]);
+        assert_eq!(imported.public_key(), key.public_key());
+
+        assert!(SecretKey::from_bytes(&mut [0; 32]).is_err());
+    }
+
+    #[test]
+    fn test_split_recover() {
+        let keys = generate_keypair();
+        let shares = keys::split(&keys, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        let recovered =
+            keys::recover(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]).unwrap();
+        assert_eq!(recovered.secret_bytes(), keys.secret_bytes());
+        assert!(keys::recover(&shares).is_ok());
+
+        assert!(keys::recover(&shares[..2]).is_err());
+        assert!(keys::recover(&[shares[0].clone(), shares[0].clone(), shares[1].clone()]).is_err());
+        let recovered = keys::recover(&[
+            shares[3].clone(),
+            shares[3].clone(),
+            shares[1].clone(),
+            shares[4].clone(),
+        ])
+        .unwrap();
+        assert_eq!(recovered.secret_bytes(), keys.secret_bytes());
+
+        let mut conflicting = shares[3].clone();
+        conflicting.data = shares[2].data;
+        conflicting.checksum = Share::compute_checksum(
+            conflicting.index,
+            conflicting.threshold,
+            &conflicting.pub_key,
+            &conflicting.data,
+        );
+        let err = keys::recover(&[
+            shares[3].clone(),
+            conflicting,
+            shares[1].clone(),
+            shares[4].clone(),
+        ])
+        .unwrap_err();
+        assert_eq!(err.to_string(), "conflicting shares with index 4");
+
+        let json_str = serde_json::to_string(&shares[1]).unwrap();
+        let share_dese: Share = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(share_dese, shares[1]);
+
+        let mut corrupted = shares[1].clone();
+        corrupted.data[0] ^= 1;
+        assert!(corrupted.verify_checksum().is_err());
+        assert!(keys::recover(&[shares[0].clone(), corrupted, shares[2].clone()]).is_err());
+
+        let other = keys::split(&generate_keypair(), 3, 5).unwrap();
+        assert!(keys::recover(&[shares[0].clone(), shares[1].clone(), other[2].clone()]).is_err());
+
+        assert!(keys::split(&keys, 1, 5).is_err());
+        assert!(keys::split(&keys, 6, 5).is_err());
+    }
+}
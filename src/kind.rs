@@ -0,0 +1,93 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Result};
+use serde::de::value::StrDeserializer;
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// The kind of a signed object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Station,
+    Qso,
+    Certificate,
+    Delegation,
+    AwardCertificate,
+    TrustBundle,
+    BatchManifest,
+    LogChunk,
+    RelayList,
+    Revocation,
+    ArchiveManifest,
+    Checkpoint,
+    Attestation,
+    Report,
+    SwlReport,
+    SwlAck,
+    NetSession,
+    NetCheckIns,
+    NetAttendance,
+    Spot,
+    BonusClaim,
+    Beacon,
+    Repeater,
+    StatsSnapshot,
+    Tombstone,
+}
+
+impl Kind {
+    /// Returns the name of the kind, as serialized.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Station => "station",
+            Kind::Qso => "qso",
+            Kind::Certificate => "certificate",
+            Kind::Delegation => "delegation",
+            Kind::AwardCertificate => "award_certificate",
+            Kind::TrustBundle => "trust_bundle",
+            Kind::BatchManifest => "batch_manifest",
+            Kind::LogChunk => "log_chunk",
+            Kind::RelayList => "relay_list",
+            Kind::Revocation => "revocation",
+            Kind::ArchiveManifest => "archive_manifest",
+            Kind::Checkpoint => "checkpoint",
+            Kind::Attestation => "attestation",
+            Kind::Report => "report",
+            Kind::SwlReport => "swl_report",
+            Kind::SwlAck => "swl_ack",
+            Kind::NetSession => "net_session",
+            Kind::NetCheckIns => "net_check_ins",
+            Kind::NetAttendance => "net_attendance",
+            Kind::Spot => "spot",
+            Kind::BonusClaim => "bonus_claim",
+            Kind::Beacon => "beacon",
+            Kind::Repeater => "repeater",
+            Kind::StatsSnapshot => "stats_snapshot",
+            Kind::Tombstone => "tombstone",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = anyhow::Error;
+
+    /// Parses the name of a kind ( e.g. `qso` ).
+    fn from_str(s: &str) -> Result<Self> {
+        let deserializer: StrDeserializer<serde::de::value::Error> = s.into_deserializer();
+        Kind::deserialize(deserializer).map_err(|_| anyhow!("unknown kind {}", s))
+    }
+}
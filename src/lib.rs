@@ -13,18 +13,200 @@
 
 //! The global QSO Database.
 
+pub mod adif;
+pub mod archive;
+mod attestation;
+pub mod awards;
+pub mod bandplan;
+mod beacon;
+mod bundle;
+mod cache;
+mod callsign;
 mod certificate;
+mod checkpoint;
+mod chunk;
+pub mod contest;
+pub mod cosign;
+pub mod csv;
+mod delegation;
+mod device;
+pub mod discovery;
+pub mod dxcc;
+mod entity;
+mod event;
+#[cfg(feature = "fldigi")]
+pub mod fldigi;
+pub mod frost;
+pub mod geo;
+pub mod hash;
+#[cfg(feature = "http")]
+pub mod http;
 mod id;
+pub mod instrument;
+mod kind;
+pub mod limits;
+mod logbook;
+pub mod map;
+pub mod merkle;
+#[cfg(feature = "n1mm")]
+pub mod n1mm;
+mod net;
+pub mod policy;
+mod pow;
+pub mod programs;
+mod progress;
+pub mod propagation;
+#[cfg(feature = "server")]
+pub mod server;
+mod session;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 mod station;
+pub mod stats;
+mod stats_snapshot;
+mod store;
+mod subscription;
+pub mod summary;
+mod swl;
+mod tag;
+pub mod template;
+pub mod test_vectors;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod text;
 mod time;
+mod tombstone;
+mod trust;
+mod validation;
+pub mod version;
+pub mod wellknown;
+#[cfg(feature = "wsjtx")]
+pub mod wsjtx;
 
 mod qso;
+pub mod query;
+mod redaction;
+mod relay;
+mod relay_list;
+mod repeater;
+mod replaceable;
+mod report;
+pub mod reputation;
+mod resolve;
+mod revocation;
+#[cfg(feature = "schemars")]
+pub mod schema;
+mod schnorr;
+mod signable;
+mod signer;
+mod spot;
 
-mod keys;
+pub mod keys;
 
+pub use crate::attestation::Attestation;
+pub use crate::beacon::Beacon;
+pub use crate::beacon::BeaconData;
+pub use crate::beacon::BeaconSchedule;
+pub use crate::beacon::BEACON_FREQ_TOLERANCE;
+pub use crate::bundle::Bundle;
+pub use crate::cache::CacheStats;
+pub use crate::cache::VerifyCache;
+pub use crate::callsign::Callsign;
+pub use crate::callsign::CallsignPattern;
+pub use crate::callsign::CallsignPolicy;
+pub use crate::callsign::ItuCallsign;
+pub use crate::callsign::PermissiveCallsign;
+pub use crate::callsign::StrictCallsign;
 pub use crate::certificate::Certificate;
+pub use crate::checkpoint::Checkpoint;
+pub use crate::chunk::ChunkQso;
+pub use crate::chunk::LogChunk;
+pub use crate::cosign::CoSignedQso;
+pub use crate::delegation::Delegation;
+pub use crate::device::DeviceRequest;
+pub use crate::device::DeviceResponse;
+pub use crate::device::DeviceSigner;
+pub use crate::entity::Entity;
+pub use crate::event::Event;
+pub use crate::event::Filter;
 pub use crate::id::Id;
 pub use crate::keys::generate_keypair;
+pub use crate::keys::generate_keypair_from_seed;
+pub use crate::keys::signing_context;
+pub use crate::keys::SecretKey;
+pub use crate::kind::Kind;
+pub use crate::logbook::DuplicateResolution;
+pub use crate::logbook::ImportReport;
+pub use crate::logbook::Logbook;
+pub use crate::logbook::MergeConflict;
+pub use crate::logbook::MergeReport;
+pub use crate::logbook::SkipReason;
+pub use crate::logbook::CONFIRMATION_WINDOW;
+pub use crate::logbook::DUPLICATE_WINDOW;
+pub use crate::net::CheckIn;
+pub use crate::net::NetAttendance;
+pub use crate::net::NetCheckIns;
+pub use crate::net::NetSession;
+pub use crate::net::MAX_CHECK_INS;
+pub use crate::progress::CancellationToken;
+pub use crate::progress::Cancelled;
+pub use crate::progress::Monitor;
+pub use crate::progress::Progress;
 pub use crate::qso::Qso;
 pub use crate::qso::QsoData;
+pub use crate::qso::RST_RCVD;
+pub use crate::redaction::Redactable;
+pub use crate::relay::Relay;
+pub use crate::relay::RelayHealth;
+pub use crate::relay::RelayPool;
+pub use crate::relay_list::RelayList;
+pub use crate::repeater::Repeater;
+pub use crate::repeater::RepeaterData;
+pub use crate::repeater::Tone;
+pub use crate::replaceable::latest_of;
+pub use crate::replaceable::Replaceable;
+pub use crate::report::Report;
+pub use crate::report::ReportReason;
+pub use crate::report::ReportSummary;
+pub use crate::resolve::resolve;
+pub use crate::resolve::Canonical;
+pub use crate::revocation::Revocation;
+pub use crate::session::BatchManifest;
+pub use crate::session::SigningSession;
+pub use crate::signable::Signable;
+pub use crate::signable::Validate;
+pub use crate::signer::ContextSigner;
+pub use crate::signer::SignRequest;
+pub use crate::signer::SignResponse;
+pub use crate::signer::Signer;
+pub use crate::spot::Spot;
+pub use crate::spot::SPOT_LIFETIME;
+pub use crate::station::Profile;
+pub use crate::station::QslRoute;
+pub use crate::station::QslVia;
 pub use crate::station::Station;
+pub use crate::station::StationData;
+pub use crate::stats_snapshot::StatsSnapshot;
+pub use crate::store::AsyncStore;
+pub use crate::store::CompactOptions;
+pub use crate::store::CompactStats;
+pub use crate::store::ImportOptions;
+pub use crate::store::ImportStats;
+pub use crate::store::MemoryStore;
+pub use crate::store::Store;
+pub use crate::subscription::Subscription;
+pub use crate::subscription::MAX_PENDING_EVENTS;
+pub use crate::swl::SwlAck;
+pub use crate::swl::SwlReport;
+pub use crate::swl::SwlReportData;
+pub use crate::tag::Tag;
+pub use crate::tag::MAX_TAGS;
+pub use crate::tombstone::Tombstone;
+pub use crate::trust::Anchor;
+pub use crate::trust::Role;
+pub use crate::trust::TrustBundle;
+pub use crate::trust::TrustStore;
+pub use crate::validation::CountryCheck;
+pub use crate::validation::ValidationOptions;
+pub use crate::validation::ValidationReport;
+pub use crate::validation::ValidationWarning;
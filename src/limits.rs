@@ -0,0 +1,313 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Size limits of the protocol.
+//!
+//! Every event must fit in the size budget of its kind, see
+//! [`max_event_size`]. Verification, relays and stores all check the budget,
+//! so an event accepted by one implementation is accepted by every other.
+//!
+//! Validation rejects oversized fields only after the whole object was
+//! deserialized. The protocol field limits, [`Limits::DEFAULT`], are checked
+//! by the deserializer itself, so a malicious peer can't make a verifier
+//! hold multi-megabyte strings. Tighter limits are set per verifier with
+//! [`ValidationOptions::with_limits`](crate::ValidationOptions::with_limits).
+
+use crate::event::Event;
+use crate::{text, Kind, Tag};
+use anyhow::{bail, Result};
+use serde::de::{Error, Visitor};
+use serde::Deserializer;
+use std::borrow::Cow;
+use std::fmt::Formatter;
+
+const KIB: usize = 1024;
+
+/// Maximum size in bytes of a serialized event of any kind.
+pub const MAX_EVENT_SIZE: usize = 1024 * KIB;
+
+/// Returns the maximum size in bytes of a serialized event of the kind, as
+/// compact JSON.
+///
+/// The budgets leave room for the extension fields and tags of a typical
+/// object. Only the batch and archive objects may reach [`MAX_EVENT_SIZE`].
+pub const fn max_event_size(kind: Kind) -> usize {
+    match kind {
+        Kind::Revocation => KIB,
+        Kind::Delegation
+        | Kind::Checkpoint
+        | Kind::SwlAck
+        | Kind::NetSession
+        | Kind::NetAttendance
+        | Kind::Spot
+        | Kind::BonusClaim
+        | Kind::Beacon
+        | Kind::Repeater
+        | Kind::Tombstone => 4 * KIB,
+        Kind::Station
+        | Kind::Qso
+        | Kind::Certificate
+        | Kind::RelayList
+        | Kind::Attestation
+        | Kind::Report
+        | Kind::SwlReport
+        | Kind::StatsSnapshot => 16 * KIB,
+        Kind::AwardCertificate | Kind::TrustBundle => 64 * KIB,
+        Kind::NetCheckIns => 128 * KIB,
+        Kind::BatchManifest | Kind::LogChunk | Kind::ArchiveManifest => MAX_EVENT_SIZE,
+    }
+}
+
+/// Returns the size in bytes of the event serialized as compact JSON.
+pub fn event_size(event: &Event) -> Result<usize> {
+    Ok(serde_json::to_vec(event)?.len())
+}
+
+/// Checks that the event fits in the budget of its kind, returning its size.
+pub fn check_event_size(event: &Event) -> Result<usize> {
+    let size = event_size(event)?;
+    check_size(event.kind(), size)?;
+    Ok(size)
+}
+
+/// Checks that a serialized event of the kind fits in its budget.
+pub fn check_size(kind: Kind, size: usize) -> Result<()> {
+    let max = max_event_size(kind);
+    if size > max {
+        bail!("{} event of {} bytes, at most {}", kind.as_str(), size, max);
+    }
+    Ok(())
+}
+
+/// Maximum lengths in bytes of the free text fields and the callsign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Limits {
+    pub callsign: usize,
+    pub operator: usize,
+    pub mode: usize,
+    pub rst: usize,
+    pub comments: usize,
+    /// Size of each tag, see [`Tag::size`].
+    pub tag: usize,
+}
+
+impl Limits {
+    /// The protocol limits. Validation limits text fields in graphemes, so
+    /// their size in bytes is bounded by the widest graphemes allowed.
+    pub const DEFAULT: Limits = Limits {
+        callsign: crate::callsign::CALLSIGN_MAX_LEN,
+        operator: text::max_bytes(crate::station::OPERATOR_MAX_LEN),
+        mode: text::max_bytes(crate::qso::MODE_MAX_LEN),
+        rst: text::max_bytes(crate::qso::RST_MAX_LEN),
+        comments: text::max_bytes(crate::qso::COMMENTS_MAX_LEN),
+        tag: crate::tag::TAG_MAX_LEN,
+    };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Checks the length in bytes of a field against its limit.
+pub(crate) fn check_len(field: &str, value: &str, max_len: usize) -> Result<()> {
+    if value.len() > max_len {
+        bail!("{} longer than {} bytes", field, max_len);
+    }
+    Ok(())
+}
+
+/// Checks the size of every tag against the limit.
+pub(crate) fn check_tags(tags: &[Tag], max_len: usize) -> Result<()> {
+    if let Some(tag) = tags.iter().find(|tag| tag.size() > max_len) {
+        bail!("{} tag longer than {} bytes", tag.name(), max_len);
+    }
+    Ok(())
+}
+
+struct Bounded {
+    field: &'static str,
+    max_len: usize,
+}
+
+impl Bounded {
+    fn check<E: Error>(&self, len: usize) -> Result<(), E> {
+        if len > self.max_len {
+            return Err(E::custom(format_args!(
+                "{} longer than {} bytes",
+                self.field, self.max_len
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Visitor<'de> for Bounded {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a string of at most {} bytes", self.max_len)
+    }
+
+    fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        self.check(v.len())?;
+        Ok(Cow::Borrowed(v))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.check(v.len())?;
+        Ok(Cow::Owned(v.to_string()))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        self.check(v.len())?;
+        Ok(Cow::Owned(v))
+    }
+}
+
+fn bounded<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    field: &'static str,
+    max_len: usize,
+) -> Result<Cow<'de, str>, D::Error> {
+    deserializer.deserialize_str(Bounded { field, max_len })
+}
+
+pub(crate) fn callsign<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    bounded(deserializer, "callsign", Limits::DEFAULT.callsign).map(Cow::into_owned)
+}
+
+pub(crate) fn operator<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    bounded(deserializer, "operator", Limits::DEFAULT.operator).map(Cow::into_owned)
+}
+
+pub(crate) fn mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    bounded(deserializer, "mode", Limits::DEFAULT.mode).map(Cow::into_owned)
+}
+
+pub(crate) fn rst<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    bounded(deserializer, "rst", Limits::DEFAULT.rst).map(Cow::into_owned)
+}
+
+pub(crate) fn comments<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    bounded(deserializer, "comments", Limits::DEFAULT.comments).map(Cow::into_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::limits::{self, Limits};
+    use crate::policy::{IngestionPolicy, Policy, Rejection};
+    use crate::test_vectors;
+    use crate::{Kind, MemoryStore, Qso, QsoData, Station, Store, Tag, ValidationOptions};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_limits() {
+        let qso_str = serde_json::to_string(&test_vectors::qso()).unwrap();
+        let station_str = serde_json::to_string(&test_vectors::station()).unwrap();
+
+        let oversized = qso_str.replace("\"73\"", &format!("\"{}\"", "7".repeat(1 << 20)));
+        let err = serde_json::from_str::<Qso>(&oversized).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("comments longer than 512 bytes"));
+
+        let oversized = station_str.replace("LU4EV", &"A".repeat(17));
+        assert!(serde_json::from_str::<Station>(&oversized).is_err());
+
+        let qso = serde_json::from_str::<Qso>(&qso_str).unwrap();
+        let station = serde_json::from_str::<Station>(&station_str).unwrap();
+
+        // Verifiers may enforce tighter limits.
+        let options = ValidationOptions::default().with_limits(Limits {
+            comments: 1,
+            ..Limits::DEFAULT
+        });
+        qso.verify_with_options(&station.pub_key, &ValidationOptions::default())
+            .unwrap();
+        assert!(qso.verify_with_options(&station.pub_key, &options).is_err());
+        station.verify_with_options(&options).unwrap();
+        let options = options.with_limits(Limits {
+            operator: 4,
+            ..Limits::DEFAULT
+        });
+        assert!(station.verify_with_options(&options).is_err());
+
+        let data = QsoData {
+            station_id: qso.station_id,
+            callsign: qso.callsign,
+            freq: qso.freq,
+            datetime: qso.datetime,
+            mode: qso.mode,
+            rst: qso.rst,
+            comments: qso.comments,
+            extra: qso.extra,
+            tags: vec![Tag::Other("x-logger".to_string(), vec!["gqdb".to_string()])],
+            ..test_vectors::qso_data()
+        };
+        let tagged = Qso::new(data, &test_vectors::station_keypair());
+        let options = ValidationOptions::default().with_limits(Limits {
+            tag: 8,
+            ..Limits::DEFAULT
+        });
+        tagged
+            .verify_with_options(
+                &test_vectors::station().pub_key,
+                &ValidationOptions::default(),
+            )
+            .unwrap();
+        assert!(tagged
+            .verify_with_options(&test_vectors::station().pub_key, &options)
+            .is_err());
+    }
+
+    #[test]
+    fn test_event_size() {
+        let qso = Event::Qso(test_vectors::qso());
+        let size = limits::check_event_size(&qso).unwrap();
+        assert_eq!(size, serde_json::to_string(&qso).unwrap().len());
+        assert_eq!(limits::max_event_size(Kind::Qso), 16 * 1024);
+
+        let base = test_vectors::qso();
+        let data = QsoData {
+            station_id: base.station_id,
+            callsign: base.callsign,
+            freq: base.freq,
+            datetime: base.datetime,
+            mode: base.mode,
+            rst: base.rst,
+            comments: base.comments,
+            extra: BTreeMap::from([("notes".to_string(), json!("7".repeat(16 * 1024)))]),
+            ..test_vectors::qso_data()
+        };
+        let oversized = Event::Qso(Qso::new(data, &test_vectors::station_keypair()));
+        assert!(limits::check_event_size(&oversized).is_err());
+        assert!(oversized
+            .verify(&test_vectors::station().pub_key)
+            .unwrap_err()
+            .to_string()
+            .starts_with("qso event of"));
+        assert!(MemoryStore::new().insert(oversized.clone()).is_err());
+        assert!(matches!(
+            Policy::new(IngestionPolicy {
+                max_event_size: None,
+                ..IngestionPolicy::default()
+            })
+            .check_event(&oversized, 0),
+            Err(Rejection::TooLarge { max: 16384, .. })
+        ));
+    }
+}
@@ -0,0 +1,765 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::Band;
+use crate::event::{Event, Filter};
+use crate::signable::Validate;
+use crate::stats::{self, ActivityBucket, ActivityQuery, Correspondent, Stats};
+use crate::store::{MemoryStore, Store};
+use crate::template::Template;
+use crate::{Callsign, Checkpoint, Id, Kind, Qso, QsoData, Station, StatsSnapshot, Tag};
+use anyhow::{bail, Result};
+use secp256k1::{Keypair, XOnlyPublicKey};
+use serde_json::Value;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+/// Maximum difference in seconds between the times of a QSO and its
+/// confirmation, the same window used by most QSL services.
+pub const CONFIRMATION_WINDOW: u64 = 30 * 60;
+
+/// Maximum difference in seconds between the times of two QSOs of the same
+/// station recording the same contact, see [`Logbook::merge`].
+pub const DUPLICATE_WINDOW: u64 = 60;
+
+/// Why [`Logbook::merge`] skipped an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The event was already stored.
+    Stored,
+    /// The QSO records the same contact as the stored QSO with the id.
+    Duplicate(Id),
+    /// The station of the event revoked its key before creating it.
+    Revoked,
+    /// The event station is unknown or the signature is invalid.
+    Unverified,
+}
+
+/// Two QSOs of the same contact which disagree on the recorded fields. The
+/// local one is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub local: Id,
+    pub other: Id,
+}
+
+/// Outcome of [`Logbook::merge`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub added: Vec<Id>,
+    pub skipped: Vec<(Id, SkipReason)>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// How [`Logbook::import_with`] handles an imported QSO recording the same
+/// contact as a stored one, but disagreeing on the recorded fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateResolution {
+    /// Skip the imported QSO, keeping the stored one.
+    Skip,
+    /// Store the imported QSO as an amendment of the stored one, referencing
+    /// it with a [`crate::Tag::Ref`].
+    Amend,
+    /// Store the imported QSO as a different contact.
+    Keep,
+}
+
+/// Outcome of [`Logbook::import`]. Records are referenced by their position
+/// in the imported list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// QSOs stored as new contacts.
+    pub added: Vec<Id>,
+    /// Records skipped as duplicates of the stored QSO with the id.
+    pub duplicates: Vec<(usize, Id)>,
+    /// Invalid records, with the reason.
+    pub invalid: Vec<(usize, String)>,
+    /// Amendments stored, with the id of the QSO they amend.
+    pub amended: Vec<(Id, Id)>,
+}
+
+/// The log of a station: its keys, its station object and the store holding
+/// its QSOs and the ones of its counterparties.
+#[derive(Debug)]
+pub struct Logbook<S: Store = MemoryStore> {
+    keys: Keypair,
+    station: Station,
+    store: S,
+}
+
+impl<S: Store> Logbook<S> {
+    /// Opens the logbook of the station, which must be signed with the keys.
+    /// The station is stored if it wasn't already.
+    pub fn new(keys: Keypair, station: Station, mut store: S) -> Result<Self> {
+        if station.pub_key != keys.x_only_public_key().0 {
+            bail!("station public key mismatch");
+        }
+        station.verify()?;
+        store.insert(Event::Station(station.clone()))?;
+
+        Ok(Self {
+            keys,
+            station,
+            store,
+        })
+    }
+
+    /// Returns the station of the logbook.
+    pub fn station(&self) -> &Station {
+        &self.station
+    }
+
+    /// Returns the underlying store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the underlying store, e.g. to add the events received from
+    /// other stations.
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    /// Signs and stores a QSO of the station. The station id of the data is
+    /// replaced by the one of the logbook station.
+    pub fn log_qso(&mut self, qso_data: QsoData) -> Result<Qso> {
+        let qso = Qso::new_with_signer(
+            QsoData {
+                station_id: self.station.id.clone(),
+                ..qso_data
+            },
+            &self.keys,
+        )?;
+        self.store.insert(Event::Qso(qso.clone()))?;
+        Ok(qso)
+    }
+
+    /// Signs and stores a QSO of the station as [`Logbook::log_qso`] does,
+    /// with the comments rendered from the template.
+    pub fn log_qso_with_template(&mut self, qso_data: QsoData, template: &Template) -> Result<Qso> {
+        self.log_qso(template.apply(qso_data)?)
+    }
+
+    /// Returns the QSOs of the station, ordered by creation time.
+    pub fn qsos(&self) -> Result<Vec<Qso>> {
+        let filter = Filter::new()
+            .kinds([Kind::Qso])
+            .station_ids([self.station.id.clone()]);
+        let mut qsos: Vec<Qso> = self
+            .store
+            .query(&filter)?
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Qso(qso) => Some(qso),
+                _ => None,
+            })
+            .collect();
+        qsos.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(qsos)
+    }
+
+    /// Returns the QSOs logged by the counterparty confirming the QSO of the
+    /// station with the given id.
+    ///
+    /// A confirmation is a QSO with the station callsign, on the same band
+    /// and mode, within [`CONFIRMATION_WINDOW`], signed by a stored station
+    /// with the callsign worked.
+    pub fn confirmations_for(&self, id: &Id) -> Result<Vec<Qso>> {
+        let qso = match self.store.get(id)? {
+            Some(Event::Qso(qso)) if qso.station_id == self.station.id => qso,
+            _ => bail!("unknown qso {}", id),
+        };
+        let candidates = self.store.query(&Filter::new().kinds([Kind::Qso]))?;
+        Ok(Confirmations::new(&self.store, &self.station, &candidates)?.for_qso(&qso))
+    }
+
+    /// Signs and stores a checkpoint of the QSOs of the station, in the order
+    /// of [`Logbook::qsos`].
+    pub fn checkpoint(&mut self) -> Result<Checkpoint> {
+        let checkpoint = Checkpoint::new(self.station.id.clone(), &self.keys, &self.qsos()?)?;
+        self.store.insert(Event::Checkpoint(checkpoint.clone()))?;
+        Ok(checkpoint)
+    }
+
+    /// Signs and stores a snapshot of the statistics of the QSOs of the
+    /// station, in the order of [`Logbook::qsos`], to be published for
+    /// leaderboards.
+    pub fn stats_snapshot(&mut self) -> Result<StatsSnapshot> {
+        let snapshot = StatsSnapshot::new(self.station.id.clone(), &self.keys, &self.qsos()?)?;
+        self.store.insert(Event::StatsSnapshot(snapshot.clone()))?;
+        Ok(snapshot)
+    }
+
+    /// Returns the statistics of the QSOs of the station.
+    pub fn stats(&self) -> Result<Stats> {
+        stats::station_stats(&self.store, &self.station)
+    }
+
+    /// Returns, per worked callsign, how many QSOs of the station it
+    /// confirmed, the ones with the most unconfirmed QSOs first.
+    pub fn confirmation_ratios(&self) -> Result<Vec<Correspondent>> {
+        stats::confirmation_ratios(&self.store, &self.station)
+    }
+
+    /// Returns the QSOs of the station per hour or day of contact.
+    pub fn activity(&self, query: &ActivityQuery) -> Result<Vec<ActivityBucket>> {
+        stats::activity(&self.store, &self.station.id, query)
+    }
+
+    /// Signs and stores the QSOs read from another log, e.g. with
+    /// [`crate::adif::read_adi`], skipping the ones already logged.
+    ///
+    /// A record of a contact already logged, the same callsign, band and
+    /// mode within [`DUPLICATE_WINDOW`], is a duplicate. Duplicates which
+    /// disagree on the recorded fields are skipped too, use
+    /// [`Logbook::import_with`] to resolve them.
+    pub fn import(&mut self, records: Vec<QsoData>) -> Result<ImportReport> {
+        self.import_with(records, |_, _| DuplicateResolution::Skip)
+    }
+
+    /// Imports the QSOs as [`Logbook::import`] does, calling `resolve` with
+    /// the stored QSO and the record of every duplicate which disagrees on
+    /// the recorded fields, e.g. to ask the operator.
+    pub fn import_with(
+        &mut self,
+        records: Vec<QsoData>,
+        mut resolve: impl FnMut(&Qso, &QsoData) -> DuplicateResolution,
+    ) -> Result<ImportReport> {
+        let _span = tracing::info_span!("import", records = records.len()).entered();
+        let mut report = ImportReport::default();
+        let mut qsos = self.qsos()?;
+
+        for (index, record) in records.into_iter().enumerate() {
+            let record = QsoData {
+                station_id: self.station.id.clone(),
+                ..record
+            };
+
+            // An exact duplicate wins over the QSOs disagreeing on the fields,
+            // e.g. over the original of an already imported amendment.
+            let contacts: Vec<&Qso> = qsos
+                .iter()
+                .filter(|stored| same_contact(stored, (&record).into()))
+                .collect();
+            let exact = contacts
+                .iter()
+                .find(|stored| same_fields(stored, (&record).into()));
+
+            let mut amends = None;
+            if let Some(stored) = exact.or(contacts.first()) {
+                let resolution = match exact {
+                    Some(_) => DuplicateResolution::Skip,
+                    None => resolve(stored, &record),
+                };
+                match resolution {
+                    DuplicateResolution::Skip => {
+                        report.duplicates.push((index, stored.id.clone()));
+                        continue;
+                    }
+                    DuplicateResolution::Amend => amends = Some(stored.id.clone()),
+                    DuplicateResolution::Keep => {}
+                }
+            }
+
+            let mut record = record;
+            record.tags.extend(amends.clone().map(Tag::Ref));
+            let qso = Qso::new_with_signer(record, &self.keys).and_then(|qso| {
+                qso.validate()?;
+                Ok(qso)
+            });
+            let qso = match qso {
+                Ok(qso) => qso,
+                Err(err) => {
+                    report.invalid.push((index, format!("{:#}", err)));
+                    continue;
+                }
+            };
+            self.store.insert(Event::Qso(qso.clone()))?;
+
+            match amends {
+                Some(amended) => report.amended.push((qso.id.clone(), amended)),
+                None => report.added.push(qso.id.clone()),
+            }
+            qsos.push(qso);
+        }
+
+        Ok(report)
+    }
+
+    /// Adds the events of the other logbook, e.g. the one of a portable
+    /// laptop, to this one.
+    ///
+    /// Events are verified against the key of their station and skipped if a
+    /// revocation stored in either logbook invalidates them. A QSO of a
+    /// station recording a contact this logbook already has, the same
+    /// callsign, band and mode within [`DUPLICATE_WINDOW`], is never added:
+    /// it's a duplicate if the QSO fields match and a conflict otherwise.
+    pub fn merge<T: Store>(&mut self, other: &Logbook<T>) -> Result<MergeReport> {
+        let _span = tracing::info_span!("merge").entered();
+        let mut report = MergeReport::default();
+
+        let mut events = other.store.query(&Filter::new())?;
+        // Stations go first so the events signed by them can be verified.
+        events.sort_by(|a, b| {
+            (!matches!(a, Event::Station(_)), a.created_at(), a.id()).cmp(&(
+                !matches!(b, Event::Station(_)),
+                b.created_at(),
+                b.id(),
+            ))
+        });
+
+        let mut revocations = vec![];
+        for event in self
+            .store
+            .query(&Filter::new().kinds([Kind::Revocation]))?
+            .iter()
+            .chain(&events)
+        {
+            if let Event::Revocation(revocation) = event {
+                if let Some(pub_key) = self.verified_key(event, &other.store)? {
+                    revocations.push((revocation.clone(), pub_key));
+                }
+            }
+        }
+
+        let mut qsos: Vec<Qso> = self
+            .store
+            .query(&Filter::new().kinds([Kind::Qso]))?
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Qso(qso) => Some(qso),
+                _ => None,
+            })
+            .collect();
+
+        for event in events {
+            let id = event.id().clone();
+
+            let stored = self.store.get(&id)?.is_some();
+            let pub_key = if stored {
+                None
+            } else {
+                self.verified_key(&event, &other.store)?
+            };
+
+            let skip = if stored {
+                Some(SkipReason::Stored)
+            } else if pub_key.is_none() {
+                Some(SkipReason::Unverified)
+            } else if pub_key.is_some_and(|pub_key| {
+                revocations.iter().any(|(revocation, revoked_key)| {
+                    revocation.check(revoked_key, &event, &pub_key).is_err()
+                })
+            }) {
+                Some(SkipReason::Revoked)
+            } else if let Event::Qso(qso) = &event {
+                match qsos.iter().find(|stored| same_contact(stored, qso.into())) {
+                    Some(stored) if same_fields(stored, qso.into()) => {
+                        Some(SkipReason::Duplicate(stored.id.clone()))
+                    }
+                    Some(stored) => {
+                        report.conflicts.push(MergeConflict {
+                            local: stored.id.clone(),
+                            other: id,
+                        });
+                        continue;
+                    }
+                    None => None,
+                }
+            } else {
+                None
+            };
+
+            match skip {
+                Some(reason) => report.skipped.push((id, reason)),
+                None => {
+                    if let Event::Qso(qso) = &event {
+                        qsos.push(qso.clone());
+                    }
+                    self.store.insert(event)?;
+                    report.added.push(id);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Verifies the event against the key of its station, stored in this
+    /// logbook or in the other store, and returns the key.
+    fn verified_key(&self, event: &Event, other: &dyn Store) -> Result<Option<XOnlyPublicKey>> {
+        let pub_key = match event {
+            Event::Station(station) => Some(station.pub_key),
+            _ => match station_key(event.station_id(), &self.store)? {
+                Some(pub_key) => Some(pub_key),
+                None => station_key(event.station_id(), other)?,
+            },
+        };
+        Ok(pub_key.filter(|pub_key| event.verify(pub_key).is_ok()))
+    }
+}
+
+/// The QSOs among the candidates confirming QSOs of the station, see
+/// [`Logbook::confirmations_for`].
+///
+/// The candidates sent to the station callsign are verified once against
+/// their station and indexed by band and mode, sorted by time, so looking up
+/// the confirmations of a QSO doesn't scan every stored QSO.
+pub(crate) struct Confirmations {
+    /// The worked station callsign and the QSO, by band and uppercase mode.
+    candidates: BTreeMap<(Band, String), Vec<(String, Qso)>>,
+}
+
+impl Confirmations {
+    pub(crate) fn new(store: &dyn Store, station: &Station, candidates: &[Event]) -> Result<Self> {
+        let mut stations: BTreeMap<&Id, Option<Station>> = BTreeMap::new();
+        let mut indexed: BTreeMap<(Band, String), Vec<(String, Qso)>> = BTreeMap::new();
+
+        for event in candidates {
+            let Event::Qso(candidate) = event else {
+                continue;
+            };
+            if candidate.station_id == station.id
+                || !Callsign::matches(&candidate.callsign, &station.callsign)
+            {
+                continue;
+            }
+            let Some(band) = Band::from_freq(candidate.freq) else {
+                continue;
+            };
+            let worked = match stations.entry(&candidate.station_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(match store.get(&candidate.station_id)? {
+                    Some(Event::Station(worked)) => Some(worked),
+                    _ => None,
+                }),
+            };
+            let Some(worked) = worked else {
+                continue;
+            };
+            if candidate.verify(&worked.pub_key).is_err() {
+                continue;
+            }
+            indexed
+                .entry((band, candidate.mode.to_ascii_uppercase()))
+                .or_default()
+                .push((worked.callsign.clone(), candidate.clone()));
+        }
+
+        for qsos in indexed.values_mut() {
+            qsos.sort_by(|(_, a), (_, b)| (a.datetime, &a.id).cmp(&(b.datetime, &b.id)));
+        }
+        Ok(Self {
+            candidates: indexed,
+        })
+    }
+
+    /// Returns the confirmations of the QSO of the station: the candidates
+    /// on the same band and mode, within [`CONFIRMATION_WINDOW`], signed by
+    /// a station with the callsign worked.
+    pub(crate) fn for_qso(&self, qso: &Qso) -> Vec<Qso> {
+        let Some(band) = Band::from_freq(qso.freq) else {
+            return vec![];
+        };
+        let Some(candidates) = self.candidates.get(&(band, qso.mode.to_ascii_uppercase())) else {
+            return vec![];
+        };
+
+        let from = qso.datetime.saturating_sub(CONFIRMATION_WINDOW);
+        let until = qso.datetime.saturating_add(CONFIRMATION_WINDOW);
+        let start = candidates.partition_point(|(_, candidate)| candidate.datetime < from);
+        candidates[start..]
+            .iter()
+            .take_while(|(_, candidate)| candidate.datetime <= until)
+            .filter(|(worked, _)| Callsign::matches(worked, &qso.callsign))
+            .map(|(_, candidate)| candidate.clone())
+            .collect()
+    }
+}
+
+pub(crate) fn station_key(id: &Id, store: &dyn Store) -> Result<Option<XOnlyPublicKey>> {
+    Ok(match store.get(id)? {
+        Some(Event::Station(station)) => Some(station.pub_key),
+        _ => None,
+    })
+}
+
+/// The recorded fields of a signed or imported QSO.
+struct Contact<'a> {
+    station_id: &'a Id,
+    callsign: &'a str,
+    freq: u64,
+    datetime: u64,
+    mode: &'a str,
+    rst: &'a str,
+    comments: &'a str,
+    extra: &'a BTreeMap<String, Value>,
+}
+
+impl<'a> From<&'a Qso> for Contact<'a> {
+    fn from(qso: &'a Qso) -> Self {
+        Self {
+            station_id: &qso.station_id,
+            callsign: &qso.callsign,
+            freq: qso.freq,
+            datetime: qso.datetime,
+            mode: &qso.mode,
+            rst: &qso.rst,
+            comments: &qso.comments,
+            extra: &qso.extra,
+        }
+    }
+}
+
+impl<'a> From<&'a QsoData> for Contact<'a> {
+    fn from(qso_data: &'a QsoData) -> Self {
+        Self {
+            station_id: &qso_data.station_id,
+            callsign: &qso_data.callsign,
+            freq: qso_data.freq,
+            datetime: qso_data.datetime,
+            mode: &qso_data.mode,
+            rst: &qso_data.rst,
+            comments: &qso_data.comments,
+            extra: &qso_data.extra,
+        }
+    }
+}
+
+/// Returns true if the QSOs of the same station record the same contact.
+fn same_contact(a: &Qso, b: Contact) -> bool {
+    a.station_id == *b.station_id
+        && Callsign::matches(&a.callsign, b.callsign)
+        && a.mode.eq_ignore_ascii_case(b.mode)
+        && a.datetime.abs_diff(b.datetime) <= DUPLICATE_WINDOW
+        && Band::from_freq(a.freq) == Band::from_freq(b.freq)
+}
+
+/// Returns true if the QSOs of the same contact agree on the recorded
+/// fields. The times may differ, e.g. if logged by hand on each computer.
+fn same_fields(a: &Qso, b: Contact) -> bool {
+    a.freq == b.freq && a.rst == b.rst && a.comments == b.comments && a.extra == *b.extra
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::keys::generate_keypair;
+    use crate::logbook::{DuplicateResolution, Logbook, MergeConflict, SkipReason};
+    use crate::store::{MemoryStore, Store};
+    use crate::{test_vectors, Id, Qso, QsoData, Revocation, Tag};
+
+    fn data(callsign: &str, datetime: u64) -> QsoData {
+        QsoData {
+            station_id: Id::new("ignored"),
+            callsign: callsign.to_string(),
+            datetime,
+            freq: 14_200_000,
+            comments: String::new(),
+            ..test_vectors::qso_data()
+        }
+    }
+
+    fn logbook() -> Logbook {
+        Logbook::new(
+            test_vectors::counterparty_keypair(),
+            test_vectors::counterparty(),
+            MemoryStore::new(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_logbook() {
+        let mut logbook = logbook();
+
+        let worked = test_vectors::station().callsign;
+        let confirmed = logbook
+            .log_qso(data(&worked, test_vectors::CREATED_AT + 60))
+            .unwrap();
+        let unconfirmed = logbook
+            .log_qso(data(&worked, test_vectors::CREATED_AT + 7200))
+            .unwrap();
+        logbook.log_qso(data("LU1AA", 0)).unwrap();
+        assert_eq!(confirmed.station_id, logbook.station().id);
+        assert_eq!(logbook.qsos().unwrap().len(), 3);
+
+        let store = logbook.store_mut();
+        store
+            .insert(Event::Station(test_vectors::station()))
+            .unwrap();
+        store.insert(Event::Qso(test_vectors::qso())).unwrap();
+
+        assert_eq!(
+            logbook.confirmations_for(&confirmed.id).unwrap(),
+            vec![test_vectors::qso()]
+        );
+        assert!(logbook
+            .confirmations_for(&unconfirmed.id)
+            .unwrap()
+            .is_empty());
+        assert!(logbook.confirmations_for(&test_vectors::qso().id).is_err());
+
+        let stats = logbook.stats().unwrap();
+        assert_eq!(stats.qsos, 3);
+        assert_eq!(stats.unique_callsigns, 2);
+        assert_eq!(stats.confirmed, 1);
+
+        let checkpoint = logbook.checkpoint().unwrap();
+        assert_eq!(checkpoint.qso_count, 3);
+        checkpoint.verify_log(&logbook.qsos().unwrap()).unwrap();
+
+        assert!(Logbook::new(
+            test_vectors::station_keypair(),
+            test_vectors::counterparty(),
+            MemoryStore::new(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut home = logbook();
+        let mut laptop = logbook();
+
+        let duplicated = home.log_qso(data("LU1AA", 1000)).unwrap();
+        let conflicting = home.log_qso(data("LU2BB", 2000)).unwrap();
+
+        let duplicate = laptop.log_qso(data("lu1aa", 1010)).unwrap();
+        let conflict = laptop
+            .log_qso(QsoData {
+                rst: "57".to_string(),
+                ..data("LU2BB", 2030)
+            })
+            .unwrap();
+        let added = laptop.log_qso(data("LU3CC", 3000)).unwrap();
+
+        let revocation = Revocation::new(
+            test_vectors::station().id,
+            &test_vectors::station_keypair(),
+            test_vectors::CREATED_AT - 1,
+        )
+        .unwrap();
+        let unverified = Qso::new(data("LU4DD", 4000), &generate_keypair());
+        let store = laptop.store_mut();
+        store
+            .insert(Event::Station(test_vectors::station()))
+            .unwrap();
+        store.insert(Event::Qso(test_vectors::qso())).unwrap();
+        store.insert(Event::Revocation(revocation.clone())).unwrap();
+        store.insert(Event::Qso(unverified.clone())).unwrap();
+
+        let report = home.merge(&laptop).unwrap();
+
+        let mut added_ids = vec![added.id.clone(), revocation.id.clone()];
+        added_ids.sort();
+        let mut report_added = report.added.clone();
+        report_added.sort();
+        assert_eq!(report_added, added_ids);
+
+        let reason = |id: &Id| {
+            report
+                .skipped
+                .iter()
+                .find(|(skipped, _)| skipped == id)
+                .map(|(_, reason)| reason.clone())
+        };
+        assert_eq!(reason(&home.station().id), Some(SkipReason::Stored));
+        assert_eq!(
+            reason(&duplicate.id),
+            Some(SkipReason::Duplicate(duplicated.id))
+        );
+        assert_eq!(
+            reason(&test_vectors::station().id),
+            Some(SkipReason::Revoked)
+        );
+        assert_eq!(reason(&test_vectors::qso().id), Some(SkipReason::Revoked));
+        assert_eq!(reason(&unverified.id), Some(SkipReason::Unverified));
+        assert_eq!(report.skipped.len(), 5);
+
+        assert_eq!(
+            report.conflicts,
+            vec![MergeConflict {
+                local: conflicting.id,
+                other: conflict.id,
+            }]
+        );
+        assert_eq!(home.qsos().unwrap().len(), 3);
+
+        let report = home.merge(&laptop).unwrap();
+        assert!(report.added.is_empty());
+        assert_eq!(report.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn test_import() {
+        let mut logbook = logbook();
+        let stored = logbook.log_qso(data("LU1AA", 1000)).unwrap();
+        let other = logbook.log_qso(data("LU2BB", 2000)).unwrap();
+
+        let amendment = QsoData {
+            rst: "57".to_string(),
+            ..data("LU1AA", 1030)
+        };
+        let records = vec![
+            data("lu1aa", 1010),
+            amendment.clone(),
+            QsoData {
+                comments: "QSB".to_string(),
+                ..data("LU2BB", 2000)
+            },
+            data("LU3CC", 3000),
+            data("", 4000),
+            data("LU3CC", 3020),
+        ];
+
+        let mut resolved = vec![];
+        let report = logbook
+            .import_with(records.clone(), |stored, record| {
+                resolved.push(stored.id.clone());
+                if record.rst == amendment.rst {
+                    DuplicateResolution::Amend
+                } else {
+                    DuplicateResolution::Skip
+                }
+            })
+            .unwrap();
+        assert_eq!(resolved, vec![stored.id.clone(), other.id.clone()]);
+
+        assert_eq!(report.added.len(), 1);
+        assert_eq!(
+            report.duplicates,
+            vec![
+                (0, stored.id.clone()),
+                (2, other.id.clone()),
+                (5, report.added[0].clone())
+            ]
+        );
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].0, 4);
+        assert_eq!(report.amended.len(), 1);
+        let (amended, original) = &report.amended[0];
+        assert_eq!(original, &stored.id);
+        match logbook.store().get(amended).unwrap() {
+            Some(Event::Qso(qso)) => assert!(qso.tags.contains(&Tag::Ref(stored.id.clone()))),
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert_eq!(logbook.qsos().unwrap().len(), 4);
+
+        let report = logbook.import(records).unwrap();
+        assert!(report.added.is_empty());
+        assert!(report.amended.is_empty());
+        assert_eq!(report.duplicates.len(), 5);
+        assert_eq!(report.duplicates[1], (1, amended.clone()));
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Map exports of a logbook for standard mapping tools: the station, the
+//! contacted stations and the great-circle paths between them, as GeoJSON or
+//! KML.
+//!
+//! Locations are the centers of the grid squares, the one of the station from
+//! [`Station::grid`] and the contacted ones from [`Qso::gridsquare`]. QSOs
+//! without a valid grid square are skipped, and paths are only drawn when the
+//! grid square of the station is known. Paths crossing the antimeridian are
+//! split in two lines.
+
+use crate::adif::escape;
+use crate::bandplan::Band;
+use crate::geo::LatLon;
+use crate::{Qso, Station};
+use anyhow::Result;
+use chrono::DateTime;
+use serde_json::{json, Map, Value};
+use std::io::Write;
+
+/// Maximum length in km of the straight segments drawing a path.
+const PATH_SEGMENT_KM: f64 = 250.0;
+
+struct Feature {
+    name: String,
+    properties: Vec<(&'static str, Value)>,
+    geometry: Geometry,
+}
+
+enum Geometry {
+    Point(LatLon),
+    Path(Vec<Vec<LatLon>>),
+}
+
+/// Writes the station, the contacted stations and the paths as a GeoJSON
+/// feature collection. Features have a `kind` property, either `station`,
+/// `contact` or `path`.
+pub fn write_geojson<W: Write>(mut writer: W, station: &Station, qsos: &[Qso]) -> Result<()> {
+    let features: Vec<Value> = features(station, qsos)
+        .into_iter()
+        .map(|feature| {
+            let geometry = match &feature.geometry {
+                Geometry::Point(point) => {
+                    json!({ "type": "Point", "coordinates": position(point) })
+                }
+                Geometry::Path(lines) if lines.len() == 1 => json!({
+                    "type": "LineString",
+                    "coordinates": positions(&lines[0]),
+                }),
+                Geometry::Path(lines) => json!({
+                    "type": "MultiLineString",
+                    "coordinates": lines.iter().map(|line| positions(line)).collect::<Vec<_>>(),
+                }),
+            };
+            let mut properties = Map::new();
+            properties.insert("name".to_string(), Value::String(feature.name));
+            for (name, value) in feature.properties {
+                properties.insert(name.to_string(), value);
+            }
+            json!({ "type": "Feature", "geometry": geometry, "properties": properties })
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(
+        &mut writer,
+        &json!({ "type": "FeatureCollection", "features": features }),
+    )?;
+    writeln!(writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes the station, the contacted stations and the paths as a KML
+/// document of placemarks, with the same properties as the GeoJSON export in
+/// their extended data.
+pub fn write_kml<W: Write>(mut writer: W, station: &Station, qsos: &[Qso]) -> Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(writer, "  <Document>")?;
+    writeln!(writer, "    <name>{}</name>", escape(&station.callsign))?;
+
+    for feature in features(station, qsos) {
+        writeln!(writer, "    <Placemark>")?;
+        writeln!(writer, "      <name>{}</name>", escape(&feature.name))?;
+        writeln!(writer, "      <ExtendedData>")?;
+        for (name, value) in &feature.properties {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            writeln!(
+                writer,
+                r#"        <Data name="{}"><value>{}</value></Data>"#,
+                name,
+                escape(&value)
+            )?;
+        }
+        writeln!(writer, "      </ExtendedData>")?;
+
+        match &feature.geometry {
+            Geometry::Point(point) => {
+                writeln!(
+                    writer,
+                    "      <Point><coordinates>{}</coordinates></Point>",
+                    coordinates(std::slice::from_ref(point))
+                )?;
+            }
+            Geometry::Path(lines) => {
+                writeln!(writer, "      <MultiGeometry>")?;
+                for line in lines {
+                    writeln!(
+                        writer,
+                        "        <LineString><tessellate>1</tessellate><coordinates>{}</coordinates></LineString>",
+                        coordinates(line)
+                    )?;
+                }
+                writeln!(writer, "      </MultiGeometry>")?;
+            }
+        }
+        writeln!(writer, "    </Placemark>")?;
+    }
+
+    writeln!(writer, "  </Document>")?;
+    writeln!(writer, "</kml>")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn features(station: &Station, qsos: &[Qso]) -> Vec<Feature> {
+    let mut features = vec![];
+
+    let home = station
+        .grid()
+        .and_then(|grid| Some((grid, LatLon::from_grid(grid).ok()?)));
+    if let Some((grid, point)) = home {
+        features.push(Feature {
+            name: station.callsign.clone(),
+            properties: vec![("kind", json!("station")), ("grid", json!(grid))],
+            geometry: Geometry::Point(point),
+        });
+    }
+
+    for qso in qsos {
+        let Some((grid, point)) = qso
+            .gridsquare()
+            .and_then(|grid| Some((grid, LatLon::from_grid(grid).ok()?)))
+        else {
+            continue;
+        };
+
+        let mut properties = vec![
+            ("id", json!(qso.id.to_string())),
+            ("grid", json!(grid)),
+            ("mode", json!(qso.mode)),
+        ];
+        if let Some(datetime) = i64::try_from(qso.datetime)
+            .ok()
+            .and_then(|datetime| DateTime::from_timestamp(datetime, 0))
+        {
+            let datetime = datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+            properties.push(("datetime", json!(datetime)));
+        }
+        if let Some(band) = Band::from_freq(qso.freq) {
+            properties.push(("band", json!(band.name())));
+        }
+
+        let mut contact = vec![("kind", json!("contact"))];
+        contact.extend(properties.iter().cloned());
+        features.push(Feature {
+            name: qso.callsign.clone(),
+            properties: contact,
+            geometry: Geometry::Point(point),
+        });
+
+        if let Some((_, home)) = home {
+            let distance = home.distance_km(&point);
+            let segments = (distance / PATH_SEGMENT_KM).ceil() as usize;
+            let mut path = vec![("kind", json!("path"))];
+            path.extend(properties);
+            path.push(("distance_km", json!(distance.round() as u64)));
+            features.push(Feature {
+                name: format!("{} - {}", station.callsign, qso.callsign),
+                properties: path,
+                geometry: Geometry::Path(split_antimeridian(home.great_circle(&point, segments))),
+            });
+        }
+    }
+
+    features
+}
+
+/// Splits the line where it crosses the antimeridian, ending and starting
+/// the lines on it.
+fn split_antimeridian(points: Vec<LatLon>) -> Vec<Vec<LatLon>> {
+    let mut lines = vec![];
+    let mut line: Vec<LatLon> = vec![];
+
+    for point in points {
+        if let Some(last) = line.last().copied() {
+            if (point.lon - last.lon).abs() > 180.0 {
+                let edge = 180f64.copysign(last.lon);
+                let lon = point.lon + 2.0 * edge;
+                let lat = last.lat + (point.lat - last.lat) * (edge - last.lon) / (lon - last.lon);
+                line.push(LatLon { lat, lon: edge });
+                lines.push(std::mem::replace(
+                    &mut line,
+                    vec![LatLon { lat, lon: -edge }],
+                ));
+            }
+        }
+        line.push(point);
+    }
+    lines.push(line);
+
+    lines
+}
+
+/// Rounds to 6 decimals, about 10 cm.
+fn round(degrees: f64) -> f64 {
+    (degrees * 1e6).round() / 1e6
+}
+
+/// Returns the GeoJSON position, longitude first.
+fn position(point: &LatLon) -> [f64; 2] {
+    [round(point.lon), round(point.lat)]
+}
+
+fn positions(line: &[LatLon]) -> Vec<[f64; 2]> {
+    line.iter().map(position).collect()
+}
+
+/// Returns the KML coordinates, longitude first.
+fn coordinates(line: &[LatLon]) -> String {
+    line.iter()
+        .map(|point| format!("{},{}", round(point.lon), round(point.lat)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::map::{write_geojson, write_kml};
+    use crate::station::GRID;
+    use crate::{test_vectors, Id, Qso, QsoData, Station, StationData};
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_map() {
+        let keys = generate_keypair();
+        let station = Station::from_data(
+            StationData {
+                callsign: "LU4EV".to_string(),
+                operator: "Radio Club Caseros".to_string(),
+                country: test_vectors::station().country,
+                extra: BTreeMap::from([(GRID.to_string(), json!("GF05tk"))]),
+                profile: None,
+                qsl_route: None,
+            },
+            &keys,
+        )
+        .unwrap();
+        let qso = |callsign: &str, grid: Option<&str>| {
+            Qso::new(
+                QsoData {
+                    station_id: Id::new("ignored"),
+                    callsign: callsign.to_string(),
+                    comments: String::new(),
+                    extra: grid
+                        .map(|grid| BTreeMap::from([("gridsquare".to_string(), json!(grid))]))
+                        .unwrap_or_default(),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            )
+        };
+        let qsos = [
+            qso("W2AAA", Some("FN20xr")),
+            qso("ZL1AAA", Some("RF72")),
+            qso("LW3DZR", None),
+        ];
+
+        let mut geojson = vec![];
+        write_geojson(&mut geojson, &station, &qsos).unwrap();
+        let geojson: Value = serde_json::from_slice(&geojson).unwrap();
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 5);
+        assert_eq!(
+            features[0]["geometry"],
+            json!({ "type": "Point", "coordinates": [-58.375, -34.5625] })
+        );
+        assert_eq!(features[1]["properties"]["name"], "W2AAA");
+        assert_eq!(features[1]["properties"]["band"], "20m");
+        assert_eq!(
+            features[1]["properties"]["datetime"],
+            "2024-01-01T20:37:06Z"
+        );
+        assert_eq!(features[2]["geometry"]["type"], "LineString");
+        assert_eq!(features[2]["properties"]["kind"], "path");
+        let distance = features[2]["properties"]["distance_km"].as_u64().unwrap();
+        assert!((8480..8580).contains(&distance));
+
+        // Buenos Aires to Auckland crosses the antimeridian.
+        let path = &features[4]["geometry"];
+        assert_eq!(path["type"], "MultiLineString");
+        let lines = path["coordinates"].as_array().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].as_array().unwrap().last().unwrap()[0], -180.0);
+        assert_eq!(lines[1][0][0], 180.0);
+        assert_eq!(
+            lines[0].as_array().unwrap().last().unwrap()[1],
+            lines[1][0][1]
+        );
+
+        let mut kml = vec![];
+        write_kml(&mut kml, &station, &qsos).unwrap();
+        let kml = String::from_utf8(kml).unwrap();
+        assert!(kml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml"));
+        assert_eq!(kml.matches("<Placemark>").count(), 5);
+        assert!(kml.contains("<name>LU4EV - W2AAA</name>"));
+        assert!(kml.contains("<Point><coordinates>-58.375,-34.5625</coordinates></Point>"));
+        assert!(kml.contains(r#"<Data name="band"><value>20m</value></Data>"#));
+        assert_eq!(kml.matches("<LineString>").count(), 3);
+        assert!(kml.ends_with("  </Document>\n</kml>\n"));
+    }
+}
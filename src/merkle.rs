@@ -0,0 +1,169 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merkle trees over object ids.
+//!
+//! Leaves are hashed as `sha256(0x00 || id)` and inner nodes as
+//! `sha256(0x01 || left || right)`, an odd node at the end of a level is
+//! promoted to the next level unchanged. The root of an empty list is the
+//! sha256 of the empty string.
+
+use crate::Id;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Sibling hashes needed to recompute the root from a leaf.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub leaf_count: usize,
+    pub siblings: Vec<Id>,
+}
+
+/// Returns the merkle root of the ids.
+pub fn root(ids: &[Id]) -> Id {
+    if ids.is_empty() {
+        return Id::from_bytes(Sha256::digest([]).into());
+    }
+
+    let mut level: Vec<Id> = ids.iter().map(leaf).collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node(left, right),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    level.remove(0)
+}
+
+/// Builds the inclusion proof of the id at `index`.
+pub fn prove(ids: &[Id], index: usize) -> Result<MerkleProof> {
+    if index >= ids.len() {
+        bail!("index out of bounds");
+    }
+
+    let mut siblings = vec![];
+    let mut level: Vec<Id> = ids.iter().map(leaf).collect();
+    let mut pos = index;
+
+    while level.len() > 1 {
+        let sibling = pos ^ 1;
+        if sibling < level.len() {
+            siblings.push(level[sibling].clone());
+        }
+
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node(left, right),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        pos /= 2;
+    }
+
+    Ok(MerkleProof {
+        index,
+        leaf_count: ids.len(),
+        siblings,
+    })
+}
+
+/// Verify that the id is included in the tree with the given root.
+pub fn verify_proof(root: &Id, id: &Id, proof: &MerkleProof) -> Result<()> {
+    if proof.index >= proof.leaf_count {
+        bail!("invalid merkle proof");
+    }
+
+    let mut hash = leaf(id);
+    let mut pos = proof.index;
+    let mut len = proof.leaf_count;
+    let mut siblings = proof.siblings.iter();
+
+    while len > 1 {
+        let sibling = pos ^ 1;
+        if sibling < len {
+            let Some(sibling_hash) = siblings.next() else {
+                bail!("invalid merkle proof");
+            };
+            hash = if pos & 1 == 0 {
+                node(&hash, sibling_hash)
+            } else {
+                node(sibling_hash, &hash)
+            };
+        }
+        pos /= 2;
+        len = len.div_ceil(2);
+    }
+
+    if siblings.next().is_some() || hash != *root {
+        bail!("invalid merkle proof");
+    }
+
+    Ok(())
+}
+
+fn leaf(id: &Id) -> Id {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(id.as_bytes());
+    Id::from_bytes(hasher.finalize().into())
+}
+
+fn node(left: &Id, right: &Id) -> Id {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    Id::from_bytes(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merkle::{prove, root, verify_proof};
+    use crate::Id;
+
+    #[test]
+    fn test_proofs() {
+        for len in 1..12 {
+            let ids: Vec<Id> = (0..len).map(|i| Id::new(&i.to_string())).collect();
+            let root = root(&ids);
+
+            for (index, id) in ids.iter().enumerate() {
+                let proof = prove(&ids, index).unwrap();
+                verify_proof(&root, id, &proof).unwrap();
+                assert!(verify_proof(&root, &Id::new("other"), &proof).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_root_changes() {
+        let ids: Vec<Id> = (0..4).map(|i| Id::new(&i.to_string())).collect();
+        let mut reordered = ids.clone();
+        reordered.swap(0, 1);
+
+        assert_ne!(root(&ids), root(&reordered));
+        assert_ne!(root(&ids), root(&ids[..3]));
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! N1MM Logger+ UDP integration, enabled by the `n1mm` feature.
+//!
+//! N1MM Logger+ broadcasts every logged contact as a `contactinfo` XML
+//! document. [`N1mmListener`] receives them and converts them into
+//! [`QsoData`] ready for signing. Edits and deletions of already logged
+//! contacts ( `contactreplace` and `contactdelete` ) are ignored, signed QSOs
+//! can't be changed.
+
+use crate::adif::unescape;
+use crate::qso::{CONTEST_ID, GRIDSQUARE, SRX_STRING};
+use crate::{Id, QsoData};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::NaiveDateTime;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+/// Default UDP port N1MM Logger+ sends its contact broadcasts to.
+pub const DEFAULT_PORT: u16 = 12060;
+
+const MAX_DATAGRAM_LEN: usize = 64 * 1024;
+
+thread_local! {
+    static ROOT: Regex = Regex::new(r"<(\w+)[\s>]").unwrap();
+    static ELEMENT: Regex = Regex::new(r"<(\w+)>([^<]*)</(\w+)>|<(\w+)\s*/>").unwrap();
+}
+
+/// Receives the contacts logged by N1MM Logger+.
+#[derive(Debug)]
+pub struct N1mmListener {
+    socket: UdpSocket,
+    station_id: Id,
+}
+
+impl N1mmListener {
+    /// Listens on the address for the contacts logged by the station.
+    pub fn bind(addr: impl ToSocketAddrs, station_id: Id) -> Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+            station_id,
+        })
+    }
+
+    /// Returns the underlying socket, e.g. to set a read timeout.
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Blocks until N1MM Logger+ logs a contact. Other broadcasts, like the
+    /// radio info ones, are skipped.
+    pub fn recv(&self) -> Result<QsoData> {
+        let mut buf = vec![0; MAX_DATAGRAM_LEN];
+        loop {
+            let (len, _) = self.socket.recv_from(&mut buf)?;
+            let xml =
+                std::str::from_utf8(&buf[..len]).context("invalid utf-8 in n1mm broadcast")?;
+            if let Some(qso) = parse_contact(xml, &self.station_id)? {
+                return Ok(qso);
+            }
+        }
+    }
+}
+
+/// Parses an N1MM Logger+ broadcast. Returns None if it isn't a
+/// `contactinfo` one.
+pub fn parse_contact(xml: &str, station_id: &Id) -> Result<Option<QsoData>> {
+    let root = ROOT
+        .with(|root| root.captures(xml).map(|captures| captures[1].to_string()))
+        .ok_or_else(|| anyhow!("not an n1mm broadcast"))?;
+    if root != "contactinfo" {
+        return Ok(None);
+    }
+
+    let elements = elements(xml)?;
+    let field = |name: &str| elements.get(name).map(String::as_str).unwrap_or_default();
+    let required = |name: &str| match field(name) {
+        "" => Err(anyhow!("missing n1mm field {}", name)),
+        value => Ok(value),
+    };
+
+    let timestamp = required("timestamp")?;
+    let datetime = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")
+        .with_context(|| format!("invalid n1mm timestamp {}", timestamp))?;
+    let datetime =
+        u64::try_from(datetime.and_utc().timestamp()).context("n1mm timestamp before 1970")?;
+
+    // Frequencies are sent in tens of Hz.
+    let txfreq = required("txfreq")?;
+    let freq = txfreq
+        .parse::<u64>()
+        .ok()
+        .and_then(|freq| freq.checked_mul(10))
+        .ok_or_else(|| anyhow!("invalid n1mm frequency {}", txfreq))?;
+
+    let mut extra = BTreeMap::new();
+    for (name, key) in [
+        ("gridsquare", GRIDSQUARE),
+        ("contestname", CONTEST_ID),
+        ("exchange1", SRX_STRING),
+    ] {
+        let value = field(name);
+        // N1MM sends DXLOG as the contest name of general logging.
+        let general_logging = name == "contestname" && value == "DXLOG";
+        if !value.is_empty() && !general_logging {
+            extra.insert(key.to_string(), Value::from(value));
+        }
+    }
+
+    Ok(Some(QsoData {
+        station_id: station_id.clone(),
+        callsign: required("call")?.to_string(),
+        datetime,
+        freq,
+        mode: required("mode")?.to_string(),
+        rst: required("snt")?.to_string(),
+        comments: field("comment").to_string(),
+        extra,
+        tags: vec![],
+        prev_id: None,
+    }))
+}
+
+/// Returns the text of the elements of the flat document by name.
+fn elements(xml: &str) -> Result<BTreeMap<String, String>> {
+    ELEMENT.with(|element| {
+        element
+            .captures_iter(xml)
+            .map(|captures| match (captures.get(1), captures.get(4)) {
+                (Some(open), _) => {
+                    if open.as_str() != &captures[3] {
+                        bail!("mismatched n1mm element {}", open.as_str());
+                    }
+                    Ok((open.as_str().to_string(), unescape(captures[2].trim())))
+                }
+                (None, Some(empty)) => Ok((empty.as_str().to_string(), String::new())),
+                (None, None) => unreachable!("one of the alternatives matched"),
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::n1mm::{parse_contact, N1mmListener};
+    use crate::qso::{CONTEST_ID, GRIDSQUARE, SRX_STRING};
+    use crate::Id;
+    use std::net::UdpSocket;
+
+    const CONTACT: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<contactinfo>
+    <app>N1MM</app>
+    <contestname>CQ-WW-CW</contestname>
+    <contestnr>73</contestnr>
+    <timestamp>2024-01-01 20:37:00</timestamp>
+    <mycall>LU4EV</mycall>
+    <band>14</band>
+    <rxfreq>1402530</rxfreq>
+    <txfreq>1402530</txfreq>
+    <operator></operator>
+    <mode>CW</mode>
+    <call>LW3DZR</call>
+    <countryprefix>LU</countryprefix>
+    <snt>599</snt>
+    <rcv>599</rcv>
+    <gridsquare />
+    <exchange1>13</exchange1>
+    <comment>tnx &amp; 73</comment>
+    <ID>0cc2a4f1bd4d4a5f9bd5e4e1c3e6d8a1</ID>
+</contactinfo>"#;
+
+    #[test]
+    fn test_parse_contact() {
+        let station_id = Id::new("station");
+
+        let qso = parse_contact(CONTACT, &station_id).unwrap().unwrap();
+        assert_eq!(qso.callsign, "LW3DZR");
+        assert_eq!(qso.datetime, 1704141420);
+        assert_eq!(qso.freq, 14025300);
+        assert_eq!(qso.mode, "CW");
+        assert_eq!(qso.rst, "599");
+        assert_eq!(qso.comments, "tnx & 73");
+        assert_eq!(qso.extra[CONTEST_ID], "CQ-WW-CW");
+        assert_eq!(qso.extra[SRX_STRING], "13");
+        assert!(!qso.extra.contains_key(GRIDSQUARE));
+
+        let radio_info = "<?xml version=\"1.0\"?><RadioInfo><Freq>1402530</Freq></RadioInfo>";
+        assert!(parse_contact(radio_info, &station_id).unwrap().is_none());
+        let replace = CONTACT.replace("contactinfo", "contactreplace");
+        assert!(parse_contact(&replace, &station_id).unwrap().is_none());
+
+        let missing_call = CONTACT.replace("<call>LW3DZR</call>", "");
+        assert!(parse_contact(&missing_call, &station_id).is_err());
+
+        let listener = N1mmListener::bind("127.0.0.1:0", station_id).unwrap();
+        let addr = listener.socket().local_addr().unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(radio_info.as_bytes(), addr).unwrap();
+        socket.send_to(CONTACT.as_bytes(), addr).unwrap();
+        assert_eq!(listener.recv().unwrap().callsign, "LW3DZR");
+    }
+}
@@ -0,0 +1,552 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::callsign::{Callsign, CallsignPolicy, StrictCallsign};
+use crate::qso::{COMMENTS_MAX_LEN, MODE_MAX_LEN};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{text, version, Id, Kind, Station};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Maximum length in bytes of a net name.
+const NAME_MAX_LEN: usize = 64;
+
+/// Maximum number of check-ins of a [`NetCheckIns`] list.
+pub const MAX_CHECK_INS: usize = 256;
+
+/// A net or roundtable, published by its net control station.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct NetSession {
+    pub id: Id,
+    /// The net control station.
+    pub station_id: Id,
+    pub name: String,
+    pub freq: u64,
+    pub mode: String,
+    pub start: u64,
+    /// End of the net, `None` while it's running.
+    pub end: Option<u64>,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl NetSession {
+    /// Current version of the net session object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the net session verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new NetSession and signs the object using the signer of the
+    /// net control station.
+    pub fn new(
+        station_id: Id,
+        signer: &dyn Signer,
+        name: String,
+        freq: u64,
+        mode: String,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(
+            &station_id,
+            &name,
+            freq,
+            &mode,
+            start,
+            end,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let session = Self {
+            id,
+            station_id,
+            name,
+            freq,
+            mode,
+            start,
+            end,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        session.validate()?;
+
+        Ok(session)
+    }
+
+    /// Verify the object signature against the net control public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Returns true if the time falls within the net, an open net has no
+    /// end.
+    pub fn contains(&self, datetime: u64) -> bool {
+        datetime >= self.start && self.end.is_none_or(|end| datetime <= end)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compute_id(
+        station_id: &Id,
+        name: &str,
+        freq: u64,
+        mode: &str,
+        start: u64,
+        end: Option<u64>,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                station_id, name, freq, mode, start, end, created_at, version,
+            ),
+        )
+    }
+}
+
+impl Validate for NetSession {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if !text::is_valid_required(&self.name, NAME_MAX_LEN) {
+            bail!("invalid net name");
+        }
+
+        if !text::is_valid_required(&self.mode, MODE_MAX_LEN) {
+            bail!("invalid mode");
+        }
+
+        if self.end.is_some_and(|end| end < self.start) {
+            bail!("net ends before its start");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for NetSession {
+    const KIND: Kind = Kind::NetSession;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.name,
+            self.freq,
+            &self.mode,
+            self.start,
+            self.end,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+/// A station checking in to a net.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CheckIn {
+    pub callsign: String,
+    pub datetime: u64,
+    /// Traffic or remarks given at check-in.
+    pub remarks: String,
+}
+
+/// The check-in list of a net, signed by its net control station.
+///
+/// Net control may publish the list several times while the net runs, the
+/// newest list replaces the previous ones. Participants confirm their
+/// check-in with a [`NetAttendance`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct NetCheckIns {
+    pub id: Id,
+    /// The net control station.
+    pub station_id: Id,
+    pub session_id: Id,
+    pub check_ins: Vec<CheckIn>,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl NetCheckIns {
+    /// Current version of the check-in list object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the check-in list verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates the check-in list of the session and signs the object using
+    /// the signer of the net control station. Callsigns are normalized.
+    pub fn new(
+        station_id: Id,
+        signer: &dyn Signer,
+        session: &NetSession,
+        check_ins: Vec<CheckIn>,
+    ) -> Result<Self> {
+        if session.station_id != station_id {
+            bail!("net controlled by another station");
+        }
+
+        if let Some(check_in) = check_ins
+            .iter()
+            .find(|check_in| !session.contains(check_in.datetime))
+        {
+            bail!("check-in of {} outside of the net", check_in.callsign);
+        }
+
+        let check_ins: Vec<CheckIn> = check_ins
+            .into_iter()
+            .map(|check_in| CheckIn {
+                callsign: Callsign::normalize(&check_in.callsign).to_string(),
+                ..check_in
+            })
+            .collect();
+
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(
+            &station_id,
+            &session.id,
+            &check_ins,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let list = Self {
+            id,
+            station_id,
+            session_id: session.id.clone(),
+            check_ins,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        list.validate()?;
+
+        Ok(list)
+    }
+
+    /// Verify the object signature against the net control public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Returns the check-in of the callsign, if any.
+    pub fn check_in(&self, callsign: &str) -> Option<&CheckIn> {
+        let callsign = Callsign::normalize(callsign);
+        self.check_ins
+            .iter()
+            .find(|check_in| check_in.callsign == callsign.as_str())
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        session_id: &Id,
+        check_ins: &[CheckIn],
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        let check_ins: Vec<(&str, u64, &str)> = check_ins
+            .iter()
+            .map(|check_in| {
+                (
+                    check_in.callsign.as_str(),
+                    check_in.datetime,
+                    check_in.remarks.as_str(),
+                )
+            })
+            .collect();
+        Id::hash(
+            version,
+            &(station_id, session_id, check_ins, created_at, version),
+        )
+    }
+}
+
+impl Validate for NetCheckIns {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.check_ins.len() > MAX_CHECK_INS {
+            bail!("more than {} check-ins", MAX_CHECK_INS);
+        }
+
+        let mut callsigns = BTreeSet::new();
+        for check_in in &self.check_ins {
+            StrictCallsign.validate(&check_in.callsign)?;
+
+            if !callsigns.insert(&check_in.callsign) {
+                bail!("duplicated check-in of {}", check_in.callsign);
+            }
+
+            if !text::is_valid(&check_in.remarks, COMMENTS_MAX_LEN) {
+                bail!("invalid remarks");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for NetCheckIns {
+    const KIND: Kind = Kind::NetCheckIns;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.session_id,
+            &self.check_ins,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+/// A participant confirming its check-in to a net.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct NetAttendance {
+    pub id: Id,
+    /// The participant station.
+    pub station_id: Id,
+    pub session_id: Id,
+    pub check_ins_id: Id,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl NetAttendance {
+    /// Current version of the attendance object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the attendance verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Confirms the check-in of the station listed by net control and signs
+    /// the object using the signer of the station.
+    pub fn new(station: &Station, signer: &dyn Signer, list: &NetCheckIns) -> Result<Self> {
+        if list.check_in(&station.callsign).is_none() {
+            bail!("{} not checked in", station.callsign);
+        }
+
+        if list.station_id == station.id {
+            bail!("net control confirming its own net");
+        }
+
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(
+            &station.id,
+            &list.session_id,
+            &list.id,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let attendance = Self {
+            id,
+            station_id: station.id.clone(),
+            session_id: list.session_id.clone(),
+            check_ins_id: list.id.clone(),
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        attendance.validate()?;
+
+        Ok(attendance)
+    }
+
+    /// Verify the object signature against the participant public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Returns true if the participant station is listed in the check-ins
+    /// it confirms. Every object must be verified first.
+    pub fn confirms(&self, station: &Station, list: &NetCheckIns) -> bool {
+        self.station_id == station.id
+            && self.check_ins_id == list.id
+            && self.session_id == list.session_id
+            && list.check_in(&station.callsign).is_some()
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        session_id: &Id,
+        check_ins_id: &Id,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(station_id, session_id, check_ins_id, created_at, version),
+        )
+    }
+}
+
+impl Validate for NetAttendance {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)
+    }
+}
+
+impl Signable for NetAttendance {
+    const KIND: Kind = Kind::NetAttendance;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.session_id,
+            &self.check_ins_id,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::net::{CheckIn, NetAttendance, NetCheckIns, NetSession};
+    use crate::test_vectors;
+
+    #[test]
+    fn test_net() {
+        let control = test_vectors::counterparty();
+        let control_keys = test_vectors::counterparty_keypair();
+        let participant = test_vectors::station();
+        let participant_keys = test_vectors::station_keypair();
+        let start = test_vectors::CREATED_AT;
+
+        let session = NetSession::new(
+            control.id.clone(),
+            &control_keys,
+            "Red Argentina de Emergencias".to_string(),
+            7_088_000,
+            "LSB".to_string(),
+            start,
+            Some(start + 3600),
+        )
+        .unwrap();
+        session.verify(&control.pub_key).unwrap();
+        assert!(session.contains(start + 60));
+        assert!(!session.contains(start + 3601));
+
+        let check_in = |callsign: &str, datetime| CheckIn {
+            callsign: callsign.to_string(),
+            datetime,
+            remarks: String::new(),
+        };
+        let list = NetCheckIns::new(
+            control.id.clone(),
+            &control_keys,
+            &session,
+            vec![
+                check_in("lu4ev", start + 60),
+                check_in("LU1AA", start + 120),
+            ],
+        )
+        .unwrap();
+        let json_str = serde_json::to_string(&list).unwrap();
+        let list: NetCheckIns = serde_json::from_str(&json_str).unwrap();
+        list.verify(&control.pub_key).unwrap();
+        assert_eq!(list.check_in("LU4EV").unwrap().datetime, start + 60);
+
+        let mut tampered = list.clone();
+        tampered.check_ins.remove(0);
+        assert!(tampered.verify(&control.pub_key).is_err());
+
+        let attendance = NetAttendance::new(&participant, &participant_keys, &list).unwrap();
+        attendance.verify(&participant.pub_key).unwrap();
+        assert!(attendance.confirms(&participant, &list));
+        assert!(!attendance.confirms(&participant, &tampered));
+
+        assert!(NetAttendance::new(&control, &control_keys, &list).is_err());
+        assert!(
+            NetCheckIns::new(participant.id.clone(), &participant_keys, &session, vec![]).is_err()
+        );
+        assert!(NetCheckIns::new(
+            control.id.clone(),
+            &control_keys,
+            &session,
+            vec![check_in("LU4EV", start + 7200)]
+        )
+        .is_err());
+        assert!(NetCheckIns::new(
+            control.id,
+            &control_keys,
+            &session,
+            vec![check_in("LU4EV", start), check_in("lu4ev", start + 60)]
+        )
+        .is_err());
+    }
+}
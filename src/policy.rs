@@ -0,0 +1,450 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Ingestion policy for relay operators.
+//!
+//! An [`IngestionPolicy`] describes which events a relay accepts: a maximum
+//! size, a minimum proof of work, the accepted kinds and the publish rate per
+//! client address and per station key. [`Policy`] enforces it and explains
+//! every refusal with a [`Rejection`], which relays send back to the client.
+
+use crate::event::Event;
+use crate::limits;
+use crate::time::unix_timstamp;
+use crate::Kind;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Maximum number of buckets, new keys past it are refused until the full
+/// buckets are dropped.
+pub const MAX_BUCKETS: usize = 10_000;
+
+/// A token bucket rate: bursts of up to `burst` events, refilled at
+/// `per_minute` events per minute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+/// The events a relay accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestionPolicy {
+    /// Maximum size in bytes of the serialized event.
+    pub max_event_size: Option<usize>,
+    /// Leading zero bits required in the event ids, see [`Event::pow_bits`].
+    pub min_pow: u32,
+    /// Accepted kinds, every kind if `None`.
+    pub allowed_kinds: Option<BTreeSet<Kind>>,
+    /// Publish rate per client address.
+    pub per_ip: Option<RateLimit>,
+    /// Publish rate per station key, applied to verified events only so a
+    /// client can't drain the bucket of another station.
+    pub per_pub_key: Option<RateLimit>,
+}
+
+impl Default for IngestionPolicy {
+    fn default() -> Self {
+        Self {
+            max_event_size: Some(64 * 1024),
+            min_pow: 0,
+            allowed_kinds: None,
+            per_ip: Some(RateLimit {
+                burst: 120,
+                per_minute: 120,
+            }),
+            per_pub_key: Some(RateLimit {
+                burst: 60,
+                per_minute: 60,
+            }),
+        }
+    }
+}
+
+/// Why an event was refused.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum Rejection {
+    TooLarge {
+        size: usize,
+        max: usize,
+    },
+    InsufficientPow {
+        bits: u32,
+        required: u32,
+    },
+    KindNotAllowed {
+        kind: Kind,
+    },
+    /// The event has a [`crate::Tag::ExpiresAt`] in the past.
+    Expired {
+        expires_at: u64,
+    },
+    /// The event was signed after the revocation of its key, see
+    /// [`crate::Revocation`].
+    Revoked {
+        revoked_at: u64,
+    },
+    /// Seconds until the next event is accepted.
+    RateLimited {
+        retry_after: u64,
+    },
+    /// The event doesn't verify or its station is unknown.
+    Invalid {
+        message: String,
+    },
+    /// The relay failed to store the event.
+    Error {
+        message: String,
+    },
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::TooLarge { size, max } => {
+                write!(f, "too-large: {} bytes, at most {}", size, max)
+            }
+            Rejection::InsufficientPow { bits, required } => {
+                write!(f, "pow: {} leading zero bits, {} required", bits, required)
+            }
+            Rejection::KindNotAllowed { kind } => {
+                write!(f, "blocked: kind {} not accepted", kind.as_str())
+            }
+            Rejection::Expired { expires_at } => write!(f, "expired: at {}", expires_at),
+            Rejection::Revoked { revoked_at } => {
+                write!(f, "revoked: station key revoked at {}", revoked_at)
+            }
+            Rejection::RateLimited { retry_after } => {
+                write!(f, "rate-limited: retry in {}s", retry_after)
+            }
+            Rejection::Invalid { message } => write!(f, "invalid: {}", message),
+            Rejection::Error { message } => write!(f, "error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token buckets by key, e.g. client address or station key.
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    rate: RateLimit,
+    buckets: HashMap<K, TokenBucket>,
+    swept_at: Option<Instant>,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    /// Creates a limiter with a full bucket for every key.
+    pub fn new(rate: RateLimit) -> Self {
+        Self {
+            rate,
+            buckets: HashMap::new(),
+            swept_at: None,
+        }
+    }
+
+    /// Takes a token from the bucket of the key, or returns the time until
+    /// the next token. Once there are [`MAX_BUCKETS`] buckets, new keys are
+    /// refused until the full buckets can be dropped.
+    pub fn take(&mut self, key: K, now: Instant) -> Result<(), Duration> {
+        if self.buckets.len() >= MAX_BUCKETS && !self.buckets.contains_key(&key) {
+            self.sweep(now)?;
+        }
+
+        let rate = self.rate;
+        let bucket = self.buckets.entry(key).or_insert(TokenBucket {
+            tokens: rate.burst as f64,
+            updated_at: now,
+        });
+        let tokens = Self::refill(rate, bucket, now);
+
+        if tokens >= 1.0 {
+            bucket.tokens = tokens - 1.0;
+            return Ok(());
+        }
+
+        if rate.per_minute == 0 {
+            return Err(Duration::MAX);
+        }
+        let missing = 1.0 - tokens;
+        Err(Duration::from_secs_f64(
+            missing * 60.0 / rate.per_minute as f64,
+        ))
+    }
+
+    /// Drops the full buckets, at most once per token interval so clients
+    /// rotating keys can't make every call scan the map. Returns the time
+    /// until the next sweep if there's still no room.
+    fn sweep(&mut self, now: Instant) -> Result<(), Duration> {
+        let rate = self.rate;
+        let interval = match rate.per_minute {
+            0 => Duration::MAX,
+            per_minute => Duration::from_secs_f64(60.0 / per_minute as f64),
+        };
+        if let Some(swept_at) = self.swept_at {
+            let elapsed = now.saturating_duration_since(swept_at);
+            if elapsed < interval {
+                return Err(interval - elapsed);
+            }
+        }
+
+        self.buckets
+            .retain(|_, bucket| Self::refill(rate, bucket, now) < rate.burst as f64);
+        self.swept_at = Some(now);
+        if self.buckets.len() >= MAX_BUCKETS {
+            return Err(interval);
+        }
+        Ok(())
+    }
+
+    fn refill(rate: RateLimit, bucket: &mut TokenBucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * rate.per_minute as f64 / 60.0)
+            .min(rate.burst as f64);
+        bucket.updated_at = now;
+        bucket.tokens
+    }
+}
+
+/// Enforces an [`IngestionPolicy`]. Safe to share between connections.
+#[derive(Debug)]
+pub struct Policy {
+    config: IngestionPolicy,
+    by_ip: Option<Mutex<RateLimiter<IpAddr>>>,
+    by_pub_key: Option<Mutex<RateLimiter<XOnlyPublicKey>>>,
+}
+
+impl Policy {
+    pub fn new(config: IngestionPolicy) -> Self {
+        Self {
+            by_ip: config.per_ip.map(|rate| Mutex::new(RateLimiter::new(rate))),
+            by_pub_key: config
+                .per_pub_key
+                .map(|rate| Mutex::new(RateLimiter::new(rate))),
+            config,
+        }
+    }
+
+    /// Returns the enforced policy.
+    pub fn config(&self) -> &IngestionPolicy {
+        &self.config
+    }
+
+    /// Checks the size, proof of work, kind and expiration of the event.
+    /// `size` is the size of the event as received, the size of the event
+    /// serialized as compact JSON must also fit in the budget of its kind,
+    /// see [`limits::max_event_size`].
+    pub fn check_event(&self, event: &Event, size: usize) -> Result<(), Rejection> {
+        if let Some(max) = self.config.max_event_size {
+            if size > max {
+                return Err(Rejection::TooLarge { size, max });
+            }
+        }
+
+        let max = limits::max_event_size(event.kind());
+        let size = limits::event_size(event).map_err(|err| Rejection::Invalid {
+            message: err.to_string(),
+        })?;
+        if size > max {
+            return Err(Rejection::TooLarge { size, max });
+        }
+
+        let bits = event.pow_bits();
+        if bits < self.config.min_pow {
+            return Err(Rejection::InsufficientPow {
+                bits,
+                required: self.config.min_pow,
+            });
+        }
+
+        if let Some(kinds) = &self.config.allowed_kinds {
+            if !kinds.contains(&event.kind()) {
+                return Err(Rejection::KindNotAllowed { kind: event.kind() });
+            }
+        }
+
+        if let Some(expires_at) = event.expires_at() {
+            if expires_at <= unix_timstamp() {
+                return Err(Rejection::Expired { expires_at });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Takes a token from the bucket of the client address.
+    pub fn check_ip(&self, ip: IpAddr, now: Instant) -> Result<(), Rejection> {
+        take(self.by_ip.as_ref(), ip, now)
+    }
+
+    /// Takes a token from the bucket of the station key. The event must be
+    /// verified against the key first.
+    pub fn check_pub_key(&self, pub_key: XOnlyPublicKey, now: Instant) -> Result<(), Rejection> {
+        take(self.by_pub_key.as_ref(), pub_key, now)
+    }
+}
+
+fn take<K: Hash + Eq>(
+    limiter: Option<&Mutex<RateLimiter<K>>>,
+    key: K,
+    now: Instant,
+) -> Result<(), Rejection> {
+    let Some(limiter) = limiter else {
+        return Ok(());
+    };
+
+    limiter
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .take(key, now)
+        .map_err(|wait| Rejection::RateLimited {
+            retry_after: wait.as_secs_f64().ceil().min(u64::MAX as f64) as u64,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::policy::{IngestionPolicy, Policy, RateLimit, RateLimiter, Rejection, MAX_BUCKETS};
+    use crate::{test_vectors, Kind, Qso, QsoData, Tag};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_policy() {
+        let policy = Policy::new(IngestionPolicy {
+            max_event_size: Some(1024),
+            min_pow: 0,
+            allowed_kinds: Some([Kind::Station].into()),
+            per_ip: Some(RateLimit {
+                burst: 2,
+                per_minute: 6,
+            }),
+            per_pub_key: None,
+        });
+        let station = Event::Station(test_vectors::station());
+        let qso = Event::Qso(test_vectors::qso());
+
+        policy.check_event(&station, 512).unwrap();
+        assert_eq!(
+            policy.check_event(&station, 2048),
+            Err(Rejection::TooLarge {
+                size: 2048,
+                max: 1024
+            })
+        );
+        assert_eq!(
+            policy.check_event(&qso, 512),
+            Err(Rejection::KindNotAllowed { kind: Kind::Qso })
+        );
+
+        let strict = Policy::new(IngestionPolicy {
+            min_pow: 255,
+            ..IngestionPolicy::default()
+        });
+        let rejection = strict.check_event(&station, 512).unwrap_err();
+        assert!(matches!(
+            rejection,
+            Rejection::InsufficientPow { required: 255, .. }
+        ));
+        assert!(rejection.to_string().starts_with("pow: "));
+
+        // The creation time prefix of sortable ids isn't a proof of work.
+        let qso = test_vectors::qso();
+        let data = QsoData {
+            station_id: qso.station_id,
+            callsign: qso.callsign,
+            freq: qso.freq,
+            datetime: qso.datetime,
+            mode: qso.mode,
+            rst: qso.rst,
+            comments: qso.comments,
+            extra: qso.extra,
+            ..test_vectors::qso_data()
+        };
+        let sortable = Qso::new_sortable(data, &test_vectors::station_keypair()).unwrap();
+        assert!(sortable.id.leading_zero_bits() >= 32);
+        let strict = Policy::new(IngestionPolicy {
+            min_pow: 8,
+            ..IngestionPolicy::default()
+        });
+        assert_eq!(
+            strict.check_event(&Event::Qso(sortable), 512),
+            Err(Rejection::InsufficientPow {
+                bits: 0,
+                required: 8
+            })
+        );
+
+        let mut expired = test_vectors::qso();
+        expired.tags = vec![Tag::ExpiresAt(test_vectors::CREATED_AT)];
+        assert_eq!(
+            Policy::new(IngestionPolicy::default()).check_event(&Event::Qso(expired), 512),
+            Err(Rejection::Expired {
+                expires_at: test_vectors::CREATED_AT
+            })
+        );
+
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let now = Instant::now();
+        policy.check_ip(ip, now).unwrap();
+        policy.check_ip(ip, now).unwrap();
+        assert_eq!(
+            policy.check_ip(ip, now),
+            Err(Rejection::RateLimited { retry_after: 10 })
+        );
+        policy.check_ip(other_ip, now).unwrap();
+        policy.check_ip(ip, now + Duration::from_secs(10)).unwrap();
+
+        let json_str = serde_json::to_string(&Rejection::RateLimited { retry_after: 10 }).unwrap();
+        assert_eq!(json_str, r#"{"code":"rate_limited","retry_after":10}"#);
+    }
+
+    #[test]
+    fn test_rate_limiter_bound() {
+        let mut limiter = RateLimiter::new(RateLimit {
+            burst: 1,
+            per_minute: 60,
+        });
+        let now = Instant::now();
+        for key in 0..MAX_BUCKETS {
+            limiter.take(key, now).unwrap();
+        }
+        assert_eq!(limiter.take(MAX_BUCKETS, now), Err(Duration::from_secs(1)));
+        assert_eq!(
+            limiter.take(MAX_BUCKETS, now + Duration::from_millis(500)),
+            Err(Duration::from_millis(500))
+        );
+        assert_eq!(limiter.buckets.len(), MAX_BUCKETS);
+
+        // Once refilled, the idle buckets make room for new keys.
+        limiter
+            .take(MAX_BUCKETS, now + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(limiter.buckets.len(), 1);
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::Id;
+use anyhow::{bail, Result};
+
+/// Maximum difficulty, the number of bits of an id.
+const MAX_DIFFICULTY: u32 = 256;
+
+/// Maximum number of nonces tried, enough for about 30 bits of difficulty.
+const MAX_ATTEMPTS: u64 = 1 << 32;
+
+/// Searches a nonce whose id has at least `difficulty` leading zero bits.
+///
+/// Every additional bit doubles the expected work, relays usually ask for
+/// something between 8 and 20 bits. Gives up after [`MAX_ATTEMPTS`] nonces.
+pub(crate) fn mine(difficulty: u32, generate_id: impl Fn(u64) -> Id) -> Result<(u64, Id)> {
+    mine_with_limit(difficulty, MAX_ATTEMPTS, generate_id)
+}
+
+fn mine_with_limit(
+    difficulty: u32,
+    max_attempts: u64,
+    generate_id: impl Fn(u64) -> Id,
+) -> Result<(u64, Id)> {
+    if difficulty > MAX_DIFFICULTY {
+        bail!(
+            "difficulty {} above the maximum of {}",
+            difficulty,
+            MAX_DIFFICULTY
+        );
+    }
+
+    let mut nonce: u64 = rand::random();
+    for _ in 0..max_attempts {
+        let id = generate_id(nonce);
+        if id.leading_zero_bits() >= difficulty {
+            return Ok((nonce, id));
+        }
+        nonce = nonce.wrapping_add(1);
+    }
+
+    bail!(
+        "no proof of work of difficulty {} found in {} attempts",
+        difficulty,
+        max_attempts
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pow::{mine, mine_with_limit};
+    use crate::{version, Id};
+
+    #[test]
+    fn test_mine() {
+        let (nonce, id) = mine(8, |nonce| Id::hash(version::V0, &nonce)).unwrap();
+        assert_eq!(id, Id::hash(version::V0, &nonce));
+        assert!(id.leading_zero_bits() >= 8);
+
+        assert!(mine(257, |nonce| Id::hash(version::V0, &nonce)).is_err());
+        let err = mine_with_limit(1, 100, |_| Id::new("LU4EV")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no proof of work of difficulty 1 found in 100 attempts"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Activation programs and the format of their references, e.g. the SOTA
+//! summit `LU/BA-001` of a QSO.
+//!
+//! References are stored in the extension fields named after the ADIF
+//! fields, lowercase: `sota_ref` for the summit of the worked station and
+//! `my_sota_ref` for the own one. QSOs and stations with malformed
+//! references are invalid.
+
+use anyhow::{bail, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+thread_local! {
+    static IOTA: Regex = Regex::new(r"^(?:AF|AN|AS|EU|NA|OC|SA)-(\d{3})$").unwrap();
+    static WWFF: Regex = Regex::new(r"^[A-Z0-9]{1,4}FF-(\d{4})$").unwrap();
+    static POTA: Regex = Regex::new(r"^[A-Z0-9]{1,4}-(\d{4,5})(@[A-Z0-9]{2}-[A-Z0-9]{1,3})?$").unwrap();
+    static SOTA: Regex = Regex::new(r"^[A-Z0-9]{1,4}/[A-Z0-9]{2}-(\d{3})$").unwrap();
+}
+
+/// An activation program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Program {
+    /// Islands On The Air, e.g. `SA-001`.
+    Iota,
+    /// World Wide Flora and Fauna, e.g. `LUFF-0001`.
+    Wwff,
+    /// Parks On The Air, e.g. `AR-0001` or `K-0059@US-ME`. A QSO may list
+    /// several parks separated by commas.
+    Pota,
+    /// Summits On The Air, e.g. `LU/BA-001`.
+    Sota,
+}
+
+impl Program {
+    pub const ALL: [Program; 4] = [Program::Iota, Program::Wwff, Program::Pota, Program::Sota];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Program::Iota => "IOTA",
+            Program::Wwff => "WWFF",
+            Program::Pota => "POTA",
+            Program::Sota => "SOTA",
+        }
+    }
+
+    /// Returns the extension fields of the worked and the own reference.
+    pub fn fields(&self) -> [&'static str; 2] {
+        match self {
+            Program::Iota => ["iota", "my_iota"],
+            Program::Wwff => ["wwff_ref", "my_wwff_ref"],
+            Program::Pota => ["pota_ref", "my_pota_ref"],
+            Program::Sota => ["sota_ref", "my_sota_ref"],
+        }
+    }
+
+    /// Returns the program of the extension field, if any.
+    pub fn from_field(field: &str) -> Option<Program> {
+        Self::ALL
+            .into_iter()
+            .find(|program| program.fields().contains(&field))
+    }
+
+    /// Normalizes a raw reference: uppercase and without whitespace.
+    pub fn normalize(reference: &str) -> String {
+        reference
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    }
+
+    /// Returns an error if the normalized reference is malformed. None of the
+    /// programs defines a check digit, but references are numbered from 1.
+    pub fn validate(&self, reference: &str) -> Result<()> {
+        let references: Vec<&str> = match self {
+            Program::Pota => reference.split(',').collect(),
+            _ => vec![reference],
+        };
+
+        for reference in references {
+            let regex = match self {
+                Program::Iota => &IOTA,
+                Program::Wwff => &WWFF,
+                Program::Pota => &POTA,
+                Program::Sota => &SOTA,
+            };
+            let number = regex.with(|regex| {
+                regex
+                    .captures(reference)
+                    .and_then(|captures| captures.get(1))
+                    .map(|number| number.as_str().to_string())
+            });
+            if number.is_none_or(|number| number.bytes().all(|digit| digit == b'0')) {
+                bail!("invalid {} reference {}", self.name(), reference);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Normalizes the program references among the extension fields.
+pub(crate) fn normalize_extra(extra: &mut BTreeMap<String, Value>) {
+    for (field, value) in extra.iter_mut() {
+        if let (Some(_), Value::String(reference)) = (Program::from_field(field), &mut *value) {
+            *reference = Program::normalize(reference);
+        }
+    }
+}
+
+/// Returns an error if any program reference among the extension fields is
+/// malformed or not a string.
+pub(crate) fn validate_extra(extra: &BTreeMap<String, Value>) -> Result<()> {
+    for (field, value) in extra {
+        let Some(program) = Program::from_field(field) else {
+            continue;
+        };
+        match value {
+            Value::String(reference) => program.validate(reference)?,
+            _ => bail!("invalid {} reference in {}", program, field),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::programs::{normalize_extra, validate_extra, Program};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_programs() {
+        let valid = [
+            (Program::Iota, "SA-001"),
+            (Program::Iota, "EU-005"),
+            (Program::Wwff, "LUFF-0001"),
+            (Program::Wwff, "KFF-1234"),
+            (Program::Pota, "AR-0001"),
+            (Program::Pota, "US-10234"),
+            (Program::Pota, "K-0059@US-ME,K-0060"),
+            (Program::Sota, "LU/BA-001"),
+            (Program::Sota, "W7W/KG-114"),
+        ];
+        for (program, reference) in valid {
+            program.validate(reference).unwrap();
+        }
+
+        let invalid = [
+            (Program::Iota, "XX-001"),
+            (Program::Iota, "SA-000"),
+            (Program::Iota, "SA-1"),
+            (Program::Wwff, "LU-0001"),
+            (Program::Pota, "AR-001"),
+            (Program::Pota, "AR-0001,"),
+            (Program::Sota, "LU/BA-1"),
+            (Program::Sota, "lu/ba-001"),
+        ];
+        for (program, reference) in invalid {
+            assert!(program.validate(reference).is_err(), "{}", reference);
+        }
+
+        assert_eq!(Program::from_field("my_pota_ref"), Some(Program::Pota));
+        assert_eq!(Program::from_field("gridsquare"), None);
+
+        let mut extra = BTreeMap::from([
+            ("my_sota_ref".to_string(), json!(" lu/ba-001")),
+            ("pota_ref".to_string(), json!("ar-0001, ar-0002")),
+            ("comment".to_string(), json!("sa-000")),
+        ]);
+        normalize_extra(&mut extra);
+        assert_eq!(extra["my_sota_ref"], "LU/BA-001");
+        assert_eq!(extra["pota_ref"], "AR-0001,AR-0002");
+        validate_extra(&extra).unwrap();
+
+        extra.insert("iota".to_string(), json!(1));
+        assert!(validate_extra(&extra).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Progress of a bulk import or export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Records processed so far, failed ones included.
+    pub processed: u64,
+    /// Records which failed, e.g. malformed or rejected ones.
+    pub errors: u64,
+    /// Number of records, when known upfront.
+    pub total: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Returns the processed fraction of the records, when the total is
+    /// known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total? {
+            0 => Some(1.0),
+            total => Some(self.processed as f64 / total as f64),
+        }
+    }
+
+    /// Returns the estimated time left at the rate so far, when the total is
+    /// known.
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        if self.processed == 0 {
+            return None;
+        }
+        let left = total.saturating_sub(self.processed);
+        Some(self.elapsed.mul_f64(left as f64 / self.processed as f64))
+    }
+}
+
+/// Cancels bulk operations from another thread, e.g. the cancel button of a
+/// progress dialog. Clones share the state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the operations watching the token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Error of a cancelled operation, see [`CancellationToken`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+type ProgressFn<'a> = Box<dyn FnMut(&Progress) + 'a>;
+
+/// Reports the progress of a bulk operation and checks its cancellation.
+///
+/// The callback is called after every record. A cancelled operation stops
+/// before the next record and fails with [`Cancelled`], the records processed
+/// so far are kept.
+#[derive(Default)]
+pub struct Monitor<'a> {
+    on_progress: Option<ProgressFn<'a>>,
+    token: Option<CancellationToken>,
+    start: Option<Instant>,
+    progress: Progress,
+}
+
+impl<'a> Monitor<'a> {
+    /// Creates a monitor without callback nor cancellation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `on_progress` after every record.
+    pub fn on_progress(mut self, on_progress: impl FnMut(&Progress) + 'a) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Stops the operation once the token is cancelled.
+    pub fn cancel_with(mut self, token: CancellationToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Returns the progress so far.
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// Starts the operation over `total` records, if known.
+    pub(crate) fn start(&mut self, total: Option<u64>) -> Result<()> {
+        self.start = Some(Instant::now());
+        self.progress = Progress {
+            total,
+            ..Progress::default()
+        };
+        self.check_cancelled()
+    }
+
+    /// Records a processed record, then checks the cancellation.
+    pub(crate) fn record(&mut self, ok: bool) -> Result<()> {
+        self.progress.processed += 1;
+        if !ok {
+            self.progress.errors += 1;
+        }
+        self.progress.elapsed = self.start.map(|start| start.elapsed()).unwrap_or_default();
+        if let Some(on_progress) = &mut self.on_progress {
+            on_progress(&self.progress);
+        }
+        self.check_cancelled()
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        if self
+            .token
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::progress::{CancellationToken, Cancelled, Monitor, Progress};
+    use std::time::Duration;
+
+    #[test]
+    fn test_monitor() {
+        let progress = Progress {
+            processed: 250,
+            errors: 0,
+            total: Some(1000),
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+        assert_eq!(Progress::default().eta(), None);
+
+        let token = CancellationToken::new();
+        let mut reports = vec![];
+        let mut monitor = Monitor::new()
+            .on_progress(|progress: &Progress| reports.push((progress.processed, progress.errors)))
+            .cancel_with(token.clone());
+        monitor.start(Some(3)).unwrap();
+        monitor.record(true).unwrap();
+        monitor.record(false).unwrap();
+        token.cancel();
+        let err = monitor.record(true).unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert!(monitor.start(None).is_err());
+        drop(monitor);
+        assert_eq!(reports, vec![(1, 0), (2, 1), (3, 1)]);
+    }
+}
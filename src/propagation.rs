@@ -0,0 +1,220 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reception datasets for propagation research.
+//!
+//! Every QSO and SWL report, beacon reports included, is a reception: a
+//! receiver heard a sender on a frequency at a given time. [`write_csv`]
+//! writes them with the column names of the PSK Reporter exports, so tools
+//! written for PSK Reporter and WSPR spot datasets can consume them.
+
+use crate::geo::LatLon;
+use crate::{Beacon, Qso, Station, SwlReport};
+use anyhow::Result;
+use std::io::Write;
+
+/// CSV header written by [`write_csv`]. Times are unix seconds, frequencies
+/// are in Hz and distances in km.
+pub const HEADER: &[&str] = &[
+    "flowStartSeconds",
+    "frequency",
+    "mode",
+    "sNR",
+    "senderCallsign",
+    "senderLocator",
+    "receiverCallsign",
+    "receiverLocator",
+    "distance",
+];
+
+/// A signal heard by a receiver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reception {
+    pub timestamp: u64,
+    pub freq: u64,
+    pub mode: String,
+    /// Signal to noise ratio in dB, only known for digital mode reports.
+    pub snr: Option<i32>,
+    pub sender_callsign: String,
+    pub sender_grid: Option<String>,
+    pub receiver_callsign: String,
+    pub receiver_grid: Option<String>,
+}
+
+impl Reception {
+    /// The station logging the QSO heard the contacted station with the
+    /// report sent, `rst`.
+    pub fn from_qso(station: &Station, qso: &Qso) -> Self {
+        Self {
+            timestamp: qso.datetime,
+            freq: qso.freq,
+            mode: qso.mode.clone(),
+            snr: parse_snr(&qso.rst),
+            sender_callsign: qso.callsign.clone(),
+            sender_grid: qso.gridsquare().map(str::to_string),
+            receiver_callsign: station.callsign.clone(),
+            receiver_grid: station.grid().map(str::to_string),
+        }
+    }
+
+    /// The listener heard the reported station. The grid of the sender is
+    /// known only if its station object is given.
+    pub fn from_swl_report(
+        listener: &Station,
+        sender: Option<&Station>,
+        report: &SwlReport,
+    ) -> Self {
+        Self {
+            timestamp: report.datetime,
+            freq: report.freq,
+            mode: report.mode.clone(),
+            snr: parse_snr(&report.rst),
+            sender_callsign: report.callsign.clone(),
+            sender_grid: sender.and_then(Station::grid).map(str::to_string),
+            receiver_callsign: listener.callsign.clone(),
+            receiver_grid: listener.grid().map(str::to_string),
+        }
+    }
+
+    /// The listener heard the beacon, see [`Beacon::heard_by`].
+    pub fn from_beacon_report(listener: &Station, beacon: &Beacon, report: &SwlReport) -> Self {
+        Self {
+            sender_grid: Some(beacon.grid.clone()),
+            ..Self::from_swl_report(listener, None, report)
+        }
+    }
+
+    /// Returns the distance in km between the sender and the receiver, when
+    /// both grids are known.
+    pub fn distance_km(&self) -> Option<f64> {
+        let from = LatLon::from_grid(self.sender_grid.as_deref()?).ok()?;
+        let to = LatLon::from_grid(self.receiver_grid.as_deref()?).ok()?;
+        Some(from.distance_km(&to))
+    }
+}
+
+/// Writes the receptions as CSV with the [`HEADER`] row. Unknown values are
+/// left empty.
+pub fn write_csv<W: Write>(writer: W, receptions: &[Reception]) -> Result<()> {
+    let mut writer = ::csv::Writer::from_writer(writer);
+    writer.write_record(HEADER)?;
+
+    for reception in receptions {
+        writer.write_record([
+            reception.timestamp.to_string(),
+            reception.freq.to_string(),
+            reception.mode.clone(),
+            reception.snr.map(|snr| snr.to_string()).unwrap_or_default(),
+            reception.sender_callsign.clone(),
+            reception.sender_grid.clone().unwrap_or_default(),
+            reception.receiver_callsign.clone(),
+            reception.receiver_grid.clone().unwrap_or_default(),
+            reception
+                .distance_km()
+                .map(|distance| format!("{:.0}", distance))
+                .unwrap_or_default(),
+        ])?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Digital modes report the SNR as a signed number of dB ( `-12`, `+05` ),
+/// RST and SINPO reports carry no SNR.
+fn parse_snr(rst: &str) -> Option<i32> {
+    if !rst.starts_with(['+', '-']) {
+        return None;
+    }
+    rst.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::propagation::{write_csv, Reception};
+    use crate::qso::GRIDSQUARE;
+    use crate::station::GRID;
+    use crate::swl::{SwlReport, SwlReportData};
+    use crate::{test_vectors, Qso, QsoData, Station, StationData};
+    use codes_iso_3166::part_1::CountryCode;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_write_csv() {
+        let keys = test_vectors::station_keypair();
+        let station = Station::from_data(
+            StationData {
+                callsign: "LU4EV".to_string(),
+                operator: "Radio Club Caseros".to_string(),
+                country: CountryCode::AR.into(),
+                extra: BTreeMap::from([(GRID.to_string(), json!("GF05tk"))]),
+                profile: None,
+                qsl_route: None,
+            },
+            &keys,
+        )
+        .unwrap();
+        let listener = test_vectors::counterparty();
+
+        let qso = Qso::new(
+            QsoData {
+                station_id: station.id.clone(),
+                callsign: "CX2ABC".to_string(),
+                freq: 14074000,
+                mode: "FT8".to_string(),
+                rst: "-12".to_string(),
+                comments: String::new(),
+                extra: BTreeMap::from([(GRIDSQUARE.to_string(), json!("GF15ak"))]),
+                ..test_vectors::qso_data()
+            },
+            &keys,
+        );
+        let report = SwlReport::new(
+            listener.id.clone(),
+            &test_vectors::counterparty_keypair(),
+            SwlReportData {
+                station_id: station.id.clone(),
+                callsign: station.callsign.clone(),
+                datetime: test_vectors::CREATED_AT,
+                freq: 14250300,
+                mode: "SSB".to_string(),
+                rst: "45444".to_string(),
+                comments: String::new(),
+            },
+        )
+        .unwrap();
+
+        let receptions = [
+            Reception::from_qso(&station, &qso),
+            Reception::from_swl_report(&listener, Some(&station), &report),
+        ];
+        assert_eq!(receptions[0].snr, Some(-12));
+        assert_eq!(receptions[1].snr, None);
+        assert_eq!(receptions[1].sender_grid.as_deref(), Some("GF05tk"));
+
+        let mut csv = vec![];
+        write_csv(&mut csv, &receptions).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "flowStartSeconds,frequency,mode,sNR,senderCallsign,senderLocator,receiverCallsign,receiverLocator,distance",
+                "1704141426,14074000,FT8,-12,CX2ABC,GF15ak,LU4EV,GF05tk,38",
+                "1704141426,14250300,SSB,,LU4EV,GF05tk,LW3DZR,,",
+            ]
+        );
+    }
+}
@@ -11,17 +11,45 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{time, Id};
-use anyhow::{bail, Result};
+use crate::callsign::{Callsign, CallsignPolicy, StrictCallsign};
+use crate::delegation::Delegation;
+use crate::geo::LatLon;
+use crate::hash::HashAlgorithm;
+use crate::id::IdBuilder;
+use crate::kind::Kind;
+use crate::limits::{self, Limits};
+use crate::redaction::Redactable;
+use crate::signable::{self, Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::tag::{Tag, MAX_TAGS};
+use crate::validation::{ValidationOptions, ValidationReport, ValidationWarning};
+use crate::{bandplan, pow, programs, text, time, version, Id, Station};
+use anyhow::{bail, Context, Result};
 use secp256k1::schnorr::Signature;
-use secp256k1::{Keypair, XOnlyPublicKey};
+use secp256k1::{Keypair, XOnlyPublicKey, SECP256K1};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::Value;
+use std::collections::BTreeMap;
 
-const MODE_MAX_LEN: usize = 16;
-const RST_MAX_LEN: usize = 8;
-const COMMENTS_MAX_LEN: usize = 128;
+pub(crate) const MODE_MAX_LEN: usize = 16;
+pub(crate) const RST_MAX_LEN: usize = 8;
+pub(crate) const COMMENTS_MAX_LEN: usize = 128;
 
+/// Extension field with the grid square of the contacted station.
+pub const GRIDSQUARE: &str = "gridsquare";
+
+/// Extension field with the signal report received, as in ADIF.
+pub const RST_RCVD: &str = "rst_rcvd";
+
+/// Extension field with the contest identifier, as in ADIF.
+#[cfg(feature = "n1mm")]
+pub const CONTEST_ID: &str = "contest_id";
+
+/// Extension field with the contest exchange received, as in ADIF.
+#[cfg(feature = "n1mm")]
+pub const SRX_STRING: &str = "srx_string";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct QsoData {
     pub station_id: Id,
     pub callsign: String,
@@ -30,41 +58,211 @@ pub struct QsoData {
     pub mode: String,
     pub rst: String,
     pub comments: String,
+    pub extra: BTreeMap<String, Value>,
+    /// References to other events and indexed values.
+    pub tags: Vec<Tag>,
+    /// Id of the previous QSO of the station, see [`Qso::verify_chain`].
+    pub prev_id: Option<Id>,
+}
+
+impl QsoData {
+    /// Returns the warnings about suspicious fields, e.g. a mode unlikely on
+    /// the frequency. Import tools should surface them for review.
+    pub fn warnings(&self) -> Vec<ValidationWarning> {
+        warnings(self.freq, &self.mode)
+    }
+}
+
+/// The comments id field, the comments themselves or their commitment when
+/// they are redactable.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Comments<'a> {
+    Plain(&'a str),
+    Commitment(&'a Id),
 }
 
-struct QsoIdSrc<'a> {
-    station_id: &'a Id,
-    callsign: &'a str,
-    datetime: u64,
-    freq: u64,
-    mode: &'a str,
-    rst: &'a str,
-    comments: &'a str,
-    created_at: u64,
-    version: u8,
+pub(crate) struct QsoIdSrc<'a> {
+    pub(crate) station_id: &'a Id,
+    pub(crate) callsign: &'a str,
+    pub(crate) datetime: u64,
+    pub(crate) freq: u64,
+    pub(crate) mode: &'a str,
+    pub(crate) rst: &'a str,
+    pub(crate) comments: &'a str,
+    pub(crate) created_at: u64,
+    pub(crate) version: u8,
+    pub(crate) nonce: Option<u64>,
+    pub(crate) extra: &'a BTreeMap<String, Value>,
+    pub(crate) redactable: Option<&'a Redactable>,
+    pub(crate) tags: &'a [Tag],
+    pub(crate) sortable: bool,
+    pub(crate) prev_id: Option<&'a Id>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Qso {
     pub id: Id,
     pub station_id: Id,
+    #[serde(deserialize_with = "limits::callsign")]
     pub callsign: String,
     pub datetime: u64,
     pub freq: u64,
+    #[serde(deserialize_with = "limits::mode")]
     pub mode: String,
+    #[serde(deserialize_with = "limits::rst")]
     pub rst: String,
+    #[serde(deserialize_with = "limits::comments")]
     pub comments: String,
     pub created_at: u64,
     pub version: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    /// Extension fields, included in the id so they are covered by the
+    /// signature.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, Value>,
+    /// Present when the comments can be redacted, see [`Qso::redact`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redactable: Option<Redactable>,
+    /// Signed tags, see [`Tag`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+    /// The id starts with the creation time, see [`Qso::new_sortable`].
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub sortable: bool,
+    /// Id of the previous QSO of the station, see [`Qso::verify_chain`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_id: Option<Id>,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
     pub sig: Signature,
 }
 
 impl Qso {
+    /// Current version of the qso object.
+    pub const VERSION: u8 = version::V1;
+
+    /// Versions accepted by the qso verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0, version::V1];
+
+    /// Returns true if the qso version is supported.
+    pub fn is_supported_version(version: u8) -> bool {
+        Self::SUPPORTED_VERSIONS.contains(&version)
+    }
+
     pub fn new(qso_data: QsoData, keys: &Keypair) -> Qso {
-        let created_at = time::unix_timstamp();
-        let version: u8 = 0;
+        Self::new_with_signer(qso_data, keys).expect("keypair signing is infallible")
+    }
+
+    /// Creates a new Qso and signs the object using the given signer.
+    pub fn new_with_signer(qso_data: QsoData, signer: &dyn Signer) -> Result<Qso> {
+        Self::create(qso_data, signer, time::unix_timstamp(), None)
+    }
 
-        let id = Self::generate_id(QsoIdSrc {
+    /// Creates a new Qso whose comments can later be redacted with
+    /// [`Qso::redact`] and signs the object using the given signer.
+    pub fn new_redactable(qso_data: QsoData, signer: &dyn Signer) -> Result<Qso> {
+        let pub_key = signer.public_key()?;
+        Self::create_with_pub_key(
+            qso_data,
+            signer,
+            Some(&pub_key),
+            time::unix_timstamp(),
+            None,
+            Some(Redactable::generate()),
+            false,
+        )
+    }
+
+    /// Creates a new Qso with a time sortable id and signs the object using
+    /// the given signer. See [`Id::sortable_prefix`] for the id layout and
+    /// its tradeoffs.
+    pub fn new_sortable(qso_data: QsoData, signer: &dyn Signer) -> Result<Qso> {
+        let pub_key = signer.public_key()?;
+        Self::create_with_pub_key(
+            qso_data,
+            signer,
+            Some(&pub_key),
+            time::unix_timstamp(),
+            None,
+            None,
+            true,
+        )
+    }
+
+    /// Creates a new Qso with a proof of work of at least `difficulty` leading
+    /// zero bits in its id and signs the object using the given signer.
+    pub fn mine(qso_data: QsoData, signer: &dyn Signer, difficulty: u32) -> Result<Qso> {
+        Self::create(qso_data, signer, time::unix_timstamp(), Some(difficulty))
+    }
+
+    /// Re-signs a QSO created with a previous version using the current
+    /// version. The QSO data and creation time are preserved, the QSO must be
+    /// signed by the signer.
+    pub fn upgrade(&self, signer: &dyn Signer) -> Result<Qso> {
+        if self.version >= Self::VERSION {
+            return Ok(self.clone());
+        }
+
+        self.verify(&signer.public_key()?)
+            .context("the QSO isn't signed by the signer")?;
+
+        Self::create(
+            QsoData {
+                station_id: self.station_id.clone(),
+                callsign: self.callsign.clone(),
+                datetime: self.datetime,
+                freq: self.freq,
+                mode: self.mode.clone(),
+                rst: self.rst.clone(),
+                comments: self.comments.clone(),
+                extra: self.extra.clone(),
+                tags: self.tags.clone(),
+                prev_id: self.prev_id.clone(),
+            },
+            signer,
+            self.created_at,
+            None,
+        )
+    }
+
+    pub(crate) fn create(
+        qso_data: QsoData,
+        signer: &dyn Signer,
+        created_at: u64,
+        difficulty: Option<u32>,
+    ) -> Result<Qso> {
+        let pub_key = signer.public_key()?;
+        Self::create_with_pub_key(
+            qso_data,
+            signer,
+            Some(&pub_key),
+            created_at,
+            difficulty,
+            None,
+            false,
+        )
+    }
+
+    /// Creates the QSO with an already known signer public key, so batch
+    /// signers can skip the public key round trip for every QSO. The
+    /// signature is checked against the key if given, batch signers that
+    /// already checked the signer pass `None`.
+    pub(crate) fn create_with_pub_key(
+        mut qso_data: QsoData,
+        signer: &dyn Signer,
+        pub_key: Option<&XOnlyPublicKey>,
+        created_at: u64,
+        difficulty: Option<u32>,
+        redactable: Option<Redactable>,
+        sortable: bool,
+    ) -> Result<Qso> {
+        qso_data.callsign = Callsign::normalize(&qso_data.callsign).to_string();
+        programs::normalize_extra(&mut qso_data.extra);
+        let version = Self::VERSION;
+
+        let id_src = |nonce| QsoIdSrc {
             station_id: &qso_data.station_id,
             callsign: &qso_data.callsign,
             datetime: qso_data.datetime,
@@ -74,11 +272,29 @@ impl Qso {
             comments: &qso_data.comments,
             created_at,
             version,
-        });
+            nonce,
+            extra: &qso_data.extra,
+            redactable: redactable.as_ref(),
+            tags: &qso_data.tags,
+            sortable,
+            prev_id: qso_data.prev_id.as_ref(),
+        };
+
+        let (nonce, id) = match difficulty {
+            Some(difficulty) => {
+                let (nonce, id) =
+                    pow::mine(difficulty, |nonce| Self::compute_id(&id_src(Some(nonce))))?;
+                (Some(nonce), id)
+            }
+            None => (None, Self::compute_id(&id_src(None))),
+        };
 
-        let sig = id.sign(keys);
+        let sig = match pub_key {
+            Some(pub_key) => signer::sign_id(signer, pub_key, &id)?,
+            None => signer::sign_id_unchecked(signer, &id)?,
+        };
 
-        Self {
+        Ok(Self {
             id,
             station_id: qso_data.station_id,
             callsign: qso_data.callsign,
@@ -89,28 +305,199 @@ impl Qso {
             comments: qso_data.comments,
             created_at,
             version,
+            nonce,
+            extra: qso_data.extra,
+            redactable,
+            tags: qso_data.tags,
+            sortable,
+            prev_id: qso_data.prev_id,
             sig,
-        }
+        })
     }
 
-    fn generate_id(qso_id_src: QsoIdSrc) -> Id {
-        let json: Value = json!([
+    pub(crate) fn compute_id(qso_id_src: &QsoIdSrc) -> Id {
+        let commitment = qso_id_src
+            .redactable
+            .map(|redactable| redactable.commitment(qso_id_src.version, qso_id_src.comments));
+
+        let fields = (
             qso_id_src.station_id,
             qso_id_src.callsign,
             qso_id_src.datetime,
             qso_id_src.freq,
             qso_id_src.mode,
             qso_id_src.rst,
-            qso_id_src.comments,
+            match &commitment {
+                Some(commitment) => Comments::Commitment(commitment),
+                None => Comments::Plain(qso_id_src.comments),
+            },
             qso_id_src.created_at,
             qso_id_src.version,
-        ]);
-        let json_str = json.to_string();
-        Id::new(&json_str)
+        );
+
+        let mut id = IdBuilder::new(HashAlgorithm::for_version(qso_id_src.version), &fields);
+        if !qso_id_src.extra.is_empty() {
+            id.optional("extra", qso_id_src.extra);
+        }
+        if let Some(nonce) = qso_id_src.nonce {
+            id.optional("nonce", &nonce);
+        }
+        if let Some(prev_id) = qso_id_src.prev_id {
+            id.optional("prev_id", prev_id);
+        }
+        if qso_id_src.redactable.is_some() {
+            id.optional("redactable", &true);
+        }
+        if qso_id_src.sortable {
+            id.optional("sortable", &true);
+        }
+        if !qso_id_src.tags.is_empty() {
+            id.optional("tags", qso_id_src.tags);
+        }
+
+        let id = id.finish();
+        if qso_id_src.sortable {
+            id.into_sortable(qso_id_src.created_at)
+        } else {
+            id
+        }
     }
 
     pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
-        let id = Self::generate_id(QsoIdSrc {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Verify the object signature and validate the QSO fields with the
+    /// options.
+    pub fn verify_with_options(
+        &self,
+        station_pub_key: &XOnlyPublicKey,
+        options: &ValidationOptions,
+    ) -> Result<()> {
+        signable::verify_with(self, SECP256K1, station_pub_key, |qso| {
+            qso.validate_with_options(options)
+        })
+    }
+
+    /// Validates the QSO fields with the callsign policy of the options and
+    /// applies the optional checks, logging software should call it right
+    /// after signing to flag mistakes before publishing.
+    pub fn validate_with_options(&self, options: &ValidationOptions) -> Result<()> {
+        validate_with_policy(&self.id_src(), options.callsign_policy())?;
+        let max = &options.limits;
+        limits::check_len("callsign", &self.callsign, max.callsign)?;
+        limits::check_len("mode", &self.mode, max.mode)?;
+        limits::check_len("rst", &self.rst, max.rst)?;
+        limits::check_len("comments", &self.comments, max.comments)?;
+        limits::check_tags(&self.tags, max.tag)?;
+        if let Some((region, license_class)) = &options.band_plan {
+            bandplan::validate_frequency(self.freq, *region, license_class)?;
+        }
+        if let Some(max_skew) = options.max_clock_skew {
+            time::check_created_at(self.created_at, max_skew)?;
+        }
+        if let Some(tolerance) = options.datetime_tolerance {
+            if self.datetime > self.created_at.saturating_add(tolerance) {
+                bail!(
+                    "datetime {} later than created_at {}",
+                    self.datetime,
+                    self.created_at
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the QSO as [`Qso::validate_with_options`] does, reporting
+    /// the warnings about suspicious fields which don't make it invalid.
+    pub fn validation_report(&self, options: &ValidationOptions) -> Result<ValidationReport> {
+        self.validate_with_options(options)?;
+        Ok(ValidationReport {
+            warnings: warnings(self.freq, &self.mode),
+        })
+    }
+
+    /// Returns a copy of the QSO with the comments stripped, keeping the id
+    /// and signature of the full record. Only QSOs created with
+    /// [`Qso::new_redactable`] can be redacted.
+    pub fn redact(&self) -> Result<Qso> {
+        match &self.redactable {
+            Some(redactable @ Redactable::Salt(_)) => Ok(Qso {
+                comments: String::new(),
+                redactable: Some(Redactable::Redacted(
+                    redactable.commitment(self.version, &self.comments),
+                )),
+                ..self.clone()
+            }),
+            Some(Redactable::Redacted(_)) => Ok(self.clone()),
+            None => bail!("qso not redactable"),
+        }
+    }
+
+    /// Returns true if the comments were redacted.
+    pub fn is_redacted(&self) -> bool {
+        matches!(self.redactable, Some(Redactable::Redacted(_)))
+    }
+
+    /// Returns the grid square of the contacted station, if present in the
+    /// extension fields.
+    pub fn gridsquare(&self) -> Option<&str> {
+        self.extra.get(GRIDSQUARE).and_then(Value::as_str)
+    }
+
+    /// Returns the distance in km between the station which logged the QSO
+    /// and the contacted station, when both grid squares are known.
+    pub fn distance_km(&self, station: &Station) -> Option<f64> {
+        let from = LatLon::from_grid(station.grid()?).ok()?;
+        let to = LatLon::from_grid(self.gridsquare()?).ok()?;
+        Some(from.distance_km(&to))
+    }
+
+    /// Verify a QSO signed by a delegate key on behalf of the station.
+    ///
+    /// The delegation must be signed by the station key, issued for the QSO
+    /// station and valid for QSOs at the QSO creation time.
+    pub fn verify_delegated(
+        &self,
+        station_pub_key: &XOnlyPublicKey,
+        delegation: &Delegation,
+    ) -> Result<()> {
+        delegation.verify(station_pub_key)?;
+
+        if delegation.station_id != self.station_id {
+            bail!("delegation station mismatch");
+        }
+
+        if !delegation.authorizes(Kind::Qso, self.created_at) {
+            bail!("qso not authorized by delegation");
+        }
+
+        self.verify_signature(&delegation.delegate_pub_key)
+    }
+
+    /// Verify a log hash chained with [`Qso::prev_id`], e.g. a published
+    /// personal log. The QSOs must be in chain order starting at the first QSO
+    /// of the station, the one without `prev_id`.
+    ///
+    /// Deleted, inserted and reordered QSOs break the chain. QSOs dropped from
+    /// the end of the log can't be detected from the log alone.
+    ///
+    /// The chain may span [`Station::update`]s, the station id changes but
+    /// every QSO must verify against the station key.
+    pub fn verify_chain(qsos: &[Qso], station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        let mut prev: Option<&Qso> = None;
+        for qso in qsos {
+            qso.verify(station_pub_key)?;
+            if qso.prev_id.as_ref() != prev.map(|prev| &prev.id) {
+                bail!("qso {} breaks the chain", qso.id);
+            }
+            prev = Some(qso);
+        }
+        Ok(())
+    }
+
+    fn id_src(&self) -> QsoIdSrc<'_> {
+        QsoIdSrc {
             station_id: &self.station_id,
             callsign: &self.callsign,
             datetime: self.datetime,
@@ -120,44 +507,121 @@ impl Qso {
             comments: &self.comments,
             created_at: self.created_at,
             version: self.version,
+            nonce: self.nonce,
+            extra: &self.extra,
+            redactable: self.redactable.as_ref(),
+            tags: &self.tags,
+            sortable: self.sortable,
+            prev_id: self.prev_id.as_ref(),
+        }
+    }
+}
+
+impl Validate for Qso {
+    fn validate(&self) -> Result<()> {
+        validate(&self.id_src())
+    }
+}
+
+impl Signable for Qso {
+    const KIND: Kind = Kind::Qso;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(&self.id_src())
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+pub(crate) fn validate(id_src: &QsoIdSrc) -> Result<()> {
+    validate_with_policy(id_src, &StrictCallsign)
+}
+
+fn warnings(freq: u64, mode: &str) -> Vec<ValidationWarning> {
+    let mut warnings = vec![];
+    if !bandplan::is_plausible_mode(freq, mode) {
+        warnings.push(ValidationWarning::ImplausibleMode {
+            freq,
+            mode: mode.to_string(),
         });
+    }
+    warnings
+}
 
-        if id != self.id {
-            bail!("invalid id");
-        }
+fn validate_with_policy(id_src: &QsoIdSrc, policy: &dyn CallsignPolicy) -> Result<()> {
+    version::check_supported(id_src.version, Qso::SUPPORTED_VERSIONS)?;
+    version::check_optional_fields(
+        id_src.version,
+        id_src.nonce.is_some()
+            || !id_src.extra.is_empty()
+            || id_src.redactable.is_some()
+            || !id_src.tags.is_empty()
+            || id_src.sortable
+            || id_src.prev_id.is_some(),
+    )?;
 
-        id.verify(station_pub_key, &self.sig)?;
-        self.validate()?;
-        Ok(())
+    if id_src.sortable && id_src.nonce.is_some() {
+        bail!("sortable ids can't carry a proof of work");
     }
 
-    fn validate(&self) -> Result<()> {
-        if !crate::station::IS_CALLSIGN.with(|is_callsign| is_callsign.is_match(&self.callsign)) {
-            bail!("invalid callsign");
-        }
+    if id_src.tags.len() > MAX_TAGS {
+        bail!("more than {} tags", MAX_TAGS);
+    }
+    limits::check_tags(id_src.tags, Limits::DEFAULT.tag)?;
 
-        if self.rst.trim().is_empty() || self.rst.len() > RST_MAX_LEN {
-            bail!("invalid rst");
-        }
+    if matches!(id_src.redactable, Some(Redactable::Redacted(_))) && !id_src.comments.is_empty() {
+        bail!("redacted qso with comments");
+    }
 
-        if self.mode.trim().is_empty() || self.mode.len() > MODE_MAX_LEN {
-            bail!("invalid mode");
-        }
+    policy.validate(id_src.callsign)?;
 
-        if self.comments.len() > COMMENTS_MAX_LEN {
-            bail!("invalid comments");
-        }
+    if !text::is_valid_required(id_src.rst, RST_MAX_LEN) {
+        bail!("invalid rst");
+    }
 
-        Ok(())
+    if !text::is_valid_required(id_src.mode, MODE_MAX_LEN) {
+        bail!("invalid mode");
+    }
+
+    if !text::is_valid_multiline(id_src.comments, COMMENTS_MAX_LEN) {
+        bail!("invalid comments");
     }
+
+    programs::validate_extra(id_src.extra)?;
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use crate::bandplan::{LicenseClass, Region};
+    use crate::delegation::Delegation;
+    use crate::event::Event;
     use crate::keys::generate_keypair;
-    use crate::qso::{Qso, QsoData};
-    use crate::Station;
+    use crate::kind::Kind;
+    use crate::qso::{Qso, QsoData, GRIDSQUARE};
+    use crate::signable::Signable;
+    use crate::station::GRID;
+    use crate::time::unix_timstamp;
+    use crate::validation::{ValidationOptions, ValidationWarning};
+    use crate::{test_vectors, Id, Station, StationData};
     use codes_iso_3166::part_1::CountryCode;
+    use serde_json::json;
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_sign_verify() {
@@ -174,12 +638,11 @@ mod test {
         let qso = Qso::new(
             QsoData {
                 station_id: station.id.clone(),
-                callsign: "LW3DZR".to_string(),
                 freq: 1704141426,
                 datetime: 14250300,
                 mode: "CW".to_string(),
                 rst: "599".to_string(),
-                comments: "73".to_string(),
+                ..test_vectors::qso_data()
             },
             &keys,
         );
@@ -189,4 +652,501 @@ mod test {
 
         qso.verify(&station.pub_key).unwrap();
     }
+
+    #[test]
+    fn test_normalized_callsign() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso = Qso::new(
+            QsoData {
+                station_id: station.id.clone(),
+                callsign: " lw3dzr/p".to_string(),
+                datetime: 1704141426,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                ..test_vectors::qso_data()
+            },
+            &keys,
+        );
+
+        assert_eq!(qso.callsign, "LW3DZR/P");
+        qso.verify(&station.pub_key).unwrap();
+    }
+
+    #[test]
+    fn test_extra() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let mut extra = BTreeMap::new();
+        extra.insert("sota_ref".to_string(), json!("LU/BA-001"));
+        extra.insert("tx_pwr".to_string(), json!(100));
+
+        let qso = Qso::new(
+            QsoData {
+                station_id: station.id.clone(),
+                datetime: 1704141426,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                extra,
+                ..test_vectors::qso_data()
+            },
+            &keys,
+        );
+
+        let qso_str = serde_json::to_string(&qso).unwrap();
+        let mut qso_dese: Qso = serde_json::from_str(&qso_str).unwrap();
+        qso_dese.verify(&station.pub_key).unwrap();
+
+        qso_dese.extra.remove("tx_pwr");
+        assert!(qso_dese.verify(&station.pub_key).is_err());
+
+        let mut qso_data = QsoData {
+            station_id: station.id.clone(),
+            datetime: 1704141426,
+            mode: "CW".to_string(),
+            rst: "599".to_string(),
+            extra: BTreeMap::from([("my_pota_ref".to_string(), json!("ar-0001"))]),
+            ..test_vectors::qso_data()
+        };
+        let qso = Qso::new(qso_data.clone(), &keys);
+        assert_eq!(qso.extra["my_pota_ref"], "AR-0001");
+        qso.verify(&station.pub_key).unwrap();
+
+        qso_data
+            .extra
+            .insert("my_pota_ref".to_string(), json!("AR-1"));
+        let qso = Qso::new(qso_data, &keys);
+        assert!(qso.verify(&station.pub_key).is_err());
+    }
+
+    #[test]
+    fn test_distance() {
+        let keys = generate_keypair();
+
+        let mut station_extra = BTreeMap::new();
+        station_extra.insert(GRID.to_string(), json!("GF05tk"));
+
+        let station = Station::from_data(
+            StationData {
+                callsign: "LU4EV".to_string(),
+                operator: "Radio Club Caseros".to_string(),
+                country: CountryCode::AR.into(),
+                extra: station_extra,
+                profile: None,
+                qsl_route: None,
+            },
+            &keys,
+        )
+        .unwrap();
+
+        let qso_data = |extra| QsoData {
+            station_id: station.id.clone(),
+            callsign: "CX2ABC".to_string(),
+            freq: 144100000,
+            datetime: 1704141426,
+            extra,
+            ..test_vectors::qso_data()
+        };
+
+        let qso = Qso::new(qso_data(BTreeMap::new()), &keys);
+        assert_eq!(qso.distance_km(&station), None);
+
+        let mut extra = BTreeMap::new();
+        extra.insert(GRIDSQUARE.to_string(), json!("GF15ak"));
+        let qso = Qso::new(qso_data(extra), &keys);
+        assert_eq!(qso.gridsquare(), Some("GF15ak"));
+
+        let distance = qso.distance_km(&station).unwrap();
+        assert!((distance - 38.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let mut qso = Qso::new(
+            QsoData {
+                station_id: station.id.clone(),
+                datetime: 1704141426,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                ..test_vectors::qso_data()
+            },
+            &keys,
+        );
+
+        qso.version = 0;
+        assert!(qso.verify(&station.pub_key).is_err());
+        assert!(qso.upgrade(&keys).is_err());
+
+        qso.id = qso.generate_id();
+        qso.sig = qso.id.sign(&keys);
+        qso.verify(&station.pub_key).unwrap();
+        assert!(qso.upgrade(&generate_keypair()).is_err());
+
+        let upgraded = qso.upgrade(&keys).unwrap();
+        upgraded.verify(&station.pub_key).unwrap();
+        assert_eq!(upgraded.version, Qso::VERSION);
+        assert!(!Qso::is_supported_version(2));
+    }
+
+    #[test]
+    fn test_pow() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso = Qso::mine(
+            QsoData {
+                station_id: station.id.clone(),
+                datetime: 1704141426,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                ..test_vectors::qso_data()
+            },
+            &keys,
+            8,
+        )
+        .unwrap();
+
+        qso.verify(&station.pub_key).unwrap();
+        Event::Qso(qso.clone()).check_pow(8).unwrap();
+
+        let qso_str = serde_json::to_string(&qso).unwrap();
+        let mut qso_dese: Qso = serde_json::from_str(&qso_str).unwrap();
+        qso_dese.verify(&station.pub_key).unwrap();
+
+        qso_dese.nonce = qso_dese.nonce.map(|nonce| nonce.wrapping_add(1));
+        assert!(qso_dese.verify(&station.pub_key).is_err());
+    }
+
+    #[test]
+    fn test_delegated() {
+        let station_keys = generate_keypair();
+        let device_keys = generate_keypair();
+
+        let station = Station::new(
+            &station_keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let now = unix_timstamp();
+        let delegation = Delegation::new(
+            station.id.clone(),
+            &station_keys,
+            device_keys.x_only_public_key().0,
+            vec![Kind::Qso],
+            now - 60,
+            now + 3600,
+        )
+        .unwrap();
+
+        let qso = Qso::new(
+            QsoData {
+                station_id: station.id.clone(),
+                freq: 1704141426,
+                datetime: 14250300,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                ..test_vectors::qso_data()
+            },
+            &device_keys,
+        );
+
+        qso.verify_delegated(&station.pub_key, &delegation).unwrap();
+        assert!(qso.verify(&station.pub_key).is_err());
+
+        let other_keys = generate_keypair();
+        assert!(qso
+            .verify_delegated(&other_keys.x_only_public_key().0, &delegation)
+            .is_err());
+    }
+
+    #[test]
+    fn test_redact() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso_data = QsoData {
+            station_id: station.id.clone(),
+            datetime: 1704141426,
+            mode: "CW".to_string(),
+            rst: "599".to_string(),
+            comments: "op John, ph 555-0100".to_string(),
+            ..test_vectors::qso_data()
+        };
+
+        let qso = Qso::new_redactable(qso_data.clone(), &keys).unwrap();
+        qso.verify(&station.pub_key).unwrap();
+        assert!(!qso.is_redacted());
+
+        let redacted = qso.redact().unwrap();
+        assert!(redacted.is_redacted());
+        assert_eq!(redacted.comments, "");
+        assert_eq!(redacted.id, qso.id);
+
+        let qso_str = serde_json::to_string(&redacted).unwrap();
+        assert!(!qso_str.contains("John"));
+        let redacted_dese: Qso = serde_json::from_str(&qso_str).unwrap();
+        redacted_dese.verify(&station.pub_key).unwrap();
+
+        let mut forged = qso.clone();
+        forged.comments = "73".to_string();
+        assert!(forged.verify(&station.pub_key).is_err());
+
+        let mut forged = redacted.clone();
+        forged.comments = "73".to_string();
+        assert!(forged.verify(&station.pub_key).is_err());
+
+        assert!(Qso::new(qso_data, &keys).redact().is_err());
+    }
+
+    #[test]
+    fn test_sortable() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso_data = QsoData {
+            station_id: station.id.clone(),
+            datetime: 1704141426,
+            mode: "CW".to_string(),
+            rst: "599".to_string(),
+            ..test_vectors::qso_data()
+        };
+
+        let qso = Qso::new_sortable(qso_data.clone(), &keys).unwrap();
+        qso.verify(&station.pub_key).unwrap();
+        assert!(qso.sortable);
+        assert_eq!(
+            qso.id.as_bytes()[..Id::SORTABLE_PREFIX_LEN],
+            Id::sortable_prefix(qso.created_at)
+        );
+        assert_ne!(qso.id, Qso::new(qso_data, &keys).id);
+
+        let qso_str = serde_json::to_string(&qso).unwrap();
+        assert!(qso_str.contains("\"sortable\":true"));
+        let qso_dese: Qso = serde_json::from_str(&qso_str).unwrap();
+        qso_dese.verify(&station.pub_key).unwrap();
+        let mut forged = qso.clone();
+        forged.sortable = false;
+        assert!(forged.verify(&station.pub_key).is_err());
+    }
+
+    #[test]
+    fn test_chain() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let mut log: Vec<Qso> = vec![];
+        for i in 0..4 {
+            let qso = Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    callsign: format!("LW{}DZR", i),
+                    datetime: 1704141426 + i,
+                    mode: "CW".to_string(),
+                    rst: "599".to_string(),
+                    prev_id: log.last().map(|prev| prev.id.clone()),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            );
+            log.push(qso);
+        }
+
+        Qso::verify_chain(&log, &station.pub_key).unwrap();
+        Qso::verify_chain(&log[..3], &station.pub_key).unwrap();
+
+        let qso_str = serde_json::to_string(&log[1]).unwrap();
+        assert!(qso_str.contains(&format!("\"prev_id\":\"{}\"", log[0].id)));
+        let mut forged: Qso = serde_json::from_str(&qso_str).unwrap();
+        forged.prev_id = None;
+        assert!(forged.verify(&station.pub_key).is_err());
+
+        let mut deleted = log.clone();
+        deleted.remove(2);
+        assert!(Qso::verify_chain(&deleted, &station.pub_key).is_err());
+        assert!(Qso::verify_chain(&log[1..], &station.pub_key).is_err());
+
+        let mut reordered = log.clone();
+        reordered.swap(1, 2);
+        assert!(Qso::verify_chain(&reordered, &station.pub_key).is_err());
+
+        // The chain goes on after a station update, which changes the id.
+        let updated = station
+            .update(
+                StationData {
+                    callsign: "LU4EV".to_string(),
+                    operator: "Gabriel Velo".to_string(),
+                    country: CountryCode::AR.into(),
+                    extra: BTreeMap::new(),
+                    profile: None,
+                    qsl_route: None,
+                },
+                &keys,
+            )
+            .unwrap();
+        assert_ne!(updated.id, station.id);
+        log.push(Qso::new(
+            QsoData {
+                station_id: updated.id.clone(),
+                datetime: 1704141430,
+                prev_id: log.last().map(|prev| prev.id.clone()),
+                ..test_vectors::qso_data()
+            },
+            &keys,
+        ));
+        Qso::verify_chain(&log, &updated.pub_key).unwrap();
+        assert!(Qso::verify_chain(&log, &generate_keypair().x_only_public_key().0).is_err());
+    }
+
+    #[test]
+    fn test_band_plan() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso_data = |freq| QsoData {
+            station_id: station.id.clone(),
+            freq,
+            datetime: 1704141426,
+            ..test_vectors::qso_data()
+        };
+
+        let strict = ValidationOptions::default().with_band_plan(Region::R2, LicenseClass::Full);
+
+        let qso = Qso::new(qso_data(7250000), &keys);
+        qso.verify_with_options(&station.pub_key, &strict).unwrap();
+
+        let qso = Qso::new(qso_data(7400000), &keys);
+        qso.verify(&station.pub_key).unwrap();
+        assert!(qso.validate_with_options(&strict).is_err());
+        assert!(qso.verify_with_options(&station.pub_key, &strict).is_err());
+        qso.verify_with_options(&station.pub_key, &ValidationOptions::lenient())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_validation_report() {
+        let keys = generate_keypair();
+        let station_id = Id::new("station");
+        let qso_data = |freq, mode: &str| QsoData {
+            station_id: station_id.clone(),
+            freq,
+            datetime: 1704141426,
+            mode: mode.to_string(),
+            ..test_vectors::qso_data()
+        };
+
+        let options = ValidationOptions::lenient();
+        let qso = Qso::new(qso_data(14_074_000, "FT8"), &keys);
+        assert!(qso.validation_report(&options).unwrap().is_clean());
+
+        let data = qso_data(14_250_000, "FT8");
+        assert_eq!(
+            data.warnings(),
+            vec![ValidationWarning::ImplausibleMode {
+                freq: 14_250_000,
+                mode: "FT8".to_string()
+            }]
+        );
+        let qso = Qso::new(data, &keys);
+        let report = qso.validation_report(&options).unwrap();
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(
+            report.warnings[0].to_string(),
+            "mode FT8 unlikely on 14250000 Hz"
+        );
+
+        let strict = ValidationOptions::default().with_band_plan(Region::R2, LicenseClass::Full);
+        let qso = Qso::new(qso_data(7_400_000, "SSB"), &keys);
+        assert!(qso.validation_report(&strict).is_err());
+    }
+
+    #[test]
+    fn test_datetime_tolerance() {
+        let keys = generate_keypair();
+        let options = ValidationOptions::default().with_datetime_tolerance(300);
+        let qso_data = |datetime| QsoData {
+            station_id: Id::new("station"),
+            freq: 14_074_000,
+            datetime,
+            mode: "FT8".to_string(),
+            rst: "-10".to_string(),
+            comments: String::new(),
+            ..test_vectors::qso_data()
+        };
+
+        let qso = Qso::new(qso_data(1704141426), &keys);
+        qso.validate_with_options(&options).unwrap();
+
+        let qso = Qso::new(qso_data(unix_timstamp() + 60), &keys);
+        qso.validate_with_options(&options).unwrap();
+
+        // The frequency logged as the datetime.
+        let qso = Qso::new(qso_data(14_074_000_000), &keys);
+        assert!(qso.validate_with_options(&options).is_err());
+        qso.validate_with_options(&ValidationOptions::lenient())
+            .unwrap();
+    }
 }
@@ -0,0 +1,182 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Text queries parsed into a [`Filter`], for command lines and search
+//! boxes, e.g. `band:20m mode:CW call:LU4EV since:2024-01-01`.
+//!
+//! A query is a list of terms separated by whitespace, either `key:value` or
+//! a bare callsign. Comma separated values match any of them, as do repeated
+//! keys, while terms with different keys must all match. The keys are:
+//!
+//! - `kind`: the kind name, e.g. `qso`.
+//! - `id`: the event id.
+//! - `station`: the id of the signing station.
+//! - `call`: the callsign or a pattern such as `LU4*`, see
+//!   [`CallsignPattern`](crate::CallsignPattern) and
+//!   [`Event::callsign`](crate::event::Event::callsign).
+//! - `band`: the band name, e.g. `20m`.
+//! - `mode`: the mode, in any case.
+//! - `grid`: the grid square or its start, e.g. `GF` for the grid field, see
+//!   [`Event::grid`](crate::event::Event::grid).
+//! - `ref`: the id of a referenced event, see [`Filter::references`].
+//! - `since` and `until`: the creation time, as a `2024-01-01` date, an
+//!   RFC 3339 time or a unix time. A date in `until` includes the whole day.
+//! - `limit`: the maximum number of events.
+
+use crate::event::Filter;
+use crate::{geo, Tag};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, NaiveDate};
+use std::str::FromStr;
+
+/// Parses a text query, see the [module docs](self) for the syntax.
+pub fn parse(query: &str) -> Result<Filter> {
+    let mut filter = Filter::new();
+
+    for term in query.split_whitespace() {
+        let Some((key, values)) = term.split_once(':') else {
+            filter.callsigns.push(term.parse()?);
+            continue;
+        };
+
+        for value in values.split(',') {
+            if value.is_empty() {
+                bail!("missing value in {}", term);
+            }
+            match key.to_ascii_lowercase().as_str() {
+                "kind" => filter.kinds.push(value.parse()?),
+                "id" => filter.ids.push(value.parse()?),
+                "station" => filter.station_ids.push(value.parse()?),
+                "call" => filter.callsigns.push(value.parse()?),
+                "band" => filter.bands.push(value.to_ascii_lowercase().parse()?),
+                "mode" => filter.modes.push(value.to_ascii_uppercase()),
+                "grid" if geo::is_valid_grid(value) => {
+                    filter.grids.push(value.to_ascii_uppercase())
+                }
+                "grid" => bail!("invalid grid square {}", value),
+                "ref" => filter.tags.push(Tag::Ref(value.parse()?)),
+                "since" => single(&mut filter.since, key, time(value, false)?)?,
+                "until" => single(&mut filter.until, key, time(value, true)?)?,
+                "limit" => single(
+                    &mut filter.limit,
+                    key,
+                    value
+                        .parse()
+                        .map_err(|_| anyhow!("invalid limit {}", value))?,
+                )?,
+                _ => bail!("unknown key {}", key),
+            }
+        }
+    }
+
+    Ok(filter)
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    /// Parses a text query, see [`crate::query`].
+    fn from_str(s: &str) -> Result<Self> {
+        parse(s)
+    }
+}
+
+fn single<T>(slot: &mut Option<T>, key: &str, value: T) -> Result<()> {
+    if slot.replace(value).is_some() {
+        bail!("{} takes a single value", key);
+    }
+    Ok(())
+}
+
+/// Parses a unix time, a date or an RFC 3339 time. Dates are the start of
+/// the day, or its last second with `end_of_day`.
+fn time(value: &str, end_of_day: bool) -> Result<u64> {
+    if value.bytes().all(|c| c.is_ascii_digit()) {
+        return Ok(value.parse()?);
+    }
+
+    let time = if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = match end_of_day {
+            true => date.and_hms_opt(23, 59, 59),
+            false => date.and_hms_opt(0, 0, 0),
+        };
+        time.map(|time| time.and_utc().timestamp())
+    } else {
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|time| time.timestamp())
+    };
+
+    time.and_then(|time| u64::try_from(time).ok())
+        .ok_or_else(|| anyhow!("invalid time {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::Band;
+    use crate::event::{Event, Filter};
+    use crate::query::parse;
+    use crate::{test_vectors, Callsign, Kind, Tag};
+
+    #[test]
+    fn test_query() {
+        let filter = parse("band:20m mode:ssb,cw call:lw3dzr since:2024-01-01").unwrap();
+        assert_eq!(
+            filter,
+            Filter::new()
+                .bands([Band::M20])
+                .modes(["SSB".to_string(), "CW".to_string()])
+                .callsigns([Callsign::normalize("LW3DZR")])
+                .since(1_704_067_200)
+        );
+        let qso = Event::Qso(test_vectors::qso());
+        assert!(filter.matches(&qso));
+        assert!(!parse("band:40m").unwrap().matches(&qso));
+        assert!(!parse("LU4EV").unwrap().matches(&qso));
+        assert!(parse("lu4ev LW3DZR").unwrap().matches(&qso));
+        assert!(parse("call:LW3*").unwrap().matches(&qso));
+        assert!(parse("lu4* lw?dzr").unwrap().matches(&qso));
+
+        let filter = parse("grid:gf,FN20").unwrap();
+        assert_eq!(filter.grids, vec!["GF".to_string(), "FN20".to_string()]);
+
+        let id = test_vectors::qso().id;
+        let filter: Filter = format!("kind:qso,station  ref:{} until:2024-01-01 limit:10", id)
+            .parse()
+            .unwrap();
+        assert_eq!(filter.kinds, vec![Kind::Qso, Kind::Station]);
+        assert_eq!(filter.tags, vec![Tag::Ref(id)]);
+        assert_eq!(filter.until, Some(1_704_153_599));
+        assert_eq!(filter.limit, Some(10));
+        assert_eq!(
+            parse("since:2024-01-01T03:00:00-03:00").unwrap().since,
+            Some(1_704_088_800)
+        );
+        assert_eq!(parse("").unwrap(), Filter::new());
+
+        for invalid in [
+            "band:21m",
+            "kind:qsl",
+            "call:LU[4]*",
+            "mode:",
+            "grid:GF0",
+            "qth:GF05",
+            "since:yesterday",
+            "since:2024-01-01 since:2024-02-01",
+            "limit:-1",
+            "id:abc",
+        ] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
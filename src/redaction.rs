@@ -0,0 +1,56 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::Id;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Length in bytes of the commitment salt.
+pub const SALT_LEN: usize = 16;
+
+/// Redaction state of the comments of a QSO.
+///
+/// The id of a redactable QSO commits to a salted hash of the comments
+/// instead of the comments themselves, so the comments can be stripped from
+/// published copies without breaking the signature. The salt keeps short
+/// comments from being guessed from the commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Redactable {
+    /// The full record, carrying the salt of the commitment.
+    Salt(
+        #[serde(with = "hex")]
+        #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Salt"))]
+        [u8; SALT_LEN],
+    ),
+    /// The comments were stripped, only their commitment is left.
+    Redacted(Id),
+}
+
+impl Redactable {
+    /// Generates a random salt.
+    pub(crate) fn generate() -> Self {
+        let mut salt = [0; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Redactable::Salt(salt)
+    }
+
+    /// Returns the commitment to the comments of a QSO of the version.
+    pub(crate) fn commitment(&self, version: u8, comments: &str) -> Id {
+        match self {
+            Redactable::Salt(salt) => Id::hash(version, &(hex::encode(salt), comments)),
+            Redactable::Redacted(commitment) => commitment.clone(),
+        }
+    }
+}
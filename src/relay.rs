@@ -0,0 +1,421 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::event::{Event, Filter};
+use crate::replaceable::latest_of;
+use crate::{Id, RelayList};
+use anyhow::{bail, Result};
+use secp256k1::XOnlyPublicKey;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Consecutive failures after which a relay is considered unhealthy.
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// A relay storing and serving events.
+///
+/// The trait abstracts the transport, implementations may talk to a remote
+/// relay over a network connection or keep the events in memory.
+pub trait Relay {
+    /// Returns the relay url.
+    fn url(&self) -> &str;
+
+    /// Publishes an event to the relay.
+    fn publish(&self, event: &Event) -> Result<()>;
+
+    /// Returns the events matching the filter.
+    fn query(&self, filter: &Filter) -> Result<Vec<Event>>;
+
+    /// Returns the number of events matching the filter, at most the filter
+    /// limit. Relays should override it to count without transferring the
+    /// events.
+    fn count(&self, filter: &Filter) -> Result<u64> {
+        Ok(self.query(filter)?.len() as u64)
+    }
+}
+
+/// Request statistics of a relay.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelayHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+impl RelayHealth {
+    /// Returns true if the relay didn't fail too many times in a row.
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures < MAX_CONSECUTIVE_FAILURES
+    }
+
+    fn record<T>(&mut self, result: &Result<T>) {
+        match result {
+            Ok(_) => {
+                self.successes += 1;
+                self.consecutive_failures = 0;
+            }
+            Err(err) => {
+                self.failures += 1;
+                self.consecutive_failures += 1;
+                self.last_error = Some(err.to_string());
+            }
+        }
+    }
+}
+
+struct PoolRelay {
+    relay: Box<dyn Relay>,
+    health: RelayHealth,
+}
+
+/// Publishes and queries events across several relays.
+///
+/// The pool follows the outbox model: events signed by a station are
+/// published to the relays the station advertises, and queries for the events
+/// of a station are sent to those relays. Stations without known relays use
+/// every relay of the pool. Unhealthy relays are skipped while there are
+/// healthy ones. [`RelayPool::gossip`] copies events between the relays so
+/// each event reaches every outbox relay of its station.
+#[derive(Default)]
+pub struct RelayPool {
+    relays: Vec<PoolRelay>,
+    outboxes: BTreeMap<Id, Vec<String>>,
+    relay_lists: BTreeMap<Id, RelayList>,
+}
+
+impl RelayPool {
+    /// Creates an empty RelayPool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a relay to the pool.
+    pub fn add_relay(&mut self, relay: Box<dyn Relay>) {
+        self.relays.push(PoolRelay {
+            relay,
+            health: RelayHealth::default(),
+        });
+    }
+
+    /// Sets the relays a station publishes its events to.
+    pub fn set_outbox(&mut self, station_id: Id, urls: Vec<String>) {
+        self.outboxes.insert(station_id, urls);
+    }
+
+    /// Verifies the relay list against the station public key and uses it as
+    /// the station outbox, unless a newer list of the station was already
+    /// loaded, see [`crate::latest_of`]. Returns true if the list was used.
+    pub fn load_relay_list(
+        &mut self,
+        relay_list: &RelayList,
+        station_pub_key: &XOnlyPublicKey,
+    ) -> Result<bool> {
+        relay_list.verify(station_pub_key)?;
+
+        if let Some(loaded) = self.relay_lists.get(&relay_list.station_id) {
+            if latest_of([loaded, relay_list]) != Some(relay_list) {
+                return Ok(false);
+            }
+        }
+
+        self.set_outbox(relay_list.station_id.clone(), relay_list.urls.clone());
+        self.relay_lists
+            .insert(relay_list.station_id.clone(), relay_list.clone());
+        Ok(true)
+    }
+
+    /// Returns the health of the relay, if it is part of the pool.
+    pub fn health(&self, url: &str) -> Option<&RelayHealth> {
+        self.relays
+            .iter()
+            .find(|pool_relay| pool_relay.relay.url() == url)
+            .map(|pool_relay| &pool_relay.health)
+    }
+
+    /// Publishes the event to the outbox relays of its station, returns the
+    /// urls of the relays which accepted it.
+    pub fn publish(&mut self, event: &Event) -> Result<Vec<String>> {
+        let targets = self.targets(std::slice::from_ref(event.station_id()));
+        let mut accepted = vec![];
+
+        for index in targets {
+            let pool_relay = &mut self.relays[index];
+            let result = pool_relay.relay.publish(event);
+            pool_relay.health.record(&result);
+            if result.is_ok() {
+                accepted.push(pool_relay.relay.url().to_string());
+            }
+        }
+
+        if accepted.is_empty() {
+            bail!("no relay accepted the event");
+        }
+
+        Ok(accepted)
+    }
+
+    /// Queries the relays and merges the results, dropping duplicated events.
+    ///
+    /// Fails only if every queried relay failed.
+    pub fn query(&mut self, filter: &Filter) -> Result<Vec<Event>> {
+        let targets = self.targets(&filter.station_ids);
+        let mut seen = BTreeSet::new();
+        let mut events = vec![];
+        let mut succeeded = false;
+
+        for index in targets {
+            let pool_relay = &mut self.relays[index];
+            let result = pool_relay.relay.query(filter);
+            pool_relay.health.record(&result);
+
+            let Ok(relay_events) = result else {
+                continue;
+            };
+
+            succeeded = true;
+            for event in relay_events {
+                if filter.matches(&event) && seen.insert(event.id().clone()) {
+                    events.push(event);
+                }
+            }
+        }
+
+        if !succeeded {
+            bail!("no relay answered the query");
+        }
+
+        if let Some(limit) = filter.limit {
+            events.truncate(limit);
+        }
+
+        Ok(events)
+    }
+
+    /// Spreads the events matching the filter between the relays of the
+    /// pool: every event found on a relay is published to the outbox relays
+    /// of its station which didn't return it. Returns the number of
+    /// deliveries.
+    ///
+    /// Fails only if every queried relay failed.
+    pub fn gossip(&mut self, filter: &Filter) -> Result<usize> {
+        let mut holders: BTreeMap<Id, (Event, BTreeSet<usize>)> = BTreeMap::new();
+        let mut succeeded = false;
+
+        for index in self.targets(&filter.station_ids) {
+            let pool_relay = &mut self.relays[index];
+            let result = pool_relay.relay.query(filter);
+            pool_relay.health.record(&result);
+
+            let Ok(relay_events) = result else {
+                continue;
+            };
+
+            succeeded = true;
+            for event in relay_events {
+                if filter.matches(&event) {
+                    holders
+                        .entry(event.id().clone())
+                        .or_insert_with(|| (event, BTreeSet::new()))
+                        .1
+                        .insert(index);
+                }
+            }
+        }
+
+        if !succeeded {
+            bail!("no relay answered the query");
+        }
+
+        let mut delivered = 0;
+        for (event, holders) in holders.into_values() {
+            for index in self.targets(std::slice::from_ref(event.station_id())) {
+                if holders.contains(&index) {
+                    continue;
+                }
+                let pool_relay = &mut self.relays[index];
+                let result = pool_relay.relay.publish(&event);
+                pool_relay.health.record(&result);
+                if result.is_ok() {
+                    delivered += 1;
+                }
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Returns the indexes of the relays to contact for the stations.
+    fn targets(&self, station_ids: &[Id]) -> Vec<usize> {
+        // Stations without a known outbox are searched everywhere.
+        let outboxes: Option<Vec<&Vec<String>>> = station_ids
+            .iter()
+            .map(|station_id| self.outboxes.get(station_id))
+            .collect();
+
+        let candidates: Vec<usize> = match outboxes {
+            Some(outboxes) if !outboxes.is_empty() => (0..self.relays.len())
+                .filter(|index| {
+                    let url = self.relays[*index].relay.url();
+                    outboxes
+                        .iter()
+                        .flat_map(|urls| urls.iter())
+                        .any(|u| u == url)
+                })
+                .collect(),
+            _ => (0..self.relays.len()).collect(),
+        };
+
+        let healthy: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|index| self.relays[*index].health.is_healthy())
+            .collect();
+
+        if healthy.is_empty() {
+            candidates
+        } else {
+            healthy
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{Event, Filter};
+    use crate::keys::generate_keypair;
+    use crate::relay::{Relay, RelayPool};
+    use crate::{RelayList, Station};
+    use anyhow::{bail, Result};
+    use codes_iso_3166::part_1::CountryCode;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone)]
+    struct MockRelay {
+        url: String,
+        down: bool,
+        events: Rc<RefCell<Vec<Event>>>,
+    }
+
+    impl MockRelay {
+        fn new(url: &str, down: bool) -> Self {
+            Self {
+                url: url.to_string(),
+                down,
+                events: Rc::new(RefCell::new(vec![])),
+            }
+        }
+    }
+
+    impl Relay for MockRelay {
+        fn url(&self) -> &str {
+            &self.url
+        }
+
+        fn publish(&self, event: &Event) -> Result<()> {
+            if self.down {
+                bail!("connection refused");
+            }
+            self.events.borrow_mut().push(event.clone());
+            Ok(())
+        }
+
+        fn query(&self, filter: &Filter) -> Result<Vec<Event>> {
+            if self.down {
+                bail!("connection refused");
+            }
+            Ok(self
+                .events
+                .borrow()
+                .iter()
+                .filter(|event| filter.matches(event))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn station(callsign: &str) -> Station {
+        Station::new(
+            &generate_keypair(),
+            callsign.to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_relay_pool() {
+        let relay_a = MockRelay::new("wss://a.example", false);
+        let relay_b = MockRelay::new("wss://b.example", false);
+        let relay_c = MockRelay::new("wss://c.example", true);
+
+        let mut pool = RelayPool::new();
+        pool.add_relay(Box::new(relay_a.clone()));
+        pool.add_relay(Box::new(relay_b.clone()));
+        pool.add_relay(Box::new(relay_c.clone()));
+
+        let lu4ev_keys = generate_keypair();
+        let lu4ev = Station::new(
+            &lu4ev_keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        let lw3dzr = station("LW3DZR");
+
+        let relay_list = RelayList::new(
+            lu4ev.id.clone(),
+            &lu4ev_keys,
+            vec!["wss://b.example".to_string()],
+        )
+        .unwrap();
+        let update = relay_list
+            .update(&lu4ev_keys, vec!["wss://a.example".to_string()])
+            .unwrap();
+        assert!(pool.load_relay_list(&relay_list, &lw3dzr.pub_key).is_err());
+        assert!(pool.load_relay_list(&update, &lu4ev.pub_key).unwrap());
+        // The older list doesn't replace the update.
+        assert!(!pool.load_relay_list(&relay_list, &lu4ev.pub_key).unwrap());
+
+        let accepted = pool.publish(&Event::Station(lu4ev.clone())).unwrap();
+        assert_eq!(accepted, vec!["wss://a.example"]);
+        assert!(relay_b.events.borrow().is_empty());
+
+        let accepted = pool.publish(&Event::Station(lw3dzr.clone())).unwrap();
+        assert_eq!(accepted, vec!["wss://a.example", "wss://b.example"]);
+
+        let health = pool.health("wss://c.example").unwrap();
+        assert_eq!(health.failures, 1);
+        assert!(health.is_healthy());
+
+        let events = pool.query(&Filter::new()).unwrap();
+        assert_eq!(events.len(), 2);
+
+        let events = pool
+            .query(&Filter::new().station_ids([lu4ev.id.clone()]))
+            .unwrap();
+        assert_eq!(events, vec![Event::Station(lu4ev)]);
+
+        pool.query(&Filter::new()).unwrap();
+        assert!(!pool.health("wss://c.example").unwrap().is_healthy());
+
+        // An event only relay b has is gossiped to relay a, once.
+        let lu1aa = Event::Station(station("LU1AA"));
+        relay_b.publish(&lu1aa).unwrap();
+        assert_eq!(pool.gossip(&Filter::new()).unwrap(), 1);
+        assert!(relay_a.events.borrow().contains(&lu1aa));
+        assert_eq!(pool.gossip(&Filter::new()).unwrap(), 0);
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::hash::HashAlgorithm;
+use crate::id::IdBuilder;
+use crate::replaceable::{self, Replaceable};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{version, Id, Kind};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+const URLS_MAX: usize = 32;
+const URL_MAX_LEN: usize = 256;
+
+/// The relays carrying the events of a station, advertised by the station.
+///
+/// Clients fetch the events of a station, e.g. its QSOs for confirmation
+/// matching, from the relays of its latest relay list.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RelayList {
+    pub id: Id,
+    pub station_id: Id,
+    pub urls: Vec<String>,
+    pub created_at: u64,
+    /// Number of updates of the relay list, see [`RelayList::update`].
+    #[serde(default, skip_serializing_if = "replaceable::is_zero")]
+    pub seq: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl RelayList {
+    /// Current version of the relay list object.
+    pub const VERSION: u8 = version::V1;
+
+    /// Versions accepted by the relay list verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0, version::V1];
+
+    /// Creates a new RelayList and signs the object using the station signer.
+    pub fn new(station_id: Id, signer: &dyn Signer, urls: Vec<String>) -> Result<Self> {
+        Self::create(station_id, signer, urls, 0)
+    }
+
+    /// Replaces the relay list with a new list of relays. The update wins
+    /// over the current list in [`crate::latest_of`].
+    pub fn update(&self, signer: &dyn Signer, urls: Vec<String>) -> Result<Self> {
+        Self::create(self.station_id.clone(), signer, urls, self.seq + 1)
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    fn create(station_id: Id, signer: &dyn Signer, urls: Vec<String>, seq: u64) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(&station_id, &urls, created_at, seq, Self::VERSION);
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let relay_list = Self {
+            id,
+            station_id,
+            urls,
+            created_at,
+            seq,
+            version: Self::VERSION,
+            sig,
+        };
+
+        relay_list.validate()?;
+
+        Ok(relay_list)
+    }
+
+    fn compute_id(station_id: &Id, urls: &[String], created_at: u64, seq: u64, version: u8) -> Id {
+        let mut id = IdBuilder::new(
+            HashAlgorithm::for_version(version),
+            &(station_id, urls, created_at, version),
+        );
+        if seq != 0 {
+            id.optional("seq", &seq);
+        }
+
+        id.finish()
+    }
+}
+
+impl Validate for RelayList {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+        version::check_optional_fields(self.version, self.seq != 0)?;
+
+        if self.urls.is_empty() || self.urls.len() > URLS_MAX {
+            bail!("invalid relay count");
+        }
+
+        for url in &self.urls {
+            if url.len() > URL_MAX_LEN
+                || !["wss://", "ws://", "https://", "http://"]
+                    .iter()
+                    .any(|scheme| url.starts_with(scheme))
+            {
+                bail!("invalid relay url");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for RelayList {
+    const KIND: Kind = Kind::RelayList;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.urls,
+            self.created_at,
+            self.seq,
+            self.version,
+        )
+    }
+}
+
+impl Replaceable for RelayList {
+    fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::relay_list::RelayList;
+    use crate::replaceable::latest_of;
+    use crate::Station;
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_relay_list() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let relay_list = RelayList::new(
+            station.id.clone(),
+            &keys,
+            vec!["wss://relay.example".to_string()],
+        )
+        .unwrap();
+
+        let json_str = serde_json::to_string(&relay_list).unwrap();
+        let mut relay_list_dese: RelayList = serde_json::from_str(&json_str).unwrap();
+        relay_list_dese.verify(&station.pub_key).unwrap();
+
+        let update = relay_list
+            .update(&keys, vec!["wss://other.example".to_string()])
+            .unwrap();
+        update.verify(&station.pub_key).unwrap();
+        assert_eq!(update.seq, 1);
+        assert_eq!(latest_of([&update, &relay_list]), Some(&update));
+
+        relay_list_dese.urls.push("wss://other.example".to_string());
+        assert!(relay_list_dese.verify(&station.pub_key).is_err());
+
+        assert!(RelayList::new(station.id.clone(), &keys, vec![]).is_err());
+        assert!(RelayList::new(station.id, &keys, vec!["ftp://relay".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,410 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::Band;
+use crate::callsign::{Callsign, CallsignPolicy, ItuCallsign};
+use crate::limits::{self, Limits};
+use crate::qso::MODE_MAX_LEN;
+use crate::replaceable::{self, Replaceable};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{geo, text, version, Id, Kind, Tag};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+const PLACE_MAX_LEN: usize = 64;
+
+/// The access tone of a repeater.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Tone {
+    /// CTCSS tone in tenths of Hz, e.g. 885 for 88.5 Hz.
+    Ctcss(u16),
+    /// DCS code, as written in octal, e.g. 23 for code 023.
+    Dcs(u16),
+}
+
+impl Tone {
+    fn validate(&self) -> Result<()> {
+        let valid = match *self {
+            Tone::Ctcss(tone) => (670..=2541).contains(&tone),
+            Tone::Dcs(code) => {
+                code <= 777
+                    && [code / 100, code / 10 % 10, code % 10]
+                        .iter()
+                        .all(|d| *d < 8)
+            }
+        };
+        if !valid {
+            bail!("invalid tone {:?}", self);
+        }
+        Ok(())
+    }
+}
+
+/// The details of a [`Repeater`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepeaterData {
+    pub callsign: String,
+    /// Frequency the repeater transmits on, in Hz.
+    pub output_freq: u64,
+    /// Frequency the repeater listens on, in Hz.
+    pub input_freq: u64,
+    pub tone: Option<Tone>,
+    /// Mode, e.g. `FM` or `DMR`.
+    pub mode: String,
+    /// The Maidenhead grid square of the site.
+    pub grid: String,
+    /// The site, e.g. the city name.
+    pub place: String,
+}
+
+/// A repeater of a decentralized directory, maintained by the trustee
+/// station.
+///
+/// The trustee replaces the listing with [`Repeater::update`], the latest
+/// version of every repeater wins as in [`crate::latest_of`]. Versions of
+/// the same repeater share its [`Repeater::key`], so a trustee may list
+/// several repeaters, even with the same callsign. Repeaters are tagged with
+/// their callsign and output band.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Repeater {
+    pub id: Id,
+    /// The trustee station.
+    pub station_id: Id,
+    pub callsign: String,
+    pub output_freq: u64,
+    pub input_freq: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tone: Option<Tone>,
+    pub mode: String,
+    pub grid: String,
+    pub place: String,
+    pub tags: Vec<Tag>,
+    pub created_at: u64,
+    /// Number of updates of the listing, see [`Repeater::update`].
+    #[serde(default, skip_serializing_if = "replaceable::is_zero")]
+    pub seq: u64,
+    /// Id of the first version of the listing, absent in the first version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin_id: Option<Id>,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl Repeater {
+    /// Current version of the repeater object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the repeater verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new Repeater and signs the object using the trustee signer.
+    pub fn new(station_id: Id, signer: &dyn Signer, data: RepeaterData) -> Result<Self> {
+        Self::create(station_id, signer, data, 0, None)
+    }
+
+    /// Replaces the listing with new details. The update wins over the
+    /// current listing in [`crate::latest_of`].
+    pub fn update(&self, signer: &dyn Signer, data: RepeaterData) -> Result<Self> {
+        Self::create(
+            self.station_id.clone(),
+            signer,
+            data,
+            self.seq + 1,
+            Some(self.key().clone()),
+        )
+    }
+
+    /// Returns the id shared by every version of the listing, the one of the
+    /// first version.
+    pub fn key(&self) -> &Id {
+        self.origin_id.as_ref().unwrap_or(&self.id)
+    }
+
+    /// Returns the offset of the input from the output, in Hz.
+    pub fn offset(&self) -> i64 {
+        self.input_freq as i64 - self.output_freq as i64
+    }
+
+    /// Verify the object signature against the trustee public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    fn create(
+        station_id: Id,
+        signer: &dyn Signer,
+        data: RepeaterData,
+        seq: u64,
+        origin_id: Option<Id>,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let callsign = Callsign::normalize(&data.callsign);
+
+        let mut tags = vec![Tag::Callsign(callsign.clone())];
+        tags.extend(Band::from_freq(data.output_freq).map(Tag::Band));
+
+        let callsign = callsign.to_string();
+        let id = Self::compute_id(
+            &station_id,
+            &callsign,
+            &data,
+            &tags,
+            created_at,
+            seq,
+            origin_id.as_ref(),
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let repeater = Self {
+            id,
+            station_id,
+            callsign,
+            output_freq: data.output_freq,
+            input_freq: data.input_freq,
+            tone: data.tone,
+            mode: data.mode,
+            grid: data.grid,
+            place: data.place,
+            tags,
+            created_at,
+            seq,
+            origin_id,
+            version: Self::VERSION,
+            sig,
+        };
+
+        repeater.validate()?;
+
+        Ok(repeater)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compute_id(
+        station_id: &Id,
+        callsign: &str,
+        data: &RepeaterData,
+        tags: &[Tag],
+        created_at: u64,
+        seq: u64,
+        origin_id: Option<&Id>,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                station_id,
+                callsign,
+                data.output_freq,
+                data.input_freq,
+                &data.tone,
+                &data.mode,
+                &data.grid,
+                &data.place,
+                tags,
+                created_at,
+                seq,
+                origin_id,
+                version,
+            ),
+        )
+    }
+
+    fn data(&self) -> RepeaterData {
+        RepeaterData {
+            callsign: self.callsign.clone(),
+            output_freq: self.output_freq,
+            input_freq: self.input_freq,
+            tone: self.tone,
+            mode: self.mode.clone(),
+            grid: self.grid.clone(),
+            place: self.place.clone(),
+        }
+    }
+}
+
+impl Validate for Repeater {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        ItuCallsign.validate(&self.callsign)?;
+
+        if self.output_freq == 0 || self.input_freq == 0 {
+            bail!("invalid freq");
+        }
+
+        if let Some(tone) = &self.tone {
+            tone.validate()?;
+        }
+
+        if !text::is_valid_required(&self.mode, MODE_MAX_LEN) {
+            bail!("invalid mode");
+        }
+
+        if !geo::is_valid_grid(&self.grid) {
+            bail!("invalid grid square {}", self.grid);
+        }
+
+        if !text::is_valid(&self.place, PLACE_MAX_LEN) {
+            bail!("invalid place");
+        }
+
+        if self.tags.len() > crate::MAX_TAGS {
+            bail!("more than {} tags", crate::MAX_TAGS);
+        }
+        limits::check_tags(&self.tags, Limits::DEFAULT.tag)?;
+
+        if (self.seq == 0) != self.origin_id.is_none() {
+            bail!("updates must reference the first version");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for Repeater {
+    const KIND: Kind = Kind::Repeater;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.callsign,
+            &self.data(),
+            &self.tags,
+            self.created_at,
+            self.seq,
+            self.origin_id.as_ref(),
+            self.version,
+        )
+    }
+}
+
+impl Replaceable for Repeater {
+    fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::Band;
+    use crate::event::{Event, Filter};
+    use crate::keys::generate_keypair;
+    use crate::repeater::{Repeater, RepeaterData, Tone};
+    use crate::resolve::resolve;
+    use crate::signable::Validate;
+    use crate::store::{MemoryStore, Store};
+    use crate::{test_vectors, Kind};
+
+    #[test]
+    fn test_repeater() {
+        let trustee = test_vectors::station();
+        let keys = test_vectors::station_keypair();
+        let data = RepeaterData {
+            callsign: "lu4ev".to_string(),
+            output_freq: 146_760_000,
+            input_freq: 146_160_000,
+            tone: Some(Tone::Ctcss(885)),
+            mode: "FM".to_string(),
+            grid: "GF05sk".to_string(),
+            place: "Caseros".to_string(),
+        };
+        let repeater = Repeater::new(trustee.id.clone(), &keys, data.clone()).unwrap();
+        assert_eq!(repeater.callsign, "LU4EV");
+        assert_eq!(repeater.offset(), -600_000);
+        assert_eq!(repeater.key(), &repeater.id);
+
+        let update = repeater
+            .update(
+                &keys,
+                RepeaterData {
+                    tone: Some(Tone::Dcs(23)),
+                    ..data.clone()
+                },
+            )
+            .unwrap();
+        assert_eq!(update.seq, 1);
+        assert_eq!(update.key(), &repeater.id);
+        let second = update.update(&keys, data.clone()).unwrap();
+        assert_eq!(second.key(), &repeater.id);
+
+        let json_str = serde_json::to_string(&Event::Repeater(update.clone())).unwrap();
+        let event: Event = serde_json::from_str(&json_str).unwrap();
+        event.verify(&trustee.pub_key).unwrap();
+
+        let other = Repeater::new(
+            trustee.id.clone(),
+            &keys,
+            RepeaterData {
+                output_freq: 438_500_000,
+                input_freq: 433_500_000,
+                ..data.clone()
+            },
+        )
+        .unwrap();
+        let canonical = resolve([
+            Event::Repeater(second.clone()),
+            Event::Repeater(repeater.clone()),
+            event,
+            Event::Repeater(other.clone()),
+        ]);
+        assert_eq!(canonical.events.len(), 2);
+        assert!(canonical.events.contains(&Event::Repeater(second.clone())));
+        assert!(canonical.events.contains(&Event::Repeater(other)));
+        assert!(canonical.superseded.contains(&repeater.id));
+        assert!(canonical.superseded.contains(&update.id));
+
+        let mut store = MemoryStore::new();
+        store.insert(Event::Repeater(second.clone())).unwrap();
+        let filter = Filter::new()
+            .kinds([Kind::Repeater])
+            .tags([Band::M2.into()]);
+        assert_eq!(store.query(&filter).unwrap().len(), 1);
+
+        for tone in [Tone::Ctcss(600), Tone::Dcs(28), Tone::Dcs(1023)] {
+            let mut invalid = repeater.clone();
+            invalid.tone = Some(tone);
+            assert!(invalid.validate().is_err(), "{:?}", tone);
+        }
+        let mut invalid = second.clone();
+        invalid.origin_id = None;
+        assert!(invalid.validate().is_err());
+        let mut invalid = second;
+        invalid.grid = "ZZ99".to_string();
+        assert!(invalid.validate().is_err());
+        assert!(invalid
+            .verify(&generate_keypair().x_only_public_key().0)
+            .is_err());
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::signable::Signable;
+
+/// An object replaced by newer versions signed with the same key, e.g. a
+/// station or a relay list.
+pub trait Replaceable: Signable {
+    /// Returns the sequence number, incremented by every update.
+    fn seq(&self) -> u64;
+}
+
+/// Returns the latest of the objects, ordered by sequence number, creation
+/// time and id.
+///
+/// The order is total, so every client picks the same object from the same
+/// set regardless of the order it received them in. The caller must verify
+/// the objects and check they were signed by the same key.
+pub fn latest_of<'a, T: Replaceable>(objects: impl IntoIterator<Item = &'a T>) -> Option<&'a T> {
+    objects
+        .into_iter()
+        .max_by(|a, b| (a.seq(), a.created_at(), a.id()).cmp(&(b.seq(), b.created_at(), b.id())))
+}
+
+pub(crate) fn is_zero(seq: &u64) -> bool {
+    *seq == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::replaceable::latest_of;
+    use crate::{Station, StationData};
+    use codes_iso_3166::part_1::CountryCode;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_latest_of() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let update = station
+            .update(
+                StationData {
+                    callsign: "LU4EV".to_string(),
+                    operator: "Gabriel Velo".to_string(),
+                    country: CountryCode::AR.into(),
+                    extra: BTreeMap::new(),
+                    profile: None,
+                    qsl_route: None,
+                },
+                &keys,
+            )
+            .unwrap();
+
+        assert_eq!(update.seq, 1);
+        update.verify().unwrap();
+
+        let json_str = serde_json::to_string(&update).unwrap();
+        let update_dese: Station = serde_json::from_str(&json_str).unwrap();
+        update_dese.verify().unwrap();
+
+        // Same second, the sequence number decides.
+        assert_eq!(latest_of([&update, &station]), Some(&update));
+        assert_eq!(latest_of([&station, &update]), Some(&update));
+        assert_eq!(latest_of::<Station>([]), None);
+
+        let mut tampered = update;
+        tampered.seq = 5;
+        assert!(tampered.verify().is_err());
+
+        assert!(station
+            .update(
+                StationData {
+                    callsign: "LU4EV".to_string(),
+                    operator: "Radio Club Caseros".to_string(),
+                    country: CountryCode::AR.into(),
+                    extra: BTreeMap::new(),
+                    profile: None,
+                    qsl_route: None,
+                },
+                &generate_keypair(),
+            )
+            .is_err());
+    }
+}
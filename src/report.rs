@@ -0,0 +1,254 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{version, Id, Kind};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Why a station or QSO was reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    /// The callsign of the QSO was copied wrong.
+    BustedCall,
+    /// The station uses a callsign it doesn't hold.
+    Pirate,
+    /// The station publishes bogus or excessive events.
+    Spam,
+}
+
+/// A moderation report signed by a station, flagging another station or a
+/// QSO.
+///
+/// Reports are opinions, relays and clients decide what to do with them,
+/// usually after aggregating the reports of the stations they trust with
+/// [`Report::aggregate`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Report {
+    pub id: Id,
+    pub reporter_id: Id,
+    pub target_id: Id,
+    /// The kind of the reported object, a station or a QSO.
+    pub target_kind: Kind,
+    pub reason: ReportReason,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+/// The reports received by a station or QSO.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportSummary {
+    pub target_kind: Option<Kind>,
+    /// Distinct reporters, for any reason.
+    pub reporters: BTreeSet<Id>,
+    /// Number of distinct reporters by reason.
+    pub by_reason: BTreeMap<ReportReason, u64>,
+}
+
+impl Report {
+    /// Current version of the report object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the report verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new Report and signs the object using the reporter signer.
+    pub fn new(
+        reporter_id: Id,
+        signer: &dyn Signer,
+        target_id: Id,
+        target_kind: Kind,
+        reason: ReportReason,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(
+            &reporter_id,
+            &target_id,
+            target_kind,
+            reason,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let report = Self {
+            id,
+            reporter_id,
+            target_id,
+            target_kind,
+            reason,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        report.validate()?;
+
+        Ok(report)
+    }
+
+    /// Verify the object signature against the reporter public key.
+    pub fn verify(&self, reporter_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(reporter_pub_key)
+    }
+
+    /// Groups the reports by target. A reporter counts once per target and
+    /// reason no matter how many times it reported it. The reports must be
+    /// verified first.
+    pub fn aggregate<'a>(
+        reports: impl IntoIterator<Item = &'a Report>,
+    ) -> BTreeMap<Id, ReportSummary> {
+        let mut votes: BTreeSet<(&Id, ReportReason, &Id)> = BTreeSet::new();
+        let mut summaries: BTreeMap<Id, ReportSummary> = BTreeMap::new();
+
+        for report in reports {
+            let summary = summaries.entry(report.target_id.clone()).or_default();
+            summary.target_kind.get_or_insert(report.target_kind);
+            summary.reporters.insert(report.reporter_id.clone());
+            if votes.insert((&report.target_id, report.reason, &report.reporter_id)) {
+                *summary.by_reason.entry(report.reason).or_default() += 1;
+            }
+        }
+
+        summaries
+    }
+
+    fn compute_id(
+        reporter_id: &Id,
+        target_id: &Id,
+        target_kind: Kind,
+        reason: ReportReason,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                reporter_id,
+                target_id,
+                target_kind,
+                reason,
+                created_at,
+                version,
+            ),
+        )
+    }
+}
+
+impl Validate for Report {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if !matches!(self.target_kind, Kind::Station | Kind::Qso) {
+            bail!("invalid report target kind {}", self.target_kind.as_str());
+        }
+
+        if self.target_id == self.reporter_id {
+            bail!("station reporting itself");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for Report {
+    const KIND: Kind = Kind::Report;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.reporter_id,
+            &self.target_id,
+            self.target_kind,
+            self.reason,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::{Report, ReportReason};
+    use crate::{test_vectors, Kind};
+
+    #[test]
+    fn test_report() {
+        let station = test_vectors::station();
+        let reporter = test_vectors::counterparty();
+        let reporter_keys = test_vectors::counterparty_keypair();
+        let qso = test_vectors::qso();
+
+        let report = |target_id, target_kind, reason| {
+            Report::new(
+                reporter.id.clone(),
+                &reporter_keys,
+                target_id,
+                target_kind,
+                reason,
+            )
+            .unwrap()
+        };
+
+        let busted = report(qso.id.clone(), Kind::Qso, ReportReason::BustedCall);
+        let json_str = serde_json::to_string(&busted).unwrap();
+        assert!(json_str.contains("\"reason\":\"busted_call\""));
+        let busted: Report = serde_json::from_str(&json_str).unwrap();
+        busted.verify(&reporter.pub_key).unwrap();
+        assert!(busted.verify(&station.pub_key).is_err());
+
+        let reports = [
+            busted.clone(),
+            busted,
+            report(station.id.clone(), Kind::Station, ReportReason::Pirate),
+            report(station.id.clone(), Kind::Station, ReportReason::Spam),
+        ];
+        let summaries = Report::aggregate(&reports);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[&qso.id].by_reason[&ReportReason::BustedCall], 1);
+        assert_eq!(summaries[&station.id].target_kind, Some(Kind::Station));
+        assert_eq!(summaries[&station.id].reporters.len(), 1);
+        assert_eq!(summaries[&station.id].by_reason.len(), 2);
+
+        assert!(Report::new(
+            reporter.id.clone(),
+            &reporter_keys,
+            station.id,
+            Kind::Certificate,
+            ReportReason::Spam,
+        )
+        .is_err());
+    }
+}
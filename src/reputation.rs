@@ -0,0 +1,267 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Station reputation scores.
+//!
+//! The score combines how close the station is to a trusted root in the
+//! certificate graph, how many of its QSOs were confirmed by the
+//! counterparty and how long ago the station was created. Every component is
+//! normalized to `0.0..=1.0` and the score is their weighted mean, so relays
+//! can pick rate limits by score range and clients can map it to badges.
+
+use crate::event::{Event, Filter};
+use crate::logbook::station_key;
+use crate::stats;
+use crate::store::Store;
+use crate::trust::{Role, TrustStore};
+use crate::{Id, Kind, Station};
+use anyhow::Result;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Weights and bounds of the reputation score.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReputationOptions {
+    pub certificate_weight: f64,
+    pub confirmation_weight: f64,
+    pub age_weight: f64,
+    /// Longest certificate chain followed from a root anchor. A station at
+    /// depth `d` gets `1 - d / (max_depth + 1)` for the certificate component.
+    pub max_depth: u32,
+    /// Age in seconds from which a station gets the full age component.
+    pub mature_age: u64,
+}
+
+impl Default for ReputationOptions {
+    fn default() -> Self {
+        Self {
+            certificate_weight: 0.5,
+            confirmation_weight: 0.3,
+            age_weight: 0.2,
+            max_depth: 3,
+            mature_age: 365 * 86_400,
+        }
+    }
+}
+
+/// The reputation of a station and the inputs of its score.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reputation {
+    /// Length of the shortest certificate chain from a root anchor, zero for
+    /// the anchors themselves. `None` if no chain within the maximum depth.
+    pub certificate_depth: Option<u32>,
+    /// Confirmed QSOs over all the QSOs of the station.
+    pub confirmation_rate: f64,
+    /// Seconds since the station was created.
+    pub age: u64,
+    /// Weighted score in `0.0..=1.0`.
+    pub score: f64,
+}
+
+/// Computes the reputation of the station from the events in the store.
+pub fn reputation(
+    store: &dyn Store,
+    trust: &TrustStore,
+    station: &Station,
+    now: u64,
+    options: &ReputationOptions,
+) -> Result<Reputation> {
+    let certificate_depth = certificate_depth(store, trust, station, options.max_depth)?;
+    let confirmation_rate = stats::station_stats(store, station)?.confirmation_rate;
+    let age = now.saturating_sub(station.created_at);
+
+    let certificate = certificate_depth.map_or(0.0, |depth| {
+        1.0 - depth as f64 / (options.max_depth as f64 + 1.0)
+    });
+    let age_component = if options.mature_age == 0 {
+        1.0
+    } else {
+        (age as f64 / options.mature_age as f64).min(1.0)
+    };
+
+    let total_weight =
+        options.certificate_weight + options.confirmation_weight + options.age_weight;
+    let score = if total_weight > 0.0 {
+        (options.certificate_weight * certificate
+            + options.confirmation_weight * confirmation_rate
+            + options.age_weight * age_component)
+            / total_weight
+    } else {
+        0.0
+    };
+
+    Ok(Reputation {
+        certificate_depth,
+        confirmation_rate,
+        age,
+        score,
+    })
+}
+
+/// Returns the length of the shortest chain of valid certificates from a
+/// root anchor of the trust store to the station, up to `max_depth`.
+///
+/// Certificates are verified against the key of the issuing station, which
+/// must be in the store unless it's an anchor.
+pub fn certificate_depth(
+    store: &dyn Store,
+    trust: &TrustStore,
+    station: &Station,
+    max_depth: u32,
+) -> Result<Option<u32>> {
+    let mut keys: BTreeMap<Id, XOnlyPublicKey> = trust
+        .anchors()
+        .filter(|anchor| anchor.roles.contains(&Role::Root))
+        .map(|anchor| (anchor.station_id.clone(), anchor.pub_key))
+        .collect();
+
+    if keys.get(&station.id) == Some(&station.pub_key) {
+        return Ok(Some(0));
+    }
+
+    let certificates: Vec<_> = store
+        .query(&Filter::new().kinds([Kind::Certificate]))?
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Certificate(certificate) => Some(certificate),
+            _ => None,
+        })
+        .collect();
+
+    let mut frontier: Vec<Id> = keys.keys().cloned().collect();
+    for depth in 1..=max_depth {
+        let mut next = vec![];
+        for certificate in &certificates {
+            let subject_id = certificate.subject_id();
+            if keys.contains_key(subject_id)
+                || next.contains(subject_id)
+                || !frontier.contains(certificate.issuer_id())
+            {
+                continue;
+            }
+            let Some(issuer_key) = keys.get(certificate.issuer_id()) else {
+                continue;
+            };
+            if certificate.verify(issuer_key).is_ok() {
+                next.push(subject_id.clone());
+            }
+        }
+
+        if next.contains(&station.id) {
+            return Ok(Some(depth));
+        }
+        if next.is_empty() {
+            break;
+        }
+
+        for id in &next {
+            if let Some(key) = station_key(id, store)? {
+                keys.insert(id.clone(), key);
+            }
+        }
+        frontier = next;
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::keys::generate_keypair;
+    use crate::reputation::{reputation, ReputationOptions};
+    use crate::store::{MemoryStore, Store};
+    use crate::trust::{Anchor, Role, TrustStore};
+    use crate::{test_vectors, Certificate, Qso, QsoData, Station};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_reputation() {
+        let root_keys = generate_keypair();
+        let root = Station::new(
+            &root_keys,
+            "LU1AA".to_string(),
+            "Radio Club Argentino".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        let club = test_vectors::counterparty();
+        let station = test_vectors::station();
+
+        let mut trust = TrustStore::new();
+        trust
+            .add_anchor(Anchor {
+                station_id: root.id.clone(),
+                pub_key: root.pub_key,
+                roles: vec![Role::Root],
+            })
+            .unwrap();
+
+        let mut store = MemoryStore::new();
+        for event in [
+            Event::Station(root.clone()),
+            Event::Station(club.clone()),
+            Event::Station(station.clone()),
+            Event::Qso(test_vectors::qso()),
+        ] {
+            store.insert(event).unwrap();
+        }
+
+        let options = ReputationOptions::default();
+        let now = station.created_at + options.mature_age / 2;
+
+        let unknown = reputation(&store, &trust, &station, now, &options).unwrap();
+        assert_eq!(unknown.certificate_depth, None);
+        assert_eq!(unknown.confirmation_rate, 0.0);
+        assert!((unknown.score - 0.1).abs() < 1e-9);
+
+        for certificate in [
+            Certificate::new(root.id.clone(), &root_keys, club.id.clone()),
+            Certificate::new(
+                club.id.clone(),
+                &test_vectors::counterparty_keypair(),
+                station.id.clone(),
+            ),
+        ] {
+            store.insert(Event::Certificate(certificate)).unwrap();
+        }
+        let confirmation = Qso::new(
+            QsoData {
+                station_id: club.id.clone(),
+                callsign: station.callsign.clone(),
+                datetime: test_vectors::CREATED_AT + 60,
+                freq: 14_250_000,
+                comments: String::new(),
+                ..test_vectors::qso_data()
+            },
+            &test_vectors::counterparty_keypair(),
+        );
+        store.insert(Event::Qso(confirmation)).unwrap();
+
+        let certified = reputation(&store, &trust, &station, now, &options).unwrap();
+        assert_eq!(certified.certificate_depth, Some(2));
+        assert_eq!(certified.confirmation_rate, 1.0);
+        assert!(certified.score > unknown.score);
+
+        let anchor = reputation(&store, &trust, &root, now, &options).unwrap();
+        assert_eq!(anchor.certificate_depth, Some(0));
+
+        let shallow = ReputationOptions {
+            max_depth: 1,
+            ..options
+        };
+        let out_of_reach = reputation(&store, &trust, &station, now, &shallow).unwrap();
+        assert_eq!(out_of_reach.certificate_depth, None);
+    }
+}
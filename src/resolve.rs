@@ -0,0 +1,419 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::event::Event;
+use crate::replaceable::latest_of;
+use crate::{Id, Qso, RelayList, Repeater, Revocation, Station, Tag};
+use secp256k1::XOnlyPublicKey;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The result of [`resolve`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Canonical {
+    /// The events every implementation keeps, ordered by creation time and
+    /// id.
+    pub events: Vec<Event>,
+    /// Ids of the events replaced by a newer version.
+    pub superseded: Vec<Id>,
+    /// Ids of the events created after the revocation of their key.
+    pub revoked: Vec<Id>,
+    /// Ids of the QSOs, and of their amendments, withdrawn by a
+    /// [`crate::Tombstone`].
+    pub withdrawn: Vec<Id>,
+}
+
+/// Identifies the signer of an event: the station public key when the station
+/// is known, the station id otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Owner {
+    Key(XOnlyPublicKey),
+    Station(Id),
+}
+
+/// Resolves conflicting events into the canonical set.
+///
+/// The events must be verified by the caller. The rules are applied in order
+/// and don't depend on the order of the input:
+///
+/// 1. Events with the same id are kept once.
+/// 2. Revocations apply to every station sharing the revoked station key, the
+///    earliest revocation time wins. Events of the key created after it are
+///    revoked, revocations themselves are always kept.
+/// 3. Among the stations with the same public key only the latest one, by
+///    [`latest_of`], is kept.
+/// 4. Among the relay lists of the same key only the latest one is kept.
+/// 5. Among the versions of a repeater of the same key, those sharing its
+///    [`Repeater::key`], only the latest one is kept.
+/// 6. A QSO with a [`Tag::Ref`] to a QSO of the same key amends it. A QSO and
+///    its amendments, direct or not, record one contact and only the end of
+///    the longest amendment chain is kept, the latest one by creation time
+///    and id among chains of the same length.
+/// 7. A [`crate::Tombstone`] withdraws the contact of the QSO it references, the
+///    QSO and its amendments, if it has the same key. Tombstones are always
+///    kept, so the contact isn't added back by a peer.
+///
+/// Every other event is kept.
+pub fn resolve(events: impl IntoIterator<Item = Event>) -> Canonical {
+    let events: BTreeMap<Id, Event> = events
+        .into_iter()
+        .map(|event| (event.id().clone(), event))
+        .collect();
+
+    let owners: BTreeMap<&Id, XOnlyPublicKey> = events
+        .values()
+        .filter_map(|event| match event {
+            Event::Station(station) => Some((&station.id, station.pub_key)),
+            _ => None,
+        })
+        .collect();
+
+    let station_owner = |station_id: &Id| match owners.get(station_id) {
+        Some(pub_key) => Owner::Key(*pub_key),
+        None => Owner::Station(station_id.clone()),
+    };
+    let owner = |event: &Event| station_owner(event.station_id());
+
+    let mut revoked_at: BTreeMap<Owner, u64> = BTreeMap::new();
+    for event in events.values() {
+        if let Event::Revocation(revocation) = event {
+            let time = revoked_at.entry(owner(event)).or_insert(u64::MAX);
+            *time = (*time).min(revocation.revoked_at);
+        }
+    }
+
+    let mut canonical = Canonical::default();
+    let mut kept: Vec<&Event> = vec![];
+
+    for event in events.values() {
+        let is_revoked = !matches!(event, Event::Revocation(_))
+            && revoked_at
+                .get(&owner(event))
+                .is_some_and(|time| event.created_at() > *time);
+
+        if is_revoked {
+            canonical.revoked.push(event.id().clone());
+        } else {
+            kept.push(event);
+        }
+    }
+
+    let mut stations: BTreeMap<Owner, Vec<&Station>> = BTreeMap::new();
+    let mut relay_lists: BTreeMap<Owner, Vec<&RelayList>> = BTreeMap::new();
+    let mut repeaters: BTreeMap<(Owner, &Id), Vec<&Repeater>> = BTreeMap::new();
+    for event in &kept {
+        match event {
+            Event::Station(station) => stations.entry(owner(event)).or_default().push(station),
+            Event::RelayList(list) => relay_lists.entry(owner(event)).or_default().push(list),
+            Event::Repeater(repeater) => repeaters
+                .entry((owner(event), repeater.key()))
+                .or_default()
+                .push(repeater),
+            _ => {}
+        }
+    }
+
+    let latest: BTreeSet<&Id> = stations
+        .values()
+        .filter_map(|stations| latest_of(stations.iter().copied()))
+        .map(|station| &station.id)
+        .chain(
+            relay_lists
+                .values()
+                .filter_map(|lists| latest_of(lists.iter().copied()))
+                .map(|list| &list.id),
+        )
+        .chain(
+            repeaters
+                .values()
+                .filter_map(|repeaters| latest_of(repeaters.iter().copied()))
+                .map(|repeater| &repeater.id),
+        )
+        .collect();
+
+    let qsos: BTreeMap<&Id, &Qso> = kept
+        .iter()
+        .filter_map(|event| match event {
+            Event::Qso(qso) => Some((&qso.id, qso)),
+            _ => None,
+        })
+        .collect();
+
+    let original_of = |qso| original(qso, &qsos, &station_owner);
+
+    let mut contacts: BTreeMap<&Id, Vec<(usize, &Qso)>> = BTreeMap::new();
+    for qso in qsos.values() {
+        let (original, depth) = original_of(qso);
+        contacts.entry(original).or_default().push((depth, qso));
+    }
+
+    let tombstoned: BTreeSet<&Id> = kept
+        .iter()
+        .filter_map(|event| match event {
+            Event::Tombstone(tombstone) => qsos
+                .get(&tombstone.qso_id)
+                .filter(|qso| station_owner(&qso.station_id) == owner(event))
+                .map(|qso| original_of(qso).0),
+            _ => None,
+        })
+        .collect();
+
+    let mut withdrawn: BTreeSet<&Id> = BTreeSet::new();
+    let mut amendments: BTreeSet<&Id> = BTreeSet::new();
+    for (original, contact) in &contacts {
+        if tombstoned.contains(original) {
+            withdrawn.extend(contact.iter().map(|(_, qso)| &qso.id));
+        } else if let Some((_, latest)) = contact
+            .iter()
+            .max_by_key(|(depth, qso)| (*depth, qso.created_at, &qso.id))
+        {
+            amendments.extend(
+                contact
+                    .iter()
+                    .filter(|(_, qso)| qso.id != latest.id)
+                    .map(|(_, qso)| &qso.id),
+            );
+        }
+    }
+
+    for event in kept {
+        let is_replaceable = matches!(
+            event,
+            Event::Station(_) | Event::RelayList(_) | Event::Repeater(_)
+        );
+        if withdrawn.contains(event.id()) {
+            canonical.withdrawn.push(event.id().clone());
+        } else if (is_replaceable && !latest.contains(event.id()))
+            || amendments.contains(event.id())
+        {
+            canonical.superseded.push(event.id().clone());
+        } else {
+            canonical.events.push(event.clone());
+        }
+    }
+
+    canonical
+        .events
+        .sort_by(|a, b| (a.created_at(), a.id()).cmp(&(b.created_at(), b.id())));
+
+    canonical
+}
+
+/// Returns the id of the QSO the amendments of the QSO start from, the QSO
+/// itself if it doesn't amend another one, and the number of amendments
+/// in between.
+fn original<'a>(
+    mut qso: &'a Qso,
+    qsos: &BTreeMap<&Id, &'a Qso>,
+    owner: &dyn Fn(&Id) -> Owner,
+) -> (&'a Id, usize) {
+    let mut depth = 0;
+    // Ids are hashes of the referenced ids, so amendments can't form cycles,
+    // the bound only guards against unverified input.
+    for _ in 0..qsos.len() {
+        let amended = qso.tags.iter().find_map(|tag| match tag {
+            Tag::Ref(id) => qsos
+                .get(id)
+                .copied()
+                .filter(|original| owner(&original.station_id) == owner(&qso.station_id)),
+            _ => None,
+        });
+        match amended {
+            Some(original) => qso = original,
+            None => break,
+        }
+        depth += 1;
+    }
+    (&qso.id, depth)
+}
+
+impl Canonical {
+    /// Returns the revocations of the canonical set.
+    pub fn revocations(&self) -> impl Iterator<Item = &Revocation> {
+        self.events.iter().filter_map(|event| match event {
+            Event::Revocation(revocation) => Some(revocation),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::keys::generate_keypair;
+    use crate::resolve::resolve;
+    use crate::{
+        test_vectors, Qso, QsoData, RelayList, Revocation, Station, StationData, Tag, Tombstone,
+    };
+    use codes_iso_3166::part_1::CountryCode;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_resolve() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let update = station
+            .update(
+                StationData {
+                    callsign: "LU4EV".to_string(),
+                    operator: "Gabriel Velo".to_string(),
+                    country: CountryCode::AR.into(),
+                    extra: BTreeMap::new(),
+                    profile: None,
+                    qsl_route: None,
+                },
+                &keys,
+            )
+            .unwrap();
+
+        let relay_list = RelayList::new(
+            station.id.clone(),
+            &keys,
+            vec!["wss://a.example".to_string()],
+        )
+        .unwrap();
+        let relay_list_update = RelayList::new(
+            update.id.clone(),
+            &keys,
+            vec!["wss://b.example".to_string()],
+        )
+        .unwrap()
+        .update(&keys, vec!["wss://b.example".to_string()])
+        .unwrap();
+
+        let qso = Qso::new(
+            QsoData {
+                station_id: station.id.clone(),
+                datetime: 1704141426,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                ..test_vectors::qso_data()
+            },
+            &keys,
+        );
+
+        let events = vec![
+            Event::Qso(qso.clone()),
+            Event::RelayList(relay_list.clone()),
+            Event::Station(update.clone()),
+            Event::Station(station.clone()),
+            Event::RelayList(relay_list_update.clone()),
+            Event::Qso(qso.clone()),
+        ];
+
+        let canonical = resolve(events.clone());
+        let mut reversed = events.clone();
+        reversed.reverse();
+        assert_eq!(resolve(reversed), canonical);
+
+        assert_eq!(canonical.events.len(), 3);
+        assert!(canonical.events.contains(&Event::Station(update.clone())));
+        assert!(canonical
+            .events
+            .contains(&Event::RelayList(relay_list_update)));
+        assert!(canonical.superseded.contains(&station.id));
+        assert!(canonical.superseded.contains(&relay_list.id));
+
+        let revocation = Revocation::new(update.id.clone(), &keys, station.created_at - 1).unwrap();
+        let mut events = events;
+        events.push(Event::Revocation(revocation.clone()));
+
+        let canonical = resolve(events);
+        assert_eq!(canonical.events, vec![Event::Revocation(revocation)]);
+        assert!(canonical.revoked.contains(&qso.id));
+        assert_eq!(canonical.revocations().count(), 1);
+    }
+
+    #[test]
+    fn test_resolve_qsos() {
+        let station = test_vectors::station();
+        let keys = test_vectors::station_keypair();
+        let qso = test_vectors::qso();
+
+        let amend = |qso: &Qso, rst: &str| {
+            let data = QsoData {
+                station_id: qso.station_id.clone(),
+                callsign: qso.callsign.clone(),
+                freq: qso.freq,
+                datetime: qso.datetime,
+                mode: qso.mode.clone(),
+                rst: rst.to_string(),
+                comments: qso.comments.clone(),
+                extra: qso.extra.clone(),
+                tags: vec![Tag::Ref(qso.id.clone())],
+                ..test_vectors::qso_data()
+            };
+            Qso::new(data, &keys)
+        };
+        let amendment = amend(&qso, "57");
+        let second = amend(&amendment, "55");
+
+        // A reference from another station is a confirmation, not an
+        // amendment.
+        let counterparty = test_vectors::counterparty();
+        let confirmation = Qso::new(
+            QsoData {
+                station_id: counterparty.id.clone(),
+                callsign: "LU4EV".to_string(),
+                freq: qso.freq,
+                datetime: qso.datetime,
+                mode: qso.mode.clone(),
+                rst: qso.rst.clone(),
+                comments: qso.comments.clone(),
+                tags: vec![Tag::Ref(qso.id.clone())],
+                ..test_vectors::qso_data()
+            },
+            &test_vectors::counterparty_keypair(),
+        );
+
+        let events = vec![
+            Event::Station(station.clone()),
+            Event::Station(counterparty),
+            Event::Qso(second.clone()),
+            Event::Qso(qso.clone()),
+            Event::Qso(confirmation.clone()),
+            Event::Qso(amendment.clone()),
+        ];
+        let canonical = resolve(events.clone());
+        assert!(canonical.events.contains(&Event::Qso(second.clone())));
+        assert!(canonical.events.contains(&Event::Qso(confirmation.clone())));
+        assert_eq!(canonical.superseded.len(), 2);
+        assert!(canonical.superseded.contains(&qso.id));
+        assert!(canonical.superseded.contains(&amendment.id));
+
+        // Withdrawing any QSO of the contact withdraws all of them.
+        let tombstone = Tombstone::new(station.id.clone(), &keys, amendment.id.clone()).unwrap();
+        let forged = Tombstone::new(station.id.clone(), &keys, confirmation.id.clone()).unwrap();
+        let mut events = events;
+        events.push(Event::Tombstone(tombstone.clone()));
+        events.push(Event::Tombstone(forged.clone()));
+
+        let canonical = resolve(events.clone());
+        events.reverse();
+        assert_eq!(resolve(events), canonical);
+
+        let mut withdrawn = vec![qso.id, amendment.id, second.id];
+        withdrawn.sort();
+        assert_eq!(canonical.withdrawn, withdrawn);
+        assert!(canonical.events.contains(&Event::Qso(confirmation)));
+        assert!(canonical.events.contains(&Event::Tombstone(tombstone)));
+        assert!(canonical.events.contains(&Event::Tombstone(forged)));
+    }
+}
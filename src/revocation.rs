@@ -0,0 +1,223 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::event::Event;
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{version, Id, Kind};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// A statement signed by a station declaring its key compromised.
+///
+/// Every event of the station created after `revoked_at` is invalid, the
+/// revocation is permanent. `revoked_at` can be earlier than the statement
+/// creation time, e.g. the time the key was found to be leaked.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Revocation {
+    pub id: Id,
+    pub station_id: Id,
+    pub revoked_at: u64,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl Revocation {
+    /// Current version of the revocation object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the revocation verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new Revocation and signs the object using the station signer.
+    pub fn new(station_id: Id, signer: &dyn Signer, revoked_at: u64) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(&station_id, revoked_at, created_at, Self::VERSION);
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let revocation = Self {
+            id,
+            station_id,
+            revoked_at,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        revocation.validate()?;
+
+        Ok(revocation)
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Returns an error if the event was signed with the revoked key after
+    /// the revocation time, whatever station id it was signed for, e.g. the
+    /// one of a [`crate::Station::update`]. Revocations are never revoked.
+    ///
+    /// `revoked_key` is the key the revocation was verified against and
+    /// `event_key` the one the event was verified against, the same rules
+    /// [`crate::resolve`] applies.
+    pub fn check(
+        &self,
+        revoked_key: &XOnlyPublicKey,
+        event: &Event,
+        event_key: &XOnlyPublicKey,
+    ) -> Result<()> {
+        if event_key == revoked_key
+            && event.created_at() > self.revoked_at
+            && !matches!(event, Event::Revocation(_))
+        {
+            bail!("station key revoked at {}", self.revoked_at);
+        }
+        Ok(())
+    }
+
+    fn compute_id(station_id: &Id, revoked_at: u64, created_at: u64, version: u8) -> Id {
+        Id::hash(version, &(station_id, revoked_at, created_at, version))
+    }
+}
+
+impl Validate for Revocation {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.revoked_at > self.created_at {
+            bail!("revocation time after creation time");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for Revocation {
+    const KIND: Kind = Kind::Revocation;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            self.revoked_at,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::keys::generate_keypair;
+    use crate::revocation::Revocation;
+    use crate::{test_vectors, Qso, QsoData, Station, StationData};
+    use codes_iso_3166::part_1::CountryCode;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_revocation() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso = Qso::new(
+            QsoData {
+                station_id: station.id.clone(),
+                datetime: 1704141426,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                ..test_vectors::qso_data()
+            },
+            &keys,
+        );
+
+        let revocation =
+            Revocation::new(station.id.clone(), &keys, station.created_at - 1).unwrap();
+
+        let json_str = serde_json::to_string(&revocation).unwrap();
+        let revocation_dese: Revocation = serde_json::from_str(&json_str).unwrap();
+        revocation_dese.verify(&station.pub_key).unwrap();
+
+        let pub_key = &station.pub_key;
+        assert!(revocation
+            .check(pub_key, &Event::Qso(qso.clone()), pub_key)
+            .is_err());
+        assert!(revocation
+            .check(pub_key, &Event::Station(station.clone()), pub_key)
+            .is_err());
+        revocation
+            .check(pub_key, &Event::Revocation(revocation.clone()), pub_key)
+            .unwrap();
+
+        // The key is revoked under the updated station id as well.
+        let update = station
+            .update(
+                StationData {
+                    callsign: "LU4EV".to_string(),
+                    operator: "Gabriel Velo".to_string(),
+                    country: CountryCode::AR.into(),
+                    extra: BTreeMap::new(),
+                    profile: None,
+                    qsl_route: None,
+                },
+                &keys,
+            )
+            .unwrap();
+        assert_ne!(update.id, station.id);
+        assert!(revocation
+            .check(pub_key, &Event::Station(update), pub_key)
+            .is_err());
+
+        let other = Station::new(
+            &generate_keypair(),
+            "LW3DZR".to_string(),
+            "Gabriel Velo".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        revocation
+            .check(pub_key, &Event::Station(other.clone()), &other.pub_key)
+            .unwrap();
+
+        let later = Revocation::new(station.id.clone(), &keys, qso.created_at).unwrap();
+        later.check(pub_key, &Event::Qso(qso), pub_key).unwrap();
+
+        assert!(Revocation::new(station.id, &keys, u64::MAX).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JSON Schemas of the signed objects and protocol messages, enabled by the
+//! `schemars` feature.
+//!
+//! The schemas describe the wire format only. Ids and signatures can't be
+//! checked without hashing and verifying the object, and the length limits
+//! are configurable at runtime, see [`crate::limits`].
+
+use crate::{Certificate, Entity, Event, Filter, Id, Qso, Station, Tag};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{
+    ArrayValidation, InstanceType, Metadata, RootSchema, Schema, SchemaObject, StringValidation,
+};
+use schemars::{schema_for, JsonSchema};
+use std::collections::BTreeMap;
+
+/// Returns the schemas of the objects, by the name of the object.
+pub fn schemas() -> BTreeMap<&'static str, RootSchema> {
+    BTreeMap::from([
+        ("certificate", schema_for!(Certificate)),
+        ("event", schema_for!(Event)),
+        ("filter", schema_for!(Filter)),
+        ("qso", schema_for!(Qso)),
+        ("station", schema_for!(Station)),
+    ])
+}
+
+/// A lowercase hex string of `len` chars.
+fn hex_string(len: u32, description: &str) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.to_string()),
+            ..Default::default()
+        })),
+        string: Some(Box::new(StringValidation {
+            max_length: Some(len),
+            min_length: Some(len),
+            pattern: Some(format!("^[0-9a-f]{{{}}}$", len)),
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Schema of the schnorr signatures, for `#[schemars(with)]`.
+pub(crate) struct Signature;
+
+impl JsonSchema for Signature {
+    fn schema_name() -> String {
+        "Signature".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(128, "Hex encoded schnorr signature of the object id.")
+    }
+}
+
+/// Schema of the x-only public keys, for `#[schemars(with)]`.
+pub(crate) struct PublicKey;
+
+impl JsonSchema for PublicKey {
+    fn schema_name() -> String {
+        "PublicKey".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(64, "Hex encoded x-only public key.")
+    }
+}
+
+/// Schema of the redaction salts, for `#[schemars(with)]`.
+pub(crate) struct Salt;
+
+impl JsonSchema for Salt {
+    fn schema_name() -> String {
+        "Salt".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(32, "Hex encoded salt of the comments commitment.")
+    }
+}
+
+impl JsonSchema for Id {
+    fn schema_name() -> String {
+        "Id".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(64, "Hex encoded sha256 object id.")
+    }
+}
+
+impl JsonSchema for Entity {
+    fn schema_name() -> String {
+        "Entity".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "ISO 3166 alpha-2 country code or lowercase DXCC entity name.".to_string(),
+                ),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl JsonSchema for Tag {
+    fn schema_name() -> String {
+        "Tag".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::Array.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some("Tag name followed by its values.".to_string()),
+                ..Default::default()
+            })),
+            array: Some(Box::new(ArrayValidation {
+                items: Some(gen.subschema_for::<String>().into()),
+                min_items: Some(1),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::schemas;
+    use crate::{test_vectors, Event};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_schemas() {
+        let schemas = schemas();
+        assert_eq!(
+            schemas.keys().copied().collect::<Vec<_>>(),
+            ["certificate", "event", "filter", "qso", "station"]
+        );
+
+        let qso = serde_json::to_value(&schemas["qso"]).unwrap();
+        let required = qso["required"].as_array().unwrap();
+        assert!(required.contains(&json!("sig")));
+        assert!(!required.contains(&json!("tags")));
+        assert_eq!(
+            qso["definitions"]["Signature"]["pattern"],
+            "^[0-9a-f]{128}$"
+        );
+
+        let event = serde_json::to_value(&schemas["event"]).unwrap();
+        let kinds: Vec<Value> = event["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| variant["properties"]["kind"]["enum"][0].clone())
+            .collect();
+        let qso_event = serde_json::to_value(Event::Qso(test_vectors::qso())).unwrap();
+        assert!(kinds.contains(&qso_event["kind"]));
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scalar and point arithmetic shared by the multi-party BIP340 signing
+//! protocols. The secp256k1 crate doesn't expose it, so it's done with k256
+//! and converted from and to the secp256k1 types at the edges.
+
+use crate::Id;
+use anyhow::{bail, Context, Result};
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use k256::{AffinePoint, EncodedPoint, ProjectivePoint, Scalar, U256};
+use secp256k1::schnorr::Signature;
+use secp256k1::{Parity, PublicKey, XOnlyPublicKey};
+use sha2::{Digest, Sha256};
+
+/// Converts a public key into a curve point.
+pub(crate) fn point(pub_key: &PublicKey) -> ProjectivePoint {
+    let encoded = EncodedPoint::from_bytes(pub_key.serialize()).expect("valid sec1 encoding");
+    AffinePoint::from_encoded_point(&encoded)
+        .map(ProjectivePoint::from)
+        .expect("public keys are valid curve points")
+}
+
+/// Returns the point with even y of the x-only public key.
+pub(crate) fn lift_x(pub_key: &XOnlyPublicKey) -> ProjectivePoint {
+    point(&pub_key.public_key(Parity::Even))
+}
+
+/// Converts a curve point into a public key, failing for the identity.
+pub(crate) fn public_key(point: &ProjectivePoint) -> Result<PublicKey> {
+    PublicKey::from_slice(point.to_affine().to_encoded_point(true).as_bytes())
+        .context("point at infinity")
+}
+
+/// Returns the x coordinate of the point.
+pub(crate) fn x_bytes(point: &ProjectivePoint) -> [u8; 32] {
+    point.to_affine().x().into()
+}
+
+pub(crate) fn has_even_y(point: &ProjectivePoint) -> bool {
+    !bool::from(point.to_affine().y_is_odd())
+}
+
+/// Parses a scalar, failing if it isn't below the curve order.
+pub(crate) fn scalar(bytes: &[u8; 32]) -> Result<Scalar> {
+    Option::from(<Scalar as k256::elliptic_curve::PrimeField>::from_repr(
+        (*bytes).into(),
+    ))
+    .context("scalar out of range")
+}
+
+/// Returns the scalar of the BIP340 tagged hash of the data.
+pub(crate) fn hash_to_scalar(tag: &str, data: &[&[u8]]) -> Scalar {
+    let tag = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag);
+    hasher.update(tag);
+    for data in data {
+        hasher.update(data);
+    }
+    <Scalar as Reduce<U256>>::reduce_bytes(&hasher.finalize())
+}
+
+/// Returns the BIP340 challenge of the signature of the id.
+pub(crate) fn challenge(nonce: &ProjectivePoint, pub_key: &XOnlyPublicKey, id: &Id) -> Scalar {
+    hash_to_scalar(
+        "BIP0340/challenge",
+        &[&x_bytes(nonce), &pub_key.serialize(), id.as_bytes()],
+    )
+}
+
+/// Returns the BIP340 signature of the nonce point and the scalar, the nonce
+/// must have an even y.
+pub(crate) fn signature(nonce: &ProjectivePoint, s: &Scalar) -> Result<Signature> {
+    if !has_even_y(nonce) {
+        bail!("nonce point with odd y");
+    }
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(&x_bytes(nonce));
+    bytes[32..].copy_from_slice(&s.to_bytes());
+    Ok(Signature::from_slice(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::schnorr::{challenge, has_even_y, lift_x, public_key, scalar, signature};
+    use crate::Id;
+    use k256::{ProjectivePoint, Scalar};
+
+    #[test]
+    fn test_schnorr() {
+        // A plain single key signature built from the helpers verifies.
+        let keys = generate_keypair();
+        let (pub_key, parity) = keys.x_only_public_key();
+        let mut secret = scalar(&keys.secret_bytes()).unwrap();
+        if parity == secp256k1::Parity::Odd {
+            secret = -secret;
+        }
+        assert_eq!(
+            public_key(&(ProjectivePoint::GENERATOR * secret)).unwrap(),
+            pub_key.public_key(secp256k1::Parity::Even)
+        );
+        assert_eq!(lift_x(&pub_key), ProjectivePoint::GENERATOR * secret);
+
+        let id = Id::new("LU4EV");
+        let mut nonce = Scalar::from(42u64);
+        let mut nonce_point = ProjectivePoint::GENERATOR * nonce;
+        if !has_even_y(&nonce_point) {
+            nonce = -nonce;
+            nonce_point = -nonce_point;
+        }
+        let s = nonce + challenge(&nonce_point, &pub_key, &id) * secret;
+        let sig = signature(&nonce_point, &s).unwrap();
+        id.verify(&pub_key, &sig).unwrap();
+
+        assert!(public_key(&ProjectivePoint::IDENTITY).is_err());
+        assert!(scalar(&[0xff; 32]).is_err());
+    }
+}
@@ -0,0 +1,652 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Embeddable WebSocket relay.
+//!
+//! Clients and relay exchange json encoded messages in WebSocket text frames:
+//!
+//! * [`ClientMessage::Publish`] stores an event. The relay answers with
+//!   [`RelayMessage::Ok`], telling whether the event was accepted.
+//! * [`ClientMessage::Subscribe`] sends the stored events matching a filter,
+//!   then [`RelayMessage::Eose`], then the matching events as they are
+//!   published, until [`ClientMessage::Unsubscribe`]. Subscriptions whose
+//!   client can't keep up are ended with [`RelayMessage::Closed`].
+//! * [`ClientMessage::Count`] counts the stored events matching a filter
+//!   without sending them. The relay answers with [`RelayMessage::Count`].
+//!
+//! Events are accepted if they verify against the key of their station,
+//! which must be published first, and pass the [`crate::policy`] of the
+//! relay. Events signed with a key after its stored [`crate::Revocation`]
+//! are refused as well. Refused events carry the [`Rejection`] in the `Ok`
+//! message.
+//! Expired events are pruned from the store periodically, see
+//! [`crate::Tag::ExpiresAt`].
+//!
+//! The relay speaks plain WebSocket, TLS is expected to be terminated by a
+//! local proxy.
+
+use crate::event::{Event, Filter};
+use crate::policy::{IngestionPolicy, Policy, Rejection};
+use crate::store::Store;
+use crate::time::unix_timstamp;
+use crate::{limits, Id, Kind};
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, Sender};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Messages queued for a connection before its subscriptions wait for the
+/// client to read them.
+const MAX_QUEUED_MESSAGES: usize = 256;
+
+/// Room for the client message around the largest accepted event.
+const MESSAGE_ENVELOPE: usize = 1024;
+
+/// Message sent by a client to the relay.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Publish {
+        event: Box<Event>,
+    },
+    /// Replaces the subscription with the same id, if any.
+    Subscribe {
+        subscription: String,
+        filter: Box<Filter>,
+    },
+    Unsubscribe {
+        subscription: String,
+    },
+    /// Counts the stored events matching the filter, `subscription` only
+    /// identifies the reply.
+    Count {
+        subscription: String,
+        filter: Box<Filter>,
+    },
+}
+
+/// Message sent by the relay to a client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RelayMessage {
+    /// The result of a publish, `reason` tells why an event was rejected.
+    Ok {
+        id: Id,
+        accepted: bool,
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<Rejection>,
+    },
+    Event {
+        subscription: String,
+        event: Box<Event>,
+    },
+    /// End of the stored events of the subscription.
+    Eose {
+        subscription: String,
+    },
+    /// The subscription was refused or ended by the relay.
+    Closed {
+        subscription: String,
+        message: String,
+    },
+    /// The number of stored events matching the filter of a count.
+    Count {
+        subscription: String,
+        count: u64,
+    },
+    Notice {
+        message: String,
+    },
+}
+
+/// Limits enforced by the relay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayConfig {
+    /// The events accepted by the relay.
+    pub policy: IngestionPolicy,
+    /// Concurrent subscriptions per connection.
+    pub max_subscriptions: usize,
+    /// Interval between prunings of the expired events, never pruned if
+    /// `None`.
+    pub prune_interval: Option<Duration>,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            policy: IngestionPolicy::default(),
+            max_subscriptions: 16,
+            prune_interval: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+struct Inner<S> {
+    store: Mutex<S>,
+    policy: Policy,
+    websocket: WebSocketConfig,
+    max_subscriptions: usize,
+    prune_interval: Option<Duration>,
+}
+
+/// A relay serving the events of a store over WebSocket.
+///
+/// Store calls are blocking and made from the connection tasks, the store
+/// should answer quickly, e.g. a [`crate::MemoryStore`] or a local SQLite
+/// database. The store must support [`Store::subscribe`].
+pub struct RelayServer<S> {
+    inner: Arc<Inner<S>>,
+}
+
+impl<S> Clone for RelayServer<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: Store + Send + 'static> RelayServer<S> {
+    /// Creates a relay serving the events of the store.
+    pub fn new(store: S, config: RelayConfig) -> Self {
+        // Oversized events are refused by the WebSocket layer, before they
+        // are buffered and parsed.
+        let max_message_size = config
+            .policy
+            .max_event_size
+            .unwrap_or(limits::MAX_EVENT_SIZE)
+            + MESSAGE_ENVELOPE;
+        Self {
+            inner: Arc::new(Inner {
+                store: Mutex::new(store),
+                policy: Policy::new(config.policy),
+                websocket: WebSocketConfig {
+                    max_message_size: Some(max_message_size),
+                    max_frame_size: Some(max_message_size),
+                    ..WebSocketConfig::default()
+                },
+                max_subscriptions: config.max_subscriptions,
+                prune_interval: config.prune_interval,
+            }),
+        }
+    }
+
+    /// Locks the store, e.g. to load events bypassing the relay checks.
+    pub fn store(&self) -> MutexGuard<'_, S> {
+        self.inner
+            .store
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Accepts connections until the listener fails, each connection is
+    /// served in its own task.
+    pub async fn serve(&self, listener: TcpListener) -> Result<()> {
+        let pruning = self.inner.prune_interval.map(|period| {
+            let server = self.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    interval.tick().await;
+                    server.prune_expired();
+                }
+            })
+        });
+        let result = self.accept(listener).await;
+        if let Some(pruning) = pruning {
+            pruning.abort();
+        }
+        result
+    }
+
+    /// Deletes the expired events from the store, returns the number of
+    /// deleted events.
+    pub fn prune_expired(&self) -> u64 {
+        match self.store().prune_expired(unix_timstamp()) {
+            Ok(pruned) => pruned,
+            Err(err) => {
+                tracing::warn!("failed to prune expired events: {:#}", err);
+                0
+            }
+        }
+    }
+
+    async fn accept(&self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(err) = server.handle_connection(stream, peer).await {
+                    tracing::debug!(%peer, "connection closed: {:#}", err);
+                }
+            });
+        }
+    }
+
+    /// Serves a single connection until the client disconnects.
+    pub async fn handle_connection(&self, stream: TcpStream, peer: SocketAddr) -> Result<()> {
+        let ws =
+            tokio_tungstenite::accept_async_with_config(stream, Some(self.inner.websocket)).await?;
+        let (mut sink, mut source) = ws.split();
+        let (tx, mut rx) = mpsc::channel(MAX_QUEUED_MESSAGES);
+        let mut subscriptions = HashMap::new();
+
+        let result = loop {
+            tokio::select! {
+                message = source.next() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | None => break Ok(()),
+                        Some(Ok(_)) => continue,
+                        Some(Err(err)) => break Err(err.into()),
+                    };
+                    let reply = match serde_json::from_str(&text) {
+                        Ok(message) => self.handle_message(message, text.len(), peer.ip(), &tx, &mut subscriptions),
+                        Err(err) => Some(RelayMessage::Notice {
+                            message: format!("invalid message: {}", err),
+                        }),
+                    };
+                    if let Some(reply) = reply {
+                        if let Err(err) = sink.send(Message::Text(serde_json::to_string(&reply)?)).await {
+                            break Err(err.into());
+                        }
+                    }
+                }
+                Some(message) = rx.recv() => {
+                    if let Err(err) = sink.send(Message::Text(serde_json::to_string(&message)?)).await {
+                        break Err(err.into());
+                    }
+                }
+            }
+        };
+
+        for task in subscriptions.values() {
+            task.abort();
+        }
+
+        result
+    }
+
+    fn handle_message(
+        &self,
+        message: ClientMessage,
+        size: usize,
+        ip: IpAddr,
+        tx: &Sender<RelayMessage>,
+        subscriptions: &mut HashMap<String, JoinHandle<()>>,
+    ) -> Option<RelayMessage> {
+        match message {
+            ClientMessage::Publish { event } => {
+                let id = event.id().clone();
+                let (accepted, message, reason) = match self.publish(*event, size, ip) {
+                    Ok(true) => (true, String::new(), None),
+                    Ok(false) => (true, "duplicate".to_string(), None),
+                    Err(rejection) => (false, rejection.to_string(), Some(rejection)),
+                };
+                Some(RelayMessage::Ok {
+                    id,
+                    accepted,
+                    message,
+                    reason,
+                })
+            }
+            ClientMessage::Subscribe {
+                subscription,
+                filter,
+            } => {
+                if let Some(task) = subscriptions.remove(&subscription) {
+                    task.abort();
+                }
+                if subscriptions.len() >= self.inner.max_subscriptions {
+                    return Some(RelayMessage::Closed {
+                        subscription,
+                        message: "too many subscriptions".to_string(),
+                    });
+                }
+                match self.subscribe(subscription.clone(), *filter, tx.clone()) {
+                    Ok(task) => {
+                        subscriptions.insert(subscription, task);
+                        None
+                    }
+                    Err(err) => Some(RelayMessage::Closed {
+                        subscription,
+                        message: err.to_string(),
+                    }),
+                }
+            }
+            ClientMessage::Unsubscribe { subscription } => {
+                if let Some(task) = subscriptions.remove(&subscription) {
+                    task.abort();
+                }
+                None
+            }
+            ClientMessage::Count {
+                subscription,
+                filter,
+            } => Some(match self.store().count(&filter) {
+                Ok(count) => RelayMessage::Count {
+                    subscription,
+                    count,
+                },
+                Err(err) => RelayMessage::Closed {
+                    subscription,
+                    message: err.to_string(),
+                },
+            }),
+        }
+    }
+
+    fn publish(&self, event: Event, size: usize, ip: IpAddr) -> Result<bool, Rejection> {
+        let policy = &self.inner.policy;
+        let now = Instant::now();
+        policy.check_event(&event, size)?;
+        policy.check_ip(ip, now)?;
+
+        let mut store = self.store();
+        let pub_key = match &event {
+            Event::Station(station) => station.pub_key,
+            _ => match store.get(event.station_id()).map_err(store_error)? {
+                Some(Event::Station(station)) => station.pub_key,
+                _ => {
+                    return Err(Rejection::Invalid {
+                        message: "unknown station".to_string(),
+                    })
+                }
+            },
+        };
+        event.verify(&pub_key).map_err(|err| Rejection::Invalid {
+            message: err.to_string(),
+        })?;
+        check_revoked(&*store, &event, &pub_key)?;
+        policy.check_pub_key(pub_key, now)?;
+
+        store.insert(event).map_err(store_error)
+    }
+
+    fn subscribe(
+        &self,
+        subscription: String,
+        filter: Filter,
+        tx: Sender<RelayMessage>,
+    ) -> Result<JoinHandle<()>> {
+        let (mut stored, mut events) = {
+            let mut store = self.store();
+            (store.count(&filter)?, store.subscribe(filter)?)
+        };
+
+        Ok(tokio::spawn(async move {
+            let eose = || RelayMessage::Eose {
+                subscription: subscription.clone(),
+            };
+            if stored == 0 && tx.send(eose()).await.is_err() {
+                return;
+            }
+            while let Some(event) = StreamExt::next(&mut events).await {
+                let message = RelayMessage::Event {
+                    subscription: subscription.clone(),
+                    event: Box::new(event),
+                };
+                if tx.send(message).await.is_err() {
+                    return;
+                }
+                if stored > 0 {
+                    stored -= 1;
+                    if stored == 0 && tx.send(eose()).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            if events.is_lagged() {
+                let closed = RelayMessage::Closed {
+                    subscription,
+                    message: "subscriber too slow".to_string(),
+                };
+                let _ = tx.send(closed).await;
+            }
+        }))
+    }
+}
+
+/// Refuses the event if it was signed with a key after one of the stored
+/// revocations of the key.
+fn check_revoked<S: Store>(
+    store: &S,
+    event: &Event,
+    pub_key: &XOnlyPublicKey,
+) -> Result<(), Rejection> {
+    let revocations = store
+        .query(&Filter::new().kinds([Kind::Revocation]))
+        .map_err(store_error)?;
+    for revocation in revocations {
+        let Event::Revocation(revocation) = revocation else {
+            continue;
+        };
+        let revoked_key = match store.get(&revocation.station_id).map_err(store_error)? {
+            Some(Event::Station(station)) => station.pub_key,
+            _ => continue,
+        };
+        if revoked_key == *pub_key
+            && revocation.verify(&revoked_key).is_ok()
+            && revocation.check(&revoked_key, event, pub_key).is_err()
+        {
+            return Err(Rejection::Revoked {
+                revoked_at: revocation.revoked_at,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn store_error(err: anyhow::Error) -> Rejection {
+    Rejection::Error {
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{Event, Filter};
+    use crate::policy::{IngestionPolicy, RateLimit, Rejection};
+    use crate::server::{ClientMessage, RelayConfig, RelayMessage, RelayServer};
+    use crate::{test_vectors, Kind, MemoryStore, Revocation};
+    use futures_util::{SinkExt, StreamExt};
+    use std::net::{IpAddr, Ipv4Addr};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    #[tokio::test]
+    async fn test_relay_server() {
+        let config = RelayConfig {
+            policy: IngestionPolicy {
+                per_ip: Some(RateLimit {
+                    burst: 3,
+                    per_minute: 1,
+                }),
+                ..IngestionPolicy::default()
+            },
+            ..RelayConfig::default()
+        };
+        let server = RelayServer::new(MemoryStore::new(), config);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.serve(listener).await }
+        });
+
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+
+        macro_rules! send {
+            ($message:expr) => {
+                ws.send(Message::Text(serde_json::to_string(&$message).unwrap()))
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! recv {
+            () => {
+                match ws.next().await.unwrap().unwrap() {
+                    Message::Text(text) => serde_json::from_str::<RelayMessage>(&text).unwrap(),
+                    message => panic!("unexpected message {:?}", message),
+                }
+            };
+        }
+        macro_rules! accepted {
+            ($message:expr) => {
+                match $message {
+                    RelayMessage::Ok { accepted, .. } => accepted,
+                    message => panic!("unexpected message {:?}", message),
+                }
+            };
+        }
+
+        let station = Event::Station(test_vectors::station());
+        let qso = Event::Qso(test_vectors::qso());
+
+        send!(ClientMessage::Publish {
+            event: Box::new(qso.clone())
+        });
+        assert!(!accepted!(recv!()));
+
+        send!(ClientMessage::Publish {
+            event: Box::new(station)
+        });
+        assert!(accepted!(recv!()));
+
+        send!(ClientMessage::Subscribe {
+            subscription: "qsos".to_string(),
+            filter: Box::new(Filter::new().kinds([Kind::Qso])),
+        });
+        assert_eq!(
+            recv!(),
+            RelayMessage::Eose {
+                subscription: "qsos".to_string()
+            }
+        );
+
+        send!(ClientMessage::Publish {
+            event: Box::new(qso.clone())
+        });
+        let mut messages = vec![recv!(), recv!()];
+        messages.sort_by_key(|message| matches!(message, RelayMessage::Event { .. }));
+        assert!(accepted!(messages.remove(0)));
+        assert_eq!(
+            messages[0],
+            RelayMessage::Event {
+                subscription: "qsos".to_string(),
+                event: Box::new(qso.clone())
+            }
+        );
+        assert_eq!(server.store().len(), 2);
+
+        send!(ClientMessage::Count {
+            subscription: "total".to_string(),
+            filter: Box::new(Filter::new().kinds([Kind::Qso])),
+        });
+        assert_eq!(
+            recv!(),
+            RelayMessage::Count {
+                subscription: "total".to_string(),
+                count: 1
+            }
+        );
+
+        send!(ClientMessage::Publish {
+            event: Box::new(qso)
+        });
+        match recv!() {
+            RelayMessage::Ok {
+                accepted,
+                message,
+                reason,
+                ..
+            } => {
+                assert!(!accepted);
+                assert!(message.starts_with("rate-limited"));
+                assert_eq!(reason, Some(Rejection::RateLimited { retry_after: 60 }));
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+
+        ws.send(Message::Text("{}".to_string())).await.unwrap();
+        assert!(matches!(recv!(), RelayMessage::Notice { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message() {
+        let config = RelayConfig {
+            policy: IngestionPolicy {
+                max_event_size: Some(1024),
+                ..IngestionPolicy::default()
+            },
+            ..RelayConfig::default()
+        };
+        let server = RelayServer::new(MemoryStore::new(), config);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.serve(listener).await }
+        });
+
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+
+        // A valid message, padded past the limit. Once parsed it would be
+        // answered with a rejection, instead the connection is dropped.
+        let publish = ClientMessage::Publish {
+            event: Box::new(Event::Station(test_vectors::station())),
+        };
+        let text = " ".repeat(4096) + &serde_json::to_string(&publish).unwrap();
+        let _ = ws.send(Message::Text(text)).await;
+        assert!(!matches!(ws.next().await, Some(Ok(Message::Text(_)))));
+        assert_eq!(server.store().len(), 0);
+    }
+
+    #[test]
+    fn test_revoked_key() {
+        let server = RelayServer::new(MemoryStore::new(), RelayConfig::default());
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let station = test_vectors::station();
+        let revocation = Revocation::new(
+            station.id.clone(),
+            &test_vectors::station_keypair(),
+            test_vectors::CREATED_AT - 1,
+        )
+        .unwrap();
+
+        assert!(server.publish(Event::Station(station), 0, ip).unwrap());
+        assert!(server
+            .publish(Event::Revocation(revocation), 0, ip)
+            .unwrap());
+        assert_eq!(
+            server.publish(Event::Qso(test_vectors::qso()), 0, ip),
+            Err(Rejection::Revoked {
+                revoked_at: test_vectors::CREATED_AT - 1
+            })
+        );
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::merkle::{self, MerkleProof};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{version, Id, Kind, Qso, QsoData, Station};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// Signs a batch of QSOs, e.g. a whole contest log.
+///
+/// The signer public key is fetched once for the whole session and every QSO
+/// shares the session `created_at`. Only the first signature is checked
+/// against the station key, it proves the signer holds it, the rest are
+/// taken as returned. The ids of the signed QSOs are kept so a
+/// [`BatchManifest`] can be issued at the end of the session.
+pub struct SigningSession<'a> {
+    signer: &'a dyn Signer,
+    pub_key: XOnlyPublicKey,
+    station_id: Id,
+    created_at: u64,
+    qso_ids: Vec<Id>,
+}
+
+impl<'a> SigningSession<'a> {
+    /// Starts a new session for the station with the current time as
+    /// creation time. The signer must sign with the station key.
+    pub fn new(signer: &'a dyn Signer, station: &Station) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        if pub_key != station.pub_key {
+            bail!("the signer doesn't hold the station key");
+        }
+
+        Ok(Self {
+            signer,
+            pub_key,
+            station_id: station.id.clone(),
+            created_at: unix_timstamp(),
+            qso_ids: vec![],
+        })
+    }
+
+    /// Returns the creation time shared by the QSOs of the session.
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    /// Returns the number of QSOs signed so far.
+    pub fn len(&self) -> usize {
+        self.qso_ids.len()
+    }
+
+    /// Returns true if no QSO was signed yet.
+    pub fn is_empty(&self) -> bool {
+        self.qso_ids.is_empty()
+    }
+
+    /// Signs a QSO of the session station.
+    pub fn sign(&mut self, qso_data: QsoData) -> Result<Qso> {
+        if qso_data.station_id != self.station_id {
+            bail!("qso of another station");
+        }
+
+        let pub_key = self.is_empty().then_some(&self.pub_key);
+        let qso = Qso::create_with_pub_key(
+            qso_data,
+            self.signer,
+            pub_key,
+            self.created_at,
+            None,
+            None,
+            false,
+        )?;
+        self.qso_ids.push(qso.id.clone());
+        Ok(qso)
+    }
+
+    /// Signs all the QSOs, stopping at the first error.
+    pub fn sign_all<I>(&mut self, qsos: I) -> Result<Vec<Qso>>
+    where
+        I: IntoIterator<Item = QsoData>,
+    {
+        qsos.into_iter()
+            .map(|qso_data| self.sign(qso_data))
+            .collect()
+    }
+
+    /// Issues a manifest covering every QSO signed in the session.
+    pub fn manifest(&self) -> Result<BatchManifest> {
+        BatchManifest::new(
+            self.station_id.clone(),
+            self.signer,
+            &self.pub_key,
+            &self.qso_ids,
+            self.created_at,
+        )
+    }
+}
+
+/// A signed summary of a signing session: the number of QSOs and the merkle
+/// root of their ids, in signing order.
+///
+/// Anyone holding the manifest can check that a QSO is part of the batch with
+/// a merkle proof, and that no QSO was added to the batch afterwards.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BatchManifest {
+    pub id: Id,
+    pub station_id: Id,
+    pub qso_count: u64,
+    pub merkle_root: Id,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl BatchManifest {
+    /// Current version of the batch manifest object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the batch manifest verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    fn new(
+        station_id: Id,
+        signer: &dyn Signer,
+        pub_key: &XOnlyPublicKey,
+        qso_ids: &[Id],
+        created_at: u64,
+    ) -> Result<Self> {
+        let qso_count = qso_ids.len() as u64;
+        let merkle_root = merkle::root(qso_ids);
+        let id = Self::compute_id(
+            &station_id,
+            qso_count,
+            &merkle_root,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, pub_key, &id)?;
+
+        let manifest = Self {
+            id,
+            station_id,
+            qso_count,
+            merkle_root,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        manifest.validate()?;
+
+        Ok(manifest)
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Verify that the QSO is part of the batch.
+    pub fn verify_qso(&self, qso: &Qso, proof: &MerkleProof) -> Result<()> {
+        if qso.station_id != self.station_id || qso.created_at != self.created_at {
+            bail!("qso not signed in the batch");
+        }
+
+        if proof.leaf_count as u64 != self.qso_count {
+            bail!("invalid proof leaf count");
+        }
+
+        merkle::verify_proof(&self.merkle_root, &qso.id, proof)
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        qso_count: u64,
+        merkle_root: &Id,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(station_id, qso_count, merkle_root, created_at, version),
+        )
+    }
+}
+
+impl Validate for BatchManifest {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)
+    }
+}
+
+impl Signable for BatchManifest {
+    const KIND: Kind = Kind::BatchManifest;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            self.qso_count,
+            &self.merkle_root,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::merkle;
+    use crate::session::{BatchManifest, SigningSession};
+    use crate::{test_vectors, Id, QsoData, Station};
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_signing_session() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso_data = |i: u64| QsoData {
+            station_id: station.id.clone(),
+            callsign: format!("LW{}DZR", i),
+            datetime: 1704141426 + i,
+            comments: "".to_string(),
+            ..test_vectors::qso_data()
+        };
+
+        let qsos = (0..10).map(qso_data);
+
+        let mut session = SigningSession::new(&keys, &station).unwrap();
+        let signed = session.sign_all(qsos).unwrap();
+        assert_eq!(session.len(), 10);
+
+        for qso in &signed {
+            qso.verify(&station.pub_key).unwrap();
+            assert_eq!(qso.created_at, session.created_at());
+        }
+
+        let manifest = session.manifest().unwrap();
+        let json_str = serde_json::to_string(&manifest).unwrap();
+        let manifest: BatchManifest = serde_json::from_str(&json_str).unwrap();
+        manifest.verify(&station.pub_key).unwrap();
+
+        let ids: Vec<_> = signed.iter().map(|qso| qso.id.clone()).collect();
+        let proof = merkle::prove(&ids, 3).unwrap();
+        manifest.verify_qso(&signed[3], &proof).unwrap();
+        assert!(manifest.verify_qso(&signed[4], &proof).is_err());
+
+        // The session only signs for the station holding the signer key.
+        assert!(SigningSession::new(&generate_keypair(), &station).is_err());
+        let mut other = qso_data(10);
+        other.station_id = Id::new("another station");
+        assert!(session.sign(other).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::instrument::{self, Verification};
+use crate::{Id, Kind};
+use anyhow::{anyhow, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::{Secp256k1, XOnlyPublicKey, SECP256K1};
+use std::time::Instant;
+
+/// Validates the fields of an object.
+pub trait Validate {
+    /// Returns an error if any of the object fields is invalid.
+    fn validate(&self) -> Result<()>;
+}
+
+/// A signed object identified by the hash of its fields.
+pub trait Signable: Validate {
+    /// The kind of the object.
+    const KIND: Kind;
+
+    /// Returns the object id.
+    fn id(&self) -> &Id;
+
+    /// Returns the object signature.
+    fn sig(&self) -> &Signature;
+
+    /// Returns the object creation time.
+    fn created_at(&self) -> u64;
+
+    /// Generates the id from the object fields.
+    fn generate_id(&self) -> Id;
+
+    /// Verify the object id and signature against the signer public key and
+    /// validate the object fields.
+    fn verify_signature(&self, pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature_with_context(SECP256K1, pub_key)
+    }
+
+    /// Like [`Signable::verify_signature`] but checks the signature with the
+    /// given context instead of the global one.
+    fn verify_signature_with_context<C: secp256k1::Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        pub_key: &XOnlyPublicKey,
+    ) -> Result<()> {
+        verify_with(self, secp, pub_key, Self::validate)
+    }
+}
+
+/// Verifies the object id and signature like [`Signable::verify_signature`]
+/// but validates the object fields with the given function.
+pub(crate) fn verify_with<T, C>(
+    object: &T,
+    secp: &Secp256k1<C>,
+    pub_key: &XOnlyPublicKey,
+    validate: impl FnOnce(&T) -> Result<()>,
+) -> Result<()>
+where
+    T: Signable + ?Sized,
+    C: secp256k1::Verification,
+{
+    let _span = tracing::debug_span!("verify", kind = T::KIND.as_str()).entered();
+    let start = Instant::now();
+
+    let id = object.generate_id();
+
+    let result = if id != *object.id() {
+        Err(anyhow!("invalid id"))
+    } else {
+        id.verify_with_context(secp, pub_key, object.sig())
+    };
+    if let Err(err) = result {
+        instrument::record_verify(T::KIND, Verification::InvalidSignature, start.elapsed());
+        return Err(err);
+    }
+
+    if let Err(err) = validate(object) {
+        instrument::record_verify(T::KIND, Verification::InvalidFields, start.elapsed());
+        return Err(err);
+    }
+
+    instrument::record_verify(T::KIND, Verification::Verified, start.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::signable::Signable;
+    use crate::{Certificate, Kind, Station};
+    use codes_iso_3166::part_1::CountryCode;
+    use secp256k1::XOnlyPublicKey;
+
+    fn verify_generic<T: Signable>(object: &T, pub_key: &XOnlyPublicKey) -> Kind {
+        object.verify_signature(pub_key).unwrap();
+        T::KIND
+    }
+
+    #[test]
+    fn test_generic_verify() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let certificate = Certificate::new(station.id.clone(), &keys, station.id.clone());
+
+        assert_eq!(verify_generic(&station, &station.pub_key), Kind::Station);
+        assert_eq!(
+            verify_generic(&certificate, &station.pub_key),
+            Kind::Certificate
+        );
+    }
+}
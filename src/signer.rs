@@ -0,0 +1,212 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{instrument, Id};
+use anyhow::{Context, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::{Keypair, Secp256k1, Signing, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+
+/// Request sent to a signer asking for the signature of an object id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SignRequest {
+    pub id: Id,
+}
+
+/// Response returned by a signer with the signature of the requested id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SignResponse {
+    pub sig: Signature,
+}
+
+/// Signs object ids on behalf of a station.
+///
+/// Implementations may keep the secret key in a separate process or in an
+/// external device, the only requirement is to answer sign requests with a
+/// valid schnorr signature for the signer public key.
+///
+/// Threshold setups plug in the same way, see [`crate::frost::Coordinator`],
+/// as long as the aggregated signature is a regular BIP340 signature for the
+/// group public key.
+pub trait Signer {
+    /// Returns the public key of the signer.
+    fn public_key(&self) -> Result<XOnlyPublicKey>;
+
+    /// Signs the requested id.
+    fn sign(&self, request: &SignRequest) -> Result<SignResponse>;
+}
+
+impl Signer for Keypair {
+    fn public_key(&self) -> Result<XOnlyPublicKey> {
+        Ok(self.x_only_public_key().0)
+    }
+
+    fn sign(&self, request: &SignRequest) -> Result<SignResponse> {
+        Ok(SignResponse {
+            sig: request.id.sign(self),
+        })
+    }
+}
+
+/// Signs with a local key pair using a caller provided context instead of the
+/// global one.
+pub struct ContextSigner<C: Signing> {
+    secp: Secp256k1<C>,
+    keys: Keypair,
+}
+
+impl<C: Signing> ContextSigner<C> {
+    /// Creates a signer for the key pair using the given context.
+    pub fn new(secp: Secp256k1<C>, keys: Keypair) -> Self {
+        Self { secp, keys }
+    }
+}
+
+impl<C: Signing> Signer for ContextSigner<C> {
+    fn public_key(&self) -> Result<XOnlyPublicKey> {
+        Ok(self.keys.x_only_public_key().0)
+    }
+
+    fn sign(&self, request: &SignRequest) -> Result<SignResponse> {
+        Ok(SignResponse {
+            sig: request.id.sign_with_context(&self.secp, &self.keys),
+        })
+    }
+}
+
+/// Signs the id with the signer and checks the returned signature, so a
+/// misbehaving signer can't produce objects that fail verification later.
+pub(crate) fn sign_id(signer: &dyn Signer, pub_key: &XOnlyPublicKey, id: &Id) -> Result<Signature> {
+    let sig = sign_id_unchecked(signer, id)?;
+    id.verify(pub_key, &sig)
+        .context("signer returned an invalid signature")?;
+    Ok(sig)
+}
+
+/// Signs the id with the signer without checking the returned signature, for
+/// callers that check the signer some other way, e.g. batch sessions.
+pub(crate) fn sign_id_unchecked(signer: &dyn Signer, id: &Id) -> Result<Signature> {
+    let _span = tracing::debug_span!("sign", %id).entered();
+    let response = signer.sign(&SignRequest { id: id.clone() })?;
+    instrument::record_sign();
+    Ok(response.sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::signable::Signable;
+    use crate::signer::{ContextSigner, SignRequest, SignResponse, Signer};
+    use crate::{signing_context, test_vectors, Qso, QsoData, Station};
+    use anyhow::Result;
+    use codes_iso_3166::part_1::CountryCode;
+    use secp256k1::{Secp256k1, XOnlyPublicKey};
+
+    struct WrongKeySigner;
+
+    impl Signer for WrongKeySigner {
+        fn public_key(&self) -> Result<XOnlyPublicKey> {
+            Ok(generate_keypair().x_only_public_key().0)
+        }
+
+        fn sign(&self, request: &SignRequest) -> Result<SignResponse> {
+            generate_keypair().sign(request)
+        }
+    }
+
+    #[test]
+    fn test_sign_with_signer() {
+        let keys = generate_keypair();
+        let signer: &dyn Signer = &keys;
+
+        let station = Station::new_with_signer(
+            signer,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        station.verify().unwrap();
+
+        let qso = Qso::new_with_signer(
+            QsoData {
+                station_id: station.id.clone(),
+                datetime: 1704141426,
+                mode: "CW".to_string(),
+                rst: "599".to_string(),
+                ..test_vectors::qso_data()
+            },
+            signer,
+        )
+        .unwrap();
+
+        qso.verify(&station.pub_key).unwrap();
+    }
+
+    #[test]
+    fn test_protocol_serde() {
+        let keys = generate_keypair();
+        let request = SignRequest {
+            id: crate::Id::new("test"),
+        };
+
+        let request_json = serde_json::to_string(&request).unwrap();
+        let request_dese: SignRequest = serde_json::from_str(&request_json).unwrap();
+
+        let response = keys.sign(&request_dese).unwrap();
+        let response_json = serde_json::to_string(&response).unwrap();
+        let response_dese: SignResponse = serde_json::from_str(&response_json).unwrap();
+
+        request
+            .id
+            .verify(&keys.x_only_public_key().0, &response_dese.sig)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_invalid_signer() {
+        let station = Station::new_with_signer(
+            &WrongKeySigner,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        );
+
+        assert!(station.is_err());
+    }
+
+    #[test]
+    fn test_context_signer() {
+        let keys = generate_keypair();
+        let signer = ContextSigner::new(signing_context(), keys);
+
+        let station = Station::new_with_signer(
+            &signer,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let secp = Secp256k1::verification_only();
+        station
+            .verify_signature_with_context(&secp, &station.pub_key)
+            .unwrap();
+
+        let other = generate_keypair().x_only_public_key().0;
+        assert!(station
+            .verify_signature_with_context(&secp, &other)
+            .is_err());
+    }
+}
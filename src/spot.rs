@@ -0,0 +1,259 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::Band;
+use crate::callsign::{Callsign, CallsignPolicy, StrictCallsign};
+use crate::limits::{self, Limits};
+use crate::qso::{COMMENTS_MAX_LEN, MODE_MAX_LEN};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{text, version, Id, Kind, Tag};
+use anyhow::{anyhow, bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// Seconds a spot stays on the relays.
+pub const SPOT_LIFETIME: u64 = 30 * 60;
+
+/// Maximum lifetime of a spot, longer expirations are invalid.
+const MAX_SPOT_LIFETIME: u64 = 2 * 60 * 60;
+
+/// A DX spot: the spotter station heard the spotted callsign on a
+/// frequency.
+///
+/// Spots are tagged with the spotted callsign, its band and a short
+/// [`Tag::ExpiresAt`], so relays can serve them as a DX cluster and prune
+/// them once they are stale.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Spot {
+    pub id: Id,
+    /// The spotter station.
+    pub station_id: Id,
+    pub callsign: String,
+    pub freq: u64,
+    /// Mode, empty if unknown.
+    pub mode: String,
+    pub comment: String,
+    pub tags: Vec<Tag>,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl Spot {
+    /// Current version of the spot object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the spot verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new Spot expiring after [`SPOT_LIFETIME`] and signs the
+    /// object using the spotter signer.
+    pub fn new(
+        station_id: Id,
+        signer: &dyn Signer,
+        callsign: &str,
+        freq: u64,
+        mode: String,
+        comment: String,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let callsign = Callsign::normalize(callsign);
+
+        let mut tags = vec![Tag::Callsign(callsign.clone())];
+        tags.extend(Band::from_freq(freq).map(Tag::Band));
+        tags.push(Tag::ExpiresAt(created_at + SPOT_LIFETIME));
+
+        let callsign = callsign.to_string();
+        let id = Self::compute_id(
+            &station_id,
+            &callsign,
+            freq,
+            &mode,
+            &comment,
+            &tags,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let spot = Self {
+            id,
+            station_id,
+            callsign,
+            freq,
+            mode,
+            comment,
+            tags,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        spot.validate()?;
+
+        Ok(spot)
+    }
+
+    /// Verify the object signature against the spotter public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Returns the expiration time of the spot.
+    pub fn expires_at(&self) -> Option<u64> {
+        self.tags
+            .iter()
+            .filter_map(|tag| match tag {
+                Tag::ExpiresAt(expires_at) => Some(*expires_at),
+                _ => None,
+            })
+            .min()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compute_id(
+        station_id: &Id,
+        callsign: &str,
+        freq: u64,
+        mode: &str,
+        comment: &str,
+        tags: &[Tag],
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                station_id, callsign, freq, mode, comment, tags, created_at, version,
+            ),
+        )
+    }
+}
+
+impl Validate for Spot {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        StrictCallsign.validate(&self.callsign)?;
+
+        if self.freq == 0 {
+            bail!("invalid freq");
+        }
+
+        if !text::is_valid(&self.mode, MODE_MAX_LEN) {
+            bail!("invalid mode");
+        }
+
+        if !text::is_valid(&self.comment, COMMENTS_MAX_LEN) {
+            bail!("invalid comment");
+        }
+
+        if self.tags.len() > crate::MAX_TAGS {
+            bail!("more than {} tags", crate::MAX_TAGS);
+        }
+        limits::check_tags(&self.tags, Limits::DEFAULT.tag)?;
+
+        let expires_at = self
+            .expires_at()
+            .ok_or_else(|| anyhow!("spot without expiration"))?;
+        if expires_at.saturating_sub(self.created_at) > MAX_SPOT_LIFETIME {
+            bail!("spot lifetime longer than {}s", MAX_SPOT_LIFETIME);
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for Spot {
+    const KIND: Kind = Kind::Spot;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.callsign,
+            self.freq,
+            &self.mode,
+            &self.comment,
+            &self.tags,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::Band;
+    use crate::event::{Event, Filter};
+    use crate::signable::Validate;
+    use crate::spot::{Spot, SPOT_LIFETIME};
+    use crate::store::{MemoryStore, Store};
+    use crate::{test_vectors, Tag};
+
+    #[test]
+    fn test_spot() {
+        let spotter = test_vectors::station();
+        let spot = Spot::new(
+            spotter.id.clone(),
+            &test_vectors::station_keypair(),
+            "vp8ldx",
+            14_025_000,
+            "CW".to_string(),
+            "up 2".to_string(),
+        )
+        .unwrap();
+        assert_eq!(spot.callsign, "VP8LDX");
+        assert_eq!(spot.expires_at(), Some(spot.created_at + SPOT_LIFETIME));
+
+        let json_str = serde_json::to_string(&Event::Spot(spot.clone())).unwrap();
+        let event: Event = serde_json::from_str(&json_str).unwrap();
+        event.verify(&spotter.pub_key).unwrap();
+        assert!(!event.is_expired(spot.created_at));
+        assert!(event.is_expired(spot.created_at + SPOT_LIFETIME));
+
+        let mut store = MemoryStore::new();
+        store.insert(event.clone()).unwrap();
+        let cluster = Filter::new().tags([Band::M20.into()]);
+        assert_eq!(store.query(&cluster).unwrap(), vec![event]);
+        assert_eq!(
+            store
+                .prune_expired(spot.created_at + SPOT_LIFETIME)
+                .unwrap(),
+            1
+        );
+
+        let mut forever = spot;
+        forever.tags.retain(|tag| !matches!(tag, Tag::ExpiresAt(_)));
+        assert!(forever.validate().is_err());
+        forever.tags.push(Tag::ExpiresAt(u64::MAX));
+        assert!(forever.validate().is_err());
+    }
+}
@@ -0,0 +1,824 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SQLite event store, enabled by the `sqlite` feature.
+//!
+//! [`SqliteStore`] implements both [`Store`] and [`AsyncStore`]. The async
+//! methods run the queries on the tokio blocking thread pool, so they must be
+//! called from a tokio runtime.
+//!
+//! A database holds many isolated logbooks, one per namespace, see
+//! [`SqliteStore::namespace`]. Databases created before namespaces existed
+//! are migrated on open, their events land in the default namespace.
+//!
+//! Callsigns are indexed normalized and grid squares uppercase, so callsign
+//! patterns and grid squares are looked up by their prefix, see
+//! [`crate::CallsignPattern`]. Databases created before an index existed are
+//! indexed on open.
+
+use crate::event::{Event, Filter};
+use crate::store::{AsyncStore, Store};
+use crate::subscription::{Subscribers, Subscription};
+use crate::{limits, Callsign, Id, Tag};
+use anyhow::{anyhow, Result};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Transaction};
+use secp256k1::XOnlyPublicKey;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS events (
+        namespace TEXT NOT NULL DEFAULT '',
+        id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        station_id TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        json TEXT NOT NULL,
+        PRIMARY KEY (namespace, id)
+    );
+    CREATE INDEX IF NOT EXISTS events_created_at ON events (namespace, created_at, id);
+    CREATE INDEX IF NOT EXISTS events_station_id ON events (namespace, station_id, created_at);
+    CREATE TABLE IF NOT EXISTS tags (
+        namespace TEXT NOT NULL DEFAULT '',
+        tag TEXT NOT NULL,
+        event_id TEXT NOT NULL,
+        PRIMARY KEY (namespace, tag, event_id)
+    );
+    CREATE TABLE IF NOT EXISTS callsigns (
+        namespace TEXT NOT NULL DEFAULT '',
+        callsign TEXT NOT NULL,
+        event_id TEXT NOT NULL,
+        PRIMARY KEY (namespace, callsign, event_id)
+    );
+    CREATE TABLE IF NOT EXISTS grids (
+        namespace TEXT NOT NULL DEFAULT '',
+        grid TEXT NOT NULL,
+        event_id TEXT NOT NULL,
+        PRIMARY KEY (namespace, grid, event_id)
+    );
+";
+
+/// Indexed values of the event content: the table, its value column and the
+/// value of an event.
+type ContentIndex = (&'static str, &'static str, fn(&Event) -> Option<String>);
+
+const CONTENT_INDEXES: &[ContentIndex] = &[
+    ("callsigns", "callsign", |event| {
+        event
+            .callsign()
+            .map(|callsign| Callsign::normalize(callsign).to_string())
+    }),
+    ("grids", "grid", |event| {
+        event.grid().map(str::to_ascii_uppercase)
+    }),
+];
+
+/// Moves the tables of a database without namespaces aside, the events are
+/// copied into the default namespace once [`SCHEMA`] is created.
+const MIGRATE_NAMESPACES: &str = "
+    ALTER TABLE events RENAME TO events_v0;
+    ALTER TABLE tags RENAME TO tags_v0;
+    DROP INDEX IF EXISTS events_created_at;
+    DROP INDEX IF EXISTS events_station_id;
+";
+
+const COPY_NAMESPACES: &str = "
+    INSERT INTO events (id, kind, station_id, created_at, json)
+        SELECT id, kind, station_id, created_at, json FROM events_v0;
+    INSERT INTO tags (tag, event_id) SELECT tag, event_id FROM tags_v0;
+    DROP TABLE events_v0;
+    DROP TABLE tags_v0;
+";
+
+/// Namespace of the stores not scoped to a station key.
+const DEFAULT_NAMESPACE: &str = "";
+
+/// A [`Store`] backed by a SQLite database. Clones share the connection.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+    namespace: String,
+    subscribers: Arc<Mutex<BTreeMap<String, Subscribers>>>,
+}
+
+impl SqliteStore {
+    /// Opens the database at the path, creating it if missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Opens a new in memory database.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        let tx = conn.unchecked_transaction()?;
+        let unnamespaced: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'events')
+                AND NOT EXISTS (SELECT 1 FROM pragma_table_info('events') WHERE name = 'namespace')",
+            [],
+            |row| row.get(0),
+        )?;
+        let mut unindexed = vec![];
+        for index in CONTENT_INDEXES {
+            let missing: bool = tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'events')
+                    AND NOT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [index.0],
+                |row| row.get(0),
+            )?;
+            if missing {
+                unindexed.push(index);
+            }
+        }
+        if unnamespaced {
+            tracing::info!("migrating sqlite store to namespaces");
+            tx.execute_batch(MIGRATE_NAMESPACES)?;
+            tx.execute_batch(SCHEMA)?;
+            tx.execute_batch(COPY_NAMESPACES)?;
+        } else {
+            tx.execute_batch(SCHEMA)?;
+        }
+        for index in unindexed {
+            tracing::info!(table = index.0, "indexing sqlite store");
+            backfill(&tx, index)?;
+        }
+        tx.commit()?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            subscribers: Arc::default(),
+        })
+    }
+
+    /// Returns the store of the logbook of the station key, sharing the
+    /// database but isolated from every other namespace: queries, exports,
+    /// subscriptions and deletions only see its own events.
+    ///
+    /// Hosting providers serve many users from one database file by giving
+    /// each one the namespace of its key.
+    pub fn namespace(&self, pub_key: &XOnlyPublicKey) -> Self {
+        Self {
+            namespace: pub_key.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the station keys of the namespaces holding events.
+    pub fn namespaces(&self) -> Result<Vec<XOnlyPublicKey>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT DISTINCT namespace FROM events WHERE namespace != ?1 ORDER BY namespace",
+            )?;
+            let rows = stmt.query_map([DEFAULT_NAMESPACE], |row| row.get::<_, String>(0))?;
+            rows.map(|namespace| Ok(XOnlyPublicKey::from_str(&namespace?)?))
+                .collect()
+        })
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow!("sqlite connection poisoned"))?;
+        f(&conn)
+    }
+
+    /// Runs `f` with the connection on the blocking thread pool.
+    fn spawn<T, F>(&self, f: F) -> impl Future<Output = Result<T>> + Send
+    where
+        T: Send + 'static,
+        F: FnOnce(&SqliteStore) -> Result<T> + Send + 'static,
+    {
+        let store = self.clone();
+        async move { tokio::task::spawn_blocking(move || f(&store)).await? }
+    }
+
+    /// Locks the subscribers of every namespace, always before the
+    /// connection.
+    fn subscribers(&self) -> Result<MutexGuard<'_, BTreeMap<String, Subscribers>>> {
+        self.subscribers
+            .lock()
+            .map_err(|_| anyhow!("sqlite subscribers poisoned"))
+    }
+
+    fn insert_event(&self, event: &Event) -> Result<bool> {
+        let _span = tracing::debug_span!("sqlite_insert", id = %event.id()).entered();
+        let json = serde_json::to_string(event)?;
+        limits::check_size(event.kind(), json.len())?;
+        let mut subscribers = self.subscribers()?;
+        let inserted = self.with_conn(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO events (namespace, id, kind, station_id, created_at, json)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    self.namespace,
+                    event.id().to_string(),
+                    event.kind().as_str(),
+                    event.station_id().to_string(),
+                    event.created_at() as i64,
+                    json,
+                ],
+            )?;
+            if inserted > 0 {
+                for tag in event.tags() {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO tags (namespace, tag, event_id) VALUES (?1, ?2, ?3)",
+                        params![
+                            self.namespace,
+                            serde_json::to_string(tag)?,
+                            event.id().to_string()
+                        ],
+                    )?;
+                }
+                for index in CONTENT_INDEXES {
+                    if let Some(value) = index.2(event) {
+                        tx.execute(
+                            &insert_indexed(index),
+                            params![self.namespace, value, event.id().to_string()],
+                        )?;
+                    }
+                }
+            }
+            tx.commit()?;
+            Ok(inserted > 0)
+        })?;
+        if inserted {
+            if let Some(subscribers) = subscribers.get_mut(&self.namespace) {
+                subscribers.notify(event);
+            }
+        }
+        Ok(inserted)
+    }
+
+    fn get_event(&self, id: &Id) -> Result<Option<Event>> {
+        let _span = tracing::debug_span!("sqlite_get", %id).entered();
+        let json: Option<String> = self.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT json FROM events WHERE namespace = ?1 AND id = ?2",
+                    [&self.namespace, &id.to_string()],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        })?;
+        json.map(|json| Ok(serde_json::from_str(&json)?))
+            .transpose()
+    }
+
+    fn query_events(&self, filter: &Filter) -> Result<Vec<Event>> {
+        let _span = tracing::debug_span!("sqlite_query").entered();
+        let (sql, values) = select("json", &self.namespace, filter);
+        let events: Vec<Event> = self.with_conn(|conn| {
+            let mut stmt = conn.prepare_cached(&sql)?;
+            let rows = stmt.query_map(params_from_iter(values), |row| row.get::<_, String>(0))?;
+            rows.map(|json| Ok(serde_json::from_str(&json?)?)).collect()
+        })?;
+        if !filter.matches_content() {
+            return Ok(events);
+        }
+        Ok(events
+            .into_iter()
+            .filter(|event| filter.matches(event))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    fn count_events(&self, filter: &Filter) -> Result<u64> {
+        let _span = tracing::debug_span!("sqlite_count").entered();
+        if filter.matches_content() {
+            return Ok(self.query_events(filter)?.len() as u64);
+        }
+        // Only the indexed columns are read, never the json.
+        let (sql, values) = select("id", &self.namespace, filter);
+        let sql = format!("SELECT COUNT(*) FROM ({})", sql);
+        self.with_conn(|conn| {
+            let count: i64 = conn
+                .prepare_cached(&sql)?
+                .query_row(params_from_iter(values), |row| row.get(0))?;
+            Ok(count as u64)
+        })
+    }
+
+    fn prune_expired_events(&self, now: u64) -> Result<u64> {
+        let _span = tracing::debug_span!("sqlite_prune_expired").entered();
+        self.with_conn(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            let mut expired = vec![];
+            {
+                let mut stmt = tx.prepare(
+                    "SELECT tag, event_id FROM tags
+                     WHERE namespace = ?1 AND tag LIKE '[\"expires_at\",%'",
+                )?;
+                let rows = stmt.query_map([&self.namespace], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?;
+                for row in rows {
+                    let (tag, event_id) = row?;
+                    if let Ok(Tag::ExpiresAt(expires_at)) = serde_json::from_str(&tag) {
+                        if expires_at <= now {
+                            expired.push(event_id);
+                        }
+                    }
+                }
+            }
+
+            let deleted = delete_in(&tx, &self.namespace, expired)?;
+            tx.commit()?;
+            Ok(deleted)
+        })
+    }
+
+    fn delete_events(&self, ids: &[Id]) -> Result<u64> {
+        let _span = tracing::debug_span!("sqlite_delete", count = ids.len()).entered();
+        self.with_conn(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            let deleted = delete_in(&tx, &self.namespace, ids.iter().map(Id::to_string))?;
+            tx.commit()?;
+            Ok(deleted)
+        })
+    }
+
+    fn max_created_at(&self) -> Result<Option<u64>> {
+        self.with_conn(|conn| {
+            let max: Option<i64> = conn.query_row(
+                "SELECT MAX(created_at) FROM events WHERE namespace = ?1",
+                [&self.namespace],
+                |row| row.get(0),
+            )?;
+            Ok(max.map(|max| max as u64))
+        })
+    }
+}
+
+/// Deletes the events, their tags and their indexed values inside the
+/// transaction.
+fn delete_in(
+    tx: &Transaction,
+    namespace: &str,
+    ids: impl IntoIterator<Item = String>,
+) -> Result<u64> {
+    let mut deleted = 0;
+    for event_id in ids {
+        deleted += tx.execute(
+            "DELETE FROM events WHERE namespace = ?1 AND id = ?2",
+            [namespace, &event_id],
+        )?;
+        tx.execute(
+            "DELETE FROM tags WHERE namespace = ?1 AND event_id = ?2",
+            [namespace, &event_id],
+        )?;
+        for (table, _, _) in CONTENT_INDEXES {
+            tx.execute(
+                &format!(
+                    "DELETE FROM {} WHERE namespace = ?1 AND event_id = ?2",
+                    table
+                ),
+                [namespace, &event_id],
+            )?;
+        }
+    }
+    Ok(deleted as u64)
+}
+
+fn insert_indexed((table, column, _): &ContentIndex) -> String {
+    format!(
+        "INSERT OR IGNORE INTO {} (namespace, {}, event_id) VALUES (?1, ?2, ?3)",
+        table, column
+    )
+}
+
+/// Indexes the values of every stored event inside the transaction.
+fn backfill(tx: &Transaction, index: &ContentIndex) -> Result<()> {
+    let mut select = tx.prepare("SELECT namespace, id, json FROM events")?;
+    let mut insert = tx.prepare(&insert_indexed(index))?;
+    let mut rows = select.query([])?;
+    while let Some(row) = rows.next()? {
+        let event: Event = serde_json::from_str(&row.get::<_, String>(2)?)?;
+        if let Some(value) = index.2(&event) {
+            insert.execute(params![
+                row.get::<_, String>(0)?,
+                value,
+                row.get::<_, String>(1)?
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds the query selecting the column of the events of the namespace
+/// matching the filter. The content of the events isn't indexed, filters
+/// matching it are applied to the selected events, without limit.
+fn select(column: &str, namespace: &str, filter: &Filter) -> (String, Vec<Value>) {
+    let mut sql = format!("SELECT {} FROM events WHERE namespace = ?", column);
+    let mut values: Vec<Value> = vec![Value::Text(namespace.to_string())];
+
+    let mut any_of = |column: &str, items: Vec<String>| {
+        if !items.is_empty() {
+            let placeholders = vec!["?"; items.len()].join(", ");
+            sql.push_str(&format!(" AND {} IN ({})", column, placeholders));
+            values.extend(items.into_iter().map(Value::Text));
+        }
+    };
+    any_of("id", filter.ids.iter().map(Id::to_string).collect());
+    any_of(
+        "kind",
+        filter
+            .kinds
+            .iter()
+            .map(|kind| kind.as_str().to_string())
+            .collect(),
+    );
+    any_of(
+        "station_id",
+        filter.station_ids.iter().map(Id::to_string).collect(),
+    );
+
+    if !filter.tags.is_empty() {
+        let placeholders = vec!["?"; filter.tags.len()].join(", ");
+        sql.push_str(&format!(
+            " AND id IN (SELECT event_id FROM tags WHERE namespace = ? AND tag IN ({}))",
+            placeholders
+        ));
+        values.push(Value::Text(namespace.to_string()));
+        values.extend(
+            filter
+                .tags
+                .iter()
+                .map(|tag| Value::Text(serde_json::to_string(tag).unwrap_or_default())),
+        );
+    }
+
+    if !filter.callsigns.is_empty() {
+        // Patterns use the same wildcards as GLOB, the range over the prefix
+        // scans the index.
+        values.push(Value::Text(namespace.to_string()));
+        let mut patterns = vec![];
+        for pattern in &filter.callsigns {
+            if pattern.is_exact() {
+                patterns.push("callsign = ?");
+            } else if let Some(end) = prefix_end(pattern.prefix()) {
+                patterns.push("(callsign >= ? AND callsign < ? AND callsign GLOB ?)");
+                values.push(Value::Text(pattern.prefix().to_string()));
+                values.push(Value::Text(end));
+            } else {
+                patterns.push("callsign GLOB ?");
+            }
+            values.push(Value::Text(pattern.to_string()));
+        }
+        sql.push_str(&format!(
+            " AND id IN (SELECT event_id FROM callsigns WHERE namespace = ? AND ({}))",
+            patterns.join(" OR ")
+        ));
+    }
+
+    if !filter.grids.is_empty() {
+        values.push(Value::Text(namespace.to_string()));
+        let mut grids = vec![];
+        for grid in &filter.grids {
+            let grid = grid.to_ascii_uppercase();
+            match prefix_end(&grid) {
+                Some(end) => {
+                    grids.push("(grid >= ? AND grid < ?)");
+                    values.push(Value::Text(grid));
+                    values.push(Value::Text(end));
+                }
+                None => grids.push("grid IS NOT NULL"),
+            }
+        }
+        sql.push_str(&format!(
+            " AND id IN (SELECT event_id FROM grids WHERE namespace = ? AND ({}))",
+            grids.join(" OR ")
+        ));
+    }
+
+    if let Some(since) = filter.since {
+        sql.push_str(" AND created_at >= ?");
+        values.push(Value::Integer(since as i64));
+    }
+    if let Some(until) = filter.until {
+        sql.push_str(" AND created_at <= ?");
+        values.push(Value::Integer(until as i64));
+    }
+    sql.push_str(" ORDER BY created_at, id");
+    if let Some(limit) = filter.limit.filter(|_| !filter.matches_content()) {
+        sql.push_str(" LIMIT ?");
+        values.push(Value::Integer(limit.try_into().unwrap_or(i64::MAX)));
+    }
+
+    (sql, values)
+}
+
+/// Returns the smallest string greater than every string starting with the
+/// ASCII prefix, None for the empty prefix.
+fn prefix_end(prefix: &str) -> Option<String> {
+    let (last, start) = prefix.as_bytes().split_last()?;
+    let mut end = String::from_utf8_lossy(start).into_owned();
+    end.push(char::from(last + 1));
+    Some(end)
+}
+
+impl Store for SqliteStore {
+    fn insert(&mut self, event: Event) -> Result<bool> {
+        self.insert_event(&event)
+    }
+
+    fn get(&self, id: &Id) -> Result<Option<Event>> {
+        self.get_event(id)
+    }
+
+    fn query(&self, filter: &Filter) -> Result<Vec<Event>> {
+        self.query_events(filter)
+    }
+
+    fn count(&self, filter: &Filter) -> Result<u64> {
+        self.count_events(filter)
+    }
+
+    fn events_since(&self, since: u64) -> Result<Vec<Event>> {
+        self.query_events(&Filter::new().since(since))
+    }
+
+    fn high_water_mark(&self) -> Result<Option<u64>> {
+        self.max_created_at()
+    }
+
+    fn subscribe(&mut self, filter: Filter) -> Result<Subscription> {
+        let mut subscribers = self.subscribers()?;
+        let stored = self.query_events(&filter)?;
+        Ok(subscribers
+            .entry(self.namespace.clone())
+            .or_default()
+            .subscribe(filter, stored))
+    }
+
+    fn prune_expired(&mut self, now: u64) -> Result<u64> {
+        self.prune_expired_events(now)
+    }
+
+    fn delete(&mut self, ids: &[Id]) -> Result<u64> {
+        self.delete_events(ids)
+    }
+}
+
+impl AsyncStore for SqliteStore {
+    fn insert(&self, event: Event) -> impl Future<Output = Result<bool>> + Send {
+        self.spawn(move |store| store.insert_event(&event))
+    }
+
+    fn get(&self, id: &Id) -> impl Future<Output = Result<Option<Event>>> + Send {
+        let id = id.clone();
+        self.spawn(move |store| store.get_event(&id))
+    }
+
+    fn query(&self, filter: &Filter) -> impl Future<Output = Result<Vec<Event>>> + Send {
+        let filter = filter.clone();
+        self.spawn(move |store| store.query_events(&filter))
+    }
+
+    fn count(&self, filter: &Filter) -> impl Future<Output = Result<u64>> + Send {
+        let filter = filter.clone();
+        self.spawn(move |store| store.count_events(&filter))
+    }
+
+    fn events_since(&self, since: u64) -> impl Future<Output = Result<Vec<Event>>> + Send {
+        self.spawn(move |store| store.query_events(&Filter::new().since(since)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::Band;
+    use crate::event::{Event, Filter};
+    use crate::geo::DistanceRange;
+    use crate::progress::Monitor;
+    use crate::sqlite::{select, SqliteStore, DEFAULT_NAMESPACE};
+    use crate::store::{AsyncStore, MemoryStore, Store};
+    use crate::{test_vectors, Callsign, CallsignPattern, Kind, Qso, QsoData, Tag};
+    use rusqlite::{params, params_from_iter, Connection};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+    use std::collections::BTreeSet;
+
+    #[tokio::test]
+    async fn test_sqlite_store() {
+        let store = SqliteStore::open_in_memory().unwrap();
+
+        let station = Event::Station(test_vectors::station());
+        let qso = Event::Qso(test_vectors::qso());
+        let certificate = Event::Certificate(test_vectors::certificate());
+
+        assert!(AsyncStore::insert(&store, station.clone()).await.unwrap());
+        assert!(AsyncStore::insert(&store, qso.clone()).await.unwrap());
+        assert!(!AsyncStore::insert(&store, qso.clone()).await.unwrap());
+        assert!(AsyncStore::insert(&store, certificate.clone())
+            .await
+            .unwrap());
+
+        assert_eq!(
+            AsyncStore::get(&store, qso.id()).await.unwrap(),
+            Some(qso.clone())
+        );
+
+        let filter = Filter::new()
+            .kinds([Kind::Qso, Kind::Certificate])
+            .station_ids([qso.station_id().clone()])
+            .limit(1);
+        let events = AsyncStore::query(&store, &filter).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(filter.matches(&events[0]));
+        assert_eq!(AsyncStore::count(&store, &filter).await.unwrap(), 1);
+        assert_eq!(
+            Store::count(&store, &Filter::new().kinds([Kind::Qso, Kind::Certificate])).unwrap(),
+            2
+        );
+
+        let calls =
+            Filter::new().callsigns([Callsign::normalize("lu4ev"), Callsign::normalize("LW3DZR")]);
+        assert_eq!(Store::count(&store, &calls).unwrap(), 2);
+        assert_eq!(Store::count(&store, &calls.limit(1)).unwrap(), 1);
+        let ssb = Filter::new().modes(["ssb".to_string()]).bands([Band::M20]);
+        assert_eq!(Store::query(&store, &ssb).unwrap(), vec![qso.clone()]);
+
+        let events = AsyncStore::events_since(&store, qso.created_at())
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 3);
+
+        let mut memory = MemoryStore::new();
+        assert_eq!(memory.sync_from(&store).unwrap(), 3);
+        assert_eq!(
+            Store::high_water_mark(&store).unwrap(),
+            Some(qso.created_at())
+        );
+
+        let tagged = Qso::new(
+            QsoData {
+                station_id: test_vectors::counterparty().id,
+                callsign: "LU4EV".to_string(),
+                datetime: qso.created_at(),
+                comments: String::new(),
+                extra: BTreeMap::from([("gridsquare".to_string(), json!("GF05tk"))]),
+                tags: vec![
+                    Tag::Ref(qso.id().clone()),
+                    Tag::ExpiresAt(qso.created_at() + 3600),
+                ],
+                ..test_vectors::qso_data()
+            },
+            &test_vectors::counterparty_keypair(),
+        );
+        AsyncStore::insert(&store, Event::Qso(tagged.clone()))
+            .await
+            .unwrap();
+        memory.insert(Event::Qso(tagged.clone())).unwrap();
+        let filter = Filter::new().tags([Tag::Ref(qso.id().clone())]);
+        assert_eq!(
+            Store::query(&store, &filter).unwrap(),
+            vec![Event::Qso(tagged)]
+        );
+        assert_eq!(Store::count(&store, &filter).unwrap(), 1);
+
+        for (pattern, count) in [
+            ("LU4*", 2),
+            ("lw?dzr", 1),
+            ("*4EV", 2),
+            ("*", 3),
+            ("LU5*", 0),
+        ] {
+            let calls = Filter::new().callsigns([CallsignPattern::new(pattern).unwrap()]);
+            assert_eq!(Store::count(&store, &calls).unwrap(), count, "{}", pattern);
+            assert_eq!(Store::count(&memory, &calls).unwrap(), count, "{}", pattern);
+        }
+        for (grid, count) in [("gf", 1), ("GF05TK", 1), ("FN", 0)] {
+            let grids = Filter::new().grids([grid.to_string()]);
+            assert_eq!(Store::count(&store, &grids).unwrap(), count, "{}", grid);
+            assert_eq!(Store::count(&memory, &grids).unwrap(), count, "{}", grid);
+        }
+        let far = Filter::new().distance(DistanceRange::beyond("JO62", 5000));
+        assert_eq!(Store::count(&store, &far).unwrap(), 1);
+
+        let calls = Filter::new().callsigns([CallsignPattern::new("LU4*").unwrap()]);
+        let (sql, values) = select("id", DEFAULT_NAMESPACE, &calls);
+        let plan: Vec<String> = store
+            .with_conn(|conn| {
+                let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+                let rows = stmt.query_map(params_from_iter(values), |row| row.get(3))?;
+                Ok(rows.collect::<Result<_, _>>()?)
+            })
+            .unwrap();
+        assert!(
+            plan.iter()
+                .any(|step| step.starts_with("SEARCH callsigns USING")
+                    && step.contains("callsign>?")),
+            "{:?}",
+            plan
+        );
+
+        let mut store = store;
+        assert_eq!(store.prune_expired(qso.created_at()).unwrap(), 0);
+        assert_eq!(store.prune_expired(qso.created_at() + 3600).unwrap(), 1);
+        assert_eq!(Store::count(&store, &filter).unwrap(), 0);
+        assert_eq!(Store::count(&store, &Filter::new()).unwrap(), 3);
+
+        assert_eq!(store.delete(&[qso.id().clone()]).unwrap(), 1);
+        assert_eq!(store.delete(&[qso.id().clone()]).unwrap(), 0);
+        assert_eq!(Store::get(&store, qso.id()).unwrap(), None);
+        assert_eq!(Store::count(&store, &Filter::new()).unwrap(), 2);
+        let calls = Filter::new().callsigns([Callsign::normalize("LW3DZR")]);
+        assert_eq!(Store::count(&store, &calls).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let station = test_vectors::station();
+        let counterparty = test_vectors::counterparty();
+        let mut lu4ev = store.namespace(&station.pub_key);
+        let mut lw3dzr = store.namespace(&counterparty.pub_key);
+
+        let qso = Event::Qso(test_vectors::qso());
+        Store::insert(&mut lu4ev, Event::Station(station.clone())).unwrap();
+        Store::insert(&mut lu4ev, qso.clone()).unwrap();
+        assert!(Store::insert(&mut lw3dzr, qso.clone()).unwrap());
+        let mut lu4ev_clone = store.namespace(&station.pub_key);
+        assert!(!Store::insert(&mut lu4ev_clone, qso.clone()).unwrap());
+
+        assert_eq!(Store::count(&lu4ev, &Filter::new()).unwrap(), 2);
+        assert_eq!(Store::count(&lw3dzr, &Filter::new()).unwrap(), 1);
+        assert_eq!(Store::count(&store, &Filter::new()).unwrap(), 0);
+        assert_eq!(Store::get(&lw3dzr, &station.id).unwrap(), None);
+        assert_eq!(
+            store.namespaces().unwrap(),
+            BTreeSet::from([station.pub_key, counterparty.pub_key])
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        let mut backup = vec![];
+        assert_eq!(
+            lu4ev.export_all(&mut backup, &mut Monitor::new()).unwrap(),
+            2
+        );
+
+        assert_eq!(lw3dzr.delete(&[qso.id().clone()]).unwrap(), 1);
+        assert_eq!(Store::get(&lu4ev, qso.id()).unwrap(), Some(qso));
+    }
+
+    #[test]
+    fn test_migrate_namespaces() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE events (
+                id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                station_id TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                json TEXT NOT NULL
+            );
+            CREATE INDEX events_created_at ON events (created_at, id);
+            CREATE INDEX events_station_id ON events (station_id, created_at);
+            CREATE TABLE tags (
+                tag TEXT NOT NULL,
+                event_id TEXT NOT NULL,
+                PRIMARY KEY (tag, event_id)
+            );",
+        )
+        .unwrap();
+        let qso = Event::Qso(test_vectors::qso());
+        conn.execute(
+            "INSERT INTO events VALUES (?1, 'qso', ?2, ?3, ?4)",
+            params![
+                qso.id().to_string(),
+                qso.station_id().to_string(),
+                qso.created_at() as i64,
+                serde_json::to_string(&qso).unwrap()
+            ],
+        )
+        .unwrap();
+
+        let store = SqliteStore::init(conn).unwrap();
+        assert_eq!(Store::get(&store, qso.id()).unwrap(), Some(qso.clone()));
+        assert!(store.namespaces().unwrap().is_empty());
+        let namespaced = store.namespace(&test_vectors::station().pub_key);
+        assert_eq!(Store::get(&namespaced, qso.id()).unwrap(), None);
+        let calls = Filter::new().callsigns([CallsignPattern::new("LW3*").unwrap()]);
+        assert_eq!(Store::query(&store, &calls).unwrap(), vec![qso]);
+    }
+}
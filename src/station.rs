@@ -12,115 +12,583 @@
 // limitations under the License.
 
 use anyhow::{bail, Result};
-use codes_iso_3166::part_1::CountryCode;
-use regex::Regex;
 use secp256k1::schnorr::Signature;
-use secp256k1::{Keypair, XOnlyPublicKey};
+use secp256k1::{Keypair, XOnlyPublicKey, SECP256K1};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value;
+use std::collections::BTreeMap;
 
+use crate::callsign::{Callsign, CallsignPolicy, StrictCallsign};
+use crate::dxcc;
+use crate::entity::Entity;
+use crate::hash::HashAlgorithm;
 use crate::id::Id;
+use crate::id::IdBuilder;
+use crate::kind::Kind;
+use crate::limits;
+use crate::pow;
+use crate::programs;
+use crate::replaceable::{self, Replaceable};
+use crate::signable::{self, Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::text;
 use crate::time;
+use crate::validation::{CountryCheck, ValidationOptions};
+use crate::version;
+
+pub(crate) const OPERATOR_MAX_LEN: usize = 64;
+const DISPLAY_NAME_MAX_LEN: usize = 64;
+const URL_MAX_LEN: usize = 256;
+
+/// Extension field with the grid square of the station.
+pub const GRID: &str = "grid";
+
+/// Extension field with the web domain vouching for the station key, see
+/// [`crate::wellknown`].
+pub const DOMAIN: &str = "domain";
+
+struct StationIdSrc<'a> {
+    pub_key: &'a XOnlyPublicKey,
+    callsign: &'a str,
+    operator: &'a str,
+    country: Entity,
+    created_at: u64,
+    version: u8,
+    nonce: Option<u64>,
+    seq: u64,
+    extra: &'a BTreeMap<String, Value>,
+    profile: Option<&'a Profile>,
+    qsl_route: Option<&'a QslRoute>,
+}
+
+/// Optional operator profile published with the station.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// sha256 of the lowercase email address, see [`Profile::hash_email`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_hash: Option<Id>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+}
+
+impl Profile {
+    /// Returns the hash of the email address to publish in the profile.
+    pub fn hash_email(email: &str) -> Id {
+        Id::new(&email.trim().to_lowercase())
+    }
 
-thread_local! { pub static  IS_CALLSIGN: Regex = Regex::new("^[A-Z0-9]{2,16}$").unwrap()}
-const OPERATOR_MAX_LEN: usize = 64;
+    fn validate(&self) -> Result<()> {
+        if let Some(display_name) = &self.display_name {
+            if !text::is_valid_required(display_name, DISPLAY_NAME_MAX_LEN) {
+                bail!("invalid display name");
+            }
+        }
+
+        for url in [&self.url, &self.avatar_url].into_iter().flatten() {
+            if url.len() > URL_MAX_LEN
+                || !(url.starts_with("https://") || url.starts_with("http://"))
+            {
+                bail!("invalid url");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// How the station wants paper QSL cards routed, for QSOs which can't be
+/// confirmed cryptographically.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct QslRoute {
+    /// Callsign of the QSL manager handling the cards of the station.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manager: Option<String>,
+    /// Whether the station accepts cards through the QSL bureau, unknown if
+    /// absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bureau: Option<bool>,
+    /// sha256 of the postal address for direct cards, see
+    /// [`QslRoute::hash_address`]. Senders holding the address, e.g. from a
+    /// callbook, can check it is the current one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direct_address_hash: Option<Id>,
+}
+
+/// Route of a paper QSL card.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum QslVia {
+    /// Send the card to the QSL manager.
+    Manager(String),
+    Bureau,
+    Direct,
+}
+
+impl QslRoute {
+    /// Returns the hash of the postal address to publish in the route. Case
+    /// and whitespace are ignored.
+    pub fn hash_address(address: &str) -> Id {
+        let address = address.split_whitespace().collect::<Vec<_>>().join(" ");
+        Id::new(&address.to_lowercase())
+    }
+
+    /// Returns the preferred route: the manager, if any, then the bureau
+    /// and last a direct card.
+    pub fn via(&self) -> Option<QslVia> {
+        if let Some(manager) = &self.manager {
+            return Some(QslVia::Manager(manager.clone()));
+        }
+        if self.bureau == Some(true) {
+            return Some(QslVia::Bureau);
+        }
+        self.direct_address_hash.as_ref().map(|_| QslVia::Direct)
+    }
+
+    fn validate(&self, policy: &dyn CallsignPolicy) -> Result<()> {
+        if let Some(manager) = &self.manager {
+            policy.validate(manager)?;
+        }
+        Ok(())
+    }
+}
+
+/// Station fields provided by the station owner.
+pub struct StationData {
+    pub callsign: String,
+    pub operator: String,
+    pub country: Entity,
+    pub extra: BTreeMap<String, Value>,
+    pub profile: Option<Profile>,
+    pub qsl_route: Option<QslRoute>,
+}
 
 /// Station represent a radio station with a callsign and an operator.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Station {
     pub id: Id,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::PublicKey"))]
     pub pub_key: XOnlyPublicKey,
+    #[serde(deserialize_with = "limits::callsign")]
     pub callsign: String,
+    #[serde(deserialize_with = "limits::operator")]
     pub operator: String,
-    pub country: CountryCode,
+    pub country: Entity,
     pub created_at: u64,
     pub version: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    /// Number of updates of the station, see [`Station::update`].
+    #[serde(default, skip_serializing_if = "replaceable::is_zero")]
+    pub seq: u64,
+    /// Extension fields, included in the id so they are covered by the
+    /// signature.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qsl_route: Option<QslRoute>,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
     pub sig: Signature,
 }
 
 impl Station {
+    /// Current version of the station object.
+    pub const VERSION: u8 = version::V2;
+
+    /// Versions accepted by the station verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0, version::V1, version::V2];
+
+    /// Returns true if the station version is supported.
+    pub fn is_supported_version(version: u8) -> bool {
+        Self::SUPPORTED_VERSIONS.contains(&version)
+    }
+
     /// Creates a new Station and signs the object.
     pub fn new(
         keys: &Keypair,
         callsign: String,
         operator: String,
-        country: CountryCode,
+        country: impl Into<Entity>,
+    ) -> Result<Self> {
+        Self::new_with_signer(keys, callsign, operator, country)
+    }
+
+    /// Creates a new Station and signs the object using the given signer.
+    pub fn new_with_signer(
+        signer: &dyn Signer,
+        callsign: String,
+        operator: String,
+        country: impl Into<Entity>,
+    ) -> Result<Self> {
+        Self::from_data(
+            StationData {
+                callsign,
+                operator,
+                country: country.into(),
+                extra: BTreeMap::new(),
+                profile: None,
+                qsl_route: None,
+            },
+            signer,
+        )
+    }
+
+    /// Creates a new Station from the station data and signs the object
+    /// using the given signer.
+    pub fn from_data(station_data: StationData, signer: &dyn Signer) -> Result<Self> {
+        Self::from_data_with_policy(station_data, signer, &StrictCallsign)
+    }
+
+    /// Like [`Station::from_data`] but validates the callsign with the given
+    /// policy. Peers must verify the station with the same policy, see
+    /// [`Station::verify_with_options`].
+    pub fn from_data_with_policy(
+        station_data: StationData,
+        signer: &dyn Signer,
+        policy: &dyn CallsignPolicy,
+    ) -> Result<Self> {
+        Self::create(station_data, signer, time::unix_timstamp(), 0, None, policy)
+    }
+
+    /// Creates a new Station with a proof of work of at least `difficulty`
+    /// leading zero bits in its id and signs the object using the given signer.
+    pub fn mine(station_data: StationData, signer: &dyn Signer, difficulty: u32) -> Result<Self> {
+        Self::create(
+            station_data,
+            signer,
+            time::unix_timstamp(),
+            0,
+            Some(difficulty),
+            &StrictCallsign,
+        )
+    }
+
+    /// Replaces the station with new station data, signed with the same key.
+    ///
+    /// The sequence number of the update is one more than the current one,
+    /// so the update wins over the current station in
+    /// [`crate::latest_of`] even if both were created in the same second.
+    pub fn update(&self, station_data: StationData, signer: &dyn Signer) -> Result<Self> {
+        self.update_with_policy(station_data, signer, &StrictCallsign)
+    }
+
+    /// Like [`Station::update`] but validates the current station and the
+    /// update with the given callsign policy.
+    pub fn update_with_policy(
+        &self,
+        station_data: StationData,
+        signer: &dyn Signer,
+        policy: &dyn CallsignPolicy,
+    ) -> Result<Self> {
+        if signer.public_key()? != self.pub_key {
+            bail!("signer public key mismatch");
+        }
+        self.verify_with_policy(policy)?;
+
+        Self::create(
+            station_data,
+            signer,
+            time::unix_timstamp(),
+            self.seq + 1,
+            None,
+            policy,
+        )
+    }
+
+    /// Re-signs a station created with a previous version using the current
+    /// version. The station data and creation time are preserved.
+    pub fn upgrade(&self, signer: &dyn Signer) -> Result<Self> {
+        self.upgrade_with_policy(signer, &StrictCallsign)
+    }
+
+    /// Like [`Station::upgrade`] but validates the current station and the
+    /// upgraded one with the given callsign policy.
+    pub fn upgrade_with_policy(
+        &self,
+        signer: &dyn Signer,
+        policy: &dyn CallsignPolicy,
     ) -> Result<Self> {
-        let (pub_key, _) = keys.x_only_public_key();
-        let created_at = time::unix_timstamp();
+        if self.version >= Self::VERSION {
+            return Ok(self.clone());
+        }
 
-        let version: u8 = 0;
-        let id = Self::generate_id(&pub_key, &callsign, &operator, country, created_at, version);
-        let sig = id.sign(keys);
+        if signer.public_key()? != self.pub_key {
+            bail!("signer public key mismatch");
+        }
+        self.verify_with_policy(policy)?;
+
+        Self::create(
+            StationData {
+                callsign: self.callsign.clone(),
+                operator: self.operator.clone(),
+                country: self.country,
+                extra: self.extra.clone(),
+                profile: self.profile.clone(),
+                qsl_route: self.qsl_route.clone(),
+            },
+            signer,
+            self.created_at,
+            self.seq,
+            None,
+            policy,
+        )
+    }
+
+    pub(crate) fn create(
+        mut station_data: StationData,
+        signer: &dyn Signer,
+        created_at: u64,
+        seq: u64,
+        difficulty: Option<u32>,
+        policy: &dyn CallsignPolicy,
+    ) -> Result<Self> {
+        station_data.callsign = Callsign::normalize(&station_data.callsign).to_string();
+        programs::normalize_extra(&mut station_data.extra);
+        if let Some(manager) = station_data
+            .qsl_route
+            .as_mut()
+            .and_then(|route| route.manager.as_mut())
+        {
+            *manager = Callsign::normalize(manager).to_string();
+        }
+        let pub_key = signer.public_key()?;
+        let version = Self::VERSION;
+
+        let id_src = |nonce| StationIdSrc {
+            pub_key: &pub_key,
+            callsign: &station_data.callsign,
+            operator: &station_data.operator,
+            country: station_data.country,
+            created_at,
+            version,
+            nonce,
+            seq,
+            extra: &station_data.extra,
+            profile: station_data.profile.as_ref(),
+            qsl_route: station_data.qsl_route.as_ref(),
+        };
+
+        let (nonce, id) = match difficulty {
+            Some(difficulty) => {
+                let (nonce, id) =
+                    pow::mine(difficulty, |nonce| Self::compute_id(id_src(Some(nonce))))?;
+                (Some(nonce), id)
+            }
+            None => (None, Self::compute_id(id_src(None))),
+        };
+
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
         let station = Self {
             id,
             pub_key,
-            callsign,
-            operator,
-            country,
+            callsign: station_data.callsign,
+            operator: station_data.operator,
+            country: station_data.country,
             created_at,
             version,
+            nonce,
+            seq,
+            extra: station_data.extra,
+            profile: station_data.profile,
+            qsl_route: station_data.qsl_route,
             sig,
         };
 
-        station.validate()?;
+        station.validate_with_policy(policy)?;
 
         Ok(station)
     }
 
+    /// Returns the grid square of the station, if present in the extension
+    /// fields.
+    pub fn grid(&self) -> Option<&str> {
+        self.extra.get(GRID).and_then(Value::as_str)
+    }
+
+    /// Returns the domain vouching for the station key, if present in the
+    /// extension fields.
+    pub fn domain(&self) -> Option<&str> {
+        self.extra.get(DOMAIN).and_then(Value::as_str)
+    }
+
+    /// Returns the preferred route of paper QSL cards, if the station
+    /// published one.
+    pub fn qsl_via(&self) -> Option<QslVia> {
+        self.qsl_route.as_ref().and_then(QslRoute::via)
+    }
+
     /// Verify the object signature.
     pub fn verify(&self) -> Result<()> {
-        let id = Self::generate_id(
-            &self.pub_key,
-            &self.callsign,
-            &self.operator,
-            self.country,
-            self.created_at,
-            self.version,
-        );
-
-        if id != self.id {
-            bail!("invalid id");
-        }
+        self.verify_signature(&self.pub_key)
+    }
 
-        id.verify(&self.pub_key, &self.sig)?;
+    fn verify_with_policy(&self, policy: &dyn CallsignPolicy) -> Result<()> {
+        signable::verify_with(self, SECP256K1, &self.pub_key, |station| {
+            station.validate_with_policy(policy)
+        })
+    }
 
-        self.validate()?;
+    /// Verify the object signature and validate the station fields with the
+    /// options.
+    pub fn verify_with_options(&self, options: &ValidationOptions) -> Result<()> {
+        signable::verify_with(self, SECP256K1, &self.pub_key, |station| {
+            station.validate_with_options(options)
+        })
+    }
 
+    /// Validates the station fields with the callsign policy of the options
+    /// and applies the optional checks.
+    pub fn validate_with_options(&self, options: &ValidationOptions) -> Result<()> {
+        self.validate_with_policy(options.callsign_policy())?;
+        let max = &options.limits;
+        limits::check_len("callsign", &self.callsign, max.callsign)?;
+        limits::check_len("operator", &self.operator, max.operator)?;
+
+        if let Some(country_check) = options.country_check {
+            let resolved = dxcc::resolve(&self.callsign);
+            let plausible = match (country_check, resolved) {
+                (_, Some(country)) if self.country == Entity::Country(country) => true,
+                (CountryCheck::Strict, _) => false,
+                (CountryCheck::Lenient, None) => true,
+                (CountryCheck::Lenient, Some(_)) => dxcc::is_special_event(&self.callsign),
+            };
+            if !plausible {
+                bail!("country {:?} not plausible for callsign", self.country);
+            }
+        }
+        if let Some(max_skew) = options.max_clock_skew {
+            time::check_created_at(self.created_at, max_skew)?;
+        }
         Ok(())
     }
 
-    fn validate(&self) -> Result<()> {
-        if !IS_CALLSIGN.with(|is_callsign| is_callsign.is_match(&self.callsign)) {
-            bail!("invalid callsign");
+    /// Generates the id for the station.
+    fn compute_id(id_src: StationIdSrc) -> Id {
+        let mut id = IdBuilder::new(
+            HashAlgorithm::for_version(id_src.version),
+            &(
+                id_src.pub_key,
+                id_src.callsign,
+                id_src.operator,
+                id_src.country,
+                id_src.created_at,
+                id_src.version,
+            ),
+        );
+        if !id_src.extra.is_empty() {
+            id.optional("extra", id_src.extra);
         }
+        if let Some(nonce) = id_src.nonce {
+            id.optional("nonce", &nonce);
+        }
+        if let Some(profile) = id_src.profile {
+            // Like any struct, the profile keys are sorted in the id.
+            id.optional("profile", &json!(profile));
+        }
+        if let Some(qsl_route) = id_src.qsl_route {
+            id.optional("qsl_route", &json!(qsl_route));
+        }
+        if id_src.seq != 0 {
+            id.optional("seq", &id_src.seq);
+        }
+
+        id.finish()
+    }
+
+    /// Validates the station fields, the callsign with the given policy.
+    fn validate_with_policy(&self, policy: &dyn CallsignPolicy) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+        version::check_optional_fields(
+            self.version,
+            self.nonce.is_some()
+                || self.seq != 0
+                || !self.extra.is_empty()
+                || self.profile.is_some()
+                || self.qsl_route.is_some(),
+        )?;
+
+        if self.version < version::V2 && self.country.country().is_none() {
+            bail!("non ISO entities require version {}", version::V2);
+        }
+
+        policy.validate(&self.callsign)?;
 
-        if self.operator.trim().is_empty() || self.operator.len() > OPERATOR_MAX_LEN {
+        if !text::is_valid_required(&self.operator, OPERATOR_MAX_LEN) {
             bail!("invalid operator");
         }
 
+        if let Some(profile) = &self.profile {
+            profile.validate()?;
+        }
+
+        if let Some(qsl_route) = &self.qsl_route {
+            qsl_route.validate(policy)?;
+        }
+
+        programs::validate_extra(&self.extra)?;
+
         Ok(())
     }
+}
+
+impl Validate for Station {
+    fn validate(&self) -> Result<()> {
+        self.validate_with_policy(&StrictCallsign)
+    }
+}
 
-    /// Generates the id for the station.
-    fn generate_id(
-        pub_key: &XOnlyPublicKey,
-        callsign: &str,
-        operator: &str,
-        country: CountryCode,
-        created_at: u64,
-        version: u8,
-    ) -> Id {
-        let json: Value = json!([pub_key, callsign, operator, country, created_at, version]);
-        let json_str = json.to_string();
-        Id::new(&json_str)
+impl Signable for Station {
+    const KIND: Kind = Kind::Station;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(StationIdSrc {
+            pub_key: &self.pub_key,
+            callsign: &self.callsign,
+            operator: &self.operator,
+            country: self.country,
+            created_at: self.created_at,
+            version: self.version,
+            nonce: self.nonce,
+            seq: self.seq,
+            extra: &self.extra,
+            profile: self.profile.as_ref(),
+            qsl_route: self.qsl_route.as_ref(),
+        })
+    }
+}
+
+impl Replaceable for Station {
+    fn seq(&self) -> u64 {
+        self.seq
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::callsign::PermissiveCallsign;
+    use crate::event::Event;
     use crate::keys::generate_keypair;
+    use codes_iso_3166::part_1::CountryCode;
 
     use super::*;
 
@@ -156,6 +624,269 @@ mod tests {
         assert!(station.verify().is_err());
     }
 
+    #[test]
+    fn test_pow() {
+        let keys = generate_keypair();
+
+        let station = Station::mine(
+            StationData {
+                callsign: "LU4EV".to_string(),
+                operator: "Radio Club Caseros".to_string(),
+                country: CountryCode::AR.into(),
+                extra: BTreeMap::new(),
+                profile: None,
+                qsl_route: None,
+            },
+            &keys,
+            8,
+        )
+        .unwrap();
+
+        station.verify().unwrap();
+        Event::Station(station).check_pow(8).unwrap();
+    }
+
+    #[test]
+    fn test_extra() {
+        let keys = generate_keypair();
+
+        let mut extra = BTreeMap::new();
+        extra.insert("my_sota_ref".to_string(), json!("LU/BA-001"));
+
+        let station = Station::from_data(
+            StationData {
+                callsign: "LU4EV".to_string(),
+                operator: "Radio Club Caseros".to_string(),
+                country: CountryCode::AR.into(),
+                extra,
+                profile: None,
+                qsl_route: None,
+            },
+            &keys,
+        )
+        .unwrap();
+
+        let json_str = serde_json::to_string(&station).unwrap();
+        let mut station_dese: Station = serde_json::from_str(&json_str).unwrap();
+        station_dese.verify().unwrap();
+        assert_eq!(station_dese, station);
+
+        station_dese
+            .extra
+            .insert("my_sota_ref".to_string(), json!("LU/BA-002"));
+        assert!(station_dese.verify().is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        let keys = generate_keypair();
+
+        let profile = Profile {
+            display_name: Some("Radio Club Caseros".to_string()),
+            url: Some("https://lu4ev.example.org".to_string()),
+            email_hash: Some(Profile::hash_email("LU4EV@example.org ")),
+            avatar_url: None,
+        };
+
+        let station = Station::from_data(
+            StationData {
+                callsign: "LU4EV".to_string(),
+                operator: "Radio Club Caseros".to_string(),
+                country: CountryCode::AR.into(),
+                extra: BTreeMap::new(),
+                profile: Some(profile.clone()),
+                qsl_route: None,
+            },
+            &keys,
+        )
+        .unwrap();
+
+        assert_eq!(
+            profile.email_hash,
+            Some(Profile::hash_email("lu4ev@example.org"))
+        );
+
+        let json_str = serde_json::to_string(&station).unwrap();
+        let mut station_dese: Station = serde_json::from_str(&json_str).unwrap();
+        station_dese.verify().unwrap();
+
+        station_dese.profile.as_mut().unwrap().url = Some("https://evil.example.org".to_string());
+        assert!(station_dese.verify().is_err());
+
+        let invalid = Station::from_data(
+            StationData {
+                callsign: "LU4EV".to_string(),
+                operator: "Radio Club Caseros".to_string(),
+                country: CountryCode::AR.into(),
+                extra: BTreeMap::new(),
+                profile: Some(Profile {
+                    url: Some("javascript:alert(1)".to_string()),
+                    ..Profile::default()
+                }),
+                qsl_route: None,
+            },
+            &keys,
+        );
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let keys = generate_keypair();
+
+        let json_str = r#"
+        {
+          "id": "dcc45a63ce8f2cf692cca8df74f9ab1f7adb978a1634c0d01671b209cf580f94",
+          "pub_key": "a83757d9f8f381fe88db128e0572c14277181efeccbf013a0411bd37ba23930b",
+          "callsign": "LU4EV",
+          "operator": "Radio Club Caseros",
+          "country": "AR",
+          "created_at": 1702871644,
+          "version": 0,
+          "sig": "7568c4f83b41f8002231a17cfd697c5550270f2b1688ce1b5f5fda3f7f8f913cdd3a31cccdf4399a4a0e11ff198345d62ff17bb4bce4ce57722d80fd01dbd8e5"
+        }
+        "#;
+
+        let station_v0: Station = serde_json::from_str(json_str).unwrap();
+        assert!(station_v0.upgrade(&keys).is_err());
+
+        let mut station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        assert_eq!(station.version, Station::VERSION);
+
+        station.version = version::V0;
+        assert!(station.upgrade(&keys).is_err());
+        station.id = station.generate_id();
+        station.sig = station.id.sign(&keys);
+        let upgraded = station.upgrade(&keys).unwrap();
+        upgraded.verify().unwrap();
+        assert_eq!(upgraded.version, Station::VERSION);
+        assert_eq!(upgraded.created_at, station.created_at);
+    }
+
+    #[test]
+    fn test_update_with_policy() {
+        let keys = generate_keypair();
+        let station_data = || StationData {
+            callsign: "ONL-12345".to_string(),
+            operator: "Radio Club Caseros".to_string(),
+            country: CountryCode::AR.into(),
+            extra: BTreeMap::new(),
+            profile: None,
+            qsl_route: None,
+        };
+        let station =
+            Station::from_data_with_policy(station_data(), &keys, &PermissiveCallsign).unwrap();
+        assert!(station.update(station_data(), &keys).is_err());
+
+        let updated = station
+            .update_with_policy(station_data(), &keys, &PermissiveCallsign)
+            .unwrap();
+        assert_eq!(updated.seq, station.seq + 1);
+        let options = ValidationOptions::default().with_callsign_policy(PermissiveCallsign);
+        updated.verify_with_options(&options).unwrap();
+
+        let mut old = station.clone();
+        old.version = version::V0;
+        old.id = old.generate_id();
+        old.sig = old.id.sign(&keys);
+        assert!(old.upgrade(&keys).is_err());
+        let upgraded = old.upgrade_with_policy(&keys, &PermissiveCallsign).unwrap();
+        assert_eq!(upgraded.version, Station::VERSION);
+        upgraded.verify_with_options(&options).unwrap();
+    }
+
+    #[test]
+    fn test_entity() {
+        let keys = generate_keypair();
+
+        let mut station = Station::new(
+            &keys,
+            "4U1ITU".to_string(),
+            "ITU Amateur Radio Club".to_string(),
+            Entity::ItuHq,
+        )
+        .unwrap();
+
+        let json_str = serde_json::to_string(&station).unwrap();
+        assert!(json_str.contains("\"country\":\"itu_hq\""));
+
+        let station_dese: Station = serde_json::from_str(&json_str).unwrap();
+        station_dese.verify().unwrap();
+
+        station.version = version::V1;
+        station.id = station.generate_id();
+        station.sig = station.id.sign(&keys);
+        assert!(station.verify().is_err());
+    }
+
+    #[test]
+    fn test_country_check() {
+        let keys = generate_keypair();
+        let strict = ValidationOptions::default().with_country_check(CountryCheck::Strict);
+        let lenient = ValidationOptions::default().with_country_check(CountryCheck::Lenient);
+
+        let station = |callsign: &str, country| {
+            Station::new(
+                &keys,
+                callsign.to_string(),
+                "Radio Club Caseros".to_string(),
+                country,
+            )
+            .unwrap()
+        };
+
+        let valid = station("LU4EV", CountryCode::AR);
+        valid.verify_with_options(&strict).unwrap();
+        valid.verify_with_options(&lenient).unwrap();
+
+        let mismatch = station("LU4EV", CountryCode::CL);
+        mismatch.verify().unwrap();
+        assert!(mismatch.verify_with_options(&strict).is_err());
+        assert!(mismatch.verify_with_options(&lenient).is_err());
+
+        let special_event = station("LU100RCA", CountryCode::UY);
+        assert!(special_event.verify_with_options(&strict).is_err());
+        special_event.verify_with_options(&lenient).unwrap();
+
+        let unknown = station("QQ1AA", CountryCode::AR);
+        assert!(unknown.verify_with_options(&strict).is_err());
+        unknown.verify_with_options(&lenient).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        assert!(Station::is_supported_version(0));
+        assert!(!Station::is_supported_version(255));
+
+        let keys = generate_keypair();
+        let mut extra = BTreeMap::new();
+        extra.insert("test".to_string(), json!(1));
+
+        let mut station = Station::from_data(
+            StationData {
+                callsign: "LU4EV".to_string(),
+                operator: "Radio Club Caseros".to_string(),
+                country: CountryCode::AR.into(),
+                extra,
+                profile: None,
+                qsl_route: None,
+            },
+            &keys,
+        )
+        .unwrap();
+
+        station.version = version::V0;
+        assert!(station.validate().is_err());
+        station.version = 255;
+        assert!(station.validate().is_err());
+    }
+
     #[test]
     fn test_serde() {
         let json_str = r#"
@@ -195,4 +926,96 @@ mod tests {
 
         assert!(station.verify().is_err());
     }
+
+    #[test]
+    fn test_max_clock_skew() {
+        let keys = generate_keypair();
+        let options = ValidationOptions::default().with_max_clock_skew(600);
+
+        let mut station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+        station.verify_with_options(&options).unwrap();
+
+        let mut resign = |created_at| {
+            station.created_at = created_at;
+            station.id = station.generate_id();
+            station.sig = station.id.sign(&keys);
+            station.clone()
+        };
+
+        let future = resign(time::unix_timstamp() + 3600);
+        future.verify().unwrap();
+        assert!(future.verify_with_options(&options).is_err());
+
+        let skewed = resign(time::unix_timstamp() + 60);
+        skewed.verify_with_options(&options).unwrap();
+
+        let ancient = resign(time::EPOCH - 1);
+        ancient.verify().unwrap();
+        assert!(ancient.verify_with_options(&options).is_err());
+    }
+
+    #[test]
+    fn test_qsl_route() {
+        let keys = generate_keypair();
+        let station_data = |qsl_route| StationData {
+            callsign: "LU4EV".to_string(),
+            operator: "Radio Club Caseros".to_string(),
+            country: CountryCode::AR.into(),
+            extra: BTreeMap::new(),
+            profile: None,
+            qsl_route,
+        };
+
+        let route = QslRoute {
+            manager: Some("lw3dzr".to_string()),
+            bureau: Some(true),
+            direct_address_hash: Some(QslRoute::hash_address(
+                "Av. San Martín 123\n  Caseros, Buenos Aires",
+            )),
+        };
+        let station = Station::from_data(station_data(Some(route.clone())), &keys).unwrap();
+        assert_eq!(
+            station.qsl_via(),
+            Some(QslVia::Manager("LW3DZR".to_string()))
+        );
+        assert_eq!(
+            station.qsl_route.as_ref().unwrap().direct_address_hash,
+            Some(QslRoute::hash_address(
+                "av. san martín 123 caseros, buenos aires"
+            ))
+        );
+
+        let json_str = serde_json::to_string(&station).unwrap();
+        let mut station_dese: Station = serde_json::from_str(&json_str).unwrap();
+        station_dese.verify().unwrap();
+        station_dese.qsl_route.as_mut().unwrap().manager = Some("LU1AA".to_string());
+        assert!(station_dese.verify().is_err());
+
+        let bureau = QslRoute {
+            manager: None,
+            ..route.clone()
+        };
+        assert_eq!(bureau.via(), Some(QslVia::Bureau));
+        let direct = QslRoute {
+            bureau: Some(false),
+            ..bureau
+        };
+        assert_eq!(direct.via(), Some(QslVia::Direct));
+        assert_eq!(QslRoute::default().via(), None);
+
+        let station = Station::from_data(station_data(None), &keys).unwrap();
+        assert_eq!(station.qsl_via(), None);
+
+        let invalid = QslRoute {
+            manager: Some("??".to_string()),
+            ..QslRoute::default()
+        };
+        assert!(Station::from_data(station_data(Some(invalid)), &keys).is_err());
+    }
 }
@@ -0,0 +1,423 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Aggregated statistics of the QSOs of a station.
+//!
+//! The statistics serialize to plain JSON objects keyed by name, so
+//! dashboards can render them without going through the raw events.
+
+use crate::bandplan::Band;
+use crate::event::{Event, Filter};
+use crate::logbook::Confirmations;
+use crate::store::Store;
+use crate::{dxcc, Callsign, Id, Kind, Qso, Station};
+use anyhow::{bail, Result};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// QSO counts of a station.
+///
+/// QSOs outside the amateur bands, with a callsign of unknown country or
+/// with an invalid time are left out of the respective grouping only.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub qsos: u64,
+    /// QSOs by band name, e.g. `20m`.
+    pub by_band: BTreeMap<String, u64>,
+    /// QSOs by uppercase mode.
+    pub by_mode: BTreeMap<String, u64>,
+    /// QSOs by ISO 3166 alpha-2 code of the country worked.
+    pub by_country: BTreeMap<String, u64>,
+    /// QSOs by UTC month of the contact, e.g. `2024-01`.
+    pub by_month: BTreeMap<String, u64>,
+    /// Number of distinct normalized callsigns worked.
+    pub unique_callsigns: u64,
+    /// Number of QSOs confirmed by the counterparty, see
+    /// [`crate::Logbook::confirmations_for`].
+    pub confirmed: u64,
+    /// Confirmed QSOs over all the QSOs, zero without QSOs.
+    pub confirmation_rate: f64,
+}
+
+/// Computes the statistics of the QSOs of the station found in the store.
+pub fn station_stats(store: &dyn Store, station: &Station) -> Result<Stats> {
+    let candidates = store.query(&Filter::new().kinds([Kind::Qso]))?;
+    let confirmations = Confirmations::new(store, station, &candidates)?;
+    let mut stats = Stats::default();
+    let mut callsigns = BTreeSet::new();
+
+    for event in &candidates {
+        let Event::Qso(qso) = event else {
+            continue;
+        };
+        if qso.station_id != station.id {
+            continue;
+        }
+
+        stats.qsos += 1;
+        if let Some(band) = Band::from_freq(qso.freq) {
+            *stats.by_band.entry(band.name().to_string()).or_default() += 1;
+        }
+        *stats.by_mode.entry(qso.mode.to_uppercase()).or_default() += 1;
+        if let Some(country) = dxcc::resolve(&qso.callsign) {
+            *stats.by_country.entry(country.to_string()).or_default() += 1;
+        }
+        if let Some(datetime) = i64::try_from(qso.datetime)
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        {
+            let month = datetime.format("%Y-%m").to_string();
+            *stats.by_month.entry(month).or_default() += 1;
+        }
+        callsigns.insert(Callsign::normalize(&qso.callsign));
+
+        if !confirmations.for_qso(qso).is_empty() {
+            stats.confirmed += 1;
+        }
+    }
+
+    stats.unique_callsigns = callsigns.len() as u64;
+    if stats.qsos > 0 {
+        stats.confirmation_rate = stats.confirmed as f64 / stats.qsos as f64;
+    }
+
+    Ok(stats)
+}
+
+/// The QSOs of a station with a worked callsign and how many of them the
+/// callsign confirmed, see [`confirmation_ratios`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Correspondent {
+    /// The normalized callsign worked.
+    pub callsign: String,
+    pub qsos: u64,
+    pub confirmed: u64,
+    /// Confirmed QSOs over all the QSOs with the callsign.
+    pub confirmation_rate: f64,
+    /// Ids of the QSOs still waiting for a confirmation, by time of contact.
+    pub unconfirmed: Vec<Id>,
+}
+
+/// Reports, per worked callsign, how many QSOs of the station it confirmed,
+/// see [`crate::Logbook::confirmations_for`].
+///
+/// Callsigns with the most unconfirmed QSOs come first, so the missing
+/// confirmations can be chased before award deadlines.
+pub fn confirmation_ratios(store: &dyn Store, station: &Station) -> Result<Vec<Correspondent>> {
+    let candidates = store.query(&Filter::new().kinds([Kind::Qso]))?;
+    let confirmations = Confirmations::new(store, station, &candidates)?;
+    let mut qsos: Vec<&Qso> = candidates
+        .iter()
+        .filter_map(|event| match event {
+            Event::Qso(qso) if qso.station_id == station.id => Some(qso),
+            _ => None,
+        })
+        .collect();
+    qsos.sort_by(|a, b| (a.datetime, &a.id).cmp(&(b.datetime, &b.id)));
+
+    let mut correspondents: BTreeMap<Callsign, Correspondent> = BTreeMap::new();
+    for qso in qsos {
+        let callsign = Callsign::normalize(&qso.callsign);
+        let correspondent =
+            correspondents
+                .entry(callsign.clone())
+                .or_insert_with(|| Correspondent {
+                    callsign: callsign.to_string(),
+                    ..Correspondent::default()
+                });
+
+        correspondent.qsos += 1;
+        if confirmations.for_qso(qso).is_empty() {
+            correspondent.unconfirmed.push(qso.id.clone());
+        } else {
+            correspondent.confirmed += 1;
+        }
+        correspondent.confirmation_rate =
+            correspondent.confirmed as f64 / correspondent.qsos as f64;
+    }
+
+    let mut correspondents: Vec<Correspondent> = correspondents.into_values().collect();
+    correspondents.sort_by_key(|correspondent| std::cmp::Reverse(correspondent.unconfirmed.len()));
+    Ok(correspondents)
+}
+
+/// Maximum number of buckets of an activity report, more than a year of
+/// hours.
+pub const MAX_ACTIVITY_BUCKETS: u64 = 10_000;
+
+/// Size of the buckets of an activity report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bucket {
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    /// Returns the length of the bucket in seconds.
+    pub fn secs(&self) -> u64 {
+        match self {
+            Bucket::Hour => 3600,
+            Bucket::Day => 86_400,
+        }
+    }
+}
+
+/// The QSOs counted by [`activity`]: the ones in the `[from, until)` UTC
+/// range, optionally on a band or mode only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityQuery {
+    pub from: u64,
+    pub until: u64,
+    pub bucket: Bucket,
+    pub band: Option<Band>,
+    pub mode: Option<String>,
+}
+
+impl ActivityQuery {
+    /// Counts every QSO in the range.
+    pub fn new(from: u64, until: u64, bucket: Bucket) -> Self {
+        Self {
+            from,
+            until,
+            bucket,
+            band: None,
+            mode: None,
+        }
+    }
+
+    /// Counts the QSOs on the band only.
+    pub fn band(mut self, band: Band) -> Self {
+        self.band = Some(band);
+        self
+    }
+
+    /// Counts the QSOs with the mode only, compared case insensitively.
+    pub fn mode(mut self, mode: impl Into<String>) -> Self {
+        self.mode = Some(mode.into());
+        self
+    }
+
+    fn matches(&self, qso: &Qso) -> bool {
+        qso.datetime >= self.from
+            && qso.datetime < self.until
+            && self
+                .band
+                .is_none_or(|band| Band::from_freq(qso.freq) == Some(band))
+            && self
+                .mode
+                .as_ref()
+                .is_none_or(|mode| mode.eq_ignore_ascii_case(&qso.mode))
+    }
+}
+
+/// Number of QSOs in the bucket starting at `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityBucket {
+    pub start: u64,
+    pub qsos: u64,
+}
+
+/// Counts the QSOs of the station per hour or day of contact.
+///
+/// Buckets are aligned to UTC hours or days, the first one contains `from`.
+/// Every bucket of the range is returned, including the empty ones, so
+/// charts don't have to fill the gaps. Ranges of more than
+/// [`MAX_ACTIVITY_BUCKETS`] buckets are rejected.
+pub fn activity(
+    store: &dyn Store,
+    station_id: &Id,
+    query: &ActivityQuery,
+) -> Result<Vec<ActivityBucket>> {
+    let secs = query.bucket.secs();
+    let first = query.from - query.from % secs;
+    let len = query.until.saturating_sub(first).div_ceil(secs);
+    if len > MAX_ACTIVITY_BUCKETS {
+        bail!(
+            "activity range of {} buckets, the maximum is {}",
+            len,
+            MAX_ACTIVITY_BUCKETS
+        );
+    }
+    let mut buckets: Vec<ActivityBucket> = (first..query.until)
+        .step_by(secs as usize)
+        .map(|start| ActivityBucket { start, qsos: 0 })
+        .collect();
+
+    let filter = Filter::new()
+        .kinds([Kind::Qso])
+        .station_ids([station_id.clone()]);
+    for event in store.query(&filter)? {
+        if let Event::Qso(qso) = event {
+            if query.matches(&qso) {
+                buckets[((qso.datetime - first) / secs) as usize].qsos += 1;
+            }
+        }
+    }
+
+    Ok(buckets)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::Band;
+    use crate::event::Event;
+    use crate::stats::{self, ActivityBucket, ActivityQuery, Bucket, MAX_ACTIVITY_BUCKETS};
+    use crate::store::{MemoryStore, Store};
+    use crate::{test_vectors, Qso, QsoData};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_station_stats() {
+        let station = test_vectors::station();
+        let keys = test_vectors::station_keypair();
+        let mut store = MemoryStore::new();
+        store.insert(Event::Station(station.clone())).unwrap();
+        store
+            .insert(Event::Station(test_vectors::counterparty()))
+            .unwrap();
+        store.insert(Event::Qso(test_vectors::qso())).unwrap();
+
+        let confirmation = Qso::new(
+            QsoData {
+                station_id: test_vectors::counterparty().id,
+                callsign: "lu4ev".to_string(),
+                datetime: test_vectors::CREATED_AT + 120,
+                freq: 14_200_000,
+                mode: "ssb".to_string(),
+                comments: String::new(),
+                ..test_vectors::qso_data()
+            },
+            &test_vectors::counterparty_keypair(),
+        );
+        store.insert(Event::Qso(confirmation)).unwrap();
+
+        for (callsign, freq, mode, datetime) in [
+            ("JA1ABC", 7_010_000, "CW", 1_706_745_600),
+            ("lw3dzr", 1_000, "FT8", 1_706_745_600),
+        ] {
+            let qso = Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    callsign: callsign.to_string(),
+                    datetime,
+                    freq,
+                    mode: mode.to_string(),
+                    rst: "599".to_string(),
+                    comments: String::new(),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            );
+            store.insert(Event::Qso(qso)).unwrap();
+        }
+
+        let stats = stats::station_stats(&store, &station).unwrap();
+        assert_eq!(stats.qsos, 3);
+        assert_eq!(
+            stats.by_band,
+            BTreeMap::from([("20m".to_string(), 1), ("40m".to_string(), 1)])
+        );
+        assert_eq!(stats.by_mode["SSB"], 1);
+        assert_eq!(stats.by_country["AR"], 2);
+        assert_eq!(stats.by_country["JP"], 1);
+        assert_eq!(stats.by_month["2024-01"], 1);
+        assert_eq!(stats.by_month["2024-02"], 2);
+        assert_eq!(stats.unique_callsigns, 2);
+        assert_eq!(stats.confirmed, 1);
+        assert_eq!(stats.confirmation_rate, 1.0 / 3.0);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["by_band"]["40m"], 1);
+
+        let correspondents = stats::confirmation_ratios(&store, &station).unwrap();
+        assert_eq!(correspondents.len(), 2);
+        assert_eq!(correspondents[0].callsign, "JA1ABC");
+        assert_eq!(correspondents[0].confirmation_rate, 0.0);
+        let lw3dzr = &correspondents[1];
+        assert_eq!(lw3dzr.callsign, "LW3DZR");
+        assert_eq!((lw3dzr.qsos, lw3dzr.confirmed), (2, 1));
+        assert_eq!(lw3dzr.confirmation_rate, 0.5);
+        assert_eq!(lw3dzr.unconfirmed.len(), 1);
+        assert_ne!(lw3dzr.unconfirmed[0], test_vectors::qso().id);
+    }
+
+    #[test]
+    fn test_activity() {
+        let station = test_vectors::station();
+        let keys = test_vectors::station_keypair();
+        let mut store = MemoryStore::new();
+
+        // 2024-02-01 00:00:00 UTC
+        let day = 1_706_745_600;
+        for (datetime, freq, mode) in [
+            (day + 600, 14_074_000, "FT8"),
+            (day + 1200, 14_074_000, "ft8"),
+            (day + 7300, 7_074_000, "FT8"),
+            (day + 7400, 14_250_000, "SSB"),
+            (day + 86_400 + 60, 14_074_000, "FT8"),
+        ] {
+            let qso = Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    datetime,
+                    freq,
+                    mode: mode.to_string(),
+                    rst: "-10".to_string(),
+                    comments: String::new(),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            );
+            store.insert(Event::Qso(qso)).unwrap();
+        }
+
+        let query = ActivityQuery::new(day + 1800, day + 3 * 3600, Bucket::Hour);
+        let buckets = stats::activity(&store, &station.id, &query).unwrap();
+        assert_eq!(
+            buckets,
+            vec![
+                ActivityBucket {
+                    start: day,
+                    qsos: 0
+                },
+                ActivityBucket {
+                    start: day + 3600,
+                    qsos: 0
+                },
+                ActivityBucket {
+                    start: day + 7200,
+                    qsos: 2
+                },
+            ]
+        );
+
+        let query = ActivityQuery::new(day, day + 2 * 86_400, Bucket::Day)
+            .band(Band::M20)
+            .mode("FT8");
+        let buckets = stats::activity(&store, &station.id, &query).unwrap();
+        assert_eq!(
+            buckets.iter().map(|bucket| bucket.qsos).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+
+        let query = ActivityQuery::new(day, day + MAX_ACTIVITY_BUCKETS * 3600, Bucket::Hour);
+        assert_eq!(
+            stats::activity(&store, &station.id, &query).unwrap().len() as u64,
+            MAX_ACTIVITY_BUCKETS
+        );
+        let query = ActivityQuery::new(0, u64::MAX, Bucket::Day);
+        assert!(stats::activity(&store, &station.id, &query).is_err());
+    }
+}
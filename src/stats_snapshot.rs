@@ -0,0 +1,424 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::Band;
+use crate::checkpoint::covered_qsos;
+use crate::qso::MODE_MAX_LEN;
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{dxcc, geo, merkle, text, version, Checkpoint, Id, Kind, Qso};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The counts of a [`StatsSnapshot`], computed from the covered QSOs.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Counts {
+    by_band: BTreeMap<String, u64>,
+    by_mode: BTreeMap<String, u64>,
+    entities: u64,
+    grids: u64,
+}
+
+impl Counts {
+    fn of(qsos: &[Qso]) -> Self {
+        let mut counts = Counts::default();
+        let mut entities = BTreeSet::new();
+        let mut grids = BTreeSet::new();
+
+        for qso in qsos {
+            if let Some(band) = Band::from_freq(qso.freq) {
+                *counts.by_band.entry(band.name().to_string()).or_default() += 1;
+            }
+            *counts.by_mode.entry(qso.mode.to_uppercase()).or_default() += 1;
+            entities.extend(dxcc::resolve(&qso.callsign).map(|country| country.to_string()));
+            if let Some(grid) = qso.gridsquare().filter(|grid| geo::is_valid_grid(grid)) {
+                grids.insert(grid[..4].to_ascii_uppercase());
+            }
+        }
+
+        counts.entities = entities.len() as u64;
+        counts.grids = grids.len() as u64;
+        counts
+    }
+}
+
+/// Signed statistics of a station log, published periodically so
+/// leaderboards can rank stations without downloading their logs.
+///
+/// Like a [`Checkpoint`], the snapshot covers the first `qso_count` QSOs of
+/// the log by the merkle root of their ids, so the statistics can be checked
+/// against the checkpoints of the station with
+/// [`StatsSnapshot::verify_checkpoint`] and recomputed from the published log
+/// with [`StatsSnapshot::verify_log`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct StatsSnapshot {
+    pub id: Id,
+    pub station_id: Id,
+    pub qso_count: u64,
+    pub merkle_root: Id,
+    /// The checkpoint covering the same QSOs, if the snapshot was taken over
+    /// one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_id: Option<Id>,
+    /// QSOs by band name, e.g. `20m`. QSOs outside the amateur bands are
+    /// left out.
+    pub by_band: BTreeMap<String, u64>,
+    /// QSOs by uppercase mode.
+    pub by_mode: BTreeMap<String, u64>,
+    /// Number of distinct countries worked, see [`crate::dxcc::resolve`].
+    pub entities: u64,
+    /// Number of distinct 4 char grid squares worked.
+    pub grids: u64,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl StatsSnapshot {
+    /// Current version of the stats snapshot object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the stats snapshot verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new StatsSnapshot of the station log and signs the object
+    /// using the station signer. The QSOs must be in log order.
+    pub fn new(station_id: Id, signer: &dyn Signer, qsos: &[Qso]) -> Result<Self> {
+        if qsos.iter().any(|qso| qso.station_id != station_id) {
+            bail!("qso station mismatch");
+        }
+
+        let ids: Vec<Id> = qsos.iter().map(|qso| qso.id.clone()).collect();
+        Self::create(station_id, signer, qsos, merkle::root(&ids), None)
+    }
+
+    /// Creates a new StatsSnapshot of the QSOs covered by the checkpoint and
+    /// signs the object using the station signer. The log must start with
+    /// the covered QSOs, see [`Checkpoint::verify_log`].
+    pub fn for_checkpoint(
+        signer: &dyn Signer,
+        checkpoint: &Checkpoint,
+        qsos: &[Qso],
+    ) -> Result<Self> {
+        checkpoint.verify_log(qsos)?;
+        let covered = &qsos[..checkpoint.qso_count as usize];
+
+        Self::create(
+            checkpoint.station_id.clone(),
+            signer,
+            covered,
+            checkpoint.merkle_root.clone(),
+            Some(checkpoint.id.clone()),
+        )
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Verify that the snapshot is consistent with a checkpoint of the
+    /// station: both must have the same merkle root when they cover as many
+    /// QSOs or the snapshot was taken over the checkpoint, and logs only
+    /// grow, so earlier checkpoints can't cover more QSOs than the snapshot
+    /// and later ones fewer. Checkpoints created in the same second as the
+    /// snapshot are not ordered. The checkpoint signature is not verified.
+    pub fn verify_checkpoint(&self, checkpoint: &Checkpoint) -> Result<()> {
+        if checkpoint.station_id != self.station_id {
+            bail!("checkpoint station mismatch");
+        }
+
+        if self.checkpoint_id.as_ref() == Some(&checkpoint.id)
+            || checkpoint.qso_count == self.qso_count
+        {
+            if checkpoint.qso_count != self.qso_count || checkpoint.merkle_root != self.merkle_root
+            {
+                bail!("stats cover other QSOs than checkpoint {}", checkpoint.id);
+            }
+        } else if checkpoint.created_at < self.created_at && checkpoint.qso_count > self.qso_count {
+            bail!(
+                "stats cover fewer QSOs than earlier checkpoint {}",
+                checkpoint.id
+            );
+        } else if checkpoint.created_at > self.created_at && checkpoint.qso_count < self.qso_count {
+            bail!(
+                "stats cover more QSOs than later checkpoint {}",
+                checkpoint.id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the log, in log order, starts with the QSOs covered by the
+    /// snapshot and that their statistics are the published ones. QSOs
+    /// logged after the snapshot are ignored.
+    pub fn verify_log(&self, qsos: &[Qso]) -> Result<()> {
+        let covered = covered_qsos(&self.station_id, self.qso_count, &self.merkle_root, qsos)?;
+        if Counts::of(covered) != self.counts() {
+            bail!("stats don't match the log");
+        }
+        Ok(())
+    }
+
+    fn create(
+        station_id: Id,
+        signer: &dyn Signer,
+        qsos: &[Qso],
+        merkle_root: Id,
+        checkpoint_id: Option<Id>,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let qso_count = qsos.len() as u64;
+        let counts = Counts::of(qsos);
+        let id = Self::compute_id(
+            &station_id,
+            qso_count,
+            &merkle_root,
+            checkpoint_id.as_ref(),
+            &counts,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let snapshot = Self {
+            id,
+            station_id,
+            qso_count,
+            merkle_root,
+            checkpoint_id,
+            by_band: counts.by_band,
+            by_mode: counts.by_mode,
+            entities: counts.entities,
+            grids: counts.grids,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        snapshot.validate()?;
+
+        Ok(snapshot)
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        qso_count: u64,
+        merkle_root: &Id,
+        checkpoint_id: Option<&Id>,
+        counts: &Counts,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                station_id,
+                qso_count,
+                merkle_root,
+                checkpoint_id,
+                &counts.by_band,
+                &counts.by_mode,
+                counts.entities,
+                counts.grids,
+                created_at,
+                version,
+            ),
+        )
+    }
+
+    fn counts(&self) -> Counts {
+        Counts {
+            by_band: self.by_band.clone(),
+            by_mode: self.by_mode.clone(),
+            entities: self.entities,
+            grids: self.grids,
+        }
+    }
+}
+
+impl Validate for StatsSnapshot {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self
+            .by_band
+            .keys()
+            .any(|band| band.parse::<Band>().is_err())
+        {
+            bail!("invalid band");
+        }
+
+        if self.by_mode.keys().any(|mode| {
+            !text::is_valid_required(mode, MODE_MAX_LEN) || *mode != mode.to_uppercase()
+        }) {
+            bail!("invalid mode");
+        }
+
+        let by_band = self
+            .by_band
+            .values()
+            .try_fold(0u64, |sum, n| sum.checked_add(*n));
+        let by_mode = self
+            .by_mode
+            .values()
+            .try_fold(0u64, |sum, n| sum.checked_add(*n));
+        if by_band.is_none_or(|qsos| qsos > self.qso_count) || by_mode != Some(self.qso_count) {
+            bail!("stats don't add up to {} qsos", self.qso_count);
+        }
+
+        if self.entities > self.qso_count || self.grids > self.qso_count {
+            bail!("more entities or grids than qsos");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for StatsSnapshot {
+    const KIND: Kind = Kind::StatsSnapshot;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            self.qso_count,
+            &self.merkle_root,
+            self.checkpoint_id.as_ref(),
+            &self.counts(),
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::keys::generate_keypair;
+    use crate::signable::Validate;
+    use crate::{test_vectors, Checkpoint, Qso, QsoData, StatsSnapshot};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_stats_snapshot() {
+        let station = test_vectors::station();
+        let keys = test_vectors::station_keypair();
+        let log: Vec<Qso> = [
+            ("LW3DZR", 14_250_300, "ssb", Some("GF05tk")),
+            ("JA1ABC", 7_010_000, "CW", Some("PM95")),
+            ("W2AAA", 14_074_000, "FT8", Some("FN20xr")),
+            ("LU1AA", 14_260_000, "SSB", Some("GF05")),
+            ("LW3DZR", 1_000, "SSB", None),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (callsign, freq, mode, grid))| {
+            Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    callsign: callsign.to_string(),
+                    freq,
+                    datetime: test_vectors::CREATED_AT + i as u64,
+                    mode: mode.to_string(),
+                    comments: String::new(),
+                    extra: grid
+                        .map(|grid| BTreeMap::from([("gridsquare".to_string(), json!(grid))]))
+                        .unwrap_or_default(),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            )
+        })
+        .collect();
+
+        let checkpoint = Checkpoint::new(station.id.clone(), &keys, &log[..4]).unwrap();
+        let snapshot = StatsSnapshot::for_checkpoint(&keys, &checkpoint, &log).unwrap();
+        assert_eq!(snapshot.qso_count, 4);
+        assert_eq!(snapshot.checkpoint_id.as_ref(), Some(&checkpoint.id));
+        assert_eq!(
+            snapshot.by_band,
+            BTreeMap::from([("20m".to_string(), 3), ("40m".to_string(), 1)])
+        );
+        assert_eq!(snapshot.by_mode["SSB"], 2);
+        assert_eq!(snapshot.entities, 3);
+        assert_eq!(snapshot.grids, 3);
+
+        let json_str = serde_json::to_string(&Event::StatsSnapshot(snapshot.clone())).unwrap();
+        let event: Event = serde_json::from_str(&json_str).unwrap();
+        event.verify(&station.pub_key).unwrap();
+
+        snapshot.verify_checkpoint(&checkpoint).unwrap();
+        snapshot.verify_log(&log).unwrap();
+
+        let full = StatsSnapshot::new(station.id.clone(), &keys, &log).unwrap();
+        assert_eq!(full.checkpoint_id, None);
+        assert_eq!(full.by_mode["SSB"], 3);
+        full.verify_checkpoint(&checkpoint).unwrap();
+        full.verify_log(&log).unwrap();
+        assert!(full.verify_log(&log[..4]).is_err());
+
+        // Inflated stats, re-signed by the station, still cover the log of
+        // the checkpoint but don't match it.
+        let mut inflated = snapshot.clone();
+        inflated.by_mode.insert("SSB".to_string(), 3);
+        inflated.by_mode.remove("CW");
+        inflated.validate().unwrap();
+        assert!(inflated.verify_log(&log).is_err());
+        assert!(inflated.verify(&station.pub_key).is_err());
+
+        let mut rewritten = full.clone();
+        rewritten.qso_count = 4;
+        assert!(rewritten.verify_checkpoint(&checkpoint).is_err());
+
+        let mut later = checkpoint.clone();
+        later.id = test_vectors::qso().id;
+        later.qso_count = 3;
+        later.created_at = full.created_at + 1;
+        assert!(full.verify_checkpoint(&later).is_err());
+        later.created_at = full.created_at - 1;
+        full.verify_checkpoint(&later).unwrap();
+        later.qso_count = 6;
+        assert!(full.verify_checkpoint(&later).is_err());
+
+        let mut invalid = snapshot;
+        invalid.grids = 5;
+        assert!(invalid.validate().is_err());
+        invalid.grids = 3;
+        invalid.by_band.insert("21m".to_string(), 0);
+        assert!(invalid.validate().is_err());
+        assert!(invalid
+            .verify(&generate_keypair().x_only_public_key().0)
+            .is_err());
+    }
+}
@@ -0,0 +1,849 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::archive::{read_line, trim_newline};
+use crate::event::{Event, Filter};
+use crate::instrument;
+use crate::limits;
+use crate::progress::Monitor;
+use crate::resolve::resolve;
+use crate::subscription::{Subscribers, Subscription};
+use crate::{Callsign, Id, Tag};
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Options of [`Store::import`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// Verify the signature of every event against the key of its station,
+    /// found in the store or in the imported events. Events failing the
+    /// verification are rejected.
+    pub verify: bool,
+}
+
+/// Outcome of [`Store::import`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub imported: u64,
+    pub duplicates: u64,
+    /// Malformed lines, oversized events and, when verifying, events with
+    /// invalid signatures or unknown stations.
+    pub rejected: u64,
+}
+
+/// Options of [`Store::compact`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactOptions {
+    /// Only report what would be deleted.
+    pub dry_run: bool,
+}
+
+/// Outcome of [`Store::compact`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactStats {
+    /// Events created after the revocation of their key.
+    pub revoked: u64,
+    /// Stations and relay lists replaced by a newer version, and amended
+    /// QSOs.
+    pub superseded: u64,
+    /// QSOs withdrawn by a tombstone.
+    pub withdrawn: u64,
+    pub expired: u64,
+    /// Size of the deleted events as JSON, the space a store reclaims
+    /// once its storage is vacuumed.
+    pub reclaimable_bytes: u64,
+}
+
+impl CompactStats {
+    /// Returns the number of deleted events.
+    pub fn deleted(&self) -> u64 {
+        self.revoked + self.superseded + self.withdrawn + self.expired
+    }
+}
+
+/// Persistent storage of events.
+///
+/// Backups are JSON Lines files with one event per line, ordered by creation
+/// time, so they can be moved between store implementations.
+pub trait Store {
+    /// Stores the event, returns false if it was already stored. Events
+    /// exceeding the size budget of their kind are rejected, see
+    /// [`limits::max_event_size`].
+    fn insert(&mut self, event: Event) -> Result<bool>;
+
+    /// Returns the event with the given id.
+    fn get(&self, id: &Id) -> Result<Option<Event>>;
+
+    /// Returns the events matching the filter.
+    fn query(&self, filter: &Filter) -> Result<Vec<Event>>;
+
+    /// Returns the number of events matching the filter, at most the filter
+    /// limit. Stores should override it to count without loading the events.
+    fn count(&self, filter: &Filter) -> Result<u64> {
+        Ok(self.query(filter)?.len() as u64)
+    }
+
+    /// Returns the events created at or after `since`, ordered by creation
+    /// time and id.
+    fn events_since(&self, since: u64) -> Result<Vec<Event>> {
+        let mut events = self.query(&Filter::new().since(since))?;
+        events.sort_by(|a, b| (a.created_at(), a.id()).cmp(&(b.created_at(), b.id())));
+        Ok(events)
+    }
+
+    /// Returns the creation time of the newest stored event.
+    fn high_water_mark(&self) -> Result<Option<u64>> {
+        Ok(self
+            .query(&Filter::new())?
+            .iter()
+            .map(Event::created_at)
+            .max())
+    }
+
+    /// Copies the events of the source created since the high-water mark of
+    /// this store. Returns the number of new events.
+    ///
+    /// This is a cheap catch up between two stores of the same user, not a
+    /// full reconciliation: events older than the high-water mark which only
+    /// the source has are not copied. Syncing in both directions makes both
+    /// stores catch up with each other.
+    fn sync_from(&mut self, source: &dyn Store) -> Result<u64> {
+        let _span = tracing::info_span!("sync_from").entered();
+        // The mark itself is included, other events may share its second.
+        let since = self.high_water_mark()?.unwrap_or(0);
+        let mut inserted = 0;
+        for event in source.events_since(since)? {
+            if self.insert(event)? {
+                inserted += 1;
+            }
+        }
+        Ok(inserted)
+    }
+
+    /// Subscribes to the events matching the filter: the stored ones, ordered
+    /// by creation time and id, and then the ones inserted afterwards. The
+    /// filter limit only applies to the stored events.
+    fn subscribe(&mut self, _filter: Filter) -> Result<Subscription> {
+        bail!("subscriptions not supported");
+    }
+
+    /// Deletes the events expired at or before `now`, see
+    /// [`Event::expires_at`]. Returns the number of deleted events.
+    fn prune_expired(&mut self, _now: u64) -> Result<u64> {
+        bail!("pruning not supported");
+    }
+
+    /// Deletes the events with the given ids. Returns the number of deleted
+    /// events.
+    fn delete(&mut self, _ids: &[Id]) -> Result<u64> {
+        bail!("deletion not supported");
+    }
+
+    /// Deletes the events no peer keeps: the ones expired at or before
+    /// `now`, the ones revoked, the replaced stations and relay lists and the
+    /// amended and withdrawn QSOs, see [`resolve`]. The stored events must have been verified.
+    ///
+    /// The default implementation loads every event, stores should override
+    /// it when they can compact incrementally.
+    fn compact(&mut self, now: u64, options: &CompactOptions) -> Result<CompactStats> {
+        let _span = tracing::info_span!("compact", dry_run = options.dry_run).entered();
+        let mut stats = CompactStats::default();
+        let mut size = BTreeMap::new();
+        let mut deleted = vec![];
+        let mut events = vec![];
+
+        for event in self.query(&Filter::new())? {
+            size.insert(event.id().clone(), serde_json::to_vec(&event)?.len() as u64);
+            if event.is_expired(now) {
+                stats.expired += 1;
+                deleted.push(event.id().clone());
+            } else {
+                events.push(event);
+            }
+        }
+
+        let canonical = resolve(events);
+        stats.revoked = canonical.revoked.len() as u64;
+        stats.superseded = canonical.superseded.len() as u64;
+        stats.withdrawn = canonical.withdrawn.len() as u64;
+        deleted.extend(canonical.revoked);
+        deleted.extend(canonical.superseded);
+        deleted.extend(canonical.withdrawn);
+        stats.reclaimable_bytes = deleted.iter().map(|id| size[id]).sum();
+
+        if !options.dry_run {
+            self.delete(&deleted)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Writes every event to the writer, reporting the progress to the
+    /// monitor. Returns the number of events written.
+    fn export_all(&self, writer: &mut dyn Write, monitor: &mut Monitor) -> Result<u64> {
+        let _span = tracing::info_span!("export_all").entered();
+        let mut events = self.query(&Filter::new())?;
+        events.sort_by(|a, b| (a.created_at(), a.id()).cmp(&(b.created_at(), b.id())));
+        monitor.start(Some(events.len() as u64))?;
+
+        let mut written = 0;
+        for event in &events {
+            serde_json::to_writer(&mut *writer, event)?;
+            writer.write_all(b"\n")?;
+            written += 1;
+            monitor.record(true)?;
+        }
+        writer.flush()?;
+
+        Ok(written)
+    }
+
+    /// Imports a backup written by [`Store::export_all`], streaming it and
+    /// reporting every line processed to the monitor, the total is unknown.
+    /// The events imported before a cancellation are kept.
+    ///
+    /// Malformed lines and oversized events are skipped, so a partially
+    /// corrupted backup restores every readable event. When verifying, the
+    /// events whose station comes later in the backup are held back until
+    /// the end.
+    fn import(
+        &mut self,
+        reader: &mut dyn Read,
+        options: &ImportOptions,
+        monitor: &mut Monitor,
+    ) -> Result<ImportStats> {
+        let _span = tracing::info_span!("import", verify = options.verify).entered();
+        let mut stats = ImportStats::default();
+        let mut reader = BufReader::new(reader);
+        let mut line = vec![];
+        let mut deferred = vec![];
+
+        monitor.start(None)?;
+        loop {
+            line.clear();
+            let event = match read_line(&mut reader, &mut line, limits::MAX_EVENT_SIZE) {
+                Ok(0) => break,
+                Ok(_) => serde_json::from_slice::<Event>(trim_newline(&line)).ok(),
+                Err(_) if line.len() > limits::MAX_EVENT_SIZE => {
+                    reader.skip_until(b'\n')?;
+                    None
+                }
+                Err(err) => return Err(err),
+            };
+            let Some(event) = event else {
+                instrument::record_import("rejected");
+                stats.rejected += 1;
+                monitor.record(false)?;
+                continue;
+            };
+
+            if options.verify
+                && !matches!(event, Event::Station(_))
+                && self.get(event.station_id())?.is_none()
+            {
+                deferred.push(event);
+                continue;
+            }
+            let valid = import_event(self, event, options, &mut stats)?;
+            monitor.record(valid)?;
+        }
+
+        for event in deferred {
+            let valid = import_event(self, event, options, &mut stats)?;
+            monitor.record(valid)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Verifies the event against the key of its station, if stored.
+    fn verify_event(&self, event: &Event) -> Result<bool> {
+        let pub_key = match event {
+            Event::Station(station) => station.pub_key,
+            _ => match self.get(event.station_id())? {
+                Some(Event::Station(station)) => station.pub_key,
+                _ => return Ok(false),
+            },
+        };
+        Ok(event.verify(&pub_key).is_ok())
+    }
+}
+
+/// Imports a parsed event of a backup, returns whether it was valid.
+fn import_event<S: Store + ?Sized>(
+    store: &mut S,
+    event: Event,
+    options: &ImportOptions,
+    stats: &mut ImportStats,
+) -> Result<bool> {
+    let valid = limits::check_event_size(&event).is_ok()
+        && (!options.verify || store.verify_event(&event)?);
+    if !valid {
+        instrument::record_import("rejected");
+        stats.rejected += 1;
+    } else if store.insert(event)? {
+        instrument::record_import("imported");
+        stats.imported += 1;
+    } else {
+        instrument::record_import("duplicate");
+        stats.duplicates += 1;
+    }
+    Ok(valid)
+}
+
+/// Async variant of [`Store`], for servers running on an async runtime where
+/// a blocking store call would stall the event loop.
+pub trait AsyncStore: Send + Sync {
+    /// Stores the event, returns false if it was already stored.
+    fn insert(&self, event: Event) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Returns the event with the given id.
+    fn get(&self, id: &Id) -> impl Future<Output = Result<Option<Event>>> + Send;
+
+    /// Returns the events matching the filter.
+    fn query(&self, filter: &Filter) -> impl Future<Output = Result<Vec<Event>>> + Send;
+
+    /// Returns the number of events matching the filter, at most the filter
+    /// limit.
+    fn count(&self, filter: &Filter) -> impl Future<Output = Result<u64>> + Send {
+        async move { Ok(self.query(filter).await?.len() as u64) }
+    }
+
+    /// Returns the events created at or after `since`, ordered by creation
+    /// time and id.
+    fn events_since(&self, since: u64) -> impl Future<Output = Result<Vec<Event>>> + Send {
+        async move {
+            let mut events = self.query(&Filter::new().since(since)).await?;
+            events.sort_by(|a, b| (a.created_at(), a.id()).cmp(&(b.created_at(), b.id())));
+            Ok(events)
+        }
+    }
+}
+
+/// A [`Store`] keeping the events in memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStore {
+    events: BTreeMap<Id, Event>,
+    by_created_at: BTreeSet<(u64, Id)>,
+    by_tag: BTreeMap<Tag, BTreeSet<Id>>,
+    /// Normalized callsigns, see [`Event::callsign`].
+    by_callsign: BTreeMap<String, BTreeSet<Id>>,
+    /// Uppercase grid squares, see [`Event::grid`].
+    by_grid: BTreeMap<String, BTreeSet<Id>>,
+    subscribers: Subscribers,
+}
+
+impl MemoryStore {
+    /// Creates an empty MemoryStore.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of stored events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns the events which may match the filter, in id order. The ids,
+    /// tags, callsigns and grid squares of the filter are looked up instead
+    /// of scanning the store, callsign patterns from their prefix.
+    fn candidates<'a>(&'a self, filter: &'a Filter) -> Box<dyn Iterator<Item = &'a Event> + 'a> {
+        let ids: BTreeSet<&Id> = if !filter.ids.is_empty() {
+            filter.ids.iter().collect()
+        } else if !filter.tags.is_empty() {
+            filter
+                .tags
+                .iter()
+                .filter_map(|tag| self.by_tag.get(tag))
+                .flatten()
+                .collect()
+        } else if !filter.callsigns.is_empty()
+            && filter
+                .callsigns
+                .iter()
+                .all(|pattern| !pattern.prefix().is_empty())
+        {
+            starting_with(
+                &self.by_callsign,
+                filter
+                    .callsigns
+                    .iter()
+                    .map(|pattern| pattern.prefix().to_string()),
+            )
+        } else if !filter.grids.is_empty() {
+            starting_with(
+                &self.by_grid,
+                filter.grids.iter().map(|grid| grid.to_ascii_uppercase()),
+            )
+        } else {
+            return Box::new(self.events.values());
+        };
+        Box::new(ids.into_iter().filter_map(|id| self.events.get(id)))
+    }
+
+    /// Removes the event and its index entries.
+    fn remove(&mut self, id: &Id) -> bool {
+        let Some(event) = self.events.remove(id) else {
+            return false;
+        };
+        self.by_created_at.remove(&(event.created_at(), id.clone()));
+        for tag in event.tags() {
+            if let Some(ids) = self.by_tag.get_mut(tag) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.by_tag.remove(tag);
+                }
+            }
+        }
+        if let Some(callsign) = event.callsign() {
+            unindex(
+                &mut self.by_callsign,
+                Callsign::normalize(callsign).as_str(),
+                id,
+            );
+        }
+        if let Some(grid) = event.grid() {
+            unindex(&mut self.by_grid, &grid.to_ascii_uppercase(), id);
+        }
+        true
+    }
+}
+
+/// Returns the ids of the index keys starting with any of the prefixes.
+fn starting_with(
+    index: &BTreeMap<String, BTreeSet<Id>>,
+    prefixes: impl Iterator<Item = String>,
+) -> BTreeSet<&Id> {
+    prefixes
+        .flat_map(|prefix| {
+            index
+                .range(prefix.clone()..)
+                .take_while(move |(key, _)| key.starts_with(&prefix))
+                .flat_map(|(_, ids)| ids)
+        })
+        .collect()
+}
+
+/// Removes the id from the index key, and the key once empty.
+fn unindex(index: &mut BTreeMap<String, BTreeSet<Id>>, key: &str, id: &Id) {
+    if let Some(ids) = index.get_mut(key) {
+        ids.remove(id);
+        if ids.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+impl Store for MemoryStore {
+    fn insert(&mut self, event: Event) -> Result<bool> {
+        if self.events.contains_key(event.id()) {
+            return Ok(false);
+        }
+        limits::check_event_size(&event)?;
+        self.by_created_at
+            .insert((event.created_at(), event.id().clone()));
+        for tag in event.tags() {
+            self.by_tag
+                .entry(tag.clone())
+                .or_default()
+                .insert(event.id().clone());
+        }
+        if let Some(callsign) = event.callsign() {
+            self.by_callsign
+                .entry(Callsign::normalize(callsign).to_string())
+                .or_default()
+                .insert(event.id().clone());
+        }
+        if let Some(grid) = event.grid() {
+            self.by_grid
+                .entry(grid.to_ascii_uppercase())
+                .or_default()
+                .insert(event.id().clone());
+        }
+        self.subscribers.notify(&event);
+        self.events.insert(event.id().clone(), event);
+        Ok(true)
+    }
+
+    fn get(&self, id: &Id) -> Result<Option<Event>> {
+        Ok(self.events.get(id).cloned())
+    }
+
+    fn query(&self, filter: &Filter) -> Result<Vec<Event>> {
+        let events = self
+            .candidates(filter)
+            .filter(|event| filter.matches(event))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        Ok(events)
+    }
+
+    fn count(&self, filter: &Filter) -> Result<u64> {
+        let count = self
+            .candidates(filter)
+            .filter(|event| filter.matches(event))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .count();
+        Ok(count as u64)
+    }
+
+    fn events_since(&self, since: u64) -> Result<Vec<Event>> {
+        let start = (since, Id::from_bytes([0; 32]));
+        Ok(self
+            .by_created_at
+            .range(start..)
+            .map(|(_, id)| self.events[id].clone())
+            .collect())
+    }
+
+    fn high_water_mark(&self) -> Result<Option<u64>> {
+        Ok(self.by_created_at.last().map(|(created_at, _)| *created_at))
+    }
+
+    fn subscribe(&mut self, filter: Filter) -> Result<Subscription> {
+        let mut stored = self.query(&filter)?;
+        stored.sort_by(|a, b| (a.created_at(), a.id()).cmp(&(b.created_at(), b.id())));
+        Ok(self.subscribers.subscribe(filter, stored))
+    }
+
+    fn prune_expired(&mut self, now: u64) -> Result<u64> {
+        let expired: BTreeSet<Id> = self
+            .by_tag
+            .range(Tag::ExpiresAt(0)..=Tag::ExpiresAt(now))
+            .flat_map(|(_, ids)| ids.iter().cloned())
+            .collect();
+
+        self.delete(&expired.into_iter().collect::<Vec<_>>())
+    }
+
+    fn delete(&mut self, ids: &[Id]) -> Result<u64> {
+        Ok(ids.iter().filter(|id| self.remove(id)).count() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::callsign::StrictCallsign;
+    use crate::event::{Event, Filter};
+    use crate::keys::generate_keypair;
+    use crate::progress::{CancellationToken, Cancelled, Monitor};
+    use crate::store::{CompactOptions, ImportOptions, ImportStats, MemoryStore, Store};
+    use crate::{limits, test_vectors, Qso, QsoData, Revocation, Station, StationData, Tag};
+    use codes_iso_3166::part_1::CountryCode;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_export_import() {
+        let keys = generate_keypair();
+
+        let station = Station::new(
+            &keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let qso = |datetime| {
+            Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    datetime,
+                    mode: "CW".to_string(),
+                    rst: "599".to_string(),
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            )
+        };
+
+        let mut store = MemoryStore::new();
+        store.insert(Event::Station(station.clone())).unwrap();
+        store.insert(Event::Qso(qso(1704141426))).unwrap();
+        let duplicate = Event::Qso(qso(1704141427));
+        store.insert(duplicate.clone()).unwrap();
+        assert!(!store.insert(duplicate).unwrap());
+
+        let mut backup = vec![];
+        let mut exported = vec![];
+        let count = store
+            .export_all(
+                &mut backup,
+                &mut Monitor::new().on_progress(|progress| exported.push(progress.processed)),
+            )
+            .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(exported, vec![1, 2, 3]);
+
+        let mut tampered = qso(1704141428);
+        tampered.rst = "339".to_string();
+        let mut backup = String::from_utf8(backup).unwrap();
+        backup.push_str("{corrupted\n");
+        backup.push_str(&serde_json::to_string(&Event::Qso(tampered)).unwrap());
+        backup.push('\n');
+
+        let options = ImportOptions { verify: true };
+        let mut restored = MemoryStore::new();
+        let stats = restored
+            .import(&mut backup.as_bytes(), &options, &mut Monitor::new())
+            .unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                imported: 3,
+                duplicates: 0,
+                rejected: 2,
+            }
+        );
+        assert_eq!(restored, store);
+
+        let stats = restored
+            .import(
+                &mut backup.as_bytes(),
+                &ImportOptions::default(),
+                &mut Monitor::new(),
+            )
+            .unwrap();
+        assert_eq!(stats.imported, 1);
+        assert_eq!(stats.duplicates, 3);
+
+        // Progress is reported line by line, events ahead of their station
+        // are imported at the end and oversized lines are skipped.
+        let mut lines: Vec<&str> = backup.lines().collect();
+        lines.swap(0, 1);
+        let oversized = " ".repeat(limits::MAX_EVENT_SIZE + 1);
+        lines.insert(2, &oversized);
+        let reordered = lines.join("\n");
+        let mut progress = vec![];
+        let stats = MemoryStore::new()
+            .import(
+                &mut reordered.as_bytes(),
+                &options,
+                &mut Monitor::new().on_progress(|p| progress.push((p.processed, p.total))),
+            )
+            .unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                imported: 3,
+                duplicates: 0,
+                rejected: 3,
+            }
+        );
+        assert_eq!(progress.len(), 6);
+        assert!(progress.iter().all(|(_, total)| total.is_none()));
+
+        let token = CancellationToken::new();
+        let mut monitor = Monitor::new()
+            .cancel_with(token.clone())
+            .on_progress(|progress| {
+                if progress.processed == 1 {
+                    token.cancel();
+                }
+            });
+        let mut partial = MemoryStore::new();
+        let error = partial
+            .import(&mut backup.as_bytes(), &options, &mut monitor)
+            .unwrap_err();
+        assert!(error.is::<Cancelled>());
+    }
+
+    #[test]
+    fn test_sync() {
+        let keys = generate_keypair();
+
+        let station = |callsign: &str, created_at| {
+            Event::Station(
+                Station::create(
+                    StationData {
+                        callsign: callsign.to_string(),
+                        operator: "Radio Club Caseros".to_string(),
+                        country: CountryCode::AR.into(),
+                        extra: BTreeMap::new(),
+                        profile: None,
+                        qsl_route: None,
+                    },
+                    &keys,
+                    created_at,
+                    0,
+                    None,
+                    &StrictCallsign,
+                )
+                .unwrap(),
+            )
+        };
+
+        let mut desktop = MemoryStore::new();
+        let mut phone = MemoryStore::new();
+        assert_eq!(phone.high_water_mark().unwrap(), None);
+
+        desktop.insert(station("LU4EV", 1704141426)).unwrap();
+        desktop.insert(station("LU1AA", 1704141430)).unwrap();
+        assert_eq!(phone.sync_from(&desktop).unwrap(), 2);
+        assert_eq!(phone, desktop);
+        assert_eq!(phone.high_water_mark().unwrap(), Some(1704141430));
+
+        assert_eq!(desktop.events_since(1704141427).unwrap().len(), 1);
+        assert!(desktop.events_since(1704141431).unwrap().is_empty());
+
+        // Same second as the high-water mark, still synced.
+        phone.insert(station("LW3DZR", 1704141430)).unwrap();
+        phone.insert(station("LU2AA", 1704141440)).unwrap();
+        assert_eq!(desktop.sync_from(&phone).unwrap(), 2);
+        assert_eq!(phone.sync_from(&desktop).unwrap(), 0);
+        assert_eq!(phone, desktop);
+
+        let lu4ev = station("LU4EV", 1704141426);
+        assert_eq!(phone.count(&Filter::new()).unwrap(), 4);
+        assert_eq!(phone.count(&Filter::new().since(1704141430)).unwrap(), 3);
+        assert_eq!(phone.count(&Filter::new().limit(2)).unwrap(), 2);
+        assert_eq!(
+            phone
+                .count(&Filter::new().ids([lu4ev.id().clone(), lu4ev.id().clone()]))
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_prune_expired() {
+        let check_in = |comments: &str, expires_at| {
+            Event::Qso(Qso::new(
+                QsoData {
+                    freq: 7088000,
+                    comments: comments.to_string(),
+                    tags: vec![Tag::ExpiresAt(expires_at)],
+                    ..test_vectors::qso_data()
+                },
+                &test_vectors::station_keypair(),
+            ))
+        };
+        let early = check_in("early", test_vectors::CREATED_AT + 60);
+        let late = check_in("late", test_vectors::CREATED_AT + 3600);
+        assert_eq!(late.expires_at(), Some(test_vectors::CREATED_AT + 3600));
+        assert!(!late.is_expired(test_vectors::CREATED_AT + 60));
+
+        let mut store = MemoryStore::new();
+        store
+            .insert(Event::Station(test_vectors::station()))
+            .unwrap();
+        store.insert(early.clone()).unwrap();
+        store.insert(late.clone()).unwrap();
+
+        assert_eq!(store.prune_expired(test_vectors::CREATED_AT).unwrap(), 0);
+        assert_eq!(
+            store.prune_expired(test_vectors::CREATED_AT + 60).unwrap(),
+            1
+        );
+        assert_eq!(store.get(early.id()).unwrap(), None);
+        assert_eq!(store.get(late.id()).unwrap(), Some(late));
+        assert_eq!(
+            store
+                .count(&Filter::new().tags([Tag::ExpiresAt(test_vectors::CREATED_AT + 60)]))
+                .unwrap(),
+            0
+        );
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn test_compact() {
+        let keys = test_vectors::station_keypair();
+        let station = test_vectors::station();
+        let update = station
+            .update(
+                StationData {
+                    callsign: station.callsign.clone(),
+                    operator: "Radio Club Caseros".to_string(),
+                    country: CountryCode::AR.into(),
+                    extra: BTreeMap::new(),
+                    profile: None,
+                    qsl_route: None,
+                },
+                &keys,
+            )
+            .unwrap();
+        let qso = |tags| {
+            Event::Qso(Qso::new(
+                QsoData {
+                    station_id: station.id.clone(),
+                    freq: 7088000,
+                    tags,
+                    ..test_vectors::qso_data()
+                },
+                &keys,
+            ))
+        };
+        let expired = qso(vec![Tag::ExpiresAt(test_vectors::CREATED_AT + 60)]);
+        let live = qso(vec![]);
+
+        let mut store = MemoryStore::new();
+        for event in [
+            Event::Station(station.clone()),
+            Event::Station(update.clone()),
+            expired.clone(),
+            live.clone(),
+        ] {
+            store.insert(event).unwrap();
+        }
+
+        let now = test_vectors::CREATED_AT + 60;
+        let dry_run = store
+            .compact(now, &CompactOptions { dry_run: true })
+            .unwrap();
+        assert_eq!(dry_run.expired, 1);
+        assert_eq!(dry_run.superseded, 1);
+        assert_eq!(dry_run.revoked, 0);
+        assert_eq!(
+            dry_run.reclaimable_bytes,
+            (serde_json::to_vec(&expired).unwrap().len()
+                + serde_json::to_vec(&Event::Station(station.clone()))
+                    .unwrap()
+                    .len()) as u64
+        );
+        assert_eq!(store.len(), 4);
+
+        let stats = store.compact(now, &CompactOptions::default()).unwrap();
+        assert_eq!(stats, dry_run);
+        assert_eq!(stats.deleted(), 2);
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(&station.id).unwrap(), None);
+        assert_eq!(store.get(live.id()).unwrap(), Some(live.clone()));
+
+        // Revoking the key before the update deletes it, the QSO was created
+        // earlier and is kept.
+        let revocation = Revocation::new(update.id.clone(), &keys, update.created_at - 1).unwrap();
+        store.insert(Event::Revocation(revocation.clone())).unwrap();
+        let stats = store.compact(now, &CompactOptions::default()).unwrap();
+        assert_eq!(stats.revoked, 1);
+        assert_eq!(store.get(&update.id).unwrap(), None);
+        assert_eq!(store.get(live.id()).unwrap(), Some(live));
+        assert!(store.get(&revocation.id).unwrap().is_some());
+
+        assert_eq!(
+            store
+                .compact(now, &CompactOptions::default())
+                .unwrap()
+                .deleted(),
+            0
+        );
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::event::{Event, Filter};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::task::{Context, Poll, Waker};
+
+/// The maximum number of newly inserted events waiting to be consumed by a
+/// subscription, on top of the stored ones it starts with.
+pub const MAX_PENDING_EVENTS: usize = 1024;
+
+/// The events matching a filter, the stored ones first and then the newly
+/// inserted ones as they arrive. See [`crate::Store::subscribe`].
+///
+/// A subscription is both a blocking [`Iterator`] and a [`Stream`]. Both end
+/// when the store is dropped, or when the consumer falls more than
+/// [`MAX_PENDING_EVENTS`] events behind the store, see
+/// [`Subscription::is_lagged`].
+pub struct Subscription {
+    shared: Arc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    events: VecDeque<Event>,
+    waker: Option<Waker>,
+    capacity: usize,
+    closed: bool,
+    lagged: bool,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        let mut state = self.state();
+        f(&mut state);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.ready.notify_all();
+    }
+}
+
+impl Subscription {
+    /// Returns the next event if one is available, without blocking.
+    pub fn try_next(&mut self) -> Option<Event> {
+        self.shared.state().events.pop_front()
+    }
+
+    /// Returns true if the subscription was closed because the consumer fell
+    /// behind, the events inserted since then were dropped.
+    pub fn is_lagged(&self) -> bool {
+        self.shared.state().lagged
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = Event;
+
+    /// Blocks until an event is available or the store is dropped.
+    fn next(&mut self) -> Option<Event> {
+        let mut state = self.shared.state();
+        loop {
+            if let Some(event) = state.events.pop_front() {
+                return Some(event);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .shared
+                .ready
+                .wait(state)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        let mut state = self.shared.state();
+        if let Some(event) = state.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Debug for Subscription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = self.shared.state();
+        f.debug_struct("Subscription")
+            .field("pending", &state.events.len())
+            .field("closed", &state.closed)
+            .field("lagged", &state.lagged)
+            .finish()
+    }
+}
+
+/// The live subscriptions of a store.
+///
+/// Clones start without subscriptions and every instance compares equal, so
+/// stores can keep deriving those traits.
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    subscribers: Vec<(Filter, Weak<Shared>)>,
+}
+
+impl Subscribers {
+    /// Creates a subscription starting with the stored events.
+    pub(crate) fn subscribe(&mut self, filter: Filter, stored: Vec<Event>) -> Subscription {
+        let shared = Arc::new(Shared::default());
+        {
+            let mut state = shared.state();
+            state.capacity = stored.len() + MAX_PENDING_EVENTS;
+            state.events.extend(stored);
+        }
+        self.subscribers.push((filter, Arc::downgrade(&shared)));
+        Subscription { shared }
+    }
+
+    /// Sends a newly inserted event to the matching subscriptions. The
+    /// subscriptions with a full queue are closed and dropped.
+    pub(crate) fn notify(&mut self, event: &Event) {
+        self.subscribers
+            .retain(|(filter, shared)| match shared.upgrade() {
+                Some(shared) => {
+                    if !filter.matches(event) {
+                        return true;
+                    }
+                    let mut live = true;
+                    shared.update(|state| {
+                        if state.events.len() < state.capacity {
+                            state.events.push_back(event.clone());
+                        } else {
+                            state.closed = true;
+                            state.lagged = true;
+                            live = false;
+                        }
+                    });
+                    live
+                }
+                None => false,
+            });
+    }
+}
+
+impl Drop for Subscribers {
+    fn drop(&mut self) {
+        for (_, shared) in &self.subscribers {
+            if let Some(shared) = shared.upgrade() {
+                shared.update(|state| state.closed = true);
+            }
+        }
+    }
+}
+
+impl Clone for Subscribers {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl PartialEq for Subscribers {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Subscribers {}
+
+impl Debug for Subscribers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscribers")
+            .field("len", &self.subscribers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{Event, Filter};
+    use crate::store::{MemoryStore, Store};
+    use crate::subscription::{Subscribers, MAX_PENDING_EVENTS};
+    use crate::{test_vectors, Kind};
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let mut store = MemoryStore::new();
+        store
+            .insert(Event::Station(test_vectors::station()))
+            .unwrap();
+        store.insert(Event::Qso(test_vectors::qso())).unwrap();
+
+        let filter = Filter::new().kinds([Kind::Qso, Kind::Certificate]);
+        let mut subscription = store.subscribe(filter).unwrap();
+        assert_eq!(
+            subscription.try_next(),
+            Some(Event::Qso(test_vectors::qso()))
+        );
+        assert_eq!(subscription.try_next(), None);
+
+        let certificate = Event::Certificate(test_vectors::certificate());
+        store
+            .insert(Event::Station(test_vectors::counterparty()))
+            .unwrap();
+        store.insert(certificate.clone()).unwrap();
+
+        let next =
+            poll_fn(|cx| futures_core::Stream::poll_next(Pin::new(&mut subscription), cx)).await;
+        assert_eq!(next, Some(certificate));
+
+        drop(store);
+        assert_eq!(subscription.next(), None);
+        assert!(!subscription.is_lagged());
+    }
+
+    #[test]
+    fn test_lagged_subscriber() {
+        let station = Event::Station(test_vectors::station());
+        let mut subscribers = Subscribers::default();
+        let mut subscription = subscribers.subscribe(Filter::new(), vec![station.clone()]);
+        for _ in 0..MAX_PENDING_EVENTS {
+            subscribers.notify(&station);
+        }
+        assert!(!subscription.is_lagged());
+        assert_eq!(subscribers.subscribers.len(), 1);
+
+        subscribers.notify(&station);
+        assert!(subscription.is_lagged());
+        assert!(subscribers.subscribers.is_empty());
+        assert_eq!(subscription.by_ref().count(), MAX_PENDING_EVENTS + 1);
+    }
+}
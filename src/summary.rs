@@ -0,0 +1,220 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Human readable one line summaries of the objects and a plain text table
+//! formatter for lists of them.
+
+use crate::qso::RST_RCVD;
+use crate::signable::Signable;
+use crate::{Certificate, Id, Qso, Station};
+use chrono::DateTime;
+use std::fmt::{Display, Formatter};
+
+/// Number of hex chars of the ids shown in summaries.
+const SHORT_ID_LEN: usize = 8;
+
+/// An object with a one line summary, split in columns for [`table`].
+pub trait Summary {
+    /// Returns the column headers.
+    fn headers() -> &'static [&'static str];
+
+    /// Returns the column values, one per header.
+    fn columns(&self) -> Vec<String>;
+
+    /// Returns the one line summary, the columns separated by spaces.
+    fn format_summary(&self) -> String {
+        self.columns().join(" ")
+    }
+}
+
+impl Summary for Qso {
+    fn headers() -> &'static [&'static str] {
+        &["DATETIME", "FREQ", "MODE", "CALLSIGN", "RST"]
+    }
+
+    /// The datetime in UTC, the frequency in MHz and the reports sent and
+    /// received, e.g. `2024-01-05 14:32 14.074 MHz FT8 LW3DZR -05/-10`.
+    fn columns(&self) -> Vec<String> {
+        let rst = match self.extra.get(RST_RCVD).and_then(|rst| rst.as_str()) {
+            Some(rst_rcvd) => format!("{}/{}", self.rst, rst_rcvd),
+            None => self.rst.clone(),
+        };
+        vec![
+            format_datetime(self.datetime),
+            format_freq(self.freq),
+            self.mode.clone(),
+            self.callsign.clone(),
+            rst,
+        ]
+    }
+}
+
+impl Summary for Station {
+    fn headers() -> &'static [&'static str] {
+        &["CALLSIGN", "OPERATOR", "COUNTRY", "ID"]
+    }
+
+    fn columns(&self) -> Vec<String> {
+        vec![
+            self.callsign.clone(),
+            self.operator.clone(),
+            self.country.to_string(),
+            short_id(&self.id),
+        ]
+    }
+
+    /// e.g. `LU4EV Radio Club Caseros (AR)`.
+    fn format_summary(&self) -> String {
+        format!("{} {} ({})", self.callsign, self.operator, self.country)
+    }
+}
+
+impl Summary for Certificate {
+    fn headers() -> &'static [&'static str] {
+        &["ISSUER", "SUBJECT", "CREATED"]
+    }
+
+    fn columns(&self) -> Vec<String> {
+        vec![
+            short_id(self.issuer_id()),
+            short_id(self.subject_id()),
+            format_datetime(self.created_at()),
+        ]
+    }
+
+    /// e.g. `1a2b3c4d certified 5e6f7a8b at 2024-01-05 14:32`.
+    fn format_summary(&self) -> String {
+        format!(
+            "{} certified {} at {}",
+            short_id(self.issuer_id()),
+            short_id(self.subject_id()),
+            format_datetime(self.created_at())
+        )
+    }
+}
+
+impl Display for Qso {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format_summary())
+    }
+}
+
+impl Display for Station {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format_summary())
+    }
+}
+
+impl Display for Certificate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format_summary())
+    }
+}
+
+/// Formats the objects as a table with a header line and left aligned
+/// columns, one line per object.
+pub fn table<'a, T, I>(items: I) -> String
+where
+    T: Summary + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    let headers: Vec<String> = T::headers().iter().map(|h| h.to_string()).collect();
+    let rows: Vec<Vec<String>> = std::iter::once(headers)
+        .chain(items.into_iter().map(Summary::columns))
+        .collect();
+
+    let mut widths = vec![0; T::headers().len()];
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(column, width)| format!("{:<width$}", column, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Formats a unix time as `YYYY-MM-DD HH:MM` UTC, or the raw seconds if it
+/// is out of range.
+fn format_datetime(datetime: u64) -> String {
+    i64::try_from(datetime)
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|datetime| datetime.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| datetime.to_string())
+}
+
+/// Formats a frequency in Hz as MHz, with at least 3 decimals and no trailing
+/// zeros beyond them.
+fn format_freq(freq: u64) -> String {
+    let decimals = format!("{:06}", freq % 1_000_000);
+    let decimals = decimals.trim_end_matches('0');
+    format!("{}.{:0<3} MHz", freq / 1_000_000, decimals)
+}
+
+fn short_id(id: &Id) -> String {
+    let mut id = id.to_string();
+    id.truncate(SHORT_ID_LEN);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::summary::{table, Summary};
+    use crate::{test_vectors, RST_RCVD};
+    use serde_json::Value;
+
+    #[test]
+    fn test_summary() {
+        let mut qso = test_vectors::qso();
+        assert_eq!(
+            qso.format_summary(),
+            "2024-01-01 20:37 14.2503 MHz SSB LW3DZR 59"
+        );
+
+        qso.freq = 14074000;
+        qso.mode = "FT8".to_string();
+        qso.rst = "-05".to_string();
+        qso.extra
+            .insert(RST_RCVD.to_string(), Value::from("-10".to_string()));
+        assert_eq!(
+            qso.to_string(),
+            "2024-01-01 20:37 14.074 MHz FT8 LW3DZR -05/-10"
+        );
+
+        let station = test_vectors::station();
+        assert_eq!(station.to_string(), "LU4EV Radio Club Caseros (AR)");
+
+        let certificate = test_vectors::certificate();
+        assert!(certificate
+            .to_string()
+            .starts_with(&station.id.to_string()[..8]));
+
+        let stations = [station, test_vectors::counterparty()];
+        let lines: Vec<String> = table(&stations).lines().map(String::from).collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("CALLSIGN  OPERATOR            COUNTRY  ID"));
+        assert!(lines[1].starts_with("LU4EV     Radio Club Caseros  AR       "));
+        assert!(lines[2].starts_with("LW3DZR    Gabriel Velo        AR       "));
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::callsign::{Callsign, CallsignPolicy, StrictCallsign};
+use crate::qso::{COMMENTS_MAX_LEN, MODE_MAX_LEN, RST_MAX_LEN};
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{text, version, Id, Kind};
+use anyhow::{bail, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// The reception details of an [`SwlReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwlReportData {
+    /// The station heard, or the [`crate::Beacon`] heard.
+    pub station_id: Id,
+    /// The callsign as heard by the listener.
+    pub callsign: String,
+    pub datetime: u64,
+    pub freq: u64,
+    pub mode: String,
+    /// Signal report, RST or SINPO.
+    pub rst: String,
+    pub comments: String,
+}
+
+/// A reception report signed by a shortwave listener: the listener heard a
+/// transmission of a station without a two-way contact.
+///
+/// The listener signs with its own station object, usually a listener
+/// identifier ( `ONL-12345` ) created with
+/// [`crate::PermissiveCallsign`]. The station heard can confirm the report
+/// with an [`SwlAck`], the digital equivalent of an SWL QSL card.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SwlReport {
+    pub id: Id,
+    pub listener_id: Id,
+    pub station_id: Id,
+    pub callsign: String,
+    pub datetime: u64,
+    pub freq: u64,
+    pub mode: String,
+    pub rst: String,
+    pub comments: String,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl SwlReport {
+    /// Current version of the SWL report object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the SWL report verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new SwlReport and signs the object using the listener
+    /// signer.
+    pub fn new(listener_id: Id, signer: &dyn Signer, data: SwlReportData) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let callsign = Callsign::normalize(&data.callsign).to_string();
+        let id = Self::compute_id(
+            &listener_id,
+            &data.station_id,
+            &callsign,
+            data.datetime,
+            data.freq,
+            &data.mode,
+            &data.rst,
+            &data.comments,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let report = Self {
+            id,
+            listener_id,
+            station_id: data.station_id,
+            callsign,
+            datetime: data.datetime,
+            freq: data.freq,
+            mode: data.mode,
+            rst: data.rst,
+            comments: data.comments,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        report.validate()?;
+
+        Ok(report)
+    }
+
+    /// Verify the object signature against the listener public key.
+    pub fn verify(&self, listener_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(listener_pub_key)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compute_id(
+        listener_id: &Id,
+        station_id: &Id,
+        callsign: &str,
+        datetime: u64,
+        freq: u64,
+        mode: &str,
+        rst: &str,
+        comments: &str,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(
+                listener_id,
+                station_id,
+                callsign,
+                datetime,
+                freq,
+                mode,
+                rst,
+                comments,
+                created_at,
+                version,
+            ),
+        )
+    }
+}
+
+impl Validate for SwlReport {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.listener_id == self.station_id {
+            bail!("station reporting its own transmission");
+        }
+
+        StrictCallsign.validate(&self.callsign)?;
+
+        if !text::is_valid_required(&self.rst, RST_MAX_LEN) {
+            bail!("invalid rst");
+        }
+
+        if !text::is_valid_required(&self.mode, MODE_MAX_LEN) {
+            bail!("invalid mode");
+        }
+
+        if !text::is_valid_multiline(&self.comments, COMMENTS_MAX_LEN) {
+            bail!("invalid comments");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for SwlReport {
+    const KIND: Kind = Kind::SwlReport;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.listener_id,
+            &self.station_id,
+            &self.callsign,
+            self.datetime,
+            self.freq,
+            &self.mode,
+            &self.rst,
+            &self.comments,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+/// The acknowledgement of an [`SwlReport`], signed by the station heard.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SwlAck {
+    pub id: Id,
+    pub station_id: Id,
+    pub report_id: Id,
+    pub listener_id: Id,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl SwlAck {
+    /// Current version of the SWL acknowledgement object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the SWL acknowledgement verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Acknowledges the report and signs the object using the signer of the
+    /// station heard.
+    pub fn new(station_id: Id, signer: &dyn Signer, report: &SwlReport) -> Result<Self> {
+        if report.station_id != station_id {
+            bail!("report addressed to another station");
+        }
+
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(
+            &station_id,
+            &report.id,
+            &report.listener_id,
+            created_at,
+            Self::VERSION,
+        );
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let ack = Self {
+            id,
+            station_id,
+            report_id: report.id.clone(),
+            listener_id: report.listener_id.clone(),
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        ack.validate()?;
+
+        Ok(ack)
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    /// Returns true if the acknowledgement confirms the report. Both objects
+    /// must be verified first.
+    pub fn acknowledges(&self, report: &SwlReport) -> bool {
+        self.report_id == report.id
+            && self.station_id == report.station_id
+            && self.listener_id == report.listener_id
+    }
+
+    fn compute_id(
+        station_id: &Id,
+        report_id: &Id,
+        listener_id: &Id,
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(station_id, report_id, listener_id, created_at, version),
+        )
+    }
+}
+
+impl Validate for SwlAck {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if self.listener_id == self.station_id {
+            bail!("station acknowledging its own report");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for SwlAck {
+    const KIND: Kind = Kind::SwlAck;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.report_id,
+            &self.listener_id,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::swl::{SwlAck, SwlReport, SwlReportData};
+    use crate::{test_vectors, PermissiveCallsign, Station, StationData};
+    use codes_iso_3166::part_1::CountryCode;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_swl_report() {
+        let listener_keys = generate_keypair();
+        let listener = Station::from_data_with_policy(
+            StationData {
+                callsign: "ONL-12345".to_string(),
+                operator: "Listener".to_string(),
+                country: CountryCode::BE.into(),
+                extra: BTreeMap::new(),
+                profile: None,
+                qsl_route: None,
+            },
+            &listener_keys,
+            &PermissiveCallsign,
+        )
+        .unwrap();
+        let station = test_vectors::station();
+        let station_keys = test_vectors::station_keypair();
+
+        let data = SwlReportData {
+            station_id: station.id.clone(),
+            callsign: "lu4ev".to_string(),
+            datetime: test_vectors::CREATED_AT,
+            freq: 14_250_300,
+            mode: "SSB".to_string(),
+            rst: "45444".to_string(),
+            comments: "strong fading".to_string(),
+        };
+        let report = SwlReport::new(listener.id.clone(), &listener_keys, data.clone()).unwrap();
+        assert_eq!(report.callsign, "LU4EV");
+
+        let json_str = serde_json::to_string(&report).unwrap();
+        let report: SwlReport = serde_json::from_str(&json_str).unwrap();
+        report.verify(&listener.pub_key).unwrap();
+        assert!(report.verify(&station.pub_key).is_err());
+
+        let ack = SwlAck::new(station.id.clone(), &station_keys, &report).unwrap();
+        let json_str = serde_json::to_string(&ack).unwrap();
+        let ack: SwlAck = serde_json::from_str(&json_str).unwrap();
+        ack.verify(&station.pub_key).unwrap();
+        assert!(ack.acknowledges(&report));
+
+        let other = SwlReport::new(
+            listener.id.clone(),
+            &listener_keys,
+            SwlReportData {
+                rst: "35433".to_string(),
+                ..data
+            },
+        )
+        .unwrap();
+        assert!(!ack.acknowledges(&other));
+
+        assert!(SwlAck::new(listener.id.clone(), &listener_keys, &report).is_err());
+        assert!(SwlReport::new(
+            station.id.clone(),
+            &station_keys,
+            SwlReportData {
+                station_id: station.id,
+                callsign: "LU4EV".to_string(),
+                datetime: test_vectors::CREATED_AT,
+                freq: 14_250_300,
+                mode: "SSB".to_string(),
+                rst: "59".to_string(),
+                comments: String::new(),
+            },
+        )
+        .is_err());
+    }
+}
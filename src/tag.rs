@@ -0,0 +1,229 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::Band;
+use crate::limits::Limits;
+use crate::{Callsign, Id};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Maximum number of tags of an event.
+pub const MAX_TAGS: usize = 32;
+
+/// Maximum size in bytes of a tag, see [`Tag::size`].
+pub(crate) const TAG_MAX_LEN: usize = 256;
+
+const REF: &str = "ref";
+const CALLSIGN: &str = "callsign";
+const BAND: &str = "band";
+const EXPIRES_AT: &str = "expires_at";
+
+/// A value attached to an event and covered by its signature, used to
+/// reference other events and to index events by value.
+///
+/// Tags serialize as arrays of strings, the tag name first, e.g.
+/// `["ref", "<id>"]`. Tags with an unknown name are kept as is, so newer
+/// tags survive a round trip through older clients without breaking the
+/// signature.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Tag {
+    /// A reference to another event, e.g. from a confirmation to the QSO.
+    Ref(Id),
+    /// A callsign, normalized.
+    Callsign(Callsign),
+    Band(Band),
+    /// Unix time after which the event is obsolete, e.g. a net check-in or a
+    /// spot. Stores prune expired events and relays refuse them.
+    ExpiresAt(u64),
+    /// A tag with an unknown name and its values.
+    Other(String, Vec<String>),
+}
+
+impl Tag {
+    /// Creates a callsign tag, normalizing the callsign.
+    pub fn callsign(callsign: &str) -> Self {
+        Tag::Callsign(Callsign::normalize(callsign))
+    }
+
+    /// Returns the tag name.
+    pub fn name(&self) -> &str {
+        match self {
+            Tag::Ref(_) => REF,
+            Tag::Callsign(_) => CALLSIGN,
+            Tag::Band(_) => BAND,
+            Tag::ExpiresAt(_) => EXPIRES_AT,
+            Tag::Other(name, _) => name,
+        }
+    }
+
+    /// Returns the tag as its name followed by its values.
+    pub fn to_strings(&self) -> Vec<String> {
+        let mut strings = vec![self.name().to_string()];
+        match self {
+            Tag::Ref(id) => strings.push(id.to_string()),
+            Tag::Callsign(callsign) => strings.push(callsign.to_string()),
+            Tag::Band(band) => strings.push(band.name().to_string()),
+            Tag::ExpiresAt(expires_at) => strings.push(expires_at.to_string()),
+            Tag::Other(_, values) => strings.extend(values.iter().cloned()),
+        }
+        strings
+    }
+
+    /// Returns the size in bytes of the name and the values of the tag,
+    /// plus one byte per string, so empty values count too.
+    pub fn size(&self) -> usize {
+        self.to_strings()
+            .iter()
+            .map(|string| string.len() + 1)
+            .sum()
+    }
+
+    /// Parses a tag from its name followed by its values. Tags larger than
+    /// the protocol limit, [`Limits::DEFAULT`], are rejected.
+    pub fn from_strings(mut strings: Vec<String>) -> anyhow::Result<Self> {
+        if strings.is_empty() {
+            anyhow::bail!("empty tag");
+        }
+        let size: usize = strings.iter().map(|string| string.len() + 1).sum();
+        if size > Limits::DEFAULT.tag {
+            anyhow::bail!(
+                "{} tag longer than {} bytes",
+                strings[0],
+                Limits::DEFAULT.tag
+            );
+        }
+        let name = strings.remove(0);
+        let value = || match strings.as_slice() {
+            [value] => Ok(value.as_str()),
+            _ => Err(anyhow::anyhow!("{} tag must have a single value", name)),
+        };
+
+        Ok(match name.as_str() {
+            REF => Tag::Ref(value()?.parse()?),
+            CALLSIGN => {
+                let callsign = Callsign::normalize(value()?);
+                if callsign.as_str() != value()? {
+                    anyhow::bail!("callsign tag not normalized");
+                }
+                Tag::Callsign(callsign)
+            }
+            BAND => Tag::Band(value()?.parse()?),
+            EXPIRES_AT => Tag::ExpiresAt(value()?.parse()?),
+            _ => Tag::Other(name, strings),
+        })
+    }
+}
+
+impl From<Band> for Tag {
+    fn from(band: Band) -> Self {
+        Tag::Band(band)
+    }
+}
+
+impl Serialize for Tag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_strings().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Tag::from_strings(Vec::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bandplan::Band;
+    use crate::event::{Event, Filter};
+    use crate::store::{MemoryStore, Store};
+    use crate::tag::{Tag, TAG_MAX_LEN};
+    use crate::{test_vectors, Qso, QsoData};
+    use serde_json::json;
+
+    #[test]
+    fn test_tags() {
+        let qso_id = test_vectors::qso().id;
+        let tags = vec![
+            Tag::Ref(qso_id.clone()),
+            Tag::callsign("lu4ev"),
+            Band::M20.into(),
+            Tag::ExpiresAt(1704145026),
+            Tag::Other("x-logger".to_string(), vec!["gqdb".to_string()]),
+        ];
+
+        let json = serde_json::to_value(&tags).unwrap();
+        assert_eq!(
+            json,
+            json!([
+                ["ref", qso_id.to_string()],
+                ["callsign", "LU4EV"],
+                ["band", "20m"],
+                ["expires_at", "1704145026"],
+                ["x-logger", "gqdb"],
+            ])
+        );
+        assert_eq!(serde_json::from_value::<Vec<Tag>>(json).unwrap(), tags);
+
+        for invalid in [
+            json!([]),
+            json!(["ref", "abc"]),
+            json!(["callsign", "lu4ev"]),
+            json!(["band", "20m", "40m"]),
+            json!(["expires_at", "tomorrow"]),
+            json!(["x-logger", "g".repeat(TAG_MAX_LEN)]),
+            json!(["x-logger"]
+                .into_iter()
+                .chain([""; TAG_MAX_LEN])
+                .collect::<Vec<_>>()),
+        ] {
+            assert!(serde_json::from_value::<Tag>(invalid).is_err());
+        }
+        let oversized = Tag::Other("x-logger".to_string(), vec!["g".repeat(TAG_MAX_LEN)]);
+        assert_eq!(oversized.size(), TAG_MAX_LEN + 10);
+
+        let qso = Qso::new(
+            QsoData {
+                station_id: test_vectors::counterparty().id,
+                callsign: "LU4EV".to_string(),
+                comments: String::new(),
+                tags: tags.clone(),
+                ..test_vectors::qso_data()
+            },
+            &test_vectors::counterparty_keypair(),
+        );
+        qso.verify(&test_vectors::counterparty().pub_key).unwrap();
+
+        let mut tampered = qso.clone();
+        tampered.tags.pop();
+        assert!(tampered
+            .verify(&test_vectors::counterparty().pub_key)
+            .is_err());
+
+        let json_str = serde_json::to_string(&qso).unwrap();
+        let qso_dese: Qso = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(qso_dese.tags, tags);
+
+        let mut store = MemoryStore::new();
+        store.insert(Event::Qso(test_vectors::qso())).unwrap();
+        store.insert(Event::Qso(qso.clone())).unwrap();
+        let referencing = Filter::new().tags([Tag::Ref(qso_id), Band::M40.into()]);
+        assert_eq!(store.query(&referencing).unwrap(), vec![Event::Qso(qso)]);
+        assert_eq!(
+            store
+                .count(&Filter::new().tags([Band::M40.into()]))
+                .unwrap(),
+            0
+        );
+    }
+}
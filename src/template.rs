@@ -0,0 +1,211 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Templates generating the comments and QSL messages of QSOs from their
+//! fields, e.g. `TNX {name|OM} {rst} ON {band}`.
+//!
+//! Placeholders are the QSO fields `callsign`, `rst`, `mode`, `band`, `freq`
+//! in MHz, `date` and `time` in UTC, or else the extension field with the
+//! name. A default after a pipe, as in `{name|OM}`, replaces a missing field.
+//! `{{` and `}}` write literal braces.
+
+use crate::bandplan::Band;
+use crate::csv::{format_freq, FreqUnit};
+use crate::QsoData;
+use anyhow::{anyhow, bail, Result};
+use chrono::DateTime;
+use serde_json::Value;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field {
+        name: String,
+        default: Option<String>,
+    },
+}
+
+/// A parsed template, see the [module docs](self) for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses the template, failing on unbalanced braces and invalid field
+    /// names.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = source.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| anyhow!("unterminated placeholder"))?;
+                    let (name, default) = match rest[..end].split_once('|') {
+                        Some((name, default)) => (name, Some(default.to_string())),
+                        None => (&rest[..end], None),
+                    };
+                    let name = name.trim();
+                    if !is_field_name(name) {
+                        bail!("invalid placeholder {{{}}}", &rest[..end]);
+                    }
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field {
+                        name: name.to_string(),
+                        default,
+                    });
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => bail!("unmatched }}"),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Renders the template with the fields of the QSO. Fails if a field
+    /// without default is missing.
+    pub fn render(&self, qso_data: &QsoData) -> Result<String> {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Field { name, default } => {
+                    let value = field(qso_data, name)
+                        .or_else(|| default.clone())
+                        .ok_or_else(|| anyhow!("missing field {}", name))?;
+                    rendered.push_str(&value);
+                }
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Returns the QSO with the comments replaced by the rendered template,
+    /// to be signed next.
+    pub fn apply(&self, qso_data: QsoData) -> Result<QsoData> {
+        Ok(QsoData {
+            comments: self.render(&qso_data)?,
+            ..qso_data
+        })
+    }
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn field(qso_data: &QsoData, name: &str) -> Option<String> {
+    let datetime = || {
+        i64::try_from(qso_data.datetime)
+            .ok()
+            .and_then(|datetime| DateTime::from_timestamp(datetime, 0))
+    };
+
+    match name {
+        "callsign" => Some(qso_data.callsign.clone()),
+        "rst" => Some(qso_data.rst.clone()),
+        "mode" => Some(qso_data.mode.clone()),
+        "band" => Band::from_freq(qso_data.freq).map(|band| band.name().to_string()),
+        "freq" => {
+            // Trailing zeros past the kHz are noise, 14.250300 reads 14.2503.
+            let freq = format_freq(qso_data.freq, FreqUnit::Mhz);
+            let kept = freq.len() - 3;
+            let trimmed = freq[kept..].trim_end_matches('0');
+            Some(format!("{}{}", &freq[..kept], trimmed))
+        }
+        "date" => datetime().map(|datetime| datetime.format("%Y-%m-%d").to_string()),
+        "time" => datetime().map(|datetime| datetime.format("%H:%M").to_string()),
+        _ => match qso_data.extra.get(name)? {
+            Value::String(value) if !value.is_empty() => Some(value.clone()),
+            Value::Number(value) => Some(value.to_string()),
+            Value::Bool(value) => Some(value.to_string()),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::template::Template;
+    use crate::{test_vectors, Id, QsoData};
+    use serde_json::json;
+
+    #[test]
+    fn test_template() {
+        let qso = test_vectors::qso();
+        let mut qso_data = QsoData {
+            station_id: Id::new("ignored"),
+            callsign: qso.callsign,
+            freq: qso.freq,
+            datetime: qso.datetime,
+            mode: qso.mode,
+            rst: qso.rst,
+            comments: String::new(),
+            extra: [("name".to_string(), json!("Gabriel"))].into(),
+            ..test_vectors::qso_data()
+        };
+
+        let template: Template = "TNX {name|OM} {rst} ON {band} {{{freq} {mode}}} {date} {time}Z"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            template.render(&qso_data).unwrap(),
+            "TNX Gabriel 59 ON 20m {14.2503 SSB} 2024-01-01 20:37Z"
+        );
+
+        qso_data.extra.clear();
+        let qso_data = template.apply(qso_data).unwrap();
+        assert!(qso_data.comments.starts_with("TNX OM 59"));
+
+        let template = Template::parse("73 {name}").unwrap();
+        assert!(template.render(&qso_data).is_err());
+
+        assert!(Template::parse("TNX {name").is_err());
+        assert!(Template::parse("TNX name}").is_err());
+        assert!(Template::parse("TNX {Name}").is_err());
+        assert!(Template::parse("TNX {}").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fixed signed objects for interoperability tests.
+//!
+//! Keys are derived from fixed seeds, creation times are fixed and signatures
+//! use no auxiliary randomness, so every call returns byte identical objects.
+//! Other implementations can check that they compute the same ids and
+//! signatures and that they accept the serialized objects.
+
+use crate::callsign::StrictCallsign;
+use crate::keys::generate_keypair_from_seed;
+use crate::signer::{SignRequest, SignResponse, Signer};
+use crate::{Certificate, Qso, QsoData, Station, StationData};
+use anyhow::Result;
+use codes_iso_3166::part_1::CountryCode;
+use secp256k1::{Keypair, Message, XOnlyPublicKey, SECP256K1};
+use std::collections::BTreeMap;
+
+/// Creation time of every test vector object.
+pub const CREATED_AT: u64 = 1704141426;
+
+/// Seed of the station key pair.
+pub const STATION_SEED: [u8; 32] = [1; 32];
+
+/// Seed of the counterparty key pair.
+pub const COUNTERPARTY_SEED: [u8; 32] = [2; 32];
+
+/// Signs with BIP340 without auxiliary randomness.
+struct DeterministicSigner(Keypair);
+
+impl Signer for DeterministicSigner {
+    fn public_key(&self) -> Result<XOnlyPublicKey> {
+        Ok(self.0.x_only_public_key().0)
+    }
+
+    fn sign(&self, request: &SignRequest) -> Result<SignResponse> {
+        let message = Message::from_digest(*request.id.as_bytes());
+        Ok(SignResponse {
+            sig: SECP256K1.sign_schnorr_no_aux_rand(&message, &self.0),
+        })
+    }
+}
+
+/// Returns the key pair of the station.
+pub fn station_keypair() -> Keypair {
+    generate_keypair_from_seed(&STATION_SEED)
+}
+
+/// Returns the key pair of the counterparty.
+pub fn counterparty_keypair() -> Keypair {
+    generate_keypair_from_seed(&COUNTERPARTY_SEED)
+}
+
+/// Returns the station LU4EV.
+pub fn station() -> Station {
+    create_station(station_keypair(), "LU4EV", "Radio Club Caseros")
+}
+
+/// Returns the counterparty station LW3DZR.
+pub fn counterparty() -> Station {
+    create_station(counterparty_keypair(), "LW3DZR", "Gabriel Velo")
+}
+
+/// Returns the data of [`qso`]. Tests build other QSOs from it with the
+/// struct update syntax, overriding the fields they exercise.
+pub fn qso_data() -> QsoData {
+    QsoData {
+        station_id: station().id,
+        callsign: "LW3DZR".to_string(),
+        freq: 14250300,
+        datetime: CREATED_AT,
+        mode: "SSB".to_string(),
+        rst: "59".to_string(),
+        comments: "73".to_string(),
+        extra: BTreeMap::new(),
+        tags: vec![],
+        prev_id: None,
+    }
+}
+
+/// Returns a QSO of the station with the counterparty.
+pub fn qso() -> Qso {
+    Qso::create(
+        qso_data(),
+        &DeterministicSigner(station_keypair()),
+        CREATED_AT,
+        None,
+    )
+    .expect("valid test vector")
+}
+
+/// Returns a certificate issued by the station to the counterparty.
+pub fn certificate() -> Certificate {
+    Certificate::create(
+        station().id,
+        &DeterministicSigner(station_keypair()),
+        counterparty().id,
+        CREATED_AT,
+    )
+    .expect("valid test vector")
+}
+
+fn create_station(keys: Keypair, callsign: &str, operator: &str) -> Station {
+    Station::create(
+        StationData {
+            callsign: callsign.to_string(),
+            operator: operator.to_string(),
+            country: CountryCode::AR.into(),
+            extra: BTreeMap::new(),
+            profile: None,
+            qsl_route: None,
+        },
+        &DeterministicSigner(keys),
+        CREATED_AT,
+        0,
+        None,
+        &StrictCallsign,
+    )
+    .expect("valid test vector")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::signable::Signable;
+    use crate::test_vectors;
+
+    #[test]
+    fn test_vectors() {
+        let station = test_vectors::station();
+        let counterparty = test_vectors::counterparty();
+        let qso = test_vectors::qso();
+        let certificate = test_vectors::certificate();
+
+        station.verify().unwrap();
+        counterparty.verify().unwrap();
+        qso.verify(&station.pub_key).unwrap();
+        certificate.verify(&station.pub_key).unwrap();
+
+        assert_eq!(test_vectors::station(), station);
+        assert_eq!(test_vectors::qso(), qso);
+        assert_eq!(test_vectors::certificate(), certificate);
+
+        // Changing any of these breaks interoperability with the published
+        // vectors.
+        assert_eq!(
+            station.pub_key.to_string(),
+            "00a50febf06efd4cf86d0afe4122e5fe5a7193349e90accab42af3ecba4af5b3"
+        );
+        assert_eq!(
+            station.id.to_string(),
+            "ad7677b978141ddc09e9805e8b5f91c1243d7a4c3d74ac68d972347426e3843e"
+        );
+        assert_eq!(
+            qso.id.to_string(),
+            "548b178935106929d0839a44bdec2ecc74060217d46cf289b3c968d2966f5fe7"
+        );
+        assert_eq!(
+            qso.sig.to_string(),
+            "5f5f79d2c5755f60fbb183ab33a77a29d2c84770a8653fdbd65ba4b528126244171e4fc262ee766d677666d443fb80eeccf75b79ab2a1047b03d7ede1d5ab87c"
+        );
+        assert_eq!(
+            certificate.id().to_string(),
+            "ff824ce56fe507dd99652607f781d1bcd540ca157a9bafc51fe6a94412877f76"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! [`Arbitrary`] implementations for property testing and fuzzing, enabled by
+//! the `testing` feature.
+//!
+//! The generated values are well formed but not necessarily valid: callsigns,
+//! modes and reports can be any string, and certificates carry random
+//! signatures. That is what ingestion pipelines have to survive.
+
+use crate::station::{Profile, QslRoute};
+use crate::{Entity, Id, QsoData, StationData};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use codes_iso_3166::part_1::ALL_CODES;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+impl<'a> Arbitrary<'a> for Id {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Id::from_bytes(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Entity {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=8)? {
+            0 => Entity::ItuHq,
+            1 => Entity::UnHq,
+            2 => Entity::Smom,
+            3 => Entity::MountAthos,
+            4 => Entity::ScarboroughReef,
+            5 => Entity::SpratlyIslands,
+            6 => Entity::Deleted(u.arbitrary()?),
+            7 => Entity::InternationalWaters,
+            _ => Entity::Country(*u.choose(&ALL_CODES)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Profile {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Profile {
+            display_name: u.arbitrary()?,
+            url: u.arbitrary()?,
+            email_hash: u.arbitrary()?,
+            avatar_url: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for QslRoute {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(QslRoute {
+            manager: u.arbitrary()?,
+            bureau: u.arbitrary()?,
+            direct_address_hash: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for QsoData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(QsoData {
+            station_id: u.arbitrary()?,
+            callsign: u.arbitrary()?,
+            datetime: u.arbitrary()?,
+            freq: u.arbitrary()?,
+            mode: u.arbitrary()?,
+            rst: u.arbitrary()?,
+            comments: u.arbitrary()?,
+            extra: arbitrary_extra(u)?,
+            tags: vec![],
+            prev_id: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for StationData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(StationData {
+            callsign: u.arbitrary()?,
+            operator: u.arbitrary()?,
+            country: u.arbitrary()?,
+            extra: arbitrary_extra(u)?,
+            profile: u.arbitrary()?,
+            qsl_route: u.arbitrary()?,
+        })
+    }
+}
+
+/// Extension fields with string, number and boolean values.
+fn arbitrary_extra(u: &mut Unstructured) -> Result<BTreeMap<String, Value>> {
+    let mut extra = BTreeMap::new();
+    for _ in 0..u.arbitrary_len::<(String, u64)>()? {
+        let value = match u.int_in_range(0..=2)? {
+            0 => Value::from(u.arbitrary::<String>()?),
+            1 => Value::from(u.arbitrary::<u64>()?),
+            _ => Value::from(u.arbitrary::<bool>()?),
+        };
+        extra.insert(u.arbitrary()?, value);
+    }
+    Ok(extra)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::{Certificate, Qso, QsoData, Station, StationData};
+    use arbitrary::{Arbitrary, Unstructured};
+
+    #[test]
+    fn test_arbitrary() {
+        let keys = generate_keypair();
+        let data: Vec<u8> = (0..8192).map(|i| (i * 37 % 251) as u8).collect();
+        let mut u = Unstructured::new(&data);
+
+        // Signing must reject invalid data without panicking.
+        for _ in 0..8 {
+            let _ = Qso::new_with_signer(QsoData::arbitrary(&mut u).unwrap(), &keys);
+            let _ = Station::from_data(StationData::arbitrary(&mut u).unwrap(), &keys);
+        }
+
+        let certificate = Certificate::arbitrary(&mut u).unwrap();
+        let json_str = serde_json::to_string(&certificate).unwrap();
+        let certificate_dese: Certificate = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(certificate_dese, certificate);
+    }
+}
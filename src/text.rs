@@ -0,0 +1,77 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The policy applied to every free text field.
+//!
+//! Lengths are counted in grapheme clusters, so a 64 character limit allows
+//! 64 CJK characters or accented letters regardless of their encoding. Each
+//! grapheme may take up to [`MAX_GRAPHEME_BYTES`], which bounds the size of
+//! the field and rejects stacks of combining marks.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Bytes allowed per grapheme, the UTF-8 length of any single code point.
+pub(crate) const MAX_GRAPHEME_BYTES: usize = 4;
+
+/// Returns the maximum size in bytes of a text of `max_len` graphemes.
+pub(crate) const fn max_bytes(max_len: usize) -> usize {
+    max_len * MAX_GRAPHEME_BYTES
+}
+
+/// Returns true if the text has at most `max_len` graphemes and no control
+/// characters. Empty text is valid.
+pub(crate) fn is_valid(text: &str, max_len: usize) -> bool {
+    is_valid_with(text, max_len, |_| false)
+}
+
+/// Like [`is_valid`], but line breaks and tabs are allowed.
+pub(crate) fn is_valid_multiline(text: &str, max_len: usize) -> bool {
+    is_valid_with(text, max_len, |c| matches!(c, '\n' | '\r' | '\t'))
+}
+
+/// Returns true if the text isn't blank and [`is_valid`].
+pub(crate) fn is_valid_required(text: &str, max_len: usize) -> bool {
+    !text.trim().is_empty() && is_valid(text, max_len)
+}
+
+fn is_valid_with(text: &str, max_len: usize, allowed: impl Fn(char) -> bool) -> bool {
+    text.len() <= max_bytes(max_len)
+        && !text.chars().any(|c| c.is_control() && !allowed(c))
+        && text.graphemes(true).count() <= max_len
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::text::{is_valid, is_valid_multiline, is_valid_required};
+
+    #[test]
+    fn test_text_policy() {
+        assert!(is_valid("", 4));
+        assert!(!is_valid_required("", 4));
+        assert!(!is_valid_required("    ", 4));
+        assert!(is_valid_required("LU4EV", 8));
+        assert!(!is_valid("LU4EV op", 4));
+
+        // Counted in graphemes, not bytes.
+        assert!(is_valid_required(&"無線".repeat(32), 64));
+        assert!(!is_valid_required(&"無線".repeat(33), 64));
+        assert!(is_valid("Jose\u{301}", 4));
+        assert!(!is_valid(&format!("e{}", "\u{301}".repeat(16)), 4));
+
+        assert!(!is_valid("73\u{0}", 8));
+        assert!(!is_valid("73\n", 8));
+        assert!(!is_valid("\u{1b}[2J", 8));
+        assert!(is_valid_multiline("73\nGL", 8));
+        assert!(!is_valid_multiline("73\u{7}", 8));
+    }
+}
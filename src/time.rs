@@ -11,8 +11,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use anyhow::{bail, Result};
 use std::time::SystemTime;
 
+/// Earliest valid creation time of an object, 2021-01-01 00:00:00 UTC. No
+/// object predates the crate.
+pub const EPOCH: u64 = 1_609_459_200;
+
 /// return the current unix time ( the number of seconds that have elapsed since
 /// 00:00:00 UTC on 1 January 1970 ).
 pub fn unix_timstamp() -> u64 {
@@ -21,3 +26,15 @@ pub fn unix_timstamp() -> u64 {
         .unwrap()
         .as_secs()
 }
+
+/// Checks that the creation time is after [`EPOCH`] and at most `max_skew`
+/// seconds ahead of the current time.
+pub fn check_created_at(created_at: u64, max_skew: u64) -> Result<()> {
+    if created_at < EPOCH {
+        bail!("created_at {} before epoch", created_at);
+    }
+    if created_at > unix_timstamp().saturating_add(max_skew) {
+        bail!("created_at {} in the future", created_at);
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{version, Id, Kind};
+use anyhow::Result;
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+/// A statement signed by a station withdrawing one of its QSOs, e.g. one
+/// logged by mistake.
+///
+/// Signed objects can't be deleted from the relays that already have them,
+/// the tombstone tells every peer to drop the QSO and its amendments, see
+/// [`crate::resolve`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Tombstone {
+    pub id: Id,
+    pub station_id: Id,
+    pub qso_id: Id,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl Tombstone {
+    /// Current version of the tombstone object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the tombstone verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new Tombstone of the station QSO and signs the object using
+    /// the station signer.
+    pub fn new(station_id: Id, signer: &dyn Signer, qso_id: Id) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(&station_id, &qso_id, created_at, Self::VERSION);
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let tombstone = Self {
+            id,
+            station_id,
+            qso_id,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        tombstone.validate()?;
+
+        Ok(tombstone)
+    }
+
+    /// Verify the object signature against the station public key.
+    pub fn verify(&self, station_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(station_pub_key)
+    }
+
+    fn compute_id(station_id: &Id, qso_id: &Id, created_at: u64, version: u8) -> Id {
+        Id::hash(version, &(station_id, qso_id, created_at, version))
+    }
+}
+
+impl Validate for Tombstone {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)
+    }
+}
+
+impl Signable for Tombstone {
+    const KIND: Kind = Kind::Tombstone;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.station_id,
+            &self.qso_id,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+    use crate::keys::generate_keypair;
+    use crate::tombstone::Tombstone;
+    use crate::{test_vectors, Kind};
+
+    #[test]
+    fn test_tombstone() {
+        let station = test_vectors::station();
+        let qso = test_vectors::qso();
+
+        let tombstone = Tombstone::new(
+            station.id.clone(),
+            &test_vectors::station_keypair(),
+            qso.id.clone(),
+        )
+        .unwrap();
+
+        let event = Event::Tombstone(tombstone.clone());
+        assert_eq!(event.kind(), Kind::Tombstone);
+        let json_str = serde_json::to_string(&event).unwrap();
+        let event_dese: Event = serde_json::from_str(&json_str).unwrap();
+        event_dese.verify(&station.pub_key).unwrap();
+
+        assert!(tombstone
+            .verify(&generate_keypair().x_only_public_key().0)
+            .is_err());
+    }
+}
@@ -0,0 +1,329 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::signable::{Signable, Validate};
+use crate::signer::{self, Signer};
+use crate::time::unix_timstamp;
+use crate::{text, version, Id, Kind};
+use anyhow::{bail, Context, Result};
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const NAME_MAX_LEN: usize = 64;
+
+/// Role granted to a trust anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Root of a certificate chain, onboards other stations.
+    Root,
+    /// Issues award certificates.
+    AwardSponsor,
+    /// Reviews and attests station logs.
+    Auditor,
+}
+
+/// A station trusted out-of-band.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Anchor {
+    pub station_id: Id,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::PublicKey"))]
+    pub pub_key: XOnlyPublicKey,
+    pub roles: Vec<Role>,
+}
+
+/// A signed list of trust anchors published by an organization ( e.g. a
+/// national society ) and distributed as a json file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TrustBundle {
+    pub id: Id,
+    pub publisher_id: Id,
+    pub name: String,
+    pub anchors: Vec<Anchor>,
+    pub created_at: u64,
+    pub version: u8,
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::Signature"))]
+    pub sig: Signature,
+}
+
+impl TrustBundle {
+    /// Current version of the trust bundle object.
+    pub const VERSION: u8 = version::V0;
+
+    /// Versions accepted by the trust bundle verification.
+    pub const SUPPORTED_VERSIONS: &'static [u8] = &[version::V0];
+
+    /// Creates a new TrustBundle and signs the object using the publisher
+    /// signer.
+    pub fn new(
+        publisher_id: Id,
+        signer: &dyn Signer,
+        name: String,
+        anchors: Vec<Anchor>,
+    ) -> Result<Self> {
+        let pub_key = signer.public_key()?;
+        let created_at = unix_timstamp();
+        let id = Self::compute_id(&publisher_id, &name, &anchors, created_at, Self::VERSION);
+        let sig = signer::sign_id(signer, &pub_key, &id)?;
+
+        let bundle = Self {
+            id,
+            publisher_id,
+            name,
+            anchors,
+            created_at,
+            version: Self::VERSION,
+            sig,
+        };
+
+        bundle.validate()?;
+
+        Ok(bundle)
+    }
+
+    /// Verify the object signature against the publisher public key.
+    pub fn verify(&self, publisher_pub_key: &XOnlyPublicKey) -> Result<()> {
+        self.verify_signature(publisher_pub_key)
+    }
+
+    fn compute_id(
+        publisher_id: &Id,
+        name: &str,
+        anchors: &[Anchor],
+        created_at: u64,
+        version: u8,
+    ) -> Id {
+        Id::hash(
+            version,
+            &(publisher_id, name, json!(anchors), created_at, version),
+        )
+    }
+}
+
+impl Validate for TrustBundle {
+    fn validate(&self) -> Result<()> {
+        version::check_supported(self.version, Self::SUPPORTED_VERSIONS)?;
+
+        if !text::is_valid_required(&self.name, NAME_MAX_LEN) {
+            bail!("invalid name");
+        }
+
+        if self.anchors.iter().any(|anchor| anchor.roles.is_empty()) {
+            bail!("invalid anchor roles");
+        }
+
+        Ok(())
+    }
+}
+
+impl Signable for TrustBundle {
+    const KIND: Kind = Kind::TrustBundle;
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn sig(&self) -> &Signature {
+        &self.sig
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    fn generate_id(&self) -> Id {
+        Self::compute_id(
+            &self.publisher_id,
+            &self.name,
+            &self.anchors,
+            self.created_at,
+            self.version,
+        )
+    }
+}
+
+/// The set of stations a client trusts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustStore {
+    anchors: BTreeMap<Id, Anchor>,
+}
+
+impl TrustStore {
+    /// Creates an empty TrustStore.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an anchor, merging its roles with the ones already known. Fails
+    /// if the station is already an anchor with a different public key, the
+    /// known key is kept.
+    pub fn add_anchor(&mut self, anchor: Anchor) -> Result<()> {
+        self.check_anchor(&anchor)?;
+        let known = self
+            .anchors
+            .entry(anchor.station_id.clone())
+            .or_insert_with(|| Anchor {
+                roles: vec![],
+                ..anchor.clone()
+            });
+        known.roles.extend(anchor.roles);
+        known.roles.sort();
+        known.roles.dedup();
+        Ok(())
+    }
+
+    /// Verifies the bundle against the publisher public key, obtained
+    /// out-of-band, and adds its anchors. Nothing is added if an anchor
+    /// conflicts with a known one.
+    pub fn load_bundle(
+        &mut self,
+        bundle: &TrustBundle,
+        publisher_pub_key: &XOnlyPublicKey,
+    ) -> Result<()> {
+        bundle.verify(publisher_pub_key)?;
+        for anchor in &bundle.anchors {
+            self.check_anchor(anchor)?;
+        }
+        for anchor in &bundle.anchors {
+            self.add_anchor(anchor.clone())?;
+        }
+        Ok(())
+    }
+
+    fn check_anchor(&self, anchor: &Anchor) -> Result<()> {
+        match self.anchors.get(&anchor.station_id) {
+            Some(known) if known.pub_key != anchor.pub_key => {
+                bail!("conflicting public key for anchor {}", anchor.station_id)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads a json encoded bundle from a file and loads it.
+    pub fn load_bundle_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        publisher_pub_key: &XOnlyPublicKey,
+    ) -> Result<()> {
+        let json = std::fs::read_to_string(path.as_ref())
+            .with_context(|| format!("failed to read {}", path.as_ref().display()))?;
+        let bundle: TrustBundle = serde_json::from_str(&json).context("invalid trust bundle")?;
+        self.load_bundle(&bundle, publisher_pub_key)
+    }
+
+    /// Returns the anchor for the station id, if any.
+    pub fn anchor(&self, station_id: &Id) -> Option<&Anchor> {
+        self.anchors.get(station_id)
+    }
+
+    /// Returns true if the station is an anchor with the given role.
+    pub fn is_trusted(&self, station_id: &Id, role: Role) -> bool {
+        self.anchor(station_id)
+            .map(|anchor| anchor.roles.contains(&role))
+            .unwrap_or(false)
+    }
+
+    /// Returns an iterator over all the anchors.
+    pub fn anchors(&self) -> impl Iterator<Item = &Anchor> {
+        self.anchors.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::trust::{Anchor, Role, TrustBundle, TrustStore};
+    use crate::Station;
+    use codes_iso_3166::part_1::CountryCode;
+
+    #[test]
+    fn test_trust_bundle() {
+        let publisher_keys = generate_keypair();
+        let anchor_keys = generate_keypair();
+
+        let publisher = Station::new(
+            &publisher_keys,
+            "LU4AA".to_string(),
+            "Radio Club Argentino".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let anchor = Station::new(
+            &anchor_keys,
+            "LU4EV".to_string(),
+            "Radio Club Caseros".to_string(),
+            CountryCode::AR,
+        )
+        .unwrap();
+
+        let bundle = TrustBundle::new(
+            publisher.id.clone(),
+            &publisher_keys,
+            "RCA anchors".to_string(),
+            vec![Anchor {
+                station_id: anchor.id.clone(),
+                pub_key: anchor.pub_key,
+                roles: vec![Role::Root],
+            }],
+        )
+        .unwrap();
+
+        let json_msg = serde_json::to_string(&bundle).unwrap();
+        let bundle_dese: TrustBundle = serde_json::from_str(&json_msg).unwrap();
+
+        let mut trust_store = TrustStore::new();
+        assert!(trust_store
+            .load_bundle(&bundle_dese, &anchor.pub_key)
+            .is_err());
+
+        trust_store
+            .load_bundle(&bundle_dese, &publisher.pub_key)
+            .unwrap();
+
+        assert!(trust_store.is_trusted(&anchor.id, Role::Root));
+        assert!(!trust_store.is_trusted(&anchor.id, Role::Auditor));
+        assert!(!trust_store.is_trusted(&publisher.id, Role::Root));
+
+        // A known anchor keeps its key.
+        let conflicting = Anchor {
+            station_id: anchor.id.clone(),
+            pub_key: publisher.pub_key,
+            roles: vec![Role::Auditor],
+        };
+        let err = trust_store.add_anchor(conflicting).unwrap_err();
+        assert!(err.to_string().starts_with("conflicting public key"));
+        assert_eq!(
+            trust_store.anchor(&anchor.id).unwrap().pub_key,
+            anchor.pub_key
+        );
+        assert!(!trust_store.is_trusted(&anchor.id, Role::Auditor));
+
+        trust_store
+            .add_anchor(Anchor {
+                station_id: anchor.id.clone(),
+                pub_key: anchor.pub_key,
+                roles: vec![Role::Auditor],
+            })
+            .unwrap();
+        assert!(trust_store.is_trusted(&anchor.id, Role::Root));
+        assert!(trust_store.is_trusted(&anchor.id, Role::Auditor));
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::bandplan::{LicenseClass, Region};
+use crate::callsign::{CallsignPolicy, StrictCallsign};
+use crate::limits::Limits;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// How strictly the declared station country is checked against the country
+/// resolved from the callsign prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CountryCheck {
+    /// The callsign prefix must resolve to the declared country.
+    Strict,
+    /// Only reject callsigns resolving to a different country, unknown
+    /// prefixes and special event callsigns are accepted.
+    Lenient,
+}
+
+/// Optional checks applied on top of the object validation.
+///
+/// The default options are lenient: only the checks every verifier must agree
+/// on are applied, so objects accepted by one peer are accepted by all of
+/// them. Strict checks are meant for logging software to flag mistakes before
+/// an object is published.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationOptions {
+    /// Reject QSO frequencies outside the amateur allocations of the region
+    /// allowed by the license class.
+    pub band_plan: Option<(Region, LicenseClass)>,
+    /// Cross-check the station country against the callsign prefix.
+    pub country_check: Option<CountryCheck>,
+    /// Callsign policy replacing [`StrictCallsign`].
+    pub callsign_policy: Option<Arc<dyn CallsignPolicy>>,
+    /// Reject objects created before 2021, the crate epoch, or further in
+    /// the future than the given seconds, which would otherwise win the
+    /// ordering of replaceable objects.
+    pub max_clock_skew: Option<u64>,
+    /// Reject QSOs whose contact time is later than their signing time by
+    /// more than the given seconds, usually a swapped datetime field.
+    pub datetime_tolerance: Option<u64>,
+    /// Maximum field lengths, tighter than the protocol limits the
+    /// deserializer enforces.
+    pub limits: Limits,
+}
+
+impl ValidationOptions {
+    /// Lenient options, no additional checks.
+    pub fn lenient() -> Self {
+        Self::default()
+    }
+
+    /// Checks the QSO frequencies against the band plan of the region.
+    pub fn with_band_plan(mut self, region: Region, license_class: LicenseClass) -> Self {
+        self.band_plan = Some((region, license_class));
+        self
+    }
+
+    /// Checks the station country against the callsign prefix.
+    pub fn with_country_check(mut self, country_check: CountryCheck) -> Self {
+        self.country_check = Some(country_check);
+        self
+    }
+
+    /// Checks the creation time, tolerating clocks up to `max_skew` seconds
+    /// ahead.
+    pub fn with_max_clock_skew(mut self, max_skew: u64) -> Self {
+        self.max_clock_skew = Some(max_skew);
+        self
+    }
+
+    /// Checks that the QSO datetime is at most `tolerance` seconds after the
+    /// QSO was signed.
+    pub fn with_datetime_tolerance(mut self, tolerance: u64) -> Self {
+        self.datetime_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Rejects fields longer than the limits.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Validates callsigns with the given policy instead of
+    /// [`StrictCallsign`].
+    pub fn with_callsign_policy(mut self, policy: impl CallsignPolicy + 'static) -> Self {
+        self.callsign_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Returns the callsign policy in effect.
+    pub fn callsign_policy(&self) -> &dyn CallsignPolicy {
+        match &self.callsign_policy {
+            Some(policy) => policy.as_ref(),
+            None => &StrictCallsign,
+        }
+    }
+}
+
+/// A suspicious value which doesn't make the object invalid, meant to be
+/// surfaced for review by import tools and logging software.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ValidationWarning {
+    /// The mode is almost certainly not used on the frequency, see
+    /// [`crate::bandplan::is_plausible_mode`].
+    ImplausibleMode { freq: u64, mode: String },
+}
+
+impl Display for ValidationWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationWarning::ImplausibleMode { freq, mode } => {
+                write!(f, "mode {} unlikely on {} Hz", mode, freq)
+            }
+        }
+    }
+}
+
+/// Result of validating an object which passed every check.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub warnings: Vec<ValidationWarning>,
+}
+
+impl ValidationReport {
+    /// Returns true if there are no warnings.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
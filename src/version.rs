@@ -0,0 +1,49 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Object versions.
+//!
+//! Every signed object carries the version of its id layout:
+//!
+//! * v0: the original layout, a json array with the object fields.
+//! * v1: the v0 layout plus an optional trailing json object with the
+//!   optional fields ( proof of work nonce, sequence number, extension
+//!   fields, profile, QSL route ).
+//! * v2: the v1 layout, stations may declare DXCC entities which are not ISO
+//!   countries.
+//!
+//! Verification accepts every known version of an object, new objects are
+//! always created with the current version.
+
+use anyhow::{bail, Result};
+
+pub const V0: u8 = 0;
+pub const V1: u8 = 1;
+pub const V2: u8 = 2;
+
+/// Checks that the version is one of the supported versions.
+pub(crate) fn check_supported(version: u8, supported: &[u8]) -> Result<()> {
+    if !supported.contains(&version) {
+        bail!("unsupported version {}", version);
+    }
+    Ok(())
+}
+
+/// Checks that an object using optional fields has a version which supports
+/// them.
+pub(crate) fn check_optional_fields(version: u8, has_optional_fields: bool) -> Result<()> {
+    if version < V1 && has_optional_fields {
+        bail!("optional fields require version {}", V1);
+    }
+    Ok(())
+}
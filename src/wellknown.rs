@@ -0,0 +1,156 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Callsign to key bindings published on a web domain.
+//!
+//! A domain ( e.g. the site of a national society ) vouches for the keys of
+//! its members serving `https://<domain>/.well-known/gqdb.json`:
+//!
+//! ```json
+//! { "names": { "LU4EV": "<hex encoded station public key>" } }
+//! ```
+//!
+//! A station claims the binding with the [`crate::station::DOMAIN`] extension
+//! field.
+
+use crate::callsign::Callsign;
+use crate::Station;
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+const DOMAIN_MAX_LEN: usize = 253;
+
+/// Fetches the body of a https url.
+///
+/// The library doesn't ship a TLS stack, applications provide the transport.
+pub trait Fetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>>;
+}
+
+/// The `gqdb.json` document served by a domain.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WellKnown {
+    pub names: BTreeMap<String, XOnlyPublicKey>,
+}
+
+impl WellKnown {
+    /// Returns the key bound to the callsign.
+    pub fn pub_key(&self, callsign: &str) -> Option<&XOnlyPublicKey> {
+        let callsign = Callsign::normalize(callsign);
+        self.names
+            .iter()
+            .find(|(name, _)| Callsign::normalize(name) == callsign)
+            .map(|(_, pub_key)| pub_key)
+    }
+
+    /// Checks that the document binds the station callsign to the station key.
+    pub fn verify(&self, station: &Station) -> Result<()> {
+        match self.pub_key(&station.callsign) {
+            Some(pub_key) if *pub_key == station.pub_key => Ok(()),
+            Some(_) => bail!("key mismatch for {}", station.callsign),
+            None => bail!("{} not found", station.callsign),
+        }
+    }
+}
+
+/// Returns the url of the `gqdb.json` document of the domain.
+pub fn url(domain: &str) -> Result<String> {
+    if !is_valid_domain(domain) {
+        bail!("invalid domain {}", domain);
+    }
+    Ok(format!("https://{}/.well-known/gqdb.json", domain))
+}
+
+/// Fetches the document of the domain claimed by the station and checks that
+/// it binds the station callsign to the station key.
+pub fn verify_dns_binding(station: &Station, fetcher: &dyn Fetcher) -> Result<()> {
+    let domain = station
+        .domain()
+        .ok_or_else(|| anyhow!("station without domain"))?;
+    let body = fetcher.fetch(&url(domain)?)?;
+    let well_known: WellKnown =
+        serde_json::from_slice(&body).context("invalid gqdb.json document")?;
+    well_known.verify(station)
+}
+
+fn is_valid_domain(domain: &str) -> bool {
+    static IS_DOMAIN: OnceLock<Regex> = OnceLock::new();
+    let is_domain = IS_DOMAIN.get_or_init(|| {
+        Regex::new(r"^([a-z0-9]([a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,63}$").unwrap()
+    });
+    domain.len() <= DOMAIN_MAX_LEN && is_domain.is_match(domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::generate_keypair;
+    use crate::station::DOMAIN;
+    use crate::wellknown::{self, Fetcher, WellKnown};
+    use crate::{Station, StationData};
+    use anyhow::{bail, Result};
+    use codes_iso_3166::part_1::CountryCode;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    struct MockFetcher(String);
+
+    impl Fetcher for MockFetcher {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+            if url != "https://lu4aa.org.ar/.well-known/gqdb.json" {
+                bail!("not found");
+            }
+            Ok(self.0.clone().into_bytes())
+        }
+    }
+
+    #[test]
+    fn test_dns_binding() {
+        let keys = generate_keypair();
+        let station = |domain: &str| {
+            Station::from_data(
+                StationData {
+                    callsign: "LU4EV".to_string(),
+                    operator: "Radio Club Caseros".to_string(),
+                    country: CountryCode::AR.into(),
+                    extra: BTreeMap::from([(DOMAIN.to_string(), json!(domain))]),
+                    profile: None,
+                    qsl_route: None,
+                },
+                &keys,
+            )
+            .unwrap()
+        };
+
+        let lu4ev = station("lu4aa.org.ar");
+        let other = generate_keypair().x_only_public_key().0;
+
+        let fetcher = |pub_key| {
+            MockFetcher(json!({ "names": { "lu4ev": pub_key, "LW3DZR": other } }).to_string())
+        };
+
+        wellknown::verify_dns_binding(&lu4ev, &fetcher(lu4ev.pub_key)).unwrap();
+        assert!(wellknown::verify_dns_binding(&lu4ev, &fetcher(other)).is_err());
+        assert!(
+            wellknown::verify_dns_binding(&station("example.org"), &fetcher(lu4ev.pub_key))
+                .is_err()
+        );
+        assert!(wellknown::verify_dns_binding(&lu4ev, &MockFetcher("{}".to_string())).is_err());
+
+        assert!(WellKnown::default().verify(&lu4ev).is_err());
+        assert!(wellknown::url("../etc").is_err());
+    }
+}
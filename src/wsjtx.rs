@@ -0,0 +1,276 @@
+// Copyright 2023 The GQDB Authors
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! WSJT-X UDP integration, enabled by the `wsjtx` feature.
+//!
+//! WSJT-X broadcasts a `QSO Logged` message every time the operator logs a
+//! contact. [`WsjtxListener`] receives them and converts them into
+//! [`QsoData`] ready for signing.
+
+use crate::qso::{GRIDSQUARE, RST_RCVD};
+use crate::{Id, QsoData};
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+/// Default UDP port WSJT-X sends its messages to.
+pub const DEFAULT_PORT: u16 = 2237;
+
+const MAGIC: u32 = 0xadbc_cbda;
+const MAX_SCHEMA: u32 = 3;
+const QSO_LOGGED: u32 = 5;
+const MAX_DATAGRAM_LEN: usize = 64 * 1024;
+
+/// Julian day number of the unix epoch.
+const UNIX_EPOCH_JULIAN_DAY: i64 = 2_440_588;
+
+/// Receives the QSOs logged by WSJT-X.
+#[derive(Debug)]
+pub struct WsjtxListener {
+    socket: UdpSocket,
+    station_id: Id,
+}
+
+impl WsjtxListener {
+    /// Listens on the address for the QSOs logged by the station.
+    pub fn bind(addr: impl ToSocketAddrs, station_id: Id) -> Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+            station_id,
+        })
+    }
+
+    /// Returns the underlying socket, e.g. to set a read timeout.
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Blocks until WSJT-X logs a QSO. Other messages, like the status and
+    /// decode ones, are skipped, and so are the malformed ones.
+    pub fn recv(&self) -> Result<QsoData> {
+        let mut buf = vec![0; MAX_DATAGRAM_LEN];
+        loop {
+            let (len, peer) = self.socket.recv_from(&mut buf)?;
+            match parse_qso_logged(&buf[..len], &self.station_id) {
+                Ok(Some(qso)) => return Ok(qso),
+                Ok(None) => {}
+                Err(err) => tracing::debug!(%peer, "skipping wsjt-x message: {:#}", err),
+            }
+        }
+    }
+}
+
+/// Parses a WSJT-X message. Returns None if it isn't a `QSO Logged` one.
+pub fn parse_qso_logged(packet: &[u8], station_id: &Id) -> Result<Option<QsoData>> {
+    let mut reader = Reader { buf: packet };
+
+    if reader.u32()? != MAGIC {
+        bail!("not a wsjt-x message");
+    }
+    let schema = reader.u32()?;
+    if schema > MAX_SCHEMA {
+        bail!("unsupported wsjt-x schema {}", schema);
+    }
+    if reader.u32()? != QSO_LOGGED {
+        return Ok(None);
+    }
+
+    let _client_id = reader.string()?;
+    let _datetime_off = reader.datetime()?;
+    let callsign = reader.string()?;
+    let grid = reader.string()?;
+    let freq = reader.u64()?;
+    let mode = reader.string()?;
+    let rst_sent = reader.string()?;
+    let rst_rcvd = reader.string()?;
+    let _tx_power = reader.string()?;
+    let comments = reader.string()?;
+    let _name = reader.string()?;
+    let datetime_on = reader.datetime()?;
+
+    let mut extra = BTreeMap::new();
+    if !grid.is_empty() {
+        extra.insert(GRIDSQUARE.to_string(), Value::from(grid));
+    }
+    if !rst_rcvd.is_empty() {
+        extra.insert(RST_RCVD.to_string(), Value::from(rst_rcvd));
+    }
+
+    Ok(Some(QsoData {
+        station_id: station_id.clone(),
+        callsign,
+        datetime: datetime_on,
+        freq,
+        mode,
+        rst: rst_sent,
+        comments,
+        extra,
+        tags: vec![],
+        prev_id: None,
+    }))
+}
+
+/// Reads the Qt `QDataStream` encoding used by WSJT-X, big endian.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.buf.len() < N {
+            bail!("truncated wsjt-x message");
+        }
+        let (bytes, rest) = self.buf.split_at(N);
+        self.buf = rest;
+        Ok(bytes.try_into()?)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(u8::from_be_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_be_bytes(self.take()?))
+    }
+
+    /// A utf-8 `QByteArray`, null arrays are read as empty strings.
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()?;
+        if len == u32::MAX {
+            return Ok(String::new());
+        }
+        let len = len as usize;
+        if self.buf.len() < len {
+            bail!("truncated wsjt-x message");
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        String::from_utf8(bytes.to_vec()).context("invalid utf-8 in wsjt-x message")
+    }
+
+    /// A `QDateTime`, returned as unix time.
+    fn datetime(&mut self) -> Result<u64> {
+        let julian_day = self.i64()?;
+        let msecs = self.u32()?;
+        let offset = match self.u8()? {
+            // UTC
+            1 => 0,
+            // Offset from UTC in seconds
+            2 => self.i32()? as i64,
+            spec => bail!("unsupported wsjt-x time spec {}", spec),
+        };
+
+        let secs = julian_day
+            .checked_sub(UNIX_EPOCH_JULIAN_DAY)
+            .and_then(|days| days.checked_mul(86_400))
+            .and_then(|secs| secs.checked_add(msecs as i64 / 1000))
+            .and_then(|secs| secs.checked_sub(offset))
+            .context("wsjt-x datetime out of range")?;
+        u64::try_from(secs).context("wsjt-x datetime before 1970")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::qso::{GRIDSQUARE, RST_RCVD};
+    use crate::wsjtx::{parse_qso_logged, WsjtxListener, MAGIC, QSO_LOGGED};
+    use crate::Id;
+    use std::net::UdpSocket;
+
+    fn string(packet: &mut Vec<u8>, value: &str) {
+        packet.extend((value.len() as u32).to_be_bytes());
+        packet.extend(value.as_bytes());
+    }
+
+    fn datetime(packet: &mut Vec<u8>, julian_day: i64, msecs: u32) {
+        packet.extend(julian_day.to_be_bytes());
+        packet.extend(msecs.to_be_bytes());
+        packet.push(1);
+    }
+
+    fn qso_logged() -> Vec<u8> {
+        let mut packet = vec![];
+        packet.extend(MAGIC.to_be_bytes());
+        packet.extend(2u32.to_be_bytes());
+        packet.extend(QSO_LOGGED.to_be_bytes());
+        string(&mut packet, "WSJT-X");
+        // 2024-01-01 20:38:15 UTC
+        datetime(&mut packet, 2_460_311, 74_295_000);
+        string(&mut packet, "LW3DZR");
+        string(&mut packet, "GF05");
+        packet.extend(14_074_000u64.to_be_bytes());
+        string(&mut packet, "FT8");
+        string(&mut packet, "-10");
+        string(&mut packet, "-12");
+        string(&mut packet, "100");
+        string(&mut packet, "");
+        packet.extend(u32::MAX.to_be_bytes());
+        // 2024-01-01 20:37:00 UTC
+        datetime(&mut packet, 2_460_311, 74_220_000);
+        string(&mut packet, "");
+        string(&mut packet, "LU4EV");
+        string(&mut packet, "GF05tk");
+        string(&mut packet, "");
+        string(&mut packet, "");
+        packet
+    }
+
+    #[test]
+    fn test_parse_qso_logged() {
+        let station_id = Id::new("station");
+
+        let qso = parse_qso_logged(&qso_logged(), &station_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(qso.callsign, "LW3DZR");
+        assert_eq!(qso.datetime, 1704141420);
+        assert_eq!(qso.freq, 14074000);
+        assert_eq!(qso.mode, "FT8");
+        assert_eq!(qso.rst, "-10");
+        assert_eq!(qso.extra[GRIDSQUARE], "GF05");
+        assert_eq!(qso.extra[RST_RCVD], "-12");
+
+        let mut status = qso_logged();
+        status[11] = 1;
+        assert!(parse_qso_logged(&status, &station_id).unwrap().is_none());
+
+        let truncated = &qso_logged()[..40];
+        assert!(parse_qso_logged(truncated, &station_id).is_err());
+
+        // The julian day of the QSO off time
+        let mut overflow = qso_logged();
+        overflow[22..30].copy_from_slice(&i64::MIN.to_be_bytes());
+        assert!(parse_qso_logged(&overflow, &station_id).is_err());
+
+        let listener = WsjtxListener::bind("127.0.0.1:0", station_id).unwrap();
+        let addr = listener.socket().local_addr().unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(&status, addr).unwrap();
+        socket.send_to(&overflow, addr).unwrap();
+        socket.send_to(&qso_logged(), addr).unwrap();
+        assert_eq!(listener.recv().unwrap().callsign, "LW3DZR");
+    }
+}